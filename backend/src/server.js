@@ -93,6 +93,46 @@ import { fileURLToPath } from 'url';
 const __filename = fileURLToPath(import.meta.url);
 const __dirname = path.dirname(__filename);
 
+// The Rust tools expect a versioned prefix before their binary header:
+// [4 bytes "PCWT"][u16 protocol version][u16 tool id] (see rust/src/protocol.rs)
+// Of the tools used here, version 2 only changes point smoothing (convergenceEpsilon in,
+// iterationsRun out).
+const RUST_PROTOCOL_VERSION = 1;
+const RUST_PROTOCOL_VERSION_SMOOTH_CONVERGENCE = 2;
+const RUST_TOOL_IDS = {
+  VOXEL_DOWNSAMPLE: 1,
+  POINT_SMOOTH: 2,
+  VOXEL_DEBUG: 3,
+};
+
+function rustProtocolHeader(toolId, version = RUST_PROTOCOL_VERSION) {
+  const header = Buffer.allocUnsafe(8);
+  header.write('PCWT', 0, 'ascii');
+  header.writeUInt16LE(version, 4);
+  header.writeUInt16LE(toolId, 6);
+  return header;
+}
+
+// On failure the Rust tools write [u32 0xFFFFFFFF][u32 errorCode][u32 msgLen][utf8 msg]
+// to stdout before exiting non-zero. Returns null when no such frame is present.
+function parseRustErrorFrame(buffer) {
+  if (buffer.length < 12 || buffer.readUInt32LE(0) !== 0xffffffff) {
+    return null;
+  }
+  const code = buffer.readUInt32LE(4);
+  const length = buffer.readUInt32LE(8);
+  const message = buffer.toString('utf8', 12, Math.min(12 + length, buffer.length));
+  return { code, message };
+}
+
+function rustFailureMessage(exitCode, outputBuffer, stderrText) {
+  const frame = parseRustErrorFrame(outputBuffer);
+  if (frame) {
+    return `Rust tool error ${frame.code}: ${frame.message}`;
+  }
+  return `Rust process exited with code ${exitCode}: ${stderrText}`;
+}
+
 // Process pools are not created at startup so the server always listens.
 // Binaries are resolved per-request; missing binaries return errors to the client.
 
@@ -212,6 +252,7 @@ wss.on('connection', (ws, req) => {
             requestId,
             smoothingRadius,
             iterations,
+            convergenceEpsilon,
           } = pendingHeader;
 
           // Convert binary data to Float32Array
@@ -450,7 +491,7 @@ wss.on('connection', (ws, req) => {
               return;
             }
 
-            const headerBuffer = Buffer.allocUnsafe(36);
+            const headerBuffer = Buffer.allocUnsafe(40);
             headerBuffer.writeUInt32LE(pointCount, 0);
             headerBuffer.writeFloatLE(voxelSize, 4);
             headerBuffer.writeFloatLE(globalBounds.minX, 8);
@@ -460,13 +501,18 @@ wss.on('connection', (ws, req) => {
             headerBuffer.writeFloatLE(globalBounds.maxY, 24);
             headerBuffer.writeFloatLE(globalBounds.maxZ, 28);
             headerBuffer.writeUInt32LE(flags, 32);
+            headerBuffer.writeUInt32LE(1, 36); // minPointsPerVoxel: keep every voxel
 
             const pointDataBuffer = Buffer.from(
               data.buffer,
               data.byteOffset,
               data.byteLength
             );
-            const inputBuffer = Buffer.concat([headerBuffer, pointDataBuffer]);
+            const inputBuffer = Buffer.concat([
+              rustProtocolHeader(RUST_TOOL_IDS.VOXEL_DOWNSAMPLE),
+              headerBuffer,
+              pointDataBuffer,
+            ]);
 
             let outputBuffer = Buffer.alloc(0);
             let errorBuffer = '';
@@ -499,7 +545,7 @@ wss.on('connection', (ws, req) => {
                     type: 'voxel_downsample_rust_result',
                     requestId,
                     success: false,
-                    error: `Rust process exited with code ${code}: ${errorBuffer}`,
+                    error: rustFailureMessage(code, outputBuffer, errorBuffer),
                   })
                 );
                 return;
@@ -601,11 +647,12 @@ wss.on('connection', (ws, req) => {
             const pointCount = points.length / 3;
             const pointsFloat32 = new Float32Array(points);
 
-            // Create binary header buffer (12 bytes: 4 for u32 + 4 for f32 + 4 for f32)
-            const headerBuffer = Buffer.allocUnsafe(12);
+            // Create binary header buffer (16 bytes: 4 for u32 + 3 * 4 for f32)
+            const headerBuffer = Buffer.allocUnsafe(16);
             headerBuffer.writeUInt32LE(pointCount, 0);
             headerBuffer.writeFloatLE(smoothingRadius, 4);
             headerBuffer.writeFloatLE(iterations, 8);
+            headerBuffer.writeFloatLE(convergenceEpsilon || 0, 12); // 0 runs every iteration
 
             // Convert Float32Array to Buffer for point data (binary, no JSON!)
             const pointDataBuffer = Buffer.from(
@@ -615,7 +662,14 @@ wss.on('connection', (ws, req) => {
             );
 
             // Combine header + data
-            const inputBuffer = Buffer.concat([headerBuffer, pointDataBuffer]);
+            const inputBuffer = Buffer.concat([
+              rustProtocolHeader(
+                RUST_TOOL_IDS.POINT_SMOOTH,
+                RUST_PROTOCOL_VERSION_SMOOTH_CONVERGENCE
+              ),
+              headerBuffer,
+              pointDataBuffer,
+            ]);
 
             let outputBuffer = Buffer.alloc(0);
             let errorData = '';
@@ -664,7 +718,7 @@ wss.on('connection', (ws, req) => {
                     type: 'point_smooth_rust_result',
                     requestId,
                     success: false,
-                    error: `Rust process exited with code ${code}: ${errorData}`,
+                    error: rustFailureMessage(code, outputBuffer, errorData),
                   })
                 );
                 return;
@@ -672,7 +726,7 @@ wss.on('connection', (ws, req) => {
 
               try {
                 // Read binary output (no JSON parsing!)
-                // Binary format: [u32 pointCount][f32* smoothedPoints]
+                // Binary format: [u32 pointCount][f32* smoothedPoints][u32 iterationsRun]
                 if (outputBuffer.length < 4) {
                   throw new Error(
                     `Invalid binary output: too short (${outputBuffer.length} bytes, expected at least 4)`
@@ -682,11 +736,12 @@ wss.on('connection', (ws, req) => {
                 const outputCount = outputBuffer.readUInt32LE(0);
                 const expectedSize = 4 + outputCount * 3 * 4; // 4 bytes header + outputCount * 3 floats * 4 bytes
 
-                if (outputBuffer.length < expectedSize) {
+                if (outputBuffer.length < expectedSize + 4) {
                   throw new Error(
-                    `Invalid binary output: expected ${expectedSize} bytes, got ${outputBuffer.length}`
+                    `Invalid binary output: expected ${expectedSize + 4} bytes, got ${outputBuffer.length}`
                   );
                 }
+                const iterationsRun = outputBuffer.readUInt32LE(expectedSize);
 
                 // Extract smoothed points (skip 4-byte header)
                 const smoothedPointsBuffer = outputBuffer.slice(
@@ -712,6 +767,7 @@ wss.on('connection', (ws, req) => {
                   processingTime: processingTime,
                   smoothingRadius: smoothingRadius,
                   iterations: iterations,
+                  iterationsRun: iterationsRun,
                   dataLength: outputCount * 3,
                 };
 
@@ -1329,8 +1385,8 @@ wss.on('connection', (ws, req) => {
             const pointCount = points.length / 3;
             const pointsFloat32 = new Float32Array(points);
 
-            // Create binary header buffer (32 bytes: 4 for u32 + 7*4 for floats)
-            const headerBuffer = Buffer.allocUnsafe(32);
+            // Create binary header buffer (36 bytes: 4 for u32 + 7*4 for floats + 4 for flags)
+            const headerBuffer = Buffer.allocUnsafe(36);
             headerBuffer.writeUInt32LE(pointCount, 0);
             headerBuffer.writeFloatLE(voxelSize, 4);
             headerBuffer.writeFloatLE(globalBounds.minX, 8);
@@ -1339,6 +1395,7 @@ wss.on('connection', (ws, req) => {
             headerBuffer.writeFloatLE(globalBounds.maxX, 20);
             headerBuffer.writeFloatLE(globalBounds.maxY, 24);
             headerBuffer.writeFloatLE(globalBounds.maxZ, 28);
+            headerBuffer.writeUInt32LE(0, 32); // flags: 0 = f32 positions
 
             // Convert Float32Array to Buffer for point data (binary, no JSON!)
             const pointDataBuffer = Buffer.from(
@@ -1348,7 +1405,11 @@ wss.on('connection', (ws, req) => {
             );
 
             // Combine header + data
-            const inputBuffer = Buffer.concat([headerBuffer, pointDataBuffer]);
+            const inputBuffer = Buffer.concat([
+              rustProtocolHeader(RUST_TOOL_IDS.VOXEL_DEBUG),
+              headerBuffer,
+              pointDataBuffer,
+            ]);
 
             let outputBuffer = Buffer.alloc(0);
             let errorData = '';
@@ -1397,7 +1458,7 @@ wss.on('connection', (ws, req) => {
                     type: 'voxel_debug_rust_result',
                     requestId,
                     success: false,
-                    error: `Rust process exited with code ${code}: ${errorData}`,
+                    error: rustFailureMessage(code, outputBuffer, errorData),
                   })
                 );
                 return;
@@ -2713,8 +2774,8 @@ app.post('/api/voxel-debug-rust', async (req, res) => {
     const rustProcess = spawn(rustExecutable);
     console.log('🔧 Rust voxel debug process spawned, PID:', rustProcess.pid);
 
-    // Create binary header buffer (32 bytes: 4 for u32 + 7*4 for floats)
-    const headerBuffer = Buffer.allocUnsafe(32);
+    // Create binary header buffer (36 bytes: 4 for u32 + 7*4 for floats + 4 for flags)
+    const headerBuffer = Buffer.allocUnsafe(36);
     headerBuffer.writeUInt32LE(pointCount, 0);
     headerBuffer.writeFloatLE(voxelSize, 4);
     headerBuffer.writeFloatLE(globalBounds.minX, 8);
@@ -2723,6 +2784,7 @@ app.post('/api/voxel-debug-rust', async (req, res) => {
     headerBuffer.writeFloatLE(globalBounds.maxX, 20);
     headerBuffer.writeFloatLE(globalBounds.maxY, 24);
     headerBuffer.writeFloatLE(globalBounds.maxZ, 28);
+    headerBuffer.writeUInt32LE(0, 32); // flags: 0 = f32 positions
 
     // Convert Float32Array to Buffer for point data (binary, no JSON!)
     const pointDataBuffer = Buffer.from(
@@ -2732,7 +2794,11 @@ app.post('/api/voxel-debug-rust', async (req, res) => {
     );
 
     // Combine header + data
-    const inputBuffer = Buffer.concat([headerBuffer, pointDataBuffer]);
+    const inputBuffer = Buffer.concat([
+      rustProtocolHeader(RUST_TOOL_IDS.VOXEL_DEBUG),
+      headerBuffer,
+      pointDataBuffer,
+    ]);
 
     let outputBuffer = Buffer.alloc(0);
     let errorBuffer = '';
@@ -116,7 +116,22 @@ function createPointSmoothInput(pointCount, radius, iterations, points) {
   return buffer;
 }
 
+// Rust tools expect the versioned protocol prefix before the legacy header:
+// [4 bytes "PCWT"][u16 version][u16 toolId]
+const RUST_TOOL_ID = 2;
+
+function withRustProtocolHeader(input) {
+  const header = Buffer.allocUnsafe(8);
+  header.write('PCWT', 0, 'ascii');
+  header.writeUInt16LE(1, 4);
+  header.writeUInt16LE(RUST_TOOL_ID, 6);
+  return Buffer.concat([header, input]);
+}
+
 function runTool(executable, input) {
+  if (executable.endsWith('_rust')) {
+    input = withRustProtocolHeader(input);
+  }
   return new Promise((resolve, reject) => {
     const process = spawn(executable);
     const chunks = [];
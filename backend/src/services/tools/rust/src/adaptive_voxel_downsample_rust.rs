@@ -0,0 +1,295 @@
+use std::io::{self, Read, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::{is_finite_point, Bounds};
+use pointcloud_tools_backend::normals::estimate_curvature;
+use pointcloud_tools_backend::protocol::{check_voxel_size, read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, ToolError, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+use pointcloud_tools_backend::voxel_hash::VoxelMap;
+
+// Curvature-aware voxel downsampling: flat regions are thinned with the base voxel size while
+// curved regions (edges, corners) use smaller voxels and keep more detail.
+// Each point's curvature (surface variation over its k nearest neighbors) is normalized by the
+// cloud's maximum and quantized to one of LEVELS levels; level l uses voxels of size
+// baseSize * (1 - curvatureWeight * l / (LEVELS - 1)), never below MIN_SIZE_FRACTION * baseSize.
+// Every level has its own grid anchored at the cloud minimum, and points are averaged only with
+// points of the same level in the same voxel.
+//
+// Density mode (mode = 1, protocol version 2 only) levels the points by local spacing instead, to even out point density
+// across the cloud: baseSize is the target spacing and each point's spacing is the mean distance
+// to its k nearest neighbors. Points spaced at half the target or closer use the full target
+// size, so dense regions thin out to roughly the target spacing, while points already spaced at
+// the target or wider use the smallest level and are kept. curvatureWeight is ignored.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 baseSize][f32 curvatureWeight][u32 k][f32* positions]
+// With protocol version 2, [u32 mode] follows k (0=curvature, 1=density; anything else is
+// rejected). Version 1 input always uses curvature mode.
+// Output format: [u32 outputCount][f32* positions]
+
+const LEVELS: usize = 8;
+const MIN_SIZE_FRACTION: f32 = 0.05;
+
+/// (level, voxel x, voxel y, voxel z)
+type LevelVoxelKey = (u8, i32, i32, i32);
+
+const MODE_CURVATURE: u32 = 0;
+const MODE_DENSITY: u32 = 1;
+
+/// Mode word from a version 2 header; unknown modes are an error rather than curvature mode
+fn check_mode(mode: u32) -> Result<u32, ToolError> {
+    match mode {
+        MODE_CURVATURE | MODE_DENSITY => Ok(mode),
+        _ => Err(ToolError::new(ErrorCode::InvalidData, format!("unknown mode {} (0 = curvature, 1 = density)", mode))),
+    }
+}
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (16 bytes: u32 + 2 * f32 + u32)
+    let (header, mut run): ([u8; 16], _) = match read_tool_header(&mut stdin, tool_id::ADAPTIVE_VOXEL_DOWNSAMPLE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let base_size = f32_at(&header, 4, run.order);
+    let curvature_weight = f32_at(&header, 8, run.order);
+    let k = u32_at(&header, 12, run.order) as usize;
+    let mode = if run.version >= 2 {
+        let mut mode = [0u8; 4];
+        if stdin.read_exact(&mut mode).is_err() {
+            ToolError::new(ErrorCode::ShortHeader, "input ends before the mode").exit(run.order);
+        }
+        match check_mode(u32_at(&mode, 0, run.order)) {
+            Ok(m) => m,
+            Err(e) => e.exit(run.order),
+        }
+    } else {
+        MODE_CURVATURE
+    };
+
+    if let Err(e) = check_voxel_size(base_size) {
+        e.exit(run.order);
+    }
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let downsampled = if mode == MODE_DENSITY {
+        density_voxel_downsample(&positions, base_size, k, &mut run)
+    } else {
+        adaptive_voxel_downsample(&positions, base_size, curvature_weight, k, &mut run)
+    };
+
+    if write_u32(&mut stdout, (downsampled.len() / 3) as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &downsampled, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, downsampled.len() / 3).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Voxel size for each curvature level
+fn level_sizes(base_size: f32, curvature_weight: f32) -> [f32; LEVELS] {
+    let mut sizes = [0.0f32; LEVELS];
+    for (level, size) in sizes.iter_mut().enumerate() {
+        let normalized = level as f32 / (LEVELS - 1) as f32;
+        *size = (base_size * (1.0 - curvature_weight * normalized)).max(base_size * MIN_SIZE_FRACTION);
+    }
+    sizes
+}
+
+fn adaptive_voxel_downsample(positions: &[f32], base_size: f32, curvature_weight: f32, k: usize, run: &mut ToolRun) -> Vec<f32> {
+    let curvature = if k > 0 { estimate_curvature(positions, k, run) } else { vec![0.0; positions.len() / 3] };
+    let max_curvature = curvature.iter().cloned().fold(0.0f32, f32::max);
+    let levels: Vec<usize> = curvature
+        .iter()
+        .map(|&c| {
+            let normalized = if max_curvature > 0.0 { c / max_curvature } else { 0.0 };
+            (normalized * (LEVELS - 1) as f32).round() as usize
+        })
+        .collect();
+    level_voxel_average(positions, &levels, &level_sizes(base_size, curvature_weight), run)
+}
+
+/// Density mode: level each point by its local spacing relative to `target_spacing`
+fn density_voxel_downsample(positions: &[f32], target_spacing: f32, k: usize, run: &mut ToolRun) -> Vec<f32> {
+    let spacing = local_spacing(positions, k.max(1), run);
+    let levels: Vec<usize> = spacing
+        .iter()
+        .map(|&s| {
+            // 0 at half the target spacing or closer, 1 at the target spacing or wider
+            let normalized = (s / target_spacing * 2.0 - 1.0).clamp(0.0, 1.0);
+            (normalized * (LEVELS - 1) as f32).round() as usize
+        })
+        .collect();
+    // Weight 1 runs the sizes from the target spacing down to the MIN_SIZE_FRACTION floor
+    level_voxel_average(positions, &levels, &level_sizes(target_spacing, 1.0), run)
+}
+
+/// Mean distance from every point to its k nearest neighbors (the point itself excluded);
+/// infinite for points without neighbors
+fn local_spacing(positions: &[f32], k: usize, run: &mut ToolRun) -> Vec<f32> {
+    let grid = SpatialGrid::new(positions, SpatialGrid::auto_cell_size(positions));
+    run.note_map_size(grid.cell_count());
+    positions
+        .chunks_exact(3)
+        .map(|p| {
+            // k + 1 because the query point finds itself
+            let neighbors = grid.k_nearest(positions, p[0], p[1], p[2], k + 1);
+            if neighbors.len() < 2 {
+                return f32::INFINITY;
+            }
+            neighbors[1..].iter().map(|&(_, d)| d.sqrt()).sum::<f32>() / (neighbors.len() - 1) as f32
+        })
+        .collect()
+}
+
+/// Average the finite points per (level, voxel), each level on its own grid anchored at the
+/// cloud minimum
+fn level_voxel_average(positions: &[f32], levels: &[usize], sizes: &[f32; LEVELS], run: &mut ToolRun) -> Vec<f32> {
+    let min = Bounds::from_points(positions).map_or([0.0; 3], |b| b.min);
+
+    // Level voxel -> (count, position sums)
+    let mut voxels: VoxelMap<LevelVoxelKey, (u32, [f64; 3])> = VoxelMap::default();
+    for (p, &level) in positions.chunks_exact(3).zip(levels) {
+        if !is_finite_point(p) {
+            continue;
+        }
+        let inv_size = 1.0 / sizes[level];
+        let key = (
+            level as u8,
+            ((p[0] - min[0]) * inv_size).floor() as i32,
+            ((p[1] - min[1]) * inv_size).floor() as i32,
+            ((p[2] - min[2]) * inv_size).floor() as i32,
+        );
+        let entry = voxels.entry(key).or_insert((0, [0.0; 3]));
+        entry.0 += 1;
+        for (sum, &v) in entry.1.iter_mut().zip(p) {
+            *sum += v as f64;
+        }
+    }
+
+    run.note_map_size(voxels.len());
+    let mut output = Vec::with_capacity(voxels.len() * 3);
+    for (count, sum) in voxels.into_values() {
+        for s in sum {
+            output.push((s / count as f64) as f32);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Points on the surface of the unit cube at the given spacing
+    fn cube_surface(steps: usize) -> Vec<f32> {
+        let mut points = Vec::new();
+        let step = 1.0 / steps as f32;
+        for i in 0..=steps {
+            for j in 0..=steps {
+                let (a, b) = (i as f32 * step, j as f32 * step);
+                for fixed in [0.0, 1.0] {
+                    points.extend_from_slice(&[fixed, a, b]);
+                    // Skip the rims already covered by the x faces
+                    if i > 0 && i < steps {
+                        points.extend_from_slice(&[a, fixed, b]);
+                        if j > 0 && j < steps {
+                            points.extend_from_slice(&[a, b, fixed]);
+                        }
+                    }
+                }
+            }
+        }
+        points
+    }
+
+    /// Near a cube edge: within `margin` of the boundary along at least two axes
+    fn near_edge(p: &[f32], margin: f32) -> bool {
+        p.iter().filter(|&&v| v < margin || v > 1.0 - margin).count() >= 2
+    }
+
+    #[test]
+    fn test_edges_keep_more_points_than_faces() {
+        let points = cube_surface(50);
+        let downsampled = adaptive_voxel_downsample(&points, 0.2, 0.9, 16, &mut ToolRun::default());
+
+        let fraction_kept = |edge: bool| -> f32 {
+            let input = points.chunks_exact(3).filter(|p| near_edge(p, 0.1) == edge).count();
+            let output = downsampled.chunks_exact(3).filter(|p| near_edge(p, 0.1) == edge).count();
+            output as f32 / input as f32
+        };
+        let edge_fraction = fraction_kept(true);
+        let face_fraction = fraction_kept(false);
+        assert!(
+            edge_fraction > face_fraction * 2.0,
+            "edges kept {} of their points, faces {}",
+            edge_fraction,
+            face_fraction
+        );
+
+        // With no curvature weighting every point uses the base size
+        let uniform = adaptive_voxel_downsample(&points, 0.2, 0.0, 16, &mut ToolRun::default());
+        assert!(uniform.len() < downsampled.len());
+    }
+
+    #[test]
+    fn test_density_mode_narrows_the_gap_between_dense_and_sparse_halves() {
+        // Plane with points every 0.02 for x < 1 and every 0.08 for x >= 1
+        let mut points = Vec::new();
+        for i in 0..50 {
+            for j in 0..100 {
+                points.extend_from_slice(&[i as f32 * 0.02, j as f32 * 0.02, 0.0]);
+            }
+        }
+        for i in 0..13 {
+            for j in 0..25 {
+                points.extend_from_slice(&[1.0 + i as f32 * 0.08, j as f32 * 0.08, 0.0]);
+            }
+        }
+        let per_half = |cloud: &[f32]| {
+            let dense = cloud.chunks_exact(3).filter(|p| p[0] < 0.99).count();
+            (dense, cloud.len() / 3 - dense)
+        };
+        let (dense_in, sparse_in) = per_half(&points);
+        let input_ratio = dense_in as f32 / sparse_in as f32;
+
+        let downsampled = density_voxel_downsample(&points, 0.1, 6, &mut ToolRun::default());
+        let (dense_out, sparse_out) = per_half(&downsampled);
+        let output_ratio = dense_out as f32 / sparse_out as f32;
+
+        // The sparse half is already wider than the target; away from the seam (where neighbors
+        // from the dense half pull the spacing down) it keeps every point
+        let beyond_seam = |cloud: &[f32]| cloud.chunks_exact(3).filter(|p| p[0] > 1.1).count();
+        assert_eq!(beyond_seam(&downsampled), beyond_seam(&points));
+        assert!(dense_out < dense_in / 10, "dense half kept {} of {}", dense_out, dense_in);
+        assert!(
+            output_ratio < input_ratio / 4.0,
+            "density ratio went from {} to {}",
+            input_ratio,
+            output_ratio
+        );
+    }
+
+    #[test]
+    fn test_unknown_mode_is_rejected() {
+        assert_eq!(check_mode(MODE_CURVATURE), Ok(MODE_CURVATURE));
+        assert_eq!(check_mode(MODE_DENSITY), Ok(MODE_DENSITY));
+        assert_eq!(check_mode(2).unwrap_err().code, ErrorCode::InvalidData);
+    }
+}
@@ -0,0 +1,180 @@
+use std::io::{self, Read, Write};
+
+// Byte-order helpers shared by the binary protocols of the tool executables.
+// Headers are read into fixed-size byte arrays and decoded field by field;
+// payload blocks are flat arrays of f32 (positions, colors, ...) or u8 (classifications, masks).
+//
+// The wire is little-endian unless the protocol prefix selects big-endian
+// (protocol::BIG_ENDIAN_FLAG); read_header returns the order in the ToolRun and every helper here
+// takes it explicitly. Tools that assemble output bytes themselves pass them through
+// to_wire_order.
+
+/// Byte order of everything after the protocol prefix
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ByteOrder {
+    #[default]
+    Little,
+    Big,
+}
+
+/// Convert little-endian encoded values of `width` bytes, packed back to back, to `order` in
+/// place (and back again: the conversion is its own inverse)
+pub fn to_wire_order(bytes: &mut [u8], width: usize, order: ByteOrder) {
+    if order == ByteOrder::Big && width > 1 {
+        bytes.chunks_exact_mut(width).for_each(|value| value.reverse());
+    }
+}
+
+/// The `N` bytes at `offset` in little-endian order
+fn wire_bytes<const N: usize>(buf: &[u8], offset: usize, order: ByteOrder) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    bytes.copy_from_slice(&buf[offset..offset + N]);
+    to_wire_order(&mut bytes, N, order);
+    bytes
+}
+
+/// Decode a u32 at `offset` in a header buffer
+pub fn u32_at(buf: &[u8], offset: usize, order: ByteOrder) -> u32 {
+    u32::from_le_bytes(wire_bytes(buf, offset, order))
+}
+
+/// Decode a u64 at `offset` in a header buffer
+pub fn u64_at(buf: &[u8], offset: usize, order: ByteOrder) -> u64 {
+    u64::from_le_bytes(wire_bytes(buf, offset, order))
+}
+
+/// Decode an f32 at `offset` in a header buffer
+pub fn f32_at(buf: &[u8], offset: usize, order: ByteOrder) -> f32 {
+    f32::from_le_bytes(wire_bytes(buf, offset, order))
+}
+
+/// Read `count` f32 values
+pub fn read_f32_vec<R: Read>(reader: &mut R, count: usize, order: ByteOrder) -> io::Result<Vec<f32>> {
+    let mut buf = vec![0u8; count * 4];
+    reader.read_exact(&mut buf)?;
+    to_wire_order(&mut buf, 4, order);
+    Ok(buf
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect())
+}
+
+/// Read `count` raw bytes (classifications, masks)
+pub fn read_u8_vec<R: Read>(reader: &mut R, count: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; count];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Write a slice of f32 in a single write
+pub fn write_f32_slice<W: Write>(writer: &mut W, values: &[f32], order: ByteOrder) -> io::Result<()> {
+    let mut bytes: Vec<u8> = values.iter().flat_map(|&f| f.to_le_bytes()).collect();
+    to_wire_order(&mut bytes, 4, order);
+    writer.write_all(&bytes)
+}
+
+/// Write a slice of u32 (indices, ids) in a single write
+pub fn write_u32_slice<W: Write>(writer: &mut W, values: &[u32], order: ByteOrder) -> io::Result<()> {
+    let mut bytes: Vec<u8> = values.iter().flat_map(|&v| v.to_le_bytes()).collect();
+    to_wire_order(&mut bytes, 4, order);
+    writer.write_all(&bytes)
+}
+
+/// Write a u32 count field
+pub fn write_u32<W: Write>(writer: &mut W, value: u32, order: ByteOrder) -> io::Result<()> {
+    let mut bytes = value.to_le_bytes();
+    to_wire_order(&mut bytes, 4, order);
+    writer.write_all(&bytes)
+}
+
+/// Write a u64 field
+pub fn write_u64<W: Write>(writer: &mut W, value: u64, order: ByteOrder) -> io::Result<()> {
+    let mut bytes = value.to_le_bytes();
+    to_wire_order(&mut bytes, 8, order);
+    writer.write_all(&bytes)
+}
+
+/// Position scalar of the binary protocols: f32 by default, f64 for tools that accept a
+/// precision flag (large georeferenced coordinates lose centimeters in f32)
+pub trait LeFloat: Copy + Default + std::ops::AddAssign {
+    const SIZE: usize;
+    fn from_le_slice(bytes: &[u8]) -> Self;
+    fn extend_le_bytes(self, out: &mut Vec<u8>);
+    fn to_f64(self) -> f64;
+    fn from_f64(value: f64) -> Self;
+}
+
+impl LeFloat for f32 {
+    const SIZE: usize = 4;
+    fn from_le_slice(bytes: &[u8]) -> Self {
+        f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+    fn extend_le_bytes(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
+
+impl LeFloat for f64 {
+    const SIZE: usize = 8;
+    fn from_le_slice(bytes: &[u8]) -> Self {
+        let mut b = [0u8; 8];
+        b.copy_from_slice(&bytes[..8]);
+        f64::from_le_bytes(b)
+    }
+    fn extend_le_bytes(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn to_f64(self) -> f64 {
+        self
+    }
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+/// Read `count` values of either precision
+pub fn read_float_vec<T: LeFloat, R: Read>(reader: &mut R, count: usize, order: ByteOrder) -> io::Result<Vec<T>> {
+    let mut buf = vec![0u8; count * T::SIZE];
+    reader.read_exact(&mut buf)?;
+    to_wire_order(&mut buf, T::SIZE, order);
+    Ok(buf.chunks_exact(T::SIZE).map(T::from_le_slice).collect())
+}
+
+/// Read up to `max_count` values into `out` (replacing its contents), stopping
+/// early at end of input; returns how many were read. Input ending inside a value is an
+/// UnexpectedEof error. Lets tools consume a payload of unknown length in bounded chunks.
+pub fn read_float_chunk<T: LeFloat, R: Read>(reader: &mut R, max_count: usize, out: &mut Vec<T>, order: ByteOrder) -> io::Result<usize> {
+    let mut buf = vec![0u8; max_count * T::SIZE];
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if filled % T::SIZE != 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "input ends inside a value"));
+    }
+    to_wire_order(&mut buf[..filled], T::SIZE, order);
+    out.clear();
+    out.extend(buf[..filled].chunks_exact(T::SIZE).map(T::from_le_slice));
+    Ok(out.len())
+}
+
+/// Write a slice of either precision in a single write
+pub fn write_float_slice<T: LeFloat, W: Write>(writer: &mut W, values: &[T], order: ByteOrder) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(values.len() * T::SIZE);
+    for &v in values {
+        v.extend_le_bytes(&mut bytes);
+    }
+    to_wire_order(&mut bytes, T::SIZE, order);
+    writer.write_all(&bytes)
+}
@@ -0,0 +1,139 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+
+// Approximate minimum enclosing sphere (Ritter's algorithm), for camera framing and LOD selection.
+// Pass one finds the extreme points along each axis and starts from the sphere spanning the most
+// distant pair; pass two grows the sphere just enough to take in every point still outside it.
+// The result encloses every point and is typically within a few percent of the minimum radius.
+// Points with a non-finite coordinate are ignored.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32* positions]
+// Output format: [f32 centerX][f32 centerY][f32 centerZ][f32 radius]
+// An empty cloud (or one without finite points) gives radius -1, the empty sphere for crop_sphere_rust.
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (4 bytes: u32)
+    let (header, run): ([u8; 4], _) = match read_tool_header(&mut stdin, tool_id::BOUNDING_SPHERE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let (center, radius) = bounding_sphere(&positions).unwrap_or(([0.0; 3], -1.0));
+
+    let mut stdout = io::stdout();
+    if write_f32_slice(&mut stdout, &[center[0], center[1], center[2], radius], run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, 1).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Ritter's bounding sphere of a flat xyz array as (center, radius); None without finite points.
+/// Two passes over the points, arithmetic in f64.
+fn bounding_sphere(positions: &[f32]) -> Option<([f32; 3], f32)> {
+    let finite = || positions.chunks_exact(3).filter(|p| is_finite_point(p)).map(|p| [p[0] as f64, p[1] as f64, p[2] as f64]);
+
+    // Pass 1: lowest and highest point along each axis
+    let first = finite().next()?;
+    let mut lowest = [first; 3];
+    let mut highest = [first; 3];
+    for p in finite() {
+        for axis in 0..3 {
+            if p[axis] < lowest[axis][axis] {
+                lowest[axis] = p;
+            }
+            if p[axis] > highest[axis][axis] {
+                highest[axis] = p;
+            }
+        }
+    }
+    let (a, b) = (0..3)
+        .map(|axis| (lowest[axis], highest[axis]))
+        .max_by(|x, y| distance_squared(x.0, x.1).total_cmp(&distance_squared(y.0, y.1)))?;
+    let mut center = [0, 1, 2].map(|i| (a[i] + b[i]) * 0.5);
+    let mut radius = distance_squared(a, b).sqrt() * 0.5;
+
+    // Pass 2: move the far side of the sphere out to each point left outside, keeping the near
+    // side where it is
+    for p in finite() {
+        let d = distance_squared(p, center).sqrt();
+        if d > radius {
+            let new_radius = (radius + d) * 0.5;
+            let shift = (new_radius - radius) / d;
+            for axis in 0..3 {
+                center[axis] += (p[axis] - center[axis]) * shift;
+            }
+            radius = new_radius;
+        }
+    }
+
+    // Pad the radius by the distance rounding the center to f32 can move it, then round up,
+    // so the f32 sphere still contains every point
+    let center_error = center.iter().fold(0.0f64, |m, c| m.max(c.abs())) * f32::EPSILON as f64;
+    Some((center.map(|c| c as f32), next_up((radius + center_error) as f32)))
+}
+
+fn distance_squared(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+/// Smallest f32 above a non-negative finite value
+fn next_up(value: f32) -> f32 {
+    f32::from_bits(value.to_bits() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_points_on_a_sphere() {
+        let center = [2.0f32, -1.0, 0.5];
+        let radius = 3.0f32;
+        let mut positions = Vec::new();
+        // Fibonacci lattice on the sphere
+        let n = 500;
+        for i in 0..n {
+            let z = 1.0 - 2.0 * (i as f32 + 0.5) / n as f32;
+            let r = (1.0 - z * z).sqrt();
+            let phi = i as f32 * 2.399_963;
+            positions.extend_from_slice(&[
+                center[0] + radius * r * phi.cos(),
+                center[1] + radius * r * phi.sin(),
+                center[2] + radius * z,
+            ]);
+        }
+
+        let (found_center, found_radius) = bounding_sphere(&positions).unwrap();
+        for axis in 0..3 {
+            assert!((found_center[axis] - center[axis]).abs() < 0.1, "center {:?}", found_center);
+        }
+        assert!((found_radius - radius).abs() < 0.1, "radius {}", found_radius);
+        for p in positions.chunks_exact(3) {
+            let d = ((p[0] - found_center[0]).powi(2) + (p[1] - found_center[1]).powi(2) + (p[2] - found_center[2]).powi(2)).sqrt();
+            assert!(d <= found_radius);
+        }
+    }
+
+    #[test]
+    fn test_single_point_and_empty_cloud() {
+        let (center, radius) = bounding_sphere(&[1.0, 2.0, 3.0, f32::NAN, 0.0, 0.0]).unwrap();
+        assert_eq!(center, [1.0, 2.0, 3.0]);
+        assert!((0.0..1e-6).contains(&radius));
+        assert_eq!(bounding_sphere(&[]), None);
+        assert_eq!(bounding_sphere(&[f32::INFINITY, 0.0, 0.0]), None);
+    }
+}
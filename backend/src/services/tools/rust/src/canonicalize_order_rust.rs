@@ -0,0 +1,159 @@
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, gather_positions, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError};
+
+// Stable global point ordering so identical clouds serialize to identical bytes.
+// Points are sorted by quantized position (x, then y, then z cell), ties broken by the exact
+// position and then by colors, intensity and classification.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 quantization][u32 flags][f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit31=drop non-finite points
+// Output format: [u32 pointCount][f32* positions][optional colors][optional intensities][optional classifications]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: u32 + f32 + u32)
+    let (header, run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::CANONICALIZE_ORDER) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let quantization = f32_at(&header, 4, run.order);
+    let flags = u32_at(&header, 8, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || quantization <= 0.0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+
+    let order = canonical_order(&positions, &attributes, quantization);
+    let sorted_positions = gather_positions(&positions, &order);
+    let sorted_attributes = attributes.gather(&order);
+
+    if write_u32(&mut stdout, order.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &sorted_positions, run.order).is_err()
+        || sorted_attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, order.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+fn compare_f32_slices(a: &[f32], b: &[f32]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| x.total_cmp(y))
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Permutation of point indices in canonical order
+fn canonical_order(positions: &[f32], attributes: &PointAttributes, quantization: f32) -> Vec<usize> {
+    let point_count = positions.len() / 3;
+    let inv_quantization = 1.0 / quantization;
+    let keys: Vec<[i64; 3]> = positions
+        .chunks_exact(3)
+        .map(|p| {
+            [
+                (p[0] * inv_quantization).floor() as i64,
+                (p[1] * inv_quantization).floor() as i64,
+                (p[2] * inv_quantization).floor() as i64,
+            ]
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..point_count).collect();
+    order.sort_by(|&a, &b| {
+        keys[a]
+            .cmp(&keys[b])
+            .then_with(|| compare_f32_slices(&positions[a * 3..a * 3 + 3], &positions[b * 3..b * 3 + 3]))
+            .then_with(|| match &attributes.colors {
+                Some(c) => compare_f32_slices(&c[a * 3..a * 3 + 3], &c[b * 3..b * 3 + 3]),
+                None => Ordering::Equal,
+            })
+            .then_with(|| match &attributes.intensities {
+                Some(v) => v[a].total_cmp(&v[b]),
+                None => Ordering::Equal,
+            })
+            .then_with(|| match &attributes.classifications {
+                Some(v) => v[a].cmp(&v[b]),
+                None => Ordering::Equal,
+            })
+    });
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serialize(positions: &[f32], attributes: &PointAttributes, quantization: f32) -> Vec<u8> {
+        let order = canonical_order(positions, attributes, quantization);
+        let mut bytes = Vec::new();
+        write_f32_slice(&mut bytes, &gather_positions(positions, &order), ByteOrder::Little).unwrap();
+        attributes.gather(&order).write(&mut bytes, ByteOrder::Little).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_shuffled_copies_produce_identical_output() {
+        let mut positions = Vec::new();
+        let mut colors = Vec::new();
+        let mut classifications = Vec::new();
+        for i in 0..50 {
+            let t = i as f32;
+            positions.extend_from_slice(&[(t * 0.7).sin(), (t * 1.3).cos(), t * 0.01]);
+            colors.extend_from_slice(&[t / 50.0, 1.0 - t / 50.0, 0.5]);
+            classifications.push((i % 4) as u8);
+        }
+        // Two points sharing a position, distinguished only by color
+        positions.extend_from_slice(&[0.25, 0.25, 0.25, 0.25, 0.25, 0.25]);
+        colors.extend_from_slice(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        classifications.extend_from_slice(&[1, 1]);
+        let point_count = positions.len() / 3;
+
+        let original = PointAttributes {
+            colors: Some(colors),
+            intensities: None,
+            classifications: Some(classifications),
+        };
+
+        // Deterministic shuffle: stride permutation (7 is coprime with 52)
+        let shuffle: Vec<usize> = (0..point_count).map(|i| (i * 7) % point_count).collect();
+        let shuffled_positions = gather_positions(&positions, &shuffle);
+        let shuffled = original.gather(&shuffle);
+
+        let a = serialize(&positions, &original, 0.1);
+        let b = serialize(&shuffled_positions, &shuffled, 0.1);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_order_follows_quantized_position() {
+        let positions = vec![1.0, 0.0, 0.0, 0.0, 5.0, 0.0, 0.0, 0.0, 0.0];
+        let order = canonical_order(&positions, &PointAttributes::default(), 1.0);
+        assert_eq!(order, vec![2, 1, 0]);
+    }
+}
@@ -0,0 +1,106 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_u32, ByteOrder};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, read_u8_payload, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Semantic edge flag: a point lies on a class boundary when any neighbor within the radius
+// carries a different classification code (e.g. road/sidewalk transitions).
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 radius][f32* positions][u8* classifications]
+// Output format: [u32 pointCount][u8* mask] (1 = class boundary, 0 = interior)
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: u32 + f32)
+    let (header, mut run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::CLASS_BOUNDARY) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let radius = f32_at(&header, 4, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || radius <= 0.0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let classifications = match read_u8_payload(&mut stdin, point_count) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let mask = class_boundary_mask(&positions, &classifications, radius, &mut run);
+
+    if write_u32(&mut stdout, mask.len() as u32, run.order).is_err()
+        || stdout.write_all(&mask).is_err()
+        || write_stats(&mut stdout, &run, point_count, mask.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+fn class_boundary_mask(positions: &[f32], classifications: &[u8], radius: f32, run: &mut ToolRun) -> Vec<u8> {
+    let point_count = positions.len() / 3;
+    let grid = SpatialGrid::new(positions, radius);
+    run.note_map_size(grid.cell_count());
+    let mut mask = vec![0u8; point_count];
+
+    for (i, flag) in mask.iter_mut().enumerate() {
+        let i3 = i * 3;
+        let class = classifications[i];
+        let mut boundary = false;
+        grid.for_each_in_radius(positions, positions[i3], positions[i3 + 1], positions[i3 + 2], radius, |j, _| {
+            boundary |= classifications[j] != class;
+        });
+        *flag = u8::from(boundary);
+    }
+
+    mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interface_flagged_interior_not() {
+        // 20 x 5 sheet: road (class 11) for x < 1.0, sidewalk (class 12) beyond
+        let mut positions = Vec::new();
+        let mut classifications = Vec::new();
+        for i in 0..20 {
+            for j in 0..5 {
+                let x = i as f32 * 0.1;
+                positions.extend_from_slice(&[x, j as f32 * 0.1, 0.0]);
+                classifications.push(if x < 1.0 { 11 } else { 12 });
+            }
+        }
+
+        let mask = class_boundary_mask(&positions, &classifications, 0.15, &mut ToolRun::default());
+
+        for (p, &m) in mask.iter().enumerate() {
+            let x = positions[p * 3];
+            // Only the columns x = 0.9 and x = 1.0 have a neighbor of the other class
+            let expected = u8::from((x - 0.95).abs() < 0.1);
+            assert_eq!(m, expected, "point at x={} flagged {}", x, m);
+        }
+    }
+
+    #[test]
+    fn test_single_class_has_no_boundary() {
+        let positions = vec![0.0, 0.0, 0.0, 0.1, 0.0, 0.0, 0.0, 0.1, 0.0];
+        let mask = class_boundary_mask(&positions, &[2, 2, 2], 0.5, &mut ToolRun::default());
+        assert_eq!(mask, vec![0, 0, 0]);
+    }
+}
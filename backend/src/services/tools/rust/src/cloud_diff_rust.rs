@@ -0,0 +1,118 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::point_attributes::{gather_positions, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, ToolError, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Change detection between two scans: the target points with no reference point within
+// tolerance, i.e. what is new or has moved. The reference cloud is bucketed into a spatial grid
+// with cell size tolerance, so each target point checks only the neighboring cells. Kept points
+// stay in target order with their attributes. Target points with a NaN or infinite coordinate are
+// never kept; reference points with one are ignored.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 referenceCount][u32 targetCount][f32 tolerance][u32 flags]
+//               [f32* referencePositions][f32* targetPositions]
+//               [optional target colors][optional target intensities][optional target classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification (all for the target cloud)
+// Output format: [u32 outputCount][f32* positions][optional colors][optional intensities][optional classifications]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (16 bytes: 2 * u32 + f32 + u32)
+    let (header, mut run): ([u8; 16], _) = match read_tool_header(&mut stdin, tool_id::CLOUD_DIFF) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let reference_count = u32_at(&header, 0, run.order) as usize;
+    let target_count = u32_at(&header, 4, run.order) as usize;
+    let tolerance = f32_at(&header, 8, run.order);
+    let flags = u32_at(&header, 12, run.order);
+
+    if !(tolerance > 0.0 && tolerance.is_finite()) {
+        ToolError::new(ErrorCode::InvalidData, "tolerance must be positive and finite").exit(run.order);
+    }
+
+    let reference = match read_f32_payload(&mut stdin, reference_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let target = match read_f32_payload(&mut stdin, target_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let attributes = match PointAttributes::read(&mut stdin, target_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+
+    let kept = cloud_diff(&reference, &target, tolerance, &mut run);
+    let kept_positions = gather_positions(&target, &kept);
+    let kept_attributes = attributes.gather(&kept);
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, kept.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &kept_positions, run.order).is_err()
+        || kept_attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, reference_count + target_count, kept.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Indices of the finite target points farther than `tolerance` from every reference point
+fn cloud_diff(reference: &[f32], target: &[f32], tolerance: f32, run: &mut ToolRun) -> Vec<usize> {
+    let grid = SpatialGrid::new(reference, tolerance);
+    run.note_map_size(grid.cell_count());
+    target
+        .chunks_exact(3)
+        .enumerate()
+        .filter(|(_, p)| is_finite_point(p))
+        .filter(|(_, p)| {
+            let mut matched = false;
+            grid.for_each_in_radius(reference, p[0], p[1], p[2], tolerance, |_, _| matched = true);
+            !matched
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_the_extra_points_are_returned() {
+        let reference: Vec<f32> = (0..2000)
+            .flat_map(|i| {
+                let t = i as f32;
+                [(t * 0.37).sin() * 5.0, (t * 0.11).cos() * 5.0, (t * 0.05).sin()]
+            })
+            .collect();
+
+        // The reference again, slightly perturbed, with extras spliced in at known positions
+        let mut target = Vec::new();
+        let mut extras = Vec::new();
+        for (i, p) in reference.chunks_exact(3).enumerate() {
+            target.extend_from_slice(&[p[0] + 0.001, p[1] - 0.001, p[2]]);
+            if i % 500 == 250 {
+                extras.push(target.len() / 3);
+                target.extend_from_slice(&[20.0 + i as f32 * 0.01, 0.0, 0.0]);
+            }
+        }
+        assert_eq!(cloud_diff(&reference, &target, 0.01, &mut ToolRun::default()), extras);
+    }
+
+    #[test]
+    fn test_tolerance_boundary_and_non_finite_points() {
+        let reference = vec![0.0, 0.0, 0.0, f32::NAN, 0.0, 0.0];
+        let target = vec![0.5, 0.0, 0.0, 0.75, 0.0, 0.0, f32::INFINITY, 0.0, 0.0];
+        // A point exactly at tolerance counts as matched
+        assert_eq!(cloud_diff(&reference, &target, 0.5, &mut ToolRun::default()), vec![1]);
+        assert_eq!(cloud_diff(&[], &target, 0.5, &mut ToolRun::default()), vec![0, 1]);
+    }
+}
@@ -0,0 +1,171 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// How well two clouds align, for registration QA: the mean, RMS and maximum (directed Hausdorff
+// distance) of the distances from each source point to its nearest target point. The target is
+// bucketed into a spatial grid sized from its own density, as in knn_rust. With bit0 the
+// measure is symmetric: each statistic is computed in both directions and the larger one
+// reported, so the maximum is the Hausdorff distance. Points with a NaN or infinite coordinate
+// are ignored on both sides.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 sourceCount][u32 targetCount][u32 flags][f32* sourcePositions][f32* targetPositions]
+// flags: bit0=symmetric
+// Output format: [f32 mean][f32 rms][f32 max]
+// Without finite points on either side all three are -1.
+
+const FLAG_SYMMETRIC: u32 = 1;
+
+/// Nearest-neighbor distance statistics
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct DistanceStats {
+    mean: f32,
+    rms: f32,
+    max: f32,
+}
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: 3 * u32)
+    let (header, mut run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::CLOUD_DISTANCE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let source_count = u32_at(&header, 0, run.order) as usize;
+    let target_count = u32_at(&header, 4, run.order) as usize;
+    let flags = u32_at(&header, 8, run.order);
+
+    let source = match read_f32_payload(&mut stdin, source_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let target = match read_f32_payload(&mut stdin, target_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let stats = if flags & FLAG_SYMMETRIC != 0 {
+        symmetric_distance(&source, &target, &mut run)
+    } else {
+        directed_distance(&source, &target, &mut run)
+    };
+    let stats = stats.unwrap_or(DistanceStats { mean: -1.0, rms: -1.0, max: -1.0 });
+
+    let mut stdout = io::stdout();
+    if write_f32_slice(&mut stdout, &[stats.mean, stats.rms, stats.max], run.order).is_err()
+        || write_stats(&mut stdout, &run, source_count + target_count, 1).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Statistics of the distances from every finite source point to its nearest target point;
+/// None when either side has no finite point
+fn directed_distance(source: &[f32], target: &[f32], run: &mut ToolRun) -> Option<DistanceStats> {
+    let grid = SpatialGrid::new(target, SpatialGrid::auto_cell_size(target));
+    run.note_map_size(grid.cell_count());
+    run.note_map_size(grid.cell_count());
+    let (mut sum, mut sum_squared, mut max, mut count) = (0.0f64, 0.0f64, 0.0f64, 0usize);
+    for p in source.chunks_exact(3).filter(|p| is_finite_point(p)) {
+        let (_, distance_squared) = grid.nearest(target, p[0], p[1], p[2])?;
+        let distance = (distance_squared as f64).sqrt();
+        sum += distance;
+        sum_squared += distance_squared as f64;
+        max = max.max(distance);
+        count += 1;
+    }
+    if count == 0 {
+        return None;
+    }
+    Some(DistanceStats {
+        mean: (sum / count as f64) as f32,
+        rms: (sum_squared / count as f64).sqrt() as f32,
+        max: max as f32,
+    })
+}
+
+/// Larger of the two directed statistics, per statistic
+fn symmetric_distance(source: &[f32], target: &[f32], run: &mut ToolRun) -> Option<DistanceStats> {
+    let forward = directed_distance(source, target, run)?;
+    let backward = directed_distance(target, source, run)?;
+    Some(DistanceStats {
+        mean: forward.mean.max(backward.mean),
+        rms: forward.rms.max(backward.rms),
+        max: forward.max.max(backward.max),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translated_copy_is_the_translation_away() {
+        // Grid with 1.0 spacing shifted by (0.03, 0.04, 0): every nearest neighbor is the
+        // point's own copy at distance 0.05
+        let mut source = Vec::new();
+        for i in 0..15 {
+            for j in 0..15 {
+                for k in 0..4 {
+                    source.extend_from_slice(&[i as f32, j as f32, k as f32]);
+                }
+            }
+        }
+        let target: Vec<f32> = source.chunks_exact(3).flat_map(|p| [p[0] + 0.03, p[1] + 0.04, p[2]]).collect();
+
+        for stats in [directed_distance(&source, &target, &mut ToolRun::default()).unwrap(), symmetric_distance(&source, &target, &mut ToolRun::default()).unwrap()] {
+            assert!((stats.mean - 0.05).abs() < 1e-5, "{:?}", stats);
+            assert!((stats.rms - 0.05).abs() < 1e-5, "{:?}", stats);
+            assert!((stats.max - 0.05).abs() < 1e-5, "{:?}", stats);
+        }
+    }
+
+    #[test]
+    fn test_clouds_far_apart_match_brute_force() {
+        // Source 20 units off the dense target along every axis, the unaligned case this tool is for
+        let mut target = Vec::new();
+        for i in 0..30 {
+            for j in 0..30 {
+                for k in 0..30 {
+                    target.extend_from_slice(&[i as f32 * 0.25, j as f32 * 0.25, k as f32 * 0.125]);
+                }
+            }
+        }
+        let source: Vec<f32> = (0..40).flat_map(|i| [20.0 + i as f32 * 0.5, 27.0, -20.0 + i as f32 * 0.25]).collect();
+
+        let stats = directed_distance(&source, &target, &mut ToolRun::default()).unwrap();
+        let distances: Vec<f64> = source
+            .chunks_exact(3)
+            .map(|p| {
+                target
+                    .chunks_exact(3)
+                    .map(|q| (0..3).map(|a| ((p[a] - q[a]) as f64).powi(2)).sum::<f64>())
+                    .fold(f64::INFINITY, f64::min)
+                    .sqrt()
+            })
+            .collect();
+        let mean = distances.iter().sum::<f64>() / distances.len() as f64;
+        let max = distances.iter().cloned().fold(0.0, f64::max);
+        assert!((stats.mean as f64 - mean).abs() < 1e-3, "{:?} vs {}", stats, mean);
+        assert!((stats.max as f64 - max).abs() < 1e-3, "{:?} vs {}", stats, max);
+    }
+
+    #[test]
+    fn test_symmetric_catches_points_missing_from_the_source() {
+        // The target has an extra point 10 away that the directed measure cannot see
+        let source = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+        let target = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 11.0, 0.0, 0.0, f32::NAN, 0.0, 0.0];
+        assert_eq!(directed_distance(&source, &target, &mut ToolRun::default()), Some(DistanceStats { mean: 0.0, rms: 0.0, max: 0.0 }));
+        let symmetric = symmetric_distance(&source, &target, &mut ToolRun::default()).unwrap();
+        assert_eq!(symmetric.max, 10.0);
+        assert!((symmetric.mean - 10.0 / 3.0).abs() < 1e-6);
+        assert_eq!(directed_distance(&source, &[], &mut ToolRun::default()), None);
+        assert_eq!(directed_distance(&[f32::NAN, 0.0, 0.0], &target, &mut ToolRun::default()), None);
+    }
+}
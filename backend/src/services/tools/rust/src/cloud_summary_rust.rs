@@ -0,0 +1,101 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::{is_finite_point, Bounds};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+
+// Lightweight summary of a cloud: centroid, axis-aligned bounds and the number of
+// points they cover, so the frontend can size the camera and grid without streaming the cloud
+// into JS. The centroid is summed in f64. Points with a non-finite coordinate are ignored and
+// not counted.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32* positions]
+// Output format: [f32 centroidX][f32 centroidY][f32 centroidZ][f32 minX][f32 minY][f32 minZ]
+//                [f32 maxX][f32 maxY][f32 maxZ][u32 finiteCount]
+// An empty cloud (or one without finite points) gives all zeros.
+
+struct CloudSummary {
+    centroid: [f32; 3],
+    bounds: Bounds,
+    count: usize,
+}
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (4 bytes: u32)
+    let (header, run): ([u8; 4], _) = match read_tool_header(&mut stdin, tool_id::CLOUD_SUMMARY) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let summary = cloud_summary(&positions);
+
+    let mut values = Vec::with_capacity(9);
+    values.extend_from_slice(&summary.centroid);
+    values.extend_from_slice(&summary.bounds.min);
+    values.extend_from_slice(&summary.bounds.max);
+
+    let mut stdout = io::stdout();
+    if write_f32_slice(&mut stdout, &values, run.order).is_err()
+        || write_u32(&mut stdout, summary.count as u32, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, 1).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Centroid, bounds and count of the finite points of a flat xyz array; zeros without any
+fn cloud_summary(positions: &[f32]) -> CloudSummary {
+    let bounds = match Bounds::from_points(positions) {
+        Some(bounds) => bounds,
+        None => return CloudSummary { centroid: [0.0; 3], bounds: Bounds { min: [0.0; 3], max: [0.0; 3] }, count: 0 },
+    };
+
+    let mut sum = [0.0f64; 3];
+    let mut count = 0;
+    for p in positions.chunks_exact(3).filter(|p| is_finite_point(p)) {
+        for (a, &v) in p.iter().enumerate() {
+            sum[a] += v as f64;
+        }
+        count += 1;
+    }
+    CloudSummary { centroid: sum.map(|s| (s / count as f64) as f32), bounds, count }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_centroid_matches_manual_average() {
+        let positions = vec![1.0, 2.0, 3.0, -4.0, 0.5, 7.0, 2.5, -1.5, 0.0, 0.5, 3.0, -2.0, f32::NAN, 0.0, 0.0];
+        let summary = cloud_summary(&positions);
+        assert_eq!(summary.count, 4);
+
+        let finite = &positions[..12];
+        for a in 0..3 {
+            let average: f32 = finite.iter().skip(a).step_by(3).sum::<f32>() / 4.0;
+            assert!((summary.centroid[a] - average).abs() < 1e-6, "axis {}: {} vs {}", a, summary.centroid[a], average);
+        }
+        assert_eq!(Some(summary.bounds), Bounds::from_points(&positions));
+    }
+
+    #[test]
+    fn test_empty_cloud_is_all_zeros() {
+        for positions in [vec![], vec![f32::INFINITY, 0.0, 0.0]] {
+            let summary = cloud_summary(&positions);
+            assert_eq!(summary.count, 0);
+            assert_eq!(summary.centroid, [0.0; 3]);
+            assert_eq!(summary.bounds, Bounds { min: [0.0; 3], max: [0.0; 3] });
+        }
+    }
+}
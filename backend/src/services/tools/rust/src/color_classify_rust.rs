@@ -0,0 +1,128 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, ToolError};
+
+// Turns RGB-labeled clouds into class ids for the downsampler's majority-vote path: each point's
+// classification is the index of the nearest palette color (squared RGB distance). Ties go to
+// the lower palette index. The palette holds 1..=256 colors so every index fits in a byte.
+// A NaN or infinite component has no nearest color, so palettes or points with one are rejected.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 paletteCount][f32* paletteColors (r, g, b)][f32* colors (r, g, b per point)]
+// Output format: [u32 pointCount][u8* classifications]
+
+const MAX_PALETTE_COLORS: usize = 256;
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: 2 * u32)
+    let (header, run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::COLOR_CLASSIFY) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let palette_count = u32_at(&header, 4, run.order) as usize;
+
+    if palette_count == 0 || palette_count > MAX_PALETTE_COLORS {
+        ToolError::new(
+            ErrorCode::InvalidData,
+            format!("palette must hold 1 to {} colors, got {}", MAX_PALETTE_COLORS, palette_count),
+        )
+        .exit(run.order);
+    }
+
+    let palette = match read_f32_payload(&mut stdin, palette_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let colors = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    if let Err(e) = check_finite_colors(&palette, &colors) {
+        e.exit(run.order);
+    }
+    let classifications = classify_by_color(&colors, &palette);
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || stdout.write_all(&classifications).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Every palette and point color must be finite
+fn check_finite_colors(palette: &[f32], colors: &[f32]) -> Result<(), ToolError> {
+    if let Some(index) = palette.chunks_exact(3).position(|c| !is_finite_point(c)) {
+        return Err(ToolError::new(ErrorCode::InvalidData, format!("palette color {} is not finite", index)));
+    }
+    if let Some(index) = colors.chunks_exact(3).position(|c| !is_finite_point(c)) {
+        return Err(ToolError::new(ErrorCode::InvalidData, format!("color of point {} is not finite", index)));
+    }
+    Ok(())
+}
+
+/// Index of the nearest palette color for each point (lowest index on ties)
+fn classify_by_color(colors: &[f32], palette: &[f32]) -> Vec<u8> {
+    colors
+        .chunks_exact(3)
+        .map(|c| {
+            let mut best = 0;
+            let mut best_distance = f32::INFINITY;
+            for (index, p) in palette.chunks_exact(3).enumerate() {
+                let distance = (c[0] - p[0]).powi(2) + (c[1] - p[1]).powi(2) + (c[2] - p[2]).powi(2);
+                if distance < best_distance {
+                    best = index;
+                    best_distance = distance;
+                }
+            }
+            best as u8
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PALETTE: [f32; 12] = [
+        0.0, 0.0, 0.0, // 0: black
+        1.0, 0.0, 0.0, // 1: red
+        0.0, 1.0, 0.0, // 2: green
+        0.2, 0.4, 1.0, // 3: blue-ish
+    ];
+
+    #[test]
+    fn test_exact_palette_colors_get_their_index() {
+        let colors: Vec<f32> = [3, 1, 0, 2, 2, 3].iter().flat_map(|&i| PALETTE[i * 3..i * 3 + 3].to_vec()).collect();
+        assert_eq!(classify_by_color(&colors, &PALETTE), vec![3, 1, 0, 2, 2, 3]);
+    }
+
+    #[test]
+    fn test_nearby_and_midpoint_colors() {
+        let colors = vec![
+            0.9, 0.1, 0.05, // close to red
+            0.1, 0.7, 0.5, // exactly halfway between green and blue-ish: lower index wins
+            0.25, 0.35, 0.9, // close to blue-ish
+        ];
+        assert_eq!(classify_by_color(&colors, &PALETTE), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_non_finite_colors_rejected() {
+        assert!(check_finite_colors(&PALETTE, &[0.5, 0.5, 0.5]).is_ok());
+        let error = check_finite_colors(&PALETTE, &[0.5, 0.5, 0.5, 0.1, f32::NAN, 0.2]).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidData);
+        assert_eq!(error.message, "color of point 1 is not finite");
+        let mut palette = PALETTE;
+        palette[5] = f32::INFINITY;
+        assert_eq!(check_finite_colors(&palette, &[]).unwrap_err().message, "palette color 1 is not finite");
+    }
+}
@@ -0,0 +1,137 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_u32, ByteOrder};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 radius][f32 varianceThreshold][f32* positions][f32* colors]
+// Output format: [u32 pointCount][u8* mask] (1 = color edge, preserve; 0 = uniform, safe to smooth)
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: u32 + 2 * f32)
+    let (header, mut run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::COLOR_EDGE_MASK) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let radius = f32_at(&header, 4, run.order);
+    let variance_threshold = f32_at(&header, 8, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || radius <= 0.0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let colors = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let mask = color_edge_mask(&positions, &colors, radius, variance_threshold, &mut run);
+
+    if write_u32(&mut stdout, mask.len() as u32, run.order).is_err()
+        || stdout.write_all(&mask).is_err()
+        || write_stats(&mut stdout, &run, point_count, mask.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Per-point color variance over the radius neighborhood (point itself included),
+/// summed over the r, g, b channels
+fn color_variance(positions: &[f32], colors: &[f32], radius: f32, run: &mut ToolRun) -> Vec<f32> {
+    let point_count = positions.len() / 3;
+    let grid = SpatialGrid::new(positions, radius);
+    run.note_map_size(grid.cell_count());
+    let mut variances = vec![0.0f32; point_count];
+
+    for (i, variance) in variances.iter_mut().enumerate() {
+        let i3 = i * 3;
+        let mut sum = [0.0f32; 3];
+        let mut sum_sq = [0.0f32; 3];
+        let mut count = 0usize;
+        grid.for_each_in_radius(positions, positions[i3], positions[i3 + 1], positions[i3 + 2], radius, |j, _| {
+            let j3 = j * 3;
+            for c in 0..3 {
+                let v = colors[j3 + c];
+                sum[c] += v;
+                sum_sq[c] += v * v;
+            }
+            count += 1;
+        });
+
+        let n = count.max(1) as f32;
+        *variance = (0..3)
+            .map(|c| {
+                let mean = sum[c] / n;
+                (sum_sq[c] / n - mean * mean).max(0.0)
+            })
+            .sum();
+    }
+
+    variances
+}
+
+/// Mark points whose local color variance exceeds `variance_threshold` as edges (1);
+/// everything else is uniform (0) and can be smoothed without bleeding across color boundaries
+fn color_edge_mask(positions: &[f32], colors: &[f32], radius: f32, variance_threshold: f32, run: &mut ToolRun) -> Vec<u8> {
+    color_variance(positions, colors, radius, run)
+        .into_iter()
+        .map(|v| u8::from(v > variance_threshold))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_boundary_flagged_as_edge() {
+        // 20 x 5 sheet of points, red for x < 1.0 and blue for x >= 1.0
+        let mut positions = Vec::new();
+        let mut colors = Vec::new();
+        for i in 0..20 {
+            for j in 0..5 {
+                let x = i as f32 * 0.1;
+                positions.extend_from_slice(&[x, j as f32 * 0.1, 0.0]);
+                if x < 1.0 {
+                    colors.extend_from_slice(&[1.0, 0.0, 0.0]);
+                } else {
+                    colors.extend_from_slice(&[0.0, 0.0, 1.0]);
+                }
+            }
+        }
+
+        let mask = color_edge_mask(&positions, &colors, 0.15, 0.01, &mut ToolRun::default());
+
+        for (p, &m) in mask.iter().enumerate() {
+            let x = positions[p * 3];
+            if (x - 0.95).abs() < 0.1 {
+                // Columns x = 0.9 and x = 1.0 straddle the boundary
+                assert_eq!(m, 1, "boundary point at x={} should be an edge", x);
+            } else if !(0.75..=1.25).contains(&x) {
+                assert_eq!(m, 0, "uniform point at x={} should not be an edge", x);
+            }
+        }
+    }
+
+    #[test]
+    fn test_uniform_color_has_zero_variance() {
+        let positions = vec![0.0, 0.0, 0.0, 0.1, 0.0, 0.0, 0.0, 0.1, 0.0];
+        let colors = vec![0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5];
+        let variances = color_variance(&positions, &colors, 0.5, &mut ToolRun::default());
+        assert!(variances.iter().all(|&v| v.abs() < 1e-6));
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::linalg::{covariance, solve3, trace};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Per-point color gradient magnitude: how fast color changes across the local neighborhood.
+// Each channel is fit by least squares as c ~ c0 + g . (p - centroid) over the radius
+// neighbors; the output is the norm of the three channel gradients stacked together.
+// High values mark sharp color transitions such as painted road markings.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 radius][f32* positions][f32* colors]
+// Output format: [u32 pointCount][f32* gradientMagnitudes] (color units per distance unit)
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: u32 + f32)
+    let (header, mut run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::COLOR_GRADIENT) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let radius = f32_at(&header, 4, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || radius <= 0.0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let colors = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let gradients = color_gradient(&positions, &colors, radius, &mut run);
+
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &gradients, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+// Ridge term relative to the neighborhood spread; keeps the fit solvable on flat or linear
+// neighborhoods, where the covariance has no extent along the surface normal
+const RIDGE: f64 = 1e-3;
+
+fn color_gradient(positions: &[f32], colors: &[f32], radius: f32, run: &mut ToolRun) -> Vec<f32> {
+    let point_count = positions.len() / 3;
+    let grid = SpatialGrid::new(positions, radius);
+    run.note_map_size(grid.cell_count());
+    let mut gradients = vec![0.0f32; point_count];
+
+    for (i, gradient) in gradients.iter_mut().enumerate() {
+        let i3 = i * 3;
+        let neighbors = grid.neighbors_in_radius(positions, positions[i3], positions[i3 + 1], positions[i3 + 2], radius);
+        if neighbors.len() < 3 {
+            continue;
+        }
+
+        let (centroid, mut cov) = covariance(positions, &neighbors);
+        let ridge = RIDGE * trace(&cov) + 1e-12;
+        for (axis, row) in cov.iter_mut().enumerate() {
+            row[axis] += ridge;
+        }
+
+        let n = neighbors.len() as f64;
+        let mut magnitude_squared = 0.0f64;
+        for channel in 0..3 {
+            let mean_c = neighbors.iter().map(|&j| colors[j * 3 + channel] as f64).sum::<f64>() / n;
+            // Cross-covariance between position and this color channel
+            let mut b = [0.0f64; 3];
+            for &j in &neighbors {
+                let dc = colors[j * 3 + channel] as f64 - mean_c;
+                for (axis, bv) in b.iter_mut().enumerate() {
+                    *bv += (positions[j * 3 + axis] as f64 - centroid[axis]) * dc;
+                }
+            }
+            for bv in b.iter_mut() {
+                *bv /= n;
+            }
+            if let Some(g) = solve3(&cov, b) {
+                magnitude_squared += g.iter().map(|v| v * v).sum::<f64>();
+            }
+        }
+        *gradient = magnitude_squared.sqrt() as f32;
+    }
+
+    gradients
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stripe_edges_score_higher_than_uniform() {
+        // Flat 40 x 10 sheet, white stripe for 0.9 <= x < 1.1 on black asphalt
+        let mut positions = Vec::new();
+        let mut colors = Vec::new();
+        for i in 0..40 {
+            for j in 0..10 {
+                let x = i as f32 * 0.05;
+                positions.extend_from_slice(&[x, j as f32 * 0.05, 0.0]);
+                let c = if (0.9..1.1).contains(&x) { 1.0 } else { 0.0 };
+                colors.extend_from_slice(&[c, c, c]);
+            }
+        }
+
+        let gradients = color_gradient(&positions, &colors, 0.12, &mut ToolRun::default());
+
+        let mut edge_min = f32::INFINITY;
+        let mut uniform_max = 0.0f32;
+        for p in 0..positions.len() / 3 {
+            let x = positions[p * 3];
+            let y = positions[p * 3 + 1];
+            if !(0.1..=0.35).contains(&y) {
+                continue; // skip sheet border rows
+            }
+            if (x - 0.875).abs() < 0.03 || (x - 1.1).abs() < 0.03 {
+                edge_min = edge_min.min(gradients[p]);
+            } else if !(0.6..=1.45).contains(&x) {
+                uniform_max = uniform_max.max(gradients[p]);
+            }
+        }
+        assert!(uniform_max < 1e-3, "uniform region gradient {}", uniform_max);
+        assert!(edge_min > 1.0, "stripe edge gradient {}", edge_min);
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::colormap::{sample_stops, Colormap};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+
+// Elevation shading: each point gets the colormap color of its Z normalized over the cloud's
+// own Z range (lowest point -> first stop, highest -> last stop), for quick inspection of
+// terrain and structure heights. The ramp is one of the built-in colormaps or, when stopCount is
+// non-zero, the stopCount RGB control points sent after the header (evenly spaced, linearly
+// interpolated). The range covers finite points only; points with a NaN or infinite coordinate,
+// and every point of a flat cloud, get the first color.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 colormap][u32 stopCount][f32*3 stops][f32* positions]
+// colormap: 0=grayscale, 1=hot, 2=jet (hue ramp), 3=viridis, 4=turbo (ignored when stopCount > 0);
+// other ids are an InvalidData error
+// Output format: [u32 pointCount][f32* colors] (r, g, b per point, aligned with the input)
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: 3 * u32)
+    let (header, run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::COLORIZE_BY_HEIGHT) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let colormap = Colormap::from_id(u32_at(&header, 4, run.order));
+    let stop_count = u32_at(&header, 8, run.order) as usize;
+
+    let custom_stops = match read_f32_payload(&mut stdin, stop_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let stops: Vec<[f32; 3]> = if stop_count > 0 {
+        custom_stops.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect()
+    } else {
+        match colormap {
+            Ok(c) => c.stops().to_vec(),
+            Err(e) => e.exit(run.order),
+        }
+    };
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let colors = colorize_by_height(&positions, &stops);
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &colors, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// RGB per point of a flat xyz array from its Z normalized over the finite points' Z range
+fn colorize_by_height(positions: &[f32], stops: &[[f32; 3]]) -> Vec<f32> {
+    let finite = || positions.chunks_exact(3).filter(|p| is_finite_point(p));
+    let min_z = finite().fold(f32::INFINITY, |m, p| m.min(p[2]));
+    let max_z = finite().fold(f32::NEG_INFINITY, |m, p| m.max(p[2]));
+    let range = max_z - min_z;
+    let inv_range = if range > 0.0 && range.is_finite() { 1.0 / range } else { 0.0 };
+
+    let mut colors = Vec::with_capacity(positions.len());
+    for p in positions.chunks_exact(3) {
+        let t = if is_finite_point(p) { (p[2] - min_z) * inv_range } else { 0.0 };
+        colors.extend_from_slice(&sample_stops(stops, t));
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color_eq(actual: &[f32], expected: [f32; 3]) {
+        for c in 0..3 {
+            assert!((actual[c] - expected[c]).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_lowest_and_highest_points_map_to_ramp_ends() {
+        let positions = vec![0.0, 0.0, 4.0, 1.0, 2.0, -3.0, 5.0, 1.0, 10.0, 2.0, 2.0, f32::NAN];
+        for colormap in [Colormap::Viridis, Colormap::Turbo, Colormap::Hot] {
+            let colors = colorize_by_height(&positions, colormap.stops());
+            assert_eq!(colors.len(), 12);
+            assert_color_eq(&colors[3..6], colormap.start());
+            assert_color_eq(&colors[6..9], colormap.end());
+            assert_color_eq(&colors[9..12], colormap.start());
+        }
+    }
+
+    #[test]
+    fn test_custom_stops_interpolate_linearly_in_height() {
+        let stops = [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [1.0, 1.0, 1.0]];
+        let positions = vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0, 4.0];
+        let colors = colorize_by_height(&positions, &stops);
+        assert_eq!(colors, vec![0.0, 0.0, 1.0, 0.5, 0.0, 0.5, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+
+        // A flat cloud has no range: every point takes the first stop
+        let flat = colorize_by_height(&[0.0, 0.0, 2.0, 1.0, 1.0, 2.0], &stops);
+        assert_eq!(flat, vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+    }
+}
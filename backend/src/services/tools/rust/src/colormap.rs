@@ -0,0 +1,130 @@
+// Colormaps for turning a normalized scalar (intensity, height, ...) into RGB in [0, 1].
+// Each map is a short table of evenly spaced color stops, linearly interpolated.
+
+use crate::protocol::{ErrorCode, ToolError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Colormap {
+    /// Black to white; intensity drives brightness only
+    Grayscale,
+    /// Black -> red -> yellow -> white
+    Hot,
+    /// Blue -> cyan -> green -> yellow -> red hue ramp
+    Jet,
+    /// Perceptually uniform dark purple -> teal -> yellow
+    Viridis,
+    /// Rainbow-like dark blue -> cyan -> green -> yellow -> red -> dark red, smoother than jet
+    Turbo,
+}
+
+const GRAYSCALE_STOPS: [[f32; 3]; 2] = [[0.0, 0.0, 0.0], [1.0, 1.0, 1.0]];
+const HOT_STOPS: [[f32; 3]; 4] = [
+    [0.0, 0.0, 0.0],
+    [1.0, 0.0, 0.0],
+    [1.0, 1.0, 0.0],
+    [1.0, 1.0, 1.0],
+];
+const JET_STOPS: [[f32; 3]; 5] = [
+    [0.0, 0.0, 1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, 1.0, 0.0],
+    [1.0, 1.0, 0.0],
+    [1.0, 0.0, 0.0],
+];
+const VIRIDIS_STOPS: [[f32; 3]; 5] = [
+    [0.267, 0.005, 0.329],
+    [0.229, 0.322, 0.546],
+    [0.128, 0.567, 0.551],
+    [0.369, 0.789, 0.383],
+    [0.993, 0.906, 0.144],
+];
+const TURBO_STOPS: [[f32; 3]; 9] = [
+    [0.190, 0.072, 0.232],
+    [0.269, 0.415, 0.935],
+    [0.148, 0.740, 0.881],
+    [0.250, 0.953, 0.573],
+    [0.589, 0.982, 0.313],
+    [0.932, 0.814, 0.177],
+    [1.000, 0.502, 0.114],
+    [0.786, 0.175, 0.047],
+    [0.480, 0.016, 0.011],
+];
+
+impl Colormap {
+    /// Map the protocol's u32 colormap id; unknown ids are an error rather than grayscale
+    pub fn from_id(id: u32) -> Result<Colormap, ToolError> {
+        match id {
+            0 => Ok(Colormap::Grayscale),
+            1 => Ok(Colormap::Hot),
+            2 => Ok(Colormap::Jet),
+            3 => Ok(Colormap::Viridis),
+            4 => Ok(Colormap::Turbo),
+            _ => Err(ToolError::new(
+                ErrorCode::InvalidData,
+                format!("unknown colormap {} (0 = grayscale, 1 = hot, 2 = jet, 3 = viridis, 4 = turbo)", id),
+            )),
+        }
+    }
+
+    /// The evenly spaced color stops of the ramp
+    pub fn stops(self) -> &'static [[f32; 3]] {
+        match self {
+            Colormap::Grayscale => &GRAYSCALE_STOPS,
+            Colormap::Hot => &HOT_STOPS,
+            Colormap::Jet => &JET_STOPS,
+            Colormap::Viridis => &VIRIDIS_STOPS,
+            Colormap::Turbo => &TURBO_STOPS,
+        }
+    }
+
+    /// Color for t in [0, 1] (values outside are clamped)
+    pub fn sample(self, t: f32) -> [f32; 3] {
+        sample_stops(self.stops(), t)
+    }
+
+    /// First color of the ramp
+    pub fn start(self) -> [f32; 3] {
+        self.stops()[0]
+    }
+
+    /// Last color of the ramp
+    pub fn end(self) -> [f32; 3] {
+        let stops = self.stops();
+        stops[stops.len() - 1]
+    }
+}
+
+/// Color for t in [0, 1] (values outside are clamped, NaN maps to the start) on a ramp of evenly
+/// spaced stops, linearly interpolated. A single stop is a constant color; no stops give black.
+pub fn sample_stops(stops: &[[f32; 3]], t: f32) -> [f32; 3] {
+    if stops.len() < 2 {
+        return stops.first().copied().unwrap_or([0.0; 3]);
+    }
+    let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+    let scaled = t * (stops.len() - 1) as f32;
+    let lower = (scaled.floor() as usize).min(stops.len() - 2);
+    let frac = scaled - lower as f32;
+    let a = stops[lower];
+    let b = stops[lower + 1];
+    [
+        a[0] + (b[0] - a[0]) * frac,
+        a[1] + (b[1] - a[1]) * frac,
+        a[2] + (b[2] - a[2]) * frac,
+    ]
+}
+
+/// Min-max normalize values to [0, 1]; a constant input maps to 0
+pub fn normalize_range(values: &[f32]) -> Vec<f32> {
+    let mut min_v = f32::INFINITY;
+    let mut max_v = f32::NEG_INFINITY;
+    for &v in values {
+        min_v = min_v.min(v);
+        max_v = max_v.max(v);
+    }
+    let range = max_v - min_v;
+    if !range.is_finite() || range <= 0.0 {
+        return vec![0.0; values.len()];
+    }
+    let inv_range = 1.0 / range;
+    values.iter().map(|&v| (v - min_v) * inv_range).collect()
+}
@@ -0,0 +1,330 @@
+// 3D convex hull by QuickHull, for collision proxies and volume estimates.
+// Starts from a tetrahedron of extreme points, then repeatedly takes the farthest point outside
+// some face, removes every face it can see and closes the hole with a fan of triangles from the
+// horizon to the new point. Points never farther than a small tolerance outside a face count as
+// inside, so coplanar points do not add slivers. Arithmetic is f64, relative to the bounds center
+// so large coordinates keep their precision. Points with a non-finite coordinate are ignored.
+
+use rustc_hash::FxHashMap;
+use crate::geometry::{is_finite_point, Bounds};
+
+// Distance tolerance relative to the largest bounds extent
+const RELATIVE_TOLERANCE: f64 = 1e-9;
+
+/// Hull vertices (a subset of the input points, in input order) and outward-facing triangles
+/// indexing into them, counter-clockwise seen from outside. Both are empty when the input has
+/// fewer than four points or all of them lie on one plane (no volume to enclose).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConvexHull {
+    pub vertices: Vec<f32>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+impl ConvexHull {
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / 3
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    /// Enclosed volume: signed volumes of the tetrahedra joining each triangle to the first
+    /// vertex, summed (positive for the outward winding); 0 for an empty hull
+    pub fn volume(&self) -> f64 {
+        let apex = self.vertex(0);
+        self.triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.map(|v| sub(self.vertex(v as usize), apex));
+                dot(a, cross(b, c))
+            })
+            .sum::<f64>()
+            / 6.0
+    }
+
+    /// Total area of the hull triangles; 0 for an empty hull
+    pub fn surface_area(&self) -> f64 {
+        self.triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.map(|v| self.vertex(v as usize));
+                let n = cross(sub(b, a), sub(c, a));
+                dot(n, n).sqrt() * 0.5
+            })
+            .sum()
+    }
+
+    fn vertex(&self, v: usize) -> [f64; 3] {
+        match self.vertices.get(v * 3..v * 3 + 3) {
+            Some(p) => [p[0] as f64, p[1] as f64, p[2] as f64],
+            None => [0.0; 3],
+        }
+    }
+}
+
+struct Face {
+    vertices: [usize; 3],
+    normal: [f64; 3],
+    offset: f64,
+    // Points farther than the tolerance in front of this face, not yet on the hull
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+impl Face {
+    fn new(points: &[[f64; 3]], vertices: [usize; 3]) -> Face {
+        let [a, b, c] = vertices.map(|v| points[v]);
+        let n = cross(sub(b, a), sub(c, a));
+        let length = dot(n, n).sqrt();
+        let normal = if length > 0.0 { n.map(|v| v / length) } else { [0.0; 3] };
+        Face { vertices, normal, offset: dot(normal, a), outside: Vec::new(), alive: true }
+    }
+
+    fn distance(&self, p: [f64; 3]) -> f64 {
+        dot(self.normal, p) - self.offset
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.vertices;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+/// Convex hull of a flat xyz array
+pub fn convex_hull(positions: &[f32]) -> ConvexHull {
+    let bounds = match Bounds::from_points(positions) {
+        Some(b) => b,
+        None => return ConvexHull::default(),
+    };
+    let center = [0, 1, 2].map(|a| (bounds.min[a] as f64 + bounds.max[a] as f64) * 0.5);
+    let tolerance = bounds.max_extent() as f64 * RELATIVE_TOLERANCE;
+
+    // Input index of every finite point, and its position relative to the center
+    let source: Vec<usize> = (0..positions.len() / 3).filter(|&i| is_finite_point(&positions[i * 3..i * 3 + 3])).collect();
+    let points: Vec<[f64; 3]> =
+        source.iter().map(|&i| [0, 1, 2].map(|a| positions[i * 3 + a] as f64 - center[a])).collect();
+
+    let simplex = match initial_simplex(&points, tolerance) {
+        Some(s) => s,
+        None => return ConvexHull::default(),
+    };
+
+    let mut faces: Vec<Face> = Vec::new();
+    let mut edge_faces: FxHashMap<(usize, usize), usize> = FxHashMap::default();
+    let [a, b, c, d] = simplex;
+    for vertices in [[a, b, c], [a, c, d], [a, d, b], [b, d, c]] {
+        add_face(&mut faces, &mut edge_faces, Face::new(&points, vertices));
+    }
+    // Flip the winding if the first face sees the opposite vertex (the tetrahedron is inside out)
+    if faces[0].distance(points[d]) > 0.0 {
+        faces.clear();
+        edge_faces.clear();
+        for vertices in [[a, c, b], [a, d, c], [a, b, d], [b, c, d]] {
+            add_face(&mut faces, &mut edge_faces, Face::new(&points, vertices));
+        }
+    }
+
+    let candidates: Vec<usize> = (0..points.len()).filter(|i| !simplex.contains(i)).collect();
+    assign_outside(&mut faces, &[0, 1, 2, 3], &candidates, &points, tolerance);
+
+    while let Some(start) = faces.iter().position(|f| f.alive && !f.outside.is_empty()) {
+        let eye = farthest(&faces[start].outside, &faces[start], &points);
+        let eye_point = points[eye];
+
+        // Faces visible from the eye, grown from the starting face across shared edges
+        let mut visible = vec![start];
+        faces[start].alive = false;
+        let mut next = 0;
+        while next < visible.len() {
+            for (from, to) in faces[visible[next]].edges() {
+                if let Some(&neighbor) = edge_faces.get(&(to, from)) {
+                    if faces[neighbor].alive && faces[neighbor].distance(eye_point) > tolerance {
+                        faces[neighbor].alive = false;
+                        visible.push(neighbor);
+                    }
+                }
+            }
+            next += 1;
+        }
+
+        // Horizon: edges of visible faces whose neighbor stays; each gets a triangle to the eye
+        let mut horizon = Vec::new();
+        for &f in &visible {
+            for (from, to) in faces[f].edges() {
+                if edge_faces.get(&(to, from)).is_some_and(|&n| faces[n].alive) {
+                    horizon.push((from, to));
+                }
+            }
+        }
+        let mut orphans = Vec::new();
+        for &f in &visible {
+            for edge in faces[f].edges() {
+                edge_faces.remove(&edge);
+            }
+            orphans.append(&mut faces[f].outside);
+        }
+        let first_new = faces.len();
+        for (from, to) in horizon {
+            add_face(&mut faces, &mut edge_faces, Face::new(&points, [from, to, eye]));
+        }
+        let new_faces: Vec<usize> = (first_new..faces.len()).collect();
+        orphans.retain(|&p| p != eye);
+        assign_outside(&mut faces, &new_faces, &orphans, &points, tolerance);
+    }
+
+    // Compact the vertices used by the surviving faces, keeping input order
+    let mut remap = vec![u32::MAX; points.len()];
+    for face in faces.iter().filter(|f| f.alive) {
+        for &v in &face.vertices {
+            remap[v] = 0;
+        }
+    }
+    let mut hull = ConvexHull::default();
+    for (p, slot) in remap.iter_mut().enumerate() {
+        if *slot == 0 {
+            *slot = hull.vertex_count() as u32;
+            let i = source[p];
+            hull.vertices.extend_from_slice(&positions[i * 3..i * 3 + 3]);
+        }
+    }
+    hull.triangles = faces.iter().filter(|f| f.alive).map(|f| f.vertices.map(|v| remap[v])).collect();
+    hull
+}
+
+/// Four points spanning a tetrahedron of non-negligible volume: the farthest pair among the
+/// axis extremes, the point farthest from their line, then the one farthest from their plane.
+/// None for collinear or coplanar input.
+fn initial_simplex(points: &[[f64; 3]], tolerance: f64) -> Option<[usize; 4]> {
+    if points.len() < 4 {
+        return None;
+    }
+    let mut extremes = [0usize; 6];
+    for (i, p) in points.iter().enumerate() {
+        for axis in 0..3 {
+            if p[axis] < points[extremes[axis * 2]][axis] {
+                extremes[axis * 2] = i;
+            }
+            if p[axis] > points[extremes[axis * 2 + 1]][axis] {
+                extremes[axis * 2 + 1] = i;
+            }
+        }
+    }
+    let mut pair = (extremes[0], extremes[1]);
+    for &i in &extremes {
+        for &j in &extremes {
+            if distance_squared(points[i], points[j]) > distance_squared(points[pair.0], points[pair.1]) {
+                pair = (i, j);
+            }
+        }
+    }
+    let (a, b) = pair;
+    let direction = sub(points[b], points[a]);
+    if dot(direction, direction).sqrt() <= tolerance {
+        return None;
+    }
+
+    let line_distance = |p: [f64; 3]| {
+        let n = cross(direction, sub(p, points[a]));
+        dot(n, n).sqrt() / dot(direction, direction).sqrt()
+    };
+    let c = (0..points.len()).max_by(|&i, &j| line_distance(points[i]).total_cmp(&line_distance(points[j])))?;
+    if line_distance(points[c]) <= tolerance {
+        return None;
+    }
+
+    let plane = Face::new(points, [a, b, c]);
+    let d = (0..points.len()).max_by(|&i, &j| plane.distance(points[i]).abs().total_cmp(&plane.distance(points[j]).abs()))?;
+    if plane.distance(points[d]).abs() <= tolerance {
+        return None;
+    }
+    Some([a, b, c, d])
+}
+
+fn add_face(faces: &mut Vec<Face>, edge_faces: &mut FxHashMap<(usize, usize), usize>, face: Face) {
+    for edge in face.edges() {
+        edge_faces.insert(edge, faces.len());
+    }
+    faces.push(face);
+}
+
+/// Give each point to the first of `targets` it lies outside of; points inside all of them
+/// are inside the hull for good
+fn assign_outside(faces: &mut [Face], targets: &[usize], candidates: &[usize], points: &[[f64; 3]], tolerance: f64) {
+    for &p in candidates {
+        if let Some(&f) = targets.iter().find(|&&f| faces[f].distance(points[p]) > tolerance) {
+            faces[f].outside.push(p);
+        }
+    }
+}
+
+fn farthest(candidates: &[usize], face: &Face, points: &[[f64; 3]]) -> usize {
+    candidates
+        .iter()
+        .copied()
+        .max_by(|&i, &j| face.distance(points[i]).total_cmp(&face.distance(points[j])))
+        .unwrap_or(candidates[0])
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn distance_squared(a: [f64; 3], b: [f64; 3]) -> f64 {
+    let d = sub(a, b);
+    dot(d, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_point_is_inside_and_edges_pair_up() {
+        let mut positions = Vec::new();
+        for i in 0..600 {
+            let t = i as f32;
+            positions.extend_from_slice(&[(t * 0.37).sin() * 2.0, (t * 0.11).cos() * 3.0, (t * 0.053).sin() + (t * 0.7).cos()]);
+        }
+        let hull = convex_hull(&positions);
+        assert!(!hull.is_empty());
+        assert_eq!(hull.triangles.len(), 2 * hull.vertex_count() - 4, "closed triangulated sphere");
+
+        // Each directed edge appears once and its reverse once: the surface is closed and consistently wound
+        let mut edges = FxHashMap::default();
+        for t in &hull.triangles {
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                assert!(edges.insert((a, b), ()).is_none());
+            }
+        }
+        assert!(edges.keys().all(|&(a, b)| edges.contains_key(&(b, a))));
+
+        let vertex = |v: u32| [0, 1, 2].map(|a| hull.vertices[v as usize * 3 + a] as f64);
+        for t in &hull.triangles {
+            let [a, b, c] = t.map(vertex);
+            let n = cross(sub(b, a), sub(c, a));
+            for p in positions.chunks_exact(3) {
+                let p = [p[0] as f64, p[1] as f64, p[2] as f64];
+                assert!(dot(n, sub(p, a)) <= 1e-5, "point in front of a hull face");
+            }
+        }
+    }
+
+    #[test]
+    fn test_degenerate_inputs_give_an_empty_hull() {
+        assert!(convex_hull(&[]).is_empty());
+        assert!(convex_hull(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]).is_empty());
+        let collinear: Vec<f32> = (0..10).flat_map(|i| [i as f32, 2.0 * i as f32, 0.5]).collect();
+        assert!(convex_hull(&collinear).is_empty());
+        let coplanar: Vec<f32> = (0..50).flat_map(|i| [(i as f32 * 0.7).sin(), (i as f32 * 0.3).cos(), 1.0]).collect();
+        assert_eq!(convex_hull(&coplanar), ConvexHull::default());
+    }
+}
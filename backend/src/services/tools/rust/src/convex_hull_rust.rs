@@ -0,0 +1,85 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, write_u32_slice, ByteOrder};
+use pointcloud_tools_backend::convex_hull::convex_hull;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+
+// 3D convex hull (QuickHull) of the cloud, for collision proxies and volume estimates.
+// Hull vertices are the input points on the hull, in input order; triangles index into them and
+// are wound counter-clockwise seen from outside. Collinear or coplanar input (and clouds of fewer
+// than four points) encloses no volume and gives an empty hull. Points with a non-finite
+// coordinate are ignored.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32* positions]
+// Output format: [u32 vertexCount][f32* vertices][u32 triangleCount][u32* vertex indices, 3 per triangle]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (4 bytes: u32)
+    let (header, run): ([u8; 4], _) = match read_tool_header(&mut stdin, tool_id::CONVEX_HULL) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let hull = convex_hull(&positions);
+    let indices: Vec<u32> = hull.triangles.iter().flatten().copied().collect();
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, hull.vertex_count() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &hull.vertices, run.order).is_err()
+        || write_u32(&mut stdout, hull.triangles.len() as u32, run.order).is_err()
+        || write_u32_slice(&mut stdout, &indices, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, hull.vertex_count()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cube_corners_give_eight_vertices_and_twelve_triangles() {
+        let mut positions = Vec::new();
+        for corner in 0..8 {
+            positions.extend_from_slice(&[(corner & 1) as f32, ((corner >> 1) & 1) as f32, ((corner >> 2) & 1) as f32]);
+        }
+        // Interior points and a point on a face are not hull vertices
+        positions.extend_from_slice(&[0.5, 0.5, 0.5, 0.25, 0.75, 0.5, 0.5, 0.5, 1.0]);
+
+        let hull = convex_hull(&positions);
+        assert_eq!(hull.vertex_count(), 8);
+        assert_eq!(hull.vertices, positions[..24]);
+        assert_eq!(hull.triangles.len(), 12);
+
+        // Every triangle lies in one of the cube's faces and faces away from the center
+        for t in &hull.triangles {
+            let [a, b, c] = t.map(|v| [0, 1, 2].map(|k| hull.vertices[v as usize * 3 + k]));
+            let shared = (0..3).find(|&k| a[k] == b[k] && b[k] == c[k]).expect("triangle spans two cube faces");
+            let n = [
+                (b[1] - a[1]) * (c[2] - a[2]) - (b[2] - a[2]) * (c[1] - a[1]),
+                (b[2] - a[2]) * (c[0] - a[0]) - (b[0] - a[0]) * (c[2] - a[2]),
+                (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]),
+            ];
+            assert_eq!(n[shared].signum(), if a[shared] == 1.0 { 1.0 } else { -1.0 });
+        }
+    }
+
+    #[test]
+    fn test_flat_cloud_gives_empty_output() {
+        let positions: Vec<f32> = (0..20).flat_map(|i| [i as f32 * 0.1, (i % 4) as f32, 3.0]).collect();
+        let hull = convex_hull(&positions);
+        assert_eq!(hull.vertex_count(), 0);
+        assert!(hull.triangles.is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, gather_positions, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError};
+
+// Axis-aligned box crop (region of interest): keeps the points inside [min, max] on every axis,
+// bounds inclusive, or with bit3 set the points outside it. Attributes follow the kept points,
+// which stay in input order. An inverted box (min > max on any axis) selects nothing.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 minX][f32 minY][f32 minZ][f32 maxX][f32 maxY][f32 maxZ][u32 flags]
+//               [f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit3=keep points outside the box, bit31=drop non-finite points
+// Output format: [u32 outputCount][f32* positions][optional colors][optional intensities][optional classifications]
+
+const FLAG_INVERT: u32 = 8;
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (32 bytes: u32 + 6 * f32 + u32)
+    let (header, run): ([u8; 32], _) = match read_tool_header(&mut stdin, tool_id::CROP_BOX) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let min = [f32_at(&header, 4, run.order), f32_at(&header, 8, run.order), f32_at(&header, 12, run.order)];
+    let max = [f32_at(&header, 16, run.order), f32_at(&header, 20, run.order), f32_at(&header, 24, run.order)];
+    let flags = u32_at(&header, 28, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+
+    let kept = crop_box(&positions, min, max, flags & FLAG_INVERT != 0);
+    let kept_positions = gather_positions(&positions, &kept);
+    let kept_attributes = attributes.gather(&kept);
+
+    if write_u32(&mut stdout, kept.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &kept_positions, run.order).is_err()
+        || kept_attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, kept.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Indices of the points inside the box (outside with `invert`), in input order
+fn crop_box(positions: &[f32], min: [f32; 3], max: [f32; 3], invert: bool) -> Vec<usize> {
+    if (0..3).any(|a| min[a] > max[a]) {
+        return Vec::new();
+    }
+    positions
+        .chunks_exact(3)
+        .enumerate()
+        .filter(|(_, p)| (0..3).all(|a| p[a] >= min[a] && p[a] <= max[a]) != invert)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lattice() -> Vec<f32> {
+        (0..27).flat_map(|i| [(i % 3) as f32, ((i / 3) % 3) as f32, (i / 9) as f32]).collect()
+    }
+
+    #[test]
+    fn test_inclusive_keep_and_invert() {
+        let positions = lattice();
+        // Bounds lie exactly on lattice planes: x and y in {0, 1}, z in {1, 2}
+        let inside = crop_box(&positions, [0.0, 0.0, 1.0], [1.0, 1.0, 2.0], false);
+        assert_eq!(inside.len(), 8);
+        for &i in &inside {
+            let p = &positions[i * 3..i * 3 + 3];
+            assert!(p[0] <= 1.0 && p[1] <= 1.0 && p[2] >= 1.0);
+        }
+
+        let outside = crop_box(&positions, [0.0, 0.0, 1.0], [1.0, 1.0, 2.0], true);
+        assert_eq!(outside.len(), 27 - 8);
+        assert!(outside.iter().all(|i| !inside.contains(i)));
+    }
+
+    #[test]
+    fn test_inverted_box_keeps_nothing() {
+        let positions = lattice();
+        assert!(crop_box(&positions, [2.0, 0.0, 0.0], [0.0, 2.0, 2.0], false).is_empty());
+        assert!(crop_box(&positions, [2.0, 0.0, 0.0], [0.0, 2.0, 2.0], true).is_empty());
+    }
+
+    #[test]
+    fn test_degenerate_box() {
+        let positions = lattice();
+        // Zero-volume box: only the point exactly at its corner survives
+        assert_eq!(crop_box(&positions, [1.0, 1.0, 1.0], [1.0, 1.0, 1.0], false), vec![13]);
+        // Zero-volume box between lattice points: empty
+        assert!(crop_box(&positions, [0.5, 0.5, 0.5], [0.5, 0.5, 0.5], false).is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, gather_positions, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError};
+
+// Sphere crop: keeps the points within radius of a center (distance <= radius, compared as
+// squared distances), or with bit3 set the points outside it. Used to extract the object around
+// a clicked location. Attributes follow the kept points, which stay in input order. A negative
+// radius is an empty sphere.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 centerX][f32 centerY][f32 centerZ][f32 radius][u32 flags]
+//               [f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit3=keep points outside the sphere, bit31=drop non-finite points
+// Output format: [u32 outputCount][f32* positions][optional colors][optional intensities][optional classifications]
+
+const FLAG_INVERT: u32 = 8;
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (24 bytes: u32 + 4 * f32 + u32)
+    let (header, run): ([u8; 24], _) = match read_tool_header(&mut stdin, tool_id::CROP_SPHERE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let center = [f32_at(&header, 4, run.order), f32_at(&header, 8, run.order), f32_at(&header, 12, run.order)];
+    let radius = f32_at(&header, 16, run.order);
+    let flags = u32_at(&header, 20, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+
+    let kept = crop_sphere(&positions, center, radius, flags & FLAG_INVERT != 0);
+    let kept_positions = gather_positions(&positions, &kept);
+    let kept_attributes = attributes.gather(&kept);
+
+    if write_u32(&mut stdout, kept.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &kept_positions, run.order).is_err()
+        || kept_attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, kept.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Indices of the points inside the sphere (outside with `invert`), in input order
+fn crop_sphere(positions: &[f32], center: [f32; 3], radius: f32, invert: bool) -> Vec<usize> {
+    // A negative radius would square to a positive one; treat it as containing nothing
+    let radius_squared = if radius >= 0.0 { radius * radius } else { -1.0 };
+    positions
+        .chunks_exact(3)
+        .enumerate()
+        .filter(|(_, p)| {
+            let dx = p[0] - center[0];
+            let dy = p[1] - center[1];
+            let dz = p[2] - center[2];
+            (dx * dx + dy * dy + dz * dz <= radius_squared) != invert
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boundary_points_are_inside() {
+        let center = [1.0, -2.0, 0.5];
+        // Exactly on the sphere along each axis, then just beyond it, then well inside
+        let mut positions = Vec::new();
+        for a in 0..3 {
+            for offset in [2.0f32, -2.0, 2.0001] {
+                let mut p = center;
+                p[a] += offset;
+                positions.extend_from_slice(&p);
+            }
+        }
+        positions.extend_from_slice(&[1.5, -1.5, 1.0]);
+
+        let inside = crop_sphere(&positions, center, 2.0, false);
+        assert_eq!(inside, vec![0, 1, 3, 4, 6, 7, 9]);
+
+        let outside = crop_sphere(&positions, center, 2.0, true);
+        assert_eq!(outside, vec![2, 5, 8]);
+    }
+
+    #[test]
+    fn test_zero_and_negative_radius() {
+        let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+        assert_eq!(crop_sphere(&positions, [0.0; 3], 0.0, false), vec![0]);
+        assert!(crop_sphere(&positions, [0.0; 3], -1.0, false).is_empty());
+        assert_eq!(crop_sphere(&positions, [0.0; 3], -1.0, true), vec![0, 1]);
+    }
+}
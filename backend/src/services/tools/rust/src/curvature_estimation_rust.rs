@@ -0,0 +1,89 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::normals::estimate_curvature;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+
+// Per-point curvature as surface variation lambda0 / (lambda0 + lambda1 + lambda2) of the
+// covariance of the k nearest neighbors (same PCA fit as normal_estimation_rust).
+// 0 on a plane, up to 1/3 for isotropic neighborhoods such as corners and noise.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 k][f32* positions]
+// Output format: [u32 pointCount][f32* curvature] (one per point)
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: 2 * u32)
+    let (header, mut run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::CURVATURE_ESTIMATION) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let k = u32_at(&header, 4, run.order) as usize;
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || k == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let curvature = estimate_curvature(&positions, k, &mut run);
+
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &curvature, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pointcloud_tools_backend::protocol::ToolRun;
+
+    #[test]
+    fn test_flat_region_low_sharp_edge_high() {
+        // Two perpendicular 20 x 20 sheets meeting along the line x = 0, z = 0 (a 90 degree fold)
+        let mut positions = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                let (a, b) = (i as f32 * 0.05, j as f32 * 0.05);
+                positions.extend_from_slice(&[a + 0.05, b, 0.0]); // floor, x > 0
+                positions.extend_from_slice(&[0.0, b, a]); // wall, z >= 0
+            }
+        }
+
+        let curvature = estimate_curvature(&positions, 12, &mut ToolRun::default());
+
+        let mut flat = Vec::new();
+        let mut edge = Vec::new();
+        for (p, &c) in positions.chunks_exact(3).zip(&curvature) {
+            // Skip the open borders of the sheets along y
+            if p[1] < 0.2 || p[1] > 0.75 {
+                continue;
+            }
+            let distance_to_fold = p[0].max(p[2]);
+            if distance_to_fold > 0.4 {
+                flat.push(c);
+            } else if distance_to_fold < 0.06 {
+                edge.push(c);
+            }
+        }
+        let max_flat = flat.iter().cloned().fold(0.0f32, f32::max);
+        let min_edge = edge.iter().cloned().fold(f32::MAX, f32::min);
+        assert!(max_flat < 1e-4, "flat curvature {}", max_flat);
+        assert!(min_edge > 0.02, "edge curvature {}", min_edge);
+    }
+}
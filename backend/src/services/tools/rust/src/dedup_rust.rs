@@ -0,0 +1,151 @@
+use std::io::{self, Write};
+use rustc_hash::FxHashSet;
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, gather_positions, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError, ToolRun};
+
+// Removes duplicate points (e.g. from overlapping scans), keeping the first occurrence and its
+// attributes. With epsilon > 0, points whose coordinates fall in the same epsilon-sized
+// quantization cell are duplicates; with epsilon <= 0 only bit-identical XYZ triples are.
+// Survivors stay in input order.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 epsilon][u32 flags][f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit31=drop non-finite points
+// Output format: [u32 outputCount][f32* positions][optional colors][optional intensities][optional classifications]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: u32 + f32 + u32)
+    let (header, mut run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::DEDUP) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let epsilon = f32_at(&header, 4, run.order);
+    let flags = u32_at(&header, 8, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+
+    let kept = dedup(&positions, epsilon, &mut run);
+    let kept_positions = gather_positions(&positions, &kept);
+    let kept_attributes = attributes.gather(&kept);
+
+    if write_u32(&mut stdout, kept.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &kept_positions, run.order).is_err()
+        || kept_attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, kept.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Indices of the first point in each distinct (quantized) position, in input order
+fn dedup(positions: &[f32], epsilon: f32, run: &mut ToolRun) -> Vec<usize> {
+    let point_count = positions.len() / 3;
+    let inv_epsilon = if epsilon > 0.0 { 1.0 / epsilon as f64 } else { 0.0 };
+    let mut seen: FxHashSet<[i64; 3]> = FxHashSet::with_capacity_and_hasher(point_count, Default::default());
+    let mut kept = Vec::with_capacity(point_count);
+    for (i, p) in positions.chunks_exact(3).enumerate() {
+        // Non-finite coordinates would all floor into cell 0 and swallow a real point there;
+        // pass them through instead
+        if epsilon > 0.0 && !is_finite_point(p) {
+            kept.push(i);
+            continue;
+        }
+        let key = if epsilon > 0.0 {
+            [0, 1, 2].map(|a| (p[a] as f64 * inv_epsilon).floor() as i64)
+        } else {
+            [0, 1, 2].map(|a| p[a].to_bits() as i64)
+        };
+        if seen.insert(key) {
+            kept.push(i);
+        }
+    }
+    run.note_map_size(seen.len());
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_half_duplicates_removed() {
+        // 500 distinct points, each appearing twice (the copy interleaved later in the cloud)
+        let distinct: Vec<f32> = (0..500)
+            .flat_map(|i| [i as f32 * 0.37, (i % 17) as f32 * 1.5, (i / 17) as f32 * 0.01])
+            .collect();
+        let mut positions = distinct.clone();
+        positions.extend_from_slice(&distinct);
+        assert_eq!(positions.len() / 3, 1000);
+
+        let kept = dedup(&positions, 0.0, &mut ToolRun::default());
+        assert_eq!(kept.len(), 500);
+        assert_eq!(kept, (0..500).collect::<Vec<_>>());
+
+        // The first occurrence's attributes survive
+        let attributes = PointAttributes {
+            intensities: Some((0..1000).map(|i| i as f32).collect()),
+            ..Default::default()
+        };
+        let kept_attributes = attributes.gather(&kept);
+        assert_eq!(kept_attributes.intensities.unwrap()[499], 499.0);
+    }
+
+    #[test]
+    fn test_epsilon_merges_nearby_points() {
+        let positions = vec![
+            1.0002, 1.0002, 1.0002, //
+            1.0006, 1.0004, 1.0003, // same 1e-3 cell as the first point
+            1.0002, 1.0002, 1.0015, // next cell along z
+        ];
+        assert_eq!(dedup(&positions, 1e-3, &mut ToolRun::default()), vec![0, 2]);
+        assert_eq!(dedup(&positions, 0.0, &mut ToolRun::default()), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_non_finite_points_pass_through_or_drop() {
+        let mut positions = vec![
+            0.01, 0.01, 0.01,
+            f32::NAN, 0.0, 0.0,
+            0.02, 0.02, 0.02,
+            f32::INFINITY, 1.0, 1.0,
+        ];
+        // Keyed normally, the NaN point would floor into cell (0, 0, 0) and be dropped as a
+        // duplicate of point 0; it passes through instead
+        assert_eq!(dedup(&positions, 0.5, &mut ToolRun::default()), vec![0, 1, 3]);
+
+        let mut attributes = PointAttributes {
+            intensities: Some(vec![10.0, 11.0, 12.0, 13.0]),
+            ..Default::default()
+        };
+        drop_non_finite(&mut positions, &mut attributes);
+        assert_eq!(positions, vec![0.01, 0.01, 0.01, 0.02, 0.02, 0.02]);
+        assert_eq!(attributes.intensities, Some(vec![10.0, 12.0]));
+        assert_eq!(dedup(&positions, 0.5, &mut ToolRun::default()), vec![0]);
+    }
+}
@@ -0,0 +1,131 @@
+// Compact position encoding for spatially coherent (e.g. Morton-sorted) clouds, used by the
+// delta_encode / delta_decode tools. Positions are quantized to integer steps of `quantization`
+// relative to the bounding-box minimum, and each point is stored as its per-axis difference from
+// the previous point as zigzag LEB128 varints. Neighboring points in a sorted cloud differ by a
+// few steps, so most deltas fit in one or two bytes instead of four. NaN and infinite coordinates
+// have no quantized value: quantize refuses them and the tools report an error.
+
+use crate::geometry::{is_finite_point, Bounds};
+
+/// Quantized integer coordinates (x, y, z per point) and the origin they are relative to;
+/// None when a coordinate is not finite
+pub fn quantize(positions: &[f32], quantization: f32) -> Option<([f32; 3], Vec<i64>)> {
+    if !positions.chunks_exact(3).all(is_finite_point::<f32>) {
+        return None;
+    }
+    let origin = Bounds::from_points(positions).map_or([0.0; 3], |bounds| bounds.min);
+
+    let step = quantization as f64;
+    let quantized = positions
+        .chunks_exact(3)
+        .flat_map(|p| (0..3).map(move |axis| ((p[axis] as f64 - origin[axis] as f64) / step).round() as i64))
+        .collect();
+    Some((origin, quantized))
+}
+
+/// Positions reconstructed from quantized coordinates
+pub fn dequantize(origin: [f32; 3], quantization: f32, quantized: &[i64]) -> Vec<f32> {
+    let step = quantization as f64;
+    quantized
+        .chunks_exact(3)
+        .flat_map(|q| (0..3).map(move |axis| (origin[axis] as f64 + q[axis] as f64 * step) as f32))
+        .collect()
+}
+
+/// Per-axis deltas between consecutive points as zigzag varints (the first point is relative to 0)
+pub fn encode_deltas(quantized: &[i64]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(quantized.len() * 2);
+    let mut previous = [0i64; 3];
+    for q in quantized.chunks_exact(3) {
+        for axis in 0..3 {
+            let delta = q[axis].wrapping_sub(previous[axis]);
+            let mut value = ((delta << 1) ^ (delta >> 63)) as u64;
+            while value >= 0x80 {
+                bytes.push((value as u8 & 0x7F) | 0x80);
+                value >>= 7;
+            }
+            bytes.push(value as u8);
+            previous[axis] = q[axis];
+        }
+    }
+    bytes
+}
+
+/// Inverse of encode_deltas; None if the stream is truncated, malformed or has trailing bytes
+pub fn decode_deltas(bytes: &[u8], point_count: usize) -> Option<Vec<i64>> {
+    let mut quantized = Vec::with_capacity(point_count * 3);
+    let mut previous = [0i64; 3];
+    let mut pos = 0usize;
+    for _ in 0..point_count {
+        for value_prev in previous.iter_mut() {
+            let mut value = 0u64;
+            let mut shift = 0u32;
+            loop {
+                let byte = *bytes.get(pos)?;
+                pos += 1;
+                if shift >= 64 {
+                    return None;
+                }
+                value |= ((byte & 0x7F) as u64) << shift;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+                shift += 7;
+            }
+            let delta = ((value >> 1) as i64) ^ -((value & 1) as i64);
+            *value_prev = value_prev.wrapping_add(delta);
+            quantized.push(*value_prev);
+        }
+    }
+    if pos != bytes.len() {
+        return None;
+    }
+    Some(quantized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_reproduces_quantized_positions() {
+        let positions = vec![
+            1.2345, -7.5, 100.0, 1.2399, -7.49, 100.001, -500.0, 300.25, 0.0, 1.0e4, 2.0e-3, -3.3,
+        ];
+        let (origin, quantized) = quantize(&positions, 0.001).unwrap();
+        let bytes = encode_deltas(&quantized);
+        let decoded = decode_deltas(&bytes, positions.len() / 3).unwrap();
+        assert_eq!(decoded, quantized);
+        assert_eq!(dequantize(origin, 0.001, &decoded), dequantize(origin, 0.001, &quantized));
+        for (a, b) in dequantize(origin, 0.001, &decoded).iter().zip(&positions) {
+            assert!((a - b).abs() <= 0.001, "{} vs {}", a, b);
+        }
+
+        assert!(decode_deltas(&bytes[..bytes.len() - 1], positions.len() / 3).is_none());
+        assert!(decode_deltas(&bytes, positions.len() / 3 - 1).is_none());
+    }
+
+    #[test]
+    fn test_non_finite_coordinates_are_not_quantized() {
+        assert!(quantize(&[1.0, 2.0, 3.0, f32::NAN, 0.0, 0.0], 0.01).is_none());
+        assert!(quantize(&[1.0, f32::NEG_INFINITY, 3.0], 0.01).is_none());
+        assert_eq!(quantize(&[], 0.01), Some(([0.0; 3], Vec::new())));
+    }
+
+    #[test]
+    fn test_coherent_cloud_smaller_than_raw() {
+        // Wavy 1 cm surface scanned row by row, so consecutive points are neighbors
+        let mut positions = Vec::new();
+        for i in 0..200 {
+            for j in 0..200 {
+                let x = i as f32 * 0.01;
+                let y = j as f32 * 0.01;
+                positions.extend_from_slice(&[x, y, (x * 3.0).sin() * 0.2 + (y * 2.0).cos() * 0.1]);
+            }
+        }
+        let (_, quantized) = quantize(&positions, 0.001).unwrap();
+        let bytes = encode_deltas(&quantized);
+        let raw_size = positions.len() * 4;
+        assert!(bytes.len() * 2 <= raw_size, "{} encoded bytes vs {} raw", bytes.len(), raw_size);
+    }
+}
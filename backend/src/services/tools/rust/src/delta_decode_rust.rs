@@ -0,0 +1,62 @@
+use std::io::{self, Read, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::delta_codec::{decode_deltas, dequantize};
+use pointcloud_tools_backend::protocol::{read_tool_header, read_u8_payload, tool_id, write_stats, ErrorCode, ToolError};
+
+// Inverse of delta_encode_rust: reconstructs the quantized positions in their encoded order.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 quantization][f32 originX][f32 originY][f32 originZ]
+//               [u32 byteLength][u8* deltaBytes]
+// Output format: [u32 pointCount][f32* positions]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read count first: the encoder writes only a zero count for empty input
+    let (count_bytes, run): ([u8; 4], _) = match read_tool_header(&mut stdin, tool_id::DELTA_DECODE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+    let point_count = u32_at(&count_bytes, 0, run.order) as usize;
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Rest of the header (20 bytes: 4 * f32 + u32)
+    let mut header = [0u8; 20];
+    if stdin.read_exact(&mut header).is_err() {
+        ToolError::new(ErrorCode::ShortHeader, "input ends inside the delta stream header").exit(run.order);
+    }
+    let quantization = f32_at(&header, 0, run.order);
+    let origin = [f32_at(&header, 4, run.order), f32_at(&header, 8, run.order), f32_at(&header, 12, run.order)];
+    let byte_length = u32_at(&header, 16, run.order) as usize;
+    if !(quantization > 0.0 && quantization.is_finite() && origin.iter().all(|v| v.is_finite())) {
+        ToolError::new(ErrorCode::InvalidData, "delta stream header has a non-finite or non-positive quantization or origin")
+            .exit(run.order);
+    }
+
+    let bytes = match read_u8_payload(&mut stdin, byte_length) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let quantized = match decode_deltas(&bytes, point_count) {
+        Some(q) => q,
+        None => ToolError::new(ErrorCode::InvalidData, "delta stream is truncated or malformed").exit(run.order),
+    };
+    let positions = dequantize(origin, quantization, &quantized);
+
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &positions, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
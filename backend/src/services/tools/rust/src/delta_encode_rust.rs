@@ -0,0 +1,57 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::delta_codec::{encode_deltas, quantize};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, ToolError};
+
+// Delta + varint encoding for compact transfer of sorted clouds (inverse: delta_decode_rust).
+// Points are encoded in input order; sort them along a Morton curve first so consecutive points
+// are close and their deltas stay small. NaN or infinite coordinates cannot be encoded and are
+// rejected; drop them first (FLAG_DROP_NON_FINITE on morton_sort).
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 quantization][f32* positions]
+// Output format: [u32 pointCount][f32 quantization][f32 originX][f32 originY][f32 originZ]
+//                [u32 byteLength][u8* deltaBytes]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: u32 + f32)
+    let (header, run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::DELTA_ENCODE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let quantization = f32_at(&header, 4, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || !(quantization > 0.0 && quantization.is_finite()) {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let (origin, quantized) = match quantize(&positions, quantization) {
+        Some(q) => q,
+        None => ToolError::new(ErrorCode::InvalidData, "positions contain NaN or infinite coordinates").exit(run.order),
+    };
+    let bytes = encode_deltas(&quantized);
+
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &[quantization, origin[0], origin[1], origin[2]], run.order).is_err()
+        || write_u32(&mut stdout, bytes.len() as u32, run.order).is_err()
+        || stdout.write_all(&bytes).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,202 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::{is_finite_point, Bounds};
+use pointcloud_tools_backend::protocol::{check_payload_size, check_voxel_size, read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, ToolError};
+
+// Dense volume over the cloud's bounds for interpolation and meshing. Cell (i, j, k) spans
+// [min + (i, j, k) * cellSize, min + (i + 1, j + 1, k + 1) * cellSize) and its value is sampled
+// at the cell center. Mode 0 writes binary occupancy (1 where a cell holds a point); mode 1
+// splats each point trilinearly into the 8 cells whose centers surround it, so every point adds
+// a total weight of 1 and the field varies smoothly between cells. The grid covers the bounds
+// (see Bounds::voxel_counts) plus an empty one-cell border on every side, so splatted weight
+// never falls off the edge and surfaces extracted from the volume are closed. Points with a NaN
+// or infinite coordinate are ignored.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 cellSize][u32 mode][f32* positions]
+// Output format: [u32 nx][u32 ny][u32 nz][f32 minX][f32 minY][f32 minZ][f32 cellSize]
+//                [f32 * nx * ny * nz values], x varying fastest (index x + nx * (y + ny * z))
+// A cloud with no finite point gives a 0 x 0 x 0 volume anchored at the origin.
+
+const MODE_OCCUPANCY: u32 = 0;
+const MODE_TRILINEAR: u32 = 1;
+
+/// Cell layout of the output volume
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct VolumeGrid {
+    dims: [usize; 3],
+    min: [f32; 3],
+    cell_size: f32,
+}
+
+impl VolumeGrid {
+    /// Grid covering `bounds` with a one-cell border, or None if its cell count overflows usize
+    fn around(bounds: &Bounds, cell_size: f32) -> Option<VolumeGrid> {
+        let counts = bounds.voxel_counts(cell_size);
+        let dims = [counts[0].checked_add(2)?, counts[1].checked_add(2)?, counts[2].checked_add(2)?];
+        dims[0].checked_mul(dims[1])?.checked_mul(dims[2])?;
+        let min = [0, 1, 2].map(|a| bounds.min[a] - cell_size);
+        Some(VolumeGrid { dims, min, cell_size })
+    }
+
+    fn cell_count(&self) -> usize {
+        self.dims[0] * self.dims[1] * self.dims[2]
+    }
+
+    /// Flat index of cell (x, y, z), or None outside the grid
+    #[inline]
+    fn index(&self, cell: [i64; 3]) -> Option<usize> {
+        if (0..3).any(|a| cell[a] < 0 || cell[a] >= self.dims[a] as i64) {
+            return None;
+        }
+        Some(cell[0] as usize + self.dims[0] * (cell[1] as usize + self.dims[1] * cell[2] as usize))
+    }
+}
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: u32 + f32 + u32)
+    let (header, run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::DENSITY_VOLUME) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let cell_size = f32_at(&header, 4, run.order);
+    let mode = u32_at(&header, 8, run.order);
+
+    if let Err(e) = check_voxel_size(cell_size) {
+        e.exit(run.order);
+    }
+    if mode != MODE_OCCUPANCY && mode != MODE_TRILINEAR {
+        ToolError::new(ErrorCode::InvalidData, format!("unknown volume mode {} (0 = occupancy, 1 = trilinear)", mode)).exit(run.order);
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count.saturating_mul(3), run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let grid = match Bounds::from_points(&positions) {
+        Some(bounds) => match VolumeGrid::around(&bounds, cell_size) {
+            Some(grid) => grid,
+            None => ToolError::new(ErrorCode::AllocationTooLarge, "volume cell count overflows").exit(run.order),
+        },
+        None => VolumeGrid { dims: [0; 3], min: [0.0; 3], cell_size },
+    };
+    if grid.dims.iter().any(|&d| d > u32::MAX as usize) {
+        ToolError::new(ErrorCode::AllocationTooLarge, "volume has more than u32::MAX cells along an axis").exit(run.order);
+    }
+    if let Err(e) = check_payload_size(grid.cell_count(), 4) {
+        e.exit(run.order);
+    }
+
+    let volume = if mode == MODE_TRILINEAR {
+        splat_trilinear(&positions, &grid)
+    } else {
+        occupancy(&positions, &grid)
+    };
+
+    let mut stdout = io::stdout();
+    if grid.dims.iter().any(|&d| write_u32(&mut stdout, d as u32, run.order).is_err())
+        || write_f32_slice(&mut stdout, &[grid.min[0], grid.min[1], grid.min[2], grid.cell_size], run.order).is_err()
+        || write_f32_slice(&mut stdout, &volume, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, volume.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Continuous cell coordinate of `p` along each axis, in f64 (cell i spans [i, i + 1))
+fn cell_coords(p: &[f32], grid: &VolumeGrid) -> [f64; 3] {
+    let inv_cell_size = 1.0 / grid.cell_size as f64;
+    [0, 1, 2].map(|a| (p[a] as f64 - grid.min[a] as f64) * inv_cell_size)
+}
+
+/// 1 in every cell holding at least one point, 0 elsewhere
+fn occupancy(positions: &[f32], grid: &VolumeGrid) -> Vec<f32> {
+    let mut volume = vec![0.0f32; grid.cell_count()];
+    for p in positions.chunks_exact(3).filter(|p| is_finite_point(p)) {
+        let cell = cell_coords(p, grid).map(|c| c.floor() as i64);
+        if let Some(index) = grid.index(cell) {
+            volume[index] = 1.0;
+        }
+    }
+    volume
+}
+
+/// Each point's unit weight spread over the 8 cells whose centers surround it, weighted by the
+/// trilinear coefficients (a point on a cell center puts all of it in that cell)
+fn splat_trilinear(positions: &[f32], grid: &VolumeGrid) -> Vec<f32> {
+    let mut volume = vec![0.0f32; grid.cell_count()];
+    for p in positions.chunks_exact(3).filter(|p| is_finite_point(p)) {
+        // Shift by half a cell so integer coordinates fall on cell centers
+        let u = cell_coords(p, grid).map(|c| c - 0.5);
+        let base = u.map(|c| c.floor() as i64);
+        let frac = [0, 1, 2].map(|a| u[a] - base[a] as f64);
+        for corner in 0..8 {
+            let mut weight = 1.0f64;
+            let mut cell = base;
+            for a in 0..3 {
+                if corner & (1 << a) != 0 {
+                    cell[a] += 1;
+                    weight *= frac[a];
+                } else {
+                    weight *= 1.0 - frac[a];
+                }
+            }
+            if weight == 0.0 {
+                continue;
+            }
+            if let Some(index) = grid.index(cell) {
+                volume[index] += weight as f32;
+            }
+        }
+    }
+    volume
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: VolumeGrid = VolumeGrid { dims: [4, 4, 4], min: [0.0, 0.0, 0.0], cell_size: 1.0 };
+
+    #[test]
+    fn test_point_at_cell_center_fills_that_cell() {
+        let volume = splat_trilinear(&[1.5, 2.5, 1.5], &GRID);
+        let index = GRID.index([1, 2, 1]).unwrap();
+        assert_eq!(volume[index], 1.0);
+        assert_eq!(volume.iter().sum::<f32>(), 1.0);
+    }
+
+    #[test]
+    fn test_point_at_cell_corner_spreads_over_eight_cells() {
+        let volume = splat_trilinear(&[2.0, 2.0, 2.0], &GRID);
+        let touched: Vec<usize> = (0..volume.len()).filter(|&i| volume[i] != 0.0).collect();
+        assert_eq!(touched.len(), 8);
+        for x in 1..=2 {
+            for y in 1..=2 {
+                for z in 1..=2 {
+                    assert_eq!(volume[GRID.index([x, y, z]).unwrap()], 0.125);
+                }
+            }
+        }
+        assert_eq!(volume.iter().sum::<f32>(), 1.0);
+    }
+
+    #[test]
+    fn test_border_keeps_every_splat_inside_the_grid() {
+        let positions = [0.0, 0.0, 0.0, 0.3, 1.7, 0.9, 2.0, 2.0, 2.0, f32::NAN, 0.0, 0.0];
+        let grid = VolumeGrid::around(&Bounds::from_points(&positions).unwrap(), 0.5).unwrap();
+        assert_eq!(grid.dims, [7, 7, 7]);
+        assert_eq!(grid.min, [-0.5, -0.5, -0.5]);
+
+        let total: f32 = splat_trilinear(&positions, &grid).iter().sum();
+        assert!((total - 3.0).abs() < 1e-5, "{}", total);
+        let occupied: f32 = occupancy(&positions, &grid).iter().sum();
+        assert_eq!(occupied, 3.0);
+    }
+}
@@ -0,0 +1,118 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::normals::fit_local_plane;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Difference of Normals (DoN): normals estimated over a small and a large radius agree on flat
+// surfaces and diverge on structures whose size lies between the two scales.
+// Output per point is |n_small - n_large| / 2 in [0, 1] (large normal sign-aligned to the small one).
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 smallRadius][f32 largeRadius][f32* positions]
+// Output format: [u32 pointCount][f32* donMagnitudes]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: u32 + 2 * f32)
+    let (header, mut run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::DIFFERENCE_OF_NORMALS) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let small_radius = f32_at(&header, 4, run.order);
+    let large_radius = f32_at(&header, 8, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || small_radius <= 0.0 || large_radius <= small_radius {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let don = difference_of_normals(&positions, small_radius, large_radius, &mut run);
+
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &don, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+fn difference_of_normals(positions: &[f32], small_radius: f32, large_radius: f32, run: &mut ToolRun) -> Vec<f32> {
+    let point_count = positions.len() / 3;
+    // One grid serves both scales: queries at the large radius just scan more cells
+    let grid = SpatialGrid::new(positions, small_radius);
+    run.note_map_size(grid.cell_count());
+    let mut don = vec![0.0f32; point_count];
+
+    for (i, value) in don.iter_mut().enumerate() {
+        let i3 = i * 3;
+        let (x, y, z) = (positions[i3], positions[i3 + 1], positions[i3 + 2]);
+        let small = fit_local_plane(positions, &grid.neighbors_in_radius(positions, x, y, z, small_radius));
+        let large = fit_local_plane(positions, &grid.neighbors_in_radius(positions, x, y, z, large_radius));
+        if let (Some(small), Some(large)) = (small, large) {
+            let dot: f32 = (0..3).map(|a| small.normal[a] * large.normal[a]).sum();
+            let sign = if dot < 0.0 { -1.0 } else { 1.0 };
+            let diff_squared: f32 = (0..3)
+                .map(|a| {
+                    let d = small.normal[a] - sign * large.normal[a];
+                    d * d
+                })
+                .sum();
+            *value = diff_squared.sqrt() * 0.5;
+        }
+    }
+
+    don
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_low_and_fold_high() {
+        // Floor (z = 0, x < 1) meeting a wall (x = 1) at a right angle
+        let mut positions = Vec::new();
+        for i in 0..=20 {
+            for j in 0..20 {
+                positions.extend_from_slice(&[i as f32 * 0.05, j as f32 * 0.05, 0.0]);
+            }
+        }
+        for k in 1..=20 {
+            for j in 0..20 {
+                positions.extend_from_slice(&[1.0, j as f32 * 0.05, k as f32 * 0.05]);
+            }
+        }
+
+        let don = difference_of_normals(&positions, 0.08, 0.3, &mut ToolRun::default());
+
+        let mut flat_max = 0.0f32;
+        let mut fold_min = f32::INFINITY;
+        for p in 0..positions.len() / 3 {
+            let (x, y, z) = (positions[p * 3], positions[p * 3 + 1], positions[p * 3 + 2]);
+            if !(0.35..=0.6).contains(&y) {
+                continue; // away from the open sides
+            }
+            if z == 0.0 && x < 0.5 {
+                flat_max = flat_max.max(don[p]);
+            } else if z == 0.0 && (0.85..0.95).contains(&x) {
+                fold_min = fold_min.min(don[p]);
+            }
+        }
+        assert!(flat_max < 0.01, "flat DoN {}", flat_max);
+        assert!(fold_min > 0.1, "fold DoN {}", fold_min);
+    }
+}
@@ -0,0 +1,159 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_u32, write_u32_slice, ByteOrder};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Euclidean clustering: connected components of the graph linking points closer than
+// clusterTolerance, found with union-find over grid neighbors (cell size = tolerance).
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 clusterTolerance][u32 minClusterSize][u32 maxClusterSize][f32* positions]
+// maxClusterSize = 0 means unlimited
+// Output format: [u32 pointCount][u32 clusterCount][u32* clusterIds]
+// Cluster ids are 0..clusterCount in order of each cluster's first point; UNCLUSTERED (0xFFFFFFFF)
+// marks points in components smaller than minClusterSize or larger than maxClusterSize
+
+const UNCLUSTERED: u32 = u32::MAX;
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (16 bytes: u32 + f32 + 2 * u32)
+    let (header, mut run): ([u8; 16], _) = match read_tool_header(&mut stdin, tool_id::EUCLIDEAN_CLUSTERING) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let cluster_tolerance = f32_at(&header, 4, run.order);
+    let min_cluster_size = u32_at(&header, 8, run.order) as usize;
+    let max_cluster_size = match u32_at(&header, 12, run.order) {
+        0 => usize::MAX,
+        n => n as usize,
+    };
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || cluster_tolerance <= 0.0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let (cluster_ids, cluster_count) =
+        euclidean_clustering(&positions, cluster_tolerance, min_cluster_size, max_cluster_size, &mut run);
+
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_u32(&mut stdout, cluster_count as u32, run.order).is_err()
+        || write_u32_slice(&mut stdout, &cluster_ids, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        // Path halving
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Per-point cluster ids and the number of clusters that passed the size limits
+fn euclidean_clustering(
+    positions: &[f32],
+    cluster_tolerance: f32,
+    min_cluster_size: usize,
+    max_cluster_size: usize,
+    run: &mut ToolRun,
+) -> (Vec<u32>, usize) {
+    let point_count = positions.len() / 3;
+    let grid = SpatialGrid::new(positions, cluster_tolerance);
+    run.note_map_size(grid.cell_count());
+    let mut parent: Vec<usize> = (0..point_count).collect();
+
+    for i in 0..point_count {
+        let i3 = i * 3;
+        grid.for_each_in_radius(positions, positions[i3], positions[i3 + 1], positions[i3 + 2], cluster_tolerance, |j, _| {
+            if j > i {
+                let root_i = find_root(&mut parent, i);
+                let root_j = find_root(&mut parent, j);
+                if root_i != root_j {
+                    parent[root_j.max(root_i)] = root_i.min(root_j);
+                }
+            }
+        });
+    }
+
+    let mut roots = vec![0usize; point_count];
+    let mut sizes = vec![0usize; point_count];
+    for i in 0..point_count {
+        roots[i] = find_root(&mut parent, i);
+        sizes[roots[i]] += 1;
+    }
+
+    let mut ids_by_root = vec![UNCLUSTERED; point_count];
+    let mut cluster_count = 0usize;
+    let mut cluster_ids = vec![UNCLUSTERED; point_count];
+    for i in 0..point_count {
+        let root = roots[i];
+        let size = sizes[root];
+        if size < min_cluster_size || size > max_cluster_size {
+            continue;
+        }
+        if ids_by_root[root] == UNCLUSTERED {
+            ids_by_root[root] = cluster_count as u32;
+            cluster_count += 1;
+        }
+        cluster_ids[i] = ids_by_root[root];
+    }
+
+    (cluster_ids, cluster_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blob(center: [f32; 3], positions: &mut Vec<f32>) {
+        for i in 0..5 {
+            for j in 0..5 {
+                positions.extend_from_slice(&[center[0] + i as f32 * 0.1, center[1] + j as f32 * 0.1, center[2]]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_two_blobs_and_noise() {
+        let mut positions = Vec::new();
+        blob([0.0, 0.0, 0.0], &mut positions);
+        blob([5.0, 0.0, 0.0], &mut positions);
+        positions.extend_from_slice(&[2.5, 2.5, 2.5]); // lone noise point
+
+        let (ids, cluster_count) = euclidean_clustering(&positions, 0.15, 3, usize::MAX, &mut ToolRun::default());
+        assert_eq!(cluster_count, 2);
+        assert!(ids[..25].iter().all(|&id| id == 0));
+        assert!(ids[25..50].iter().all(|&id| id == 1));
+        assert_eq!(ids[50], UNCLUSTERED);
+    }
+
+    #[test]
+    fn test_max_cluster_size_rejects_large() {
+        let mut positions = Vec::new();
+        blob([0.0, 0.0, 0.0], &mut positions);
+        positions.extend_from_slice(&[3.0, 0.0, 0.0, 3.1, 0.0, 0.0]);
+        let (ids, cluster_count) = euclidean_clustering(&positions, 0.15, 2, 10, &mut ToolRun::default());
+        assert_eq!(cluster_count, 1);
+        assert!(ids[..25].iter().all(|&id| id == UNCLUSTERED));
+        assert_eq!(&ids[25..], &[0, 0]);
+    }
+}
@@ -0,0 +1,173 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::point_attributes::gather_positions;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 targetCount][f32* positions]
+// Output format: [u32 outputCount][f32* positions] (selection order; outputCount = min(targetCount, finite point count))
+// Points with a NaN or infinite coordinate are never selected, and no point is selected twice: once
+// every distinct position is taken, the remaining picks are duplicates of them in index order.
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: 2 * u32)
+    let (header, run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::FARTHEST_POINT_SAMPLING) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let target_count = u32_at(&header, 4, run.order) as usize;
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || target_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let selected = farthest_point_sampling(&positions, target_count);
+    let sampled = gather_positions(&positions, &selected);
+
+    if write_u32(&mut stdout, selected.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &sampled, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, selected.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Greedy farthest-point sampling seeded from the first finite point; points with a non-finite
+/// coordinate are never selected (their distances would be NaN or infinite).
+/// Keeps a running min-distance-to-selection array so each step is a single O(n) pass
+/// (O(n * k) total) instead of recomputing distances to every selected point.
+/// Returns the selected indices in selection order, each at most once.
+fn farthest_point_sampling(positions: &[f32], target_count: usize) -> Vec<usize> {
+    // Non-finite points start at -1, and selected points are set to -1, so neither is ever the
+    // farthest. Without that, a cloud with fewer distinct positions than the target would pick an
+    // already selected point (min distance 0, like its duplicates) again.
+    let mut min_distance: Vec<f32> = positions
+        .chunks_exact(3)
+        .map(|p| if is_finite_point(p) { f32::INFINITY } else { -1.0 })
+        .collect();
+    let finite_count = min_distance.iter().filter(|&&d| d >= 0.0).count();
+    let target_count = target_count.min(finite_count);
+    let mut selected = Vec::with_capacity(target_count);
+    if target_count == 0 {
+        return selected;
+    }
+
+    let mut current = min_distance.iter().position(|&d| d >= 0.0).unwrap_or(0);
+    min_distance[current] = -1.0;
+    selected.push(current);
+
+    while selected.len() < target_count {
+        let c3 = current * 3;
+        let (cx, cy, cz) = (positions[c3], positions[c3 + 1], positions[c3 + 2]);
+        let mut farthest = 0usize;
+        let mut farthest_distance = -1.0f32;
+
+        for (i, min_d) in min_distance.iter_mut().enumerate() {
+            if *min_d < 0.0 {
+                continue;
+            }
+            let i3 = i * 3;
+            let dx = positions[i3] - cx;
+            let dy = positions[i3 + 1] - cy;
+            let dz = positions[i3 + 2] - cz;
+            let distance_squared = dx * dx + dy * dy + dz * dz;
+            if distance_squared < *min_d {
+                *min_d = distance_squared;
+            }
+            if *min_d > farthest_distance {
+                farthest_distance = *min_d;
+                farthest = i;
+            }
+        }
+
+        current = farthest;
+        min_distance[current] = -1.0;
+        selected.push(current);
+    }
+
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collinear_endpoints_chosen_first() {
+        // 11 points on the X axis, point 0 at one end
+        let positions: Vec<f32> = (0..11).flat_map(|i| [i as f32, 0.0, 0.0]).collect();
+        let selected = farthest_point_sampling(&positions, 3);
+        assert_eq!(selected, vec![0, 10, 5]);
+    }
+
+    #[test]
+    fn test_exact_target_count_and_distinct() {
+        let positions: Vec<f32> = (0..100)
+            .flat_map(|i| {
+                let t = i as f32 * 0.1;
+                [t.sin(), t.cos(), t * 0.2]
+            })
+            .collect();
+        let mut selected = farthest_point_sampling(&positions, 25);
+        assert_eq!(selected.len(), 25);
+        selected.sort_unstable();
+        selected.dedup();
+        assert_eq!(selected.len(), 25);
+    }
+
+    #[test]
+    fn test_non_finite_points_never_selected() {
+        let positions = vec![
+            f32::NAN, 0.0, 0.0,
+            0.0, 0.0, 0.0,
+            f32::INFINITY, 0.0, 0.0,
+            5.0, 0.0, 0.0,
+            2.0, f32::NEG_INFINITY, 0.0,
+            2.0, 0.0, 0.0,
+        ];
+        assert_eq!(farthest_point_sampling(&positions, 10), vec![1, 3, 5]);
+        assert!(farthest_point_sampling(&[f32::NAN, 0.0, 0.0], 1).is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_positions_never_select_an_index_twice() {
+        // Three distinct positions, each repeated, and a target above the distinct count
+        let positions = vec![
+            0.0, 0.0, 0.0,
+            4.0, 0.0, 0.0,
+            0.0, 0.0, 0.0,
+            4.0, 0.0, 0.0,
+            1.0, 0.0, 0.0,
+            0.0, 0.0, 0.0,
+        ];
+        let selected = farthest_point_sampling(&positions, 5);
+        assert_eq!(selected, vec![0, 1, 4, 2, 3]);
+
+        let mut all = farthest_point_sampling(&positions, 10);
+        assert_eq!(all.len(), 6);
+        all.sort_unstable();
+        assert_eq!(all, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_target_larger_than_cloud() {
+        let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+        assert_eq!(farthest_point_sampling(&positions, 10).len(), 2);
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_u32, ByteOrder};
+use pointcloud_tools_backend::protocol::{check_payload_size, read_tool_header, read_u8_payload, tool_id, write_stats, ErrorCode, ToolError};
+
+// Apply an index map returned by another tool (a downsampler's representative indices, a sort
+// permutation, a selection) to an attribute buffer the frontend keeps separately, so it stays in
+// sync with the reordered positions. Elements are stride values of valueBytes bytes each (e.g.
+// stride 3 and valueBytes 4 for f32 RGB, stride 1 and valueBytes 1 for classifications) and are
+// copied whole, so values keep their byte order. Indices may repeat or skip elements.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 elementCount][u32 indexCount][u32 stride][u32 valueBytes]
+//               [u32* indices][elementCount * stride * valueBytes bytes of attribute data]
+// Output format: [u32 indexCount][indexCount * stride * valueBytes bytes of gathered data]
+// valueBytes is 1, 2, 4 or 8; an index at or past elementCount is an error, and so is a gathered
+// output larger than the payload limit.
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (16 bytes: 4 * u32)
+    let (header, run): ([u8; 16], _) = match read_tool_header(&mut stdin, tool_id::GATHER) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let element_count = u32_at(&header, 0, run.order) as usize;
+    let index_count = u32_at(&header, 4, run.order) as usize;
+    let stride = u32_at(&header, 8, run.order) as usize;
+    let value_bytes = u32_at(&header, 12, run.order) as usize;
+
+    if stride == 0 || ![1, 2, 4, 8].contains(&value_bytes) {
+        ToolError::new(ErrorCode::InvalidData, "stride must be at least 1 and valueBytes 1, 2, 4 or 8").exit(run.order);
+    }
+    let element_bytes = match stride.checked_mul(value_bytes) {
+        Some(b) => b,
+        None => ToolError::new(ErrorCode::AllocationTooLarge, "stride * valueBytes overflows").exit(run.order),
+    };
+    // Repeated indices can make the output larger than the input, so cap it before reading
+    if let Err(e) = check_payload_size(index_count, element_bytes) {
+        e.exit(run.order);
+    }
+
+    let index_bytes = match read_u8_payload(&mut stdin, index_count.saturating_mul(4)) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let indices: Vec<u32> = (0..index_count).map(|i| u32_at(&index_bytes, i * 4, run.order)).collect();
+    let buffer = match read_u8_payload(&mut stdin, element_count.saturating_mul(element_bytes)) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let gathered = match gather(&buffer, element_bytes, &indices) {
+        Ok(g) => g,
+        Err(index) => ToolError::new(
+            ErrorCode::InvalidData,
+            format!("index {} is out of range for {} elements", index, element_count),
+        )
+        .exit(run.order),
+    };
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, index_count as u32, run.order).is_err()
+        || stdout.write_all(&gathered).is_err()
+        || write_stats(&mut stdout, &run, element_count, index_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Elements of `element_bytes` bytes picked from `buffer` in index order; Err with the first
+/// index past the end of the buffer
+fn gather(buffer: &[u8], element_bytes: usize, indices: &[u32]) -> Result<Vec<u8>, u32> {
+    let element_count = buffer.len() / element_bytes;
+    let mut gathered = Vec::with_capacity(indices.len() * element_bytes);
+    for &index in indices {
+        if index as usize >= element_count {
+            return Err(index);
+        }
+        let start = index as usize * element_bytes;
+        gathered.extend_from_slice(&buffer[start..start + element_bytes]);
+    }
+    Ok(gathered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shuffled_rgb_colors() {
+        let colors: Vec<f32> = (0..15).map(|v| v as f32 * 0.25).collect();
+        let bytes: Vec<u8> = colors.iter().flat_map(|c| c.to_le_bytes()).collect();
+        let shuffle = [3u32, 0, 4, 1, 2];
+
+        let gathered = gather(&bytes, 3 * 4, &shuffle).unwrap();
+        let reordered: Vec<f32> = gathered.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+        let expected: Vec<f32> = shuffle.iter().flat_map(|&i| colors[i as usize * 3..i as usize * 3 + 3].to_vec()).collect();
+        assert_eq!(reordered, expected);
+        assert_eq!(reordered[..3], [2.25, 2.5, 2.75]);
+    }
+
+    #[test]
+    fn test_selection_with_repeats_and_out_of_range_index() {
+        let classes = [2u8, 6, 9];
+        assert_eq!(gather(&classes, 1, &[2, 2, 0]), Ok(vec![9, 9, 2]));
+        assert_eq!(gather(&classes, 1, &[]), Ok(vec![]));
+        assert_eq!(gather(&classes, 1, &[1, 3]), Err(3));
+    }
+}
@@ -0,0 +1,164 @@
+// Axis-aligned bounds of a point cloud, shared by the tools that anchor a grid at the cloud
+// minimum (voxel downsampling and debug, smoothing, the spatial grid). Computing them once here
+// replaces the per-tool min/max loops, and a caller that already has bounds can pass them along.
+//
+// Scanner exports occasionally carry NaN or infinite coordinates. Such points are ignored by the
+// bounds and skipped by every grid (they would otherwise land in arbitrary cells), and tools with
+// a flags word remove them entirely when FLAG_DROP_NON_FINITE is set, via `sanitize`.
+
+use crate::binary_io::LeFloat;
+
+/// Whether all three coordinates of a point (either precision) are finite
+#[inline]
+pub fn is_finite_point<T: LeFloat>(p: &[T]) -> bool {
+    p[0].to_f64().is_finite() && p[1].to_f64().is_finite() && p[2].to_f64().is_finite()
+}
+
+/// Remove every point with a NaN or infinite coordinate from a flat xyz array. Returns the
+/// original indices of the kept points (to gather attributes with), or None when every point
+/// was finite and the array is unchanged.
+pub fn sanitize(points: &mut Vec<f32>) -> Option<Vec<usize>> {
+    if points.chunks_exact(3).all(is_finite_point::<f32>) {
+        return None;
+    }
+    let kept: Vec<usize> = points
+        .chunks_exact(3)
+        .enumerate()
+        .filter(|(_, p)| is_finite_point(p))
+        .map(|(i, _)| i)
+        .collect();
+    let mut write = 0;
+    for &i in &kept {
+        points.copy_within(i * 3..i * 3 + 3, write * 3);
+        write += 1;
+    }
+    points.truncate(write * 3);
+    Some(kept)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Bounds {
+    /// Per-axis minimum and maximum of a flat xyz array in a single pass, ignoring points with
+    /// a non-finite coordinate; None when it holds no complete finite point
+    pub fn from_points(points: &[f32]) -> Option<Bounds> {
+        let mut chunks = points.chunks_exact(3).filter(|p| is_finite_point(p));
+        let first = chunks.next()?;
+        let mut bounds = Bounds {
+            min: [first[0], first[1], first[2]],
+            max: [first[0], first[1], first[2]],
+        };
+        for p in chunks {
+            for (a, &v) in p.iter().enumerate() {
+                bounds.min[a] = bounds.min[a].min(v);
+                bounds.max[a] = bounds.max[a].max(v);
+            }
+        }
+        Some(bounds)
+    }
+
+    /// max - min per axis
+    pub fn extent(&self) -> [f32; 3] {
+        [
+            self.max[0] - self.min[0],
+            self.max[1] - self.min[1],
+            self.max[2] - self.min[2],
+        ]
+    }
+
+    /// Largest extent over the three axes
+    pub fn max_extent(&self) -> f32 {
+        let [x, y, z] = self.extent();
+        x.max(y).max(z)
+    }
+
+    /// Number of cells of `voxel_size` per axis in a grid anchored at `min` that covers every
+    /// point inside the bounds: floor(extent / size) + 1, so a point exactly on `max` still has
+    /// a cell of its own
+    pub fn voxel_counts(&self, voxel_size: f32) -> [usize; 3] {
+        let inv_voxel_size = 1.0 / voxel_size;
+        self.extent().map(|e| (e * inv_voxel_size) as usize + 1)
+    }
+
+    /// The same grid as bounds: `min` is kept and `max` grows to the far edge of the last cell
+    /// along each axis (see `voxel_counts`)
+    pub fn expand_to_voxel_grid(&self, voxel_size: f32) -> Bounds {
+        let counts = self.voxel_counts(voxel_size);
+        let mut max = self.min;
+        for a in 0..3 {
+            max[a] += counts[a] as f32 * voxel_size;
+        }
+        Bounds { min: self.min, max }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_points_matches_hand_rolled_loops() {
+        let mut points = Vec::new();
+        for i in 0..97 {
+            let t = i as f32 * 0.37;
+            points.extend_from_slice(&[t.sin() * 4.0 - 1.0, (t * 1.3).cos() * 2.5, t * 0.1 - 3.0]);
+        }
+
+        let mut min_x = points[0];
+        let mut max_x = points[0];
+        let mut min_y = points[1];
+        let mut max_y = points[1];
+        let mut min_z = points[2];
+        let mut max_z = points[2];
+        for i in (0..points.len()).step_by(3) {
+            min_x = min_x.min(points[i]);
+            max_x = max_x.max(points[i]);
+            min_y = min_y.min(points[i + 1]);
+            max_y = max_y.max(points[i + 1]);
+            min_z = min_z.min(points[i + 2]);
+            max_z = max_z.max(points[i + 2]);
+        }
+
+        let bounds = Bounds::from_points(&points).unwrap();
+        assert_eq!(bounds.min, [min_x, min_y, min_z]);
+        assert_eq!(bounds.max, [max_x, max_y, max_z]);
+        assert_eq!(Bounds::from_points(&[]), None);
+        assert_eq!(Bounds::from_points(&[1.0, 2.0]), None);
+    }
+
+    #[test]
+    fn test_expand_to_voxel_grid_covers_whole_cells() {
+        let bounds = Bounds { min: [0.0, -1.0, 2.0], max: [1.5, -1.0, 4.0] };
+        assert_eq!(bounds.voxel_counts(0.5), [4, 1, 5]);
+
+        let grid = bounds.expand_to_voxel_grid(0.5);
+        assert_eq!(grid.min, bounds.min);
+        assert_eq!(grid.max, [2.0, -0.5, 4.5]);
+    }
+
+    #[test]
+    fn test_non_finite_points_are_ignored_and_sanitized() {
+        let mut points = vec![
+            1.0, 2.0, 3.0,
+            f32::NAN, 0.0, 0.0,
+            -1.0, 5.0, 0.5,
+            0.0, f32::INFINITY, 0.0,
+            0.0, 0.0, f32::NEG_INFINITY,
+            2.0, -1.0, 1.0,
+        ];
+        let bounds = Bounds::from_points(&points).unwrap();
+        assert_eq!(bounds.min, [-1.0, -1.0, 0.5]);
+        assert_eq!(bounds.max, [2.0, 5.0, 3.0]);
+        assert_eq!(Bounds::from_points(&[f32::NAN, 0.0, 0.0]), None);
+
+        assert_eq!(sanitize(&mut points), Some(vec![0, 2, 5]));
+        assert_eq!(points, vec![1.0, 2.0, 3.0, -1.0, 5.0, 0.5, 2.0, -1.0, 1.0]);
+        // Already finite: untouched
+        assert_eq!(sanitize(&mut points), None);
+        assert_eq!(points.len(), 9);
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::{is_finite_point, Bounds};
+use pointcloud_tools_backend::protocol::{check_voxel_size, read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, ToolError, ToolRun};
+use pointcloud_tools_backend::voxel_hash::VoxelSet;
+
+// Candidate surface gaps for inspection. Points are binned into voxels on a grid anchored at the
+// cloud's minimum; an empty voxel is reported as a hole when, within its own Z layer, an occupied
+// voxel lies at most ringRadius voxels away along each of the 8 XY directions (the 4 axes and
+// the 4 diagonals). Open boundaries of the cloud therefore never qualify; a gap up to
+// 2 * ringRadius - 1 voxels across is flagged around its middle, and one no wider than
+// ringRadius in every direction is flagged entirely. Points with a NaN or infinite coordinate are
+// ignored.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 voxelSize][u32 ringRadius][f32* positions]
+// Output format: [u32 holeCount][f32* holeCenters], world-space voxel centers sorted by (z, y, x)
+// ringRadius is between 1 and MAX_RING_RADIUS. A voxelSize so small that the cloud spans more
+// than MAX_AXIS_VOXELS voxels along an axis is an error.
+
+const MAX_RING_RADIUS: u32 = 64;
+// Voxel indices are i32; leave room for stepping a full ring past the last voxel
+const MAX_AXIS_VOXELS: f64 = (i32::MAX - MAX_RING_RADIUS as i32) as f64;
+
+const DIRECTIONS: [[i32; 2]; 8] = [[1, 0], [-1, 0], [0, 1], [0, -1], [1, 1], [1, -1], [-1, 1], [-1, -1]];
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: u32 + f32 + u32)
+    let (header, mut run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::HOLE_DETECTION) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let voxel_size = f32_at(&header, 4, run.order);
+    let ring_radius = u32_at(&header, 8, run.order);
+
+    if let Err(e) = check_voxel_size(voxel_size) {
+        e.exit(run.order);
+    }
+    if ring_radius == 0 || ring_radius > MAX_RING_RADIUS {
+        ToolError::new(
+            ErrorCode::InvalidData,
+            format!("ringRadius must be between 1 and {}, got {}", MAX_RING_RADIUS, ring_radius),
+        )
+        .exit(run.order);
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count.saturating_mul(3), run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let centers = match Bounds::from_points(&positions) {
+        Some(bounds) => {
+            if let Err(e) = check_grid_extent(&bounds, voxel_size) {
+                e.exit(run.order);
+            }
+            detect_holes(&positions, voxel_size, bounds.min, ring_radius as i32, &mut run)
+        }
+        None => Vec::new(),
+    };
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, (centers.len() / 3) as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &centers, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, centers.len() / 3).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// The grid anchored at `bounds.min` must index every point, plus a ring around it, in i32
+fn check_grid_extent(bounds: &Bounds, voxel_size: f32) -> Result<(), ToolError> {
+    let inv_voxel_size = 1.0 / voxel_size as f64;
+    let axis_voxels = (0..3)
+        .map(|a| (bounds.max[a] as f64 - bounds.min[a] as f64) * inv_voxel_size)
+        .fold(0.0f64, f64::max);
+    if axis_voxels > MAX_AXIS_VOXELS {
+        return Err(ToolError::new(
+            ErrorCode::InvalidData,
+            format!("voxelSize {} spans {:.0} voxels along an axis, more than {}", voxel_size, axis_voxels, MAX_AXIS_VOXELS),
+        ));
+    }
+    Ok(())
+}
+
+/// World-space centers (flat xyz) of the empty voxels enclosed in XY by occupied ones.
+/// The grid must pass check_grid_extent.
+fn detect_holes(positions: &[f32], voxel_size: f32, min: [f32; 3], ring_radius: i32, run: &mut ToolRun) -> Vec<f32> {
+    let inv_voxel_size = 1.0 / voxel_size as f64;
+    let occupied: VoxelSet<[i32; 3]> = positions
+        .chunks_exact(3)
+        .filter(|p| is_finite_point(p))
+        .map(|p| [0, 1, 2].map(|a| ((p[a] as f64 - min[a] as f64) * inv_voxel_size).floor() as i32))
+        .collect();
+
+    // An enclosed voxel has an occupied one at most ring_radius steps along +x, so walking -x
+    // from every occupied voxel up to the next occupied one finds every candidate: at most
+    // ring_radius per occupied voxel
+    let mut candidates: VoxelSet<[i32; 3]> = VoxelSet::default();
+    for cell in &occupied {
+        for step in 1..=ring_radius {
+            let candidate = [cell[0] - step, cell[1], cell[2]];
+            if occupied.contains(&candidate) {
+                break;
+            }
+            candidates.insert(candidate);
+        }
+    }
+    run.note_map_size(occupied.len() + candidates.len());
+
+    let is_enclosed = |cell: &[i32; 3]| {
+        DIRECTIONS.iter().all(|[dx, dy]| {
+            (1..=ring_radius).any(|step| occupied.contains(&[cell[0] + dx * step, cell[1] + dy * step, cell[2]]))
+        })
+    };
+    let mut holes: Vec<[i32; 3]> = candidates.into_iter().filter(is_enclosed).collect();
+    holes.sort_unstable_by_key(|&[x, y, z]| [z, y, x]);
+
+    holes
+        .iter()
+        .flat_map(|cell| [0, 1, 2].map(|a| (min[a] as f64 + (cell[a] as f64 + 0.5) * voxel_size as f64) as f32))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 20 x 20 plane at z = 0 with one point per unit voxel, minus the points `skip` rejects
+    fn plane(skip: impl Fn(i32, i32) -> bool) -> Vec<f32> {
+        let mut positions = Vec::new();
+        for y in 0..20 {
+            for x in 0..20 {
+                if !skip(x, y) {
+                    positions.extend_from_slice(&[x as f32 + 0.25, y as f32 + 0.25, 0.25]);
+                }
+            }
+        }
+        positions
+    }
+
+    #[test]
+    fn test_removed_patch_center_is_reported() {
+        // 3 x 3 patch removed around voxel (10, 10)
+        let positions = plane(|x, y| (9..=11).contains(&x) && (9..=11).contains(&y));
+        let centers = detect_holes(&positions, 1.0, [0.25, 0.25, 0.25], 2, &mut ToolRun::default());
+        assert_eq!(centers, vec![10.75, 10.75, 0.75]);
+
+        // A ring as wide as the patch reaches across it from every removed voxel
+        let centers = detect_holes(&positions, 1.0, [0.25, 0.25, 0.25], 3, &mut ToolRun::default());
+        let holes: Vec<&[f32]> = centers.chunks_exact(3).collect();
+        assert_eq!(holes.len(), 9);
+        assert!(holes.contains(&&[10.75f32, 10.75, 0.75][..]));
+        for h in holes {
+            assert!((9.0..12.0).contains(&(h[0] - 0.25)) && (9.0..12.0).contains(&(h[1] - 0.25)), "{:?}", h);
+        }
+    }
+
+    #[test]
+    fn test_complete_plane_and_wide_gap_report_nothing() {
+        assert!(detect_holes(&plane(|_, _| false), 1.0, [0.25, 0.25, 0.25], 3, &mut ToolRun::default()).is_empty());
+
+        // No voxel of a 7-voxel-wide patch sees both of its sides within a ring of 2, and the
+        // open edges of the plane are never enclosed
+        let positions = plane(|x, y| (7..=13).contains(&x) && (7..=13).contains(&y));
+        assert!(detect_holes(&positions, 1.0, [0.25, 0.25, 0.25], 2, &mut ToolRun::default()).is_empty());
+        assert_eq!(detect_holes(&positions, 1.0, [0.25, 0.25, 0.25], 4, &mut ToolRun::default()), vec![10.75, 10.75, 0.75]);
+    }
+
+    #[test]
+    fn test_candidates_bounded_by_ring_per_occupied_voxel() {
+        let positions = plane(|x, y| (9..=11).contains(&x) && (9..=11).contains(&y));
+        let occupied = positions.len() / 3;
+        let mut run = ToolRun::default();
+        let centers = detect_holes(&positions, 1.0, [0.25, 0.25, 0.25], MAX_RING_RADIUS as i32, &mut run);
+        assert_eq!(centers.len(), 9 * 3);
+        // Each of the 20 rows yields the ring left of the plane, plus the 3 removed voxels in the
+        // rows crossing the patch
+        assert_eq!(run.peak_map_size() as usize, occupied + 20 * MAX_RING_RADIUS as usize + 3 * 3);
+    }
+
+    #[test]
+    fn test_oversize_grid_rejected() {
+        let bounds = Bounds { min: [0.0; 3], max: [1.0e6, 0.0, 0.0] };
+        assert!(check_grid_extent(&bounds, 1.0).is_ok());
+        assert_eq!(check_grid_extent(&bounds, 1.0e-4).unwrap_err().code, ErrorCode::InvalidData);
+        let huge = Bounds { min: [-3.0e38, 0.0, 0.0], max: [3.0e38, 0.0, 0.0] };
+        assert!(check_grid_extent(&huge, 1.0).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, ByteOrder};
+use pointcloud_tools_backend::convex_hull::convex_hull;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+
+// Volume and surface area of the cloud's convex hull (same QuickHull as convex_hull_rust), for
+// stockpile and other volumetric measurements straight from the points. The volume is the sum of
+// signed tetrahedra over the hull triangles and the area the sum of their areas, both in f64.
+// Being a hull, it overestimates concave shapes. Points with a non-finite coordinate are ignored.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32* positions]
+// Output format: [f32 volume][f32 surfaceArea]
+// Collinear or coplanar input (and clouds of fewer than four points) gives 0 for both.
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (4 bytes: u32)
+    let (header, run): ([u8; 4], _) = match read_tool_header(&mut stdin, tool_id::HULL_VOLUME) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let hull = convex_hull(&positions);
+    let measurements = [hull.volume() as f32, hull.surface_area() as f32];
+
+    let mut stdout = io::stdout();
+    if write_f32_slice(&mut stdout, &measurements, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, 1).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_cube_corners() {
+        let mut positions = Vec::new();
+        for corner in 0..8 {
+            positions.extend_from_slice(&[(corner & 1) as f32, ((corner >> 1) & 1) as f32, ((corner >> 2) & 1) as f32]);
+        }
+        let hull = convex_hull(&positions);
+        assert!((hull.volume() - 1.0).abs() < 1e-9, "volume {}", hull.volume());
+        assert!((hull.surface_area() - 6.0).abs() < 1e-9, "area {}", hull.surface_area());
+
+        // Moving the cube far from the origin and adding interior points changes nothing
+        let shifted: Vec<f32> = positions.iter().map(|v| v + 1000.0).chain([1000.5, 1000.5, 1000.5]).collect();
+        let hull = convex_hull(&shifted);
+        assert!((hull.volume() - 1.0).abs() < 1e-6 && (hull.surface_area() - 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sphere_sample_approaches_sphere_measures() {
+        // Fibonacci lattice on a sphere of radius 2: the inscribed hull is slightly smaller
+        let n = 2000;
+        let mut positions = Vec::new();
+        for i in 0..n {
+            let z = 1.0 - 2.0 * (i as f32 + 0.5) / n as f32;
+            let r = (1.0 - z * z).sqrt();
+            let phi = i as f32 * 2.399_963;
+            positions.extend_from_slice(&[2.0 * r * phi.cos(), 2.0 * r * phi.sin(), 2.0 * z]);
+        }
+        let hull = convex_hull(&positions);
+        let (volume, area) = (hull.volume(), hull.surface_area());
+        let sphere_volume = 4.0 / 3.0 * std::f64::consts::PI * 8.0;
+        let sphere_area = 4.0 * std::f64::consts::PI * 4.0;
+        assert!(volume < sphere_volume && volume > sphere_volume * 0.99, "volume {}", volume);
+        assert!(area < sphere_area && area > sphere_area * 0.99, "area {}", area);
+
+        assert_eq!(convex_hull(&positions[..9]).volume(), 0.0);
+    }
+}
@@ -0,0 +1,268 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::linalg::symmetric_eigen_jacobi;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Point-to-point ICP: estimates the rigid transform that aligns the source cloud to the target.
+// Each iteration pairs every transformed source point with its nearest target point (spatial
+// grid), solves for the rigid motion minimizing the squared pair distances, and composes it into
+// the running transform. Stops after maxIterations or when the RMS pair distance changes by less
+// than tolerance. The least-squares rotation (the Kabsch solution) is taken from Horn's
+// quaternion form: the eigenvector of the largest eigenvalue of a symmetric 4x4 matrix, which is
+// always a proper rotation, so no reflection correction is needed.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 sourceCount][u32 targetCount][u32 maxIterations][f32 tolerance]
+//               [f32* sourcePositions][f32* targetPositions]
+// Output format: [f32*16 matrix (column-major, maps source onto target)][f32 rmsError][u32 iterations]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (16 bytes: 3 * u32 + f32)
+    let (header, mut run): ([u8; 16], _) = match read_tool_header(&mut stdin, tool_id::ICP) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let source_count = u32_at(&header, 0, run.order) as usize;
+    let target_count = u32_at(&header, 4, run.order) as usize;
+    let max_iterations = u32_at(&header, 8, run.order) as usize;
+    let tolerance = f32_at(&header, 12, run.order) as f64;
+
+    let source = match read_f32_payload(&mut stdin, source_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let target = match read_f32_payload(&mut stdin, target_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    // Nothing to pair: identity with zero error
+    let result = if source_count == 0 || target_count == 0 {
+        IcpResult { transform: RigidTransform::identity(), rms: 0.0, iterations: 0 }
+    } else {
+        icp(&source, &target, max_iterations, tolerance, &mut run)
+    };
+
+    let mut stdout = io::stdout();
+    if write_f32_slice(&mut stdout, &result.transform.to_column_major(), run.order).is_err()
+        || write_f32_slice(&mut stdout, &[result.rms as f32], run.order).is_err()
+        || write_u32(&mut stdout, result.iterations as u32, run.order).is_err()
+        || write_stats(&mut stdout, &run, source_count + target_count, source_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct RigidTransform {
+    rotation: [[f64; 3]; 3],
+    translation: [f64; 3],
+}
+
+impl RigidTransform {
+    fn identity() -> RigidTransform {
+        RigidTransform {
+            rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            translation: [0.0; 3],
+        }
+    }
+
+    fn apply(&self, p: [f64; 3]) -> [f64; 3] {
+        let r = &self.rotation;
+        let mut out = self.translation;
+        for (a, o) in out.iter_mut().enumerate() {
+            *o += r[a][0] * p[0] + r[a][1] * p[1] + r[a][2] * p[2];
+        }
+        out
+    }
+
+    /// self applied after `first`
+    fn compose(&self, first: &RigidTransform) -> RigidTransform {
+        let mut rotation = [[0.0f64; 3]; 3];
+        for (i, row) in rotation.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.rotation[i][k] * first.rotation[k][j]).sum();
+            }
+        }
+        RigidTransform { rotation, translation: self.apply(first.translation) }
+    }
+
+    fn to_column_major(self) -> [f32; 16] {
+        let mut m = [0.0f32; 16];
+        for c in 0..3 {
+            for r in 0..3 {
+                m[c * 4 + r] = self.rotation[r][c] as f32;
+            }
+        }
+        for r in 0..3 {
+            m[12 + r] = self.translation[r] as f32;
+        }
+        m[15] = 1.0;
+        m
+    }
+}
+
+struct IcpResult {
+    transform: RigidTransform,
+    rms: f64,
+    iterations: usize,
+}
+
+fn point(positions: &[f32], i: usize) -> [f64; 3] {
+    [positions[i * 3] as f64, positions[i * 3 + 1] as f64, positions[i * 3 + 2] as f64]
+}
+
+/// Least-squares rigid transform mapping `from[i]` onto `to[i]` (Horn's quaternion method)
+fn best_rigid_transform(from: &[[f64; 3]], to: &[[f64; 3]]) -> RigidTransform {
+    let n = from.len() as f64;
+    let mut from_centroid = [0.0f64; 3];
+    let mut to_centroid = [0.0f64; 3];
+    for (p, q) in from.iter().zip(to) {
+        for a in 0..3 {
+            from_centroid[a] += p[a] / n;
+            to_centroid[a] += q[a] / n;
+        }
+    }
+
+    // Cross-covariance s[a][b] = sum (p_a - cp_a)(q_b - cq_b)
+    let mut s = [[0.0f64; 3]; 3];
+    for (p, q) in from.iter().zip(to) {
+        for a in 0..3 {
+            for b in 0..3 {
+                s[a][b] += (p[a] - from_centroid[a]) * (q[b] - to_centroid[b]);
+            }
+        }
+    }
+    let (sxx, sxy, sxz) = (s[0][0], s[0][1], s[0][2]);
+    let (syx, syy, syz) = (s[1][0], s[1][1], s[1][2]);
+    let (szx, szy, szz) = (s[2][0], s[2][1], s[2][2]);
+    let horn = [
+        [sxx + syy + szz, syz - szy, szx - sxz, sxy - syx],
+        [syz - szy, sxx - syy - szz, sxy + syx, szx + sxz],
+        [szx - sxz, sxy + syx, -sxx + syy - szz, syz + szy],
+        [sxy - syx, szx + sxz, syz + szy, -sxx - syy + szz],
+    ];
+    let (values, vectors) = symmetric_eigen_jacobi(&horn);
+    let best = (0..4).max_by(|&a, &b| values[a].total_cmp(&values[b])).unwrap_or(0);
+    let (w, x, y, z) = (vectors[0][best], vectors[1][best], vectors[2][best], vectors[3][best]);
+
+    let rotation = [
+        [w * w + x * x - y * y - z * z, 2.0 * (x * y - w * z), 2.0 * (x * z + w * y)],
+        [2.0 * (x * y + w * z), w * w - x * x + y * y - z * z, 2.0 * (y * z - w * x)],
+        [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), w * w - x * x - y * y + z * z],
+    ];
+    let rotated = RigidTransform { rotation, translation: [0.0; 3] }.apply(from_centroid);
+    let translation = [
+        to_centroid[0] - rotated[0],
+        to_centroid[1] - rotated[1],
+        to_centroid[2] - rotated[2],
+    ];
+    RigidTransform { rotation, translation }
+}
+
+fn icp(source: &[f32], target: &[f32], max_iterations: usize, tolerance: f64, run: &mut ToolRun) -> IcpResult {
+    let grid = SpatialGrid::new(target, SpatialGrid::auto_cell_size(target));
+    run.note_map_size(grid.cell_count());
+    let source_count = source.len() / 3;
+    let mut transform = RigidTransform::identity();
+    let mut moved: Vec<[f64; 3]> = (0..source_count).map(|i| point(source, i)).collect();
+    let mut matched: Vec<[f64; 3]> = vec![[0.0; 3]; source_count];
+
+    // Pair every moved source point with its nearest target point; returns the RMS distance
+    let correspond = |moved: &[[f64; 3]], matched: &mut [[f64; 3]]| -> f64 {
+        let mut sum = 0.0f64;
+        for (p, m) in moved.iter().zip(matched.iter_mut()) {
+            if let Some((j, _)) = grid.nearest(target, p[0] as f32, p[1] as f32, p[2] as f32) {
+                *m = point(target, j);
+            }
+            sum += (0..3).map(|a| (p[a] - m[a]) * (p[a] - m[a])).sum::<f64>();
+        }
+        (sum / moved.len() as f64).sqrt()
+    };
+
+    let mut rms = correspond(&moved, &mut matched);
+    let mut iterations = 0;
+    while iterations < max_iterations {
+        let step = best_rigid_transform(&moved, &matched);
+        transform = step.compose(&transform);
+        for (i, p) in moved.iter_mut().enumerate() {
+            *p = transform.apply(point(source, i));
+        }
+        iterations += 1;
+
+        let new_rms = correspond(&moved, &mut matched);
+        let change = (rms - new_rms).abs();
+        rms = new_rms;
+        if change < tolerance {
+            break;
+        }
+    }
+
+    IcpResult { transform, rms, iterations }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotation_xz(ax: f64, az: f64) -> [[f64; 3]; 3] {
+        let (sx, cx) = ax.sin_cos();
+        let (sz, cz) = az.sin_cos();
+        // Rz * Rx
+        [
+            [cz, -sz * cx, sz * sx],
+            [sz, cz * cx, -cz * sx],
+            [0.0, sx, cx],
+        ]
+    }
+
+    #[test]
+    fn test_best_rigid_transform_recovers_exact_motion() {
+        let known = RigidTransform { rotation: rotation_xz(0.4, -1.1), translation: [1.0, -2.0, 0.5] };
+        let from: Vec<[f64; 3]> = (0..20).map(|i| [i as f64 * 0.3, (i * i % 7) as f64, (i % 3) as f64]).collect();
+        let to: Vec<[f64; 3]> = from.iter().map(|&p| known.apply(p)).collect();
+        let estimated = best_rigid_transform(&from, &to);
+        for r in 0..3 {
+            for c in 0..3 {
+                assert!((estimated.rotation[r][c] - known.rotation[r][c]).abs() < 1e-9);
+            }
+            assert!((estimated.translation[r] - known.translation[r]).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_converges_to_inverse_of_applied_transform() {
+        // Target: a bumpy, asymmetric height field
+        let mut target = Vec::new();
+        for i in 0..30 {
+            for j in 0..30 {
+                let (x, y) = (i as f32 * 0.05, j as f32 * 0.05);
+                target.extend_from_slice(&[x, y, 0.3 * (4.0 * x).sin() * (3.0 * y).cos() + 0.2 * x * y]);
+            }
+        }
+        // Source: the target moved by a known transform, so aligning source to target must
+        // recover that transform's inverse
+        let applied = RigidTransform { rotation: rotation_xz(0.03, 0.05), translation: [0.02, -0.015, 0.01] };
+        let source: Vec<f32> = (0..target.len() / 3)
+            .flat_map(|i| applied.apply(point(&target, i)).map(|v| v as f32))
+            .collect();
+
+        let result = icp(&source, &target, 100, 1e-9, &mut ToolRun::default());
+        assert!(result.rms < 1e-3, "rms {}", result.rms);
+
+        // result.transform * applied should be the identity
+        let round_trip = result.transform.compose(&applied);
+        for r in 0..3 {
+            for c in 0..3 {
+                let expected = if r == c { 1.0 } else { 0.0 };
+                assert!((round_trip.rotation[r][c] - expected).abs() < 1e-3, "{:?}", round_trip);
+            }
+            assert!(round_trip.translation[r].abs() < 1e-3, "{:?}", round_trip);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, gather_positions, FLAG_INTENSITY, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError};
+
+// Intensity filter for LiDAR clouds: keeps the points whose intensity lies in
+// [minIntensity, maxIntensity] (inclusive), dropping dark/low-return noise before downsampling.
+// The intensity block is required (bit1); every attribute block present is filtered alongside
+// the positions, and kept points stay in input order.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 minIntensity][f32 maxIntensity][u32 flags]
+//               [f32* positions][optional colors][f32* intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity (must be set), bit2=classification, bit31=drop non-finite points
+// Output format: [u32 outputCount][f32* positions][optional colors][f32* intensities][optional classifications]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (16 bytes: u32 + 2 * f32 + u32)
+    let (header, run): ([u8; 16], _) = match read_tool_header(&mut stdin, tool_id::INTENSITY_FILTER) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let min_intensity = f32_at(&header, 4, run.order);
+    let max_intensity = f32_at(&header, 8, run.order);
+    let flags = u32_at(&header, 12, run.order);
+
+    if flags & FLAG_INTENSITY == 0 {
+        ToolError::new(ErrorCode::InvalidData, "intensity filter needs the intensity block (flags bit1)").exit(run.order);
+    }
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+    let intensities = attributes.intensities.as_deref().unwrap_or_default();
+
+    let kept = filter_by_intensity(intensities, min_intensity, max_intensity);
+    let kept_positions = gather_positions(&positions, &kept);
+    let kept_attributes = attributes.gather(&kept);
+
+    if write_u32(&mut stdout, kept.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &kept_positions, run.order).is_err()
+        || kept_attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, kept.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Indices of the points with min_intensity <= intensity <= max_intensity, in input order
+fn filter_by_intensity(intensities: &[f32], min_intensity: f32, max_intensity: f32) -> Vec<usize> {
+    intensities
+        .iter()
+        .enumerate()
+        .filter(|&(_, &v)| v >= min_intensity && v <= max_intensity)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_in_range_points_survive_with_their_positions() {
+        // Point i sits at x = i and has intensity (i * 37) % 100, so each survivor's position
+        // identifies which intensity it must carry
+        let count = 50;
+        let positions: Vec<f32> = (0..count).flat_map(|i| [i as f32, 0.5, -1.0]).collect();
+        let intensities: Vec<f32> = (0..count).map(|i| ((i * 37) % 100) as f32).collect();
+        let attributes = PointAttributes {
+            intensities: Some(intensities.clone()),
+            classifications: Some((0..count).map(|i| i as u8).collect()),
+            ..Default::default()
+        };
+
+        let kept = filter_by_intensity(&intensities, 20.0, 60.0);
+        assert!(!kept.is_empty() && kept.len() < count);
+        let kept_positions = gather_positions(&positions, &kept);
+        let kept_attributes = attributes.gather(&kept);
+        let kept_intensities = kept_attributes.intensities.unwrap();
+        let kept_classes = kept_attributes.classifications.unwrap();
+
+        for ((p, &intensity), &class) in kept_positions.chunks_exact(3).zip(&kept_intensities).zip(&kept_classes) {
+            assert!((20.0..=60.0).contains(&intensity));
+            let i = p[0] as usize;
+            assert_eq!(intensity, intensities[i]);
+            assert_eq!(class as usize, i);
+        }
+        let expected = intensities.iter().filter(|&&v| (20.0..=60.0).contains(&v)).count();
+        assert_eq!(kept.len(), expected);
+    }
+
+    #[test]
+    fn test_bounds_are_inclusive_and_nan_is_dropped() {
+        let intensities = vec![0.0, 10.0, 10.5, 20.0, 20.5, f32::NAN];
+        assert_eq!(filter_by_intensity(&intensities, 10.0, 20.0), vec![1, 2, 3]);
+    }
+}
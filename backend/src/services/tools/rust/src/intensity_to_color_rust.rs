@@ -0,0 +1,103 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::colormap::{normalize_range, Colormap};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 colormap][f32* intensities]
+// colormap: 0=grayscale, 1=hot, 2=jet (hue ramp), 3=viridis, 4=turbo; other ids are an InvalidData error
+// Output format: [u32 pointCount][f32* colors] (r, g, b in [0, 1] per point)
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: 2 * u32)
+    let (header, run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::INTENSITY_TO_COLOR) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let colormap = match Colormap::from_id(u32_at(&header, 4, run.order)) {
+        Ok(c) => c,
+        Err(e) => e.exit(run.order),
+    };
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let intensities = match read_f32_payload(&mut stdin, point_count, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let colors = intensity_to_color(&intensities, colormap);
+
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &colors, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Normalize intensities to [0, 1] over the cloud's own range and look each one up in the colormap
+fn intensity_to_color(intensities: &[f32], colormap: Colormap) -> Vec<f32> {
+    let normalized = normalize_range(intensities);
+    let mut colors = Vec::with_capacity(intensities.len() * 3);
+    for t in normalized {
+        colors.extend_from_slice(&colormap.sample(t));
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pointcloud_tools_backend::protocol::ErrorCode;
+
+    fn assert_color_eq(actual: &[f32], expected: [f32; 3]) {
+        for c in 0..3 {
+            assert!((actual[c] - expected[c]).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_intensity_extremes_map_to_colormap_ends() {
+        let intensities = vec![120.0, 5.0, 60.0, 250.0];
+        for colormap in [Colormap::Grayscale, Colormap::Hot, Colormap::Jet, Colormap::Viridis, Colormap::Turbo] {
+            let colors = intensity_to_color(&intensities, colormap);
+            assert_eq!(colors.len(), 12);
+            assert_color_eq(&colors[3..6], colormap.start());
+            assert_color_eq(&colors[9..12], colormap.end());
+        }
+    }
+
+    #[test]
+    fn test_grayscale_brightness_follows_intensity() {
+        let colors = intensity_to_color(&[0.0, 0.5, 1.0], Colormap::Grayscale);
+        assert_color_eq(&colors[3..6], [0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_unknown_colormap_id_is_rejected() {
+        assert_eq!(Colormap::from_id(0), Ok(Colormap::Grayscale));
+        assert_eq!(Colormap::from_id(4), Ok(Colormap::Turbo));
+        for id in [5, u32::MAX] {
+            assert_eq!(Colormap::from_id(id).unwrap_err().code, ErrorCode::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_constant_intensity_maps_to_start() {
+        let colors = intensity_to_color(&[7.0, 7.0], Colormap::Jet);
+        assert_color_eq(&colors[0..3], Colormap::Jet.start());
+    }
+}
@@ -0,0 +1,174 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::linalg::solve3;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+
+// Robust ground model by iterative plane refinement for sloped terrain.
+// Fit z = a*x + b*y + c by least squares, keep the points that lie below the plane or at most
+// `threshold` above it, refit on those, and repeat until the ground set stops changing or the
+// iteration cap is reached. Above-ground structure drops out after the first few passes.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 threshold][u32 maxIterations][f32* positions]
+// Output format: [u32 pointCount][f32 nx][f32 ny][f32 nz][f32 d][u8* groundMask]
+// plane: nx*x + ny*y + nz*z + d = 0 with unit normal, nz > 0; mask 1 = ground
+// Points with a NaN or infinite coordinate are left out of every fit and are never ground.
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: u32 + f32 + u32)
+    let (header, run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::ITERATIVE_GROUND) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let threshold = f32_at(&header, 4, run.order);
+    let max_iterations = u32_at(&header, 8, run.order) as usize;
+
+    let mut stdout = io::stdout();
+
+    if point_count < 3 || threshold < 0.0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let (plane, mask) = iterative_ground(&positions, threshold, max_iterations.max(1));
+
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &plane, run.order).is_err()
+        || stdout.write_all(&mask).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Least-squares z = a*x + b*y + c over the masked points (centered for conditioning)
+fn fit_height_plane(positions: &[f32], mask: &[u8]) -> Option<[f64; 3]> {
+    let mut count = 0usize;
+    let mut mean = [0.0f64; 3];
+    for (p, _) in positions.chunks_exact(3).zip(mask).filter(|(_, &m)| m != 0) {
+        for axis in 0..3 {
+            mean[axis] += p[axis] as f64;
+        }
+        count += 1;
+    }
+    if count < 3 {
+        return None;
+    }
+    for m in mean.iter_mut() {
+        *m /= count as f64;
+    }
+
+    let mut normal_matrix = [[0.0f64; 3]; 3];
+    let mut rhs = [0.0f64; 3];
+    for (p, _) in positions.chunks_exact(3).zip(mask).filter(|(_, &m)| m != 0) {
+        let row = [p[0] as f64 - mean[0], p[1] as f64 - mean[1], 1.0];
+        let dz = p[2] as f64 - mean[2];
+        for r in 0..3 {
+            for c in 0..3 {
+                normal_matrix[r][c] += row[r] * row[c];
+            }
+            rhs[r] += row[r] * dz;
+        }
+    }
+    let [a, b, c0] = solve3(&normal_matrix, rhs)?;
+    Some([a, b, mean[2] + c0 - a * mean[0] - b * mean[1]])
+}
+
+/// Returns the final plane as [nx, ny, nz, d] and the ground mask
+fn iterative_ground(positions: &[f32], threshold: f32, max_iterations: usize) -> ([f32; 4], Vec<u8>) {
+    let mut mask: Vec<u8> = positions.chunks_exact(3).map(|p| u8::from(is_finite_point(p))).collect();
+    let mut coefficients = match fit_height_plane(positions, &mask) {
+        Some(c) => c,
+        None => return ([0.0, 0.0, 1.0, 0.0], mask),
+    };
+
+    for _ in 0..max_iterations {
+        let [a, b, c] = coefficients;
+        let next_mask: Vec<u8> = positions
+            .chunks_exact(3)
+            .map(|p| {
+                let residual = p[2] as f64 - (a * p[0] as f64 + b * p[1] as f64 + c);
+                u8::from(is_finite_point(p) && residual <= threshold as f64)
+            })
+            .collect();
+        let converged = next_mask == mask;
+        mask = next_mask;
+        if converged {
+            break;
+        }
+        match fit_height_plane(positions, &mask) {
+            Some(c) => coefficients = c,
+            None => break,
+        }
+    }
+
+    // z = a*x + b*y + c  <=>  (-a, -b, 1) . p - c = 0, normalized
+    let [a, b, c] = coefficients;
+    let length = (a * a + b * b + 1.0).sqrt();
+    let plane = [
+        (-a / length) as f32,
+        (-b / length) as f32,
+        (1.0 / length) as f32,
+        (-c / length) as f32,
+    ];
+    (plane, mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_building_on_slope_excluded() {
+        // 10 m x 10 m slope rising 0.2 m per meter in x, with a 3 m tall building footprint
+        let mut positions = Vec::new();
+        let mut is_building = Vec::new();
+        for i in 0..40 {
+            for j in 0..40 {
+                let x = i as f32 * 0.25;
+                let y = j as f32 * 0.25;
+                positions.extend_from_slice(&[x, y, 0.2 * x]);
+                is_building.push(false);
+            }
+        }
+        for i in 0..12 {
+            for j in 0..12 {
+                let x = 6.0 + i as f32 * 0.25;
+                let y = 2.0 + j as f32 * 0.25;
+                for level in 1..=6 {
+                    positions.extend_from_slice(&[x, y, 0.2 * x + level as f32 * 0.5]);
+                    is_building.push(true);
+                }
+            }
+        }
+
+        // Scanner dropouts below and inside the slope
+        for p in [[f32::NAN, 1.0, 0.0], [2.0, 2.0, f32::NEG_INFINITY], [f32::INFINITY, 3.0, 0.6]] {
+            positions.extend_from_slice(&p);
+        }
+        let (plane, mask) = iterative_ground(&positions, 0.1, 20);
+        assert_eq!(mask[mask.len() - 3..], [0, 0, 0]);
+
+        for (p, (&m, &building)) in mask.iter().zip(&is_building).enumerate() {
+            assert_eq!(m == 1, !building, "point {} misclassified", p);
+        }
+        let length = (0.2f32 * 0.2 + 1.0).sqrt();
+        assert!((plane[0] - (-0.2 / length)).abs() < 1e-3);
+        assert!(plane[1].abs() < 1e-3);
+        assert!((plane[2] - 1.0 / length).abs() < 1e-3);
+        assert!(plane[3].abs() < 1e-3);
+    }
+}
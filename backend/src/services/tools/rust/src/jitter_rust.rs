@@ -0,0 +1,147 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, u64_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError};
+use pointcloud_tools_backend::rng::Pcg32;
+
+// Add random noise to every coordinate, to generate synthetic test data and stress the smoothers.
+// Uniform noise is drawn from [-sigma, sigma]; Gaussian noise has standard deviation sigma. The
+// PCG32 stream is seeded, so the same seed and input give the same output. Attributes pass
+// through unchanged and points with a NaN or infinite coordinate are left where they are (they
+// draw no samples).
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 sigma][u64 seed][u32 flags]
+//               [f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit3=Gaussian instead of uniform noise,
+//        bit31=drop non-finite points
+// Output format: [u32 pointCount][f32* positions][optional colors][optional intensities][optional classifications]
+// sigma <= 0 leaves the positions unchanged.
+
+const FLAG_GAUSSIAN: u32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Distribution {
+    Uniform,
+    Gaussian,
+}
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (20 bytes: u32 + f32 + u64 + u32)
+    let (header, run): ([u8; 20], _) = match read_tool_header(&mut stdin, tool_id::JITTER) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let sigma = f32_at(&header, 4, run.order);
+    let seed = u64_at(&header, 8, run.order);
+    let flags = u32_at(&header, 16, run.order);
+    let distribution = if flags & FLAG_GAUSSIAN != 0 { Distribution::Gaussian } else { Distribution::Uniform };
+
+    if !sigma.is_finite() {
+        ToolError::new(ErrorCode::InvalidData, "sigma must be finite").exit(run.order);
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+
+    jitter(&mut positions, sigma, seed, distribution);
+
+    let output_count = positions.len() / 3;
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, output_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &positions, run.order).is_err()
+        || attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, output_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Displace every finite point of a flat xyz array by seeded noise of amplitude sigma
+fn jitter(positions: &mut [f32], sigma: f32, seed: u64, distribution: Distribution) {
+    if sigma <= 0.0 {
+        return;
+    }
+    let sigma = sigma as f64;
+    let mut rng = Pcg32::new(seed);
+    for p in positions.chunks_exact_mut(3).filter(|p| is_finite_point(p)) {
+        for v in p.iter_mut() {
+            let noise = match distribution {
+                Distribution::Uniform => (rng.next_f64() * 2.0 - 1.0) * sigma,
+                Distribution::Gaussian => rng.next_gaussian() * sigma,
+            };
+            *v = (*v as f64 + noise) as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid_cloud() -> Vec<f32> {
+        (0..5000).flat_map(|i| [(i % 50) as f32 * 0.1, (i / 50) as f32 * 0.1, 1.0]).collect()
+    }
+
+    #[test]
+    fn test_same_seed_reproduces_the_same_output() {
+        for distribution in [Distribution::Uniform, Distribution::Gaussian] {
+            let mut a = grid_cloud();
+            let mut b = grid_cloud();
+            let mut c = grid_cloud();
+            jitter(&mut a, 0.05, 17, distribution);
+            jitter(&mut b, 0.05, 17, distribution);
+            jitter(&mut c, 0.05, 18, distribution);
+            assert_eq!(a, b);
+            assert_ne!(a, c);
+            assert_ne!(a, grid_cloud());
+        }
+
+        let mut unchanged = vec![1.0, 2.0, 3.0, f32::NAN, 0.0, 0.0];
+        jitter(&mut unchanged, 0.0, 1, Distribution::Uniform);
+        assert_eq!(unchanged[..3], [1.0, 2.0, 3.0]);
+        jitter(&mut unchanged, 1.0, 1, Distribution::Gaussian);
+        assert!(unchanged[3].is_nan() && unchanged[4] == 0.0);
+    }
+
+    #[test]
+    fn test_displacement_is_centered_with_the_requested_spread() {
+        let sigma = 0.2f32;
+        for distribution in [Distribution::Uniform, Distribution::Gaussian] {
+            let original = grid_cloud();
+            let mut jittered = original.clone();
+            jitter(&mut jittered, sigma, 5, distribution);
+
+            let displacements: Vec<f64> = jittered.iter().zip(&original).map(|(&a, &b)| (a - b) as f64).collect();
+            let n = displacements.len() as f64;
+            let mean = displacements.iter().sum::<f64>() / n;
+            let variance = displacements.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / n;
+            assert!(mean.abs() < 0.01, "{:?} mean {}", distribution, mean);
+
+            // Uniform on [-sigma, sigma] has standard deviation sigma / sqrt(3)
+            let expected = match distribution {
+                Distribution::Uniform => sigma as f64 / 3f64.sqrt(),
+                Distribution::Gaussian => sigma as f64,
+            };
+            assert!((variance.sqrt() - expected).abs() < 0.05 * expected, "{:?} std {}", distribution, variance.sqrt());
+            if distribution == Distribution::Uniform {
+                assert!(displacements.iter().all(|d| d.abs() <= sigma as f64 + 1e-6));
+            }
+        }
+    }
+}
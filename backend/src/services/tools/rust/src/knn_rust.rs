@@ -0,0 +1,127 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{to_wire_order, u32_at, write_u32, ByteOrder};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// k-nearest-neighbor queries against a cloud. The cloud is bucketed into a uniform grid sized
+// from its own density (auto cell size), and each query searches shells of cells outward until
+// the k-th candidate is closer than any unvisited shell, so no search radius has to be guessed.
+// k is clamped to the cloud size; neighbors are listed nearest first.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 cloudCount][u32 queryCount][u32 k][f32* cloudPositions][f32* queryPositions]
+// Output format: [u32 queryCount][u32 k] then per query k pairs of [u32 index][f32 distSq]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: 3 * u32)
+    let (header, mut run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::KNN) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let cloud_count = u32_at(&header, 0, run.order) as usize;
+    let query_count = u32_at(&header, 4, run.order) as usize;
+    let k = (u32_at(&header, 8, run.order) as usize).min(cloud_count);
+
+    let cloud = match read_f32_payload(&mut stdin, cloud_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let queries = match read_f32_payload(&mut stdin, query_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let mut stdout = io::stdout();
+
+    if k == 0 || query_count == 0 {
+        if write_u32(&mut stdout, query_count as u32, run.order).is_err()
+            || write_u32(&mut stdout, 0, run.order).is_err()
+            || write_stats(&mut stdout, &run, cloud_count + query_count, query_count).is_err()
+            || stdout.flush().is_err()
+        {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let neighbors = k_nearest_neighbors(&cloud, &queries, k, &mut run);
+
+    let mut bytes = Vec::with_capacity(neighbors.len() * 8);
+    for (index, distance_squared) in &neighbors {
+        bytes.extend_from_slice(&(*index as u32).to_le_bytes());
+        bytes.extend_from_slice(&distance_squared.to_le_bytes());
+    }
+    to_wire_order(&mut bytes, 4, run.order);
+    if write_u32(&mut stdout, query_count as u32, run.order).is_err()
+        || write_u32(&mut stdout, k as u32, run.order).is_err()
+        || stdout.write_all(&bytes).is_err()
+        || write_stats(&mut stdout, &run, cloud_count + query_count, query_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// For each query, its `k` nearest cloud points as (index, distance_squared), nearest first,
+/// flattened query by query
+fn k_nearest_neighbors(cloud: &[f32], queries: &[f32], k: usize, run: &mut ToolRun) -> Vec<(usize, f32)> {
+    let grid = SpatialGrid::new(cloud, SpatialGrid::auto_cell_size(cloud));
+    run.note_map_size(grid.cell_count());
+    let mut neighbors = Vec::with_capacity(queries.len() / 3 * k);
+    for q in queries.chunks_exact(3) {
+        neighbors.extend(grid.k_nearest(cloud, q[0], q[1], q[2], k));
+    }
+    neighbors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lattice() -> Vec<f32> {
+        let mut points = Vec::new();
+        for i in 0..8 {
+            for j in 0..8 {
+                for l in 0..4 {
+                    points.extend_from_slice(&[i as f32 * 0.5, j as f32 * 0.5, l as f32 * 0.5]);
+                }
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn test_cloud_points_are_their_own_nearest_neighbor() {
+        let cloud = lattice();
+        let count = cloud.len() / 3;
+        let neighbors = k_nearest_neighbors(&cloud, &cloud, 3, &mut ToolRun::default());
+        assert_eq!(neighbors.len(), count * 3);
+        for (q, result) in neighbors.chunks_exact(3).enumerate() {
+            assert_eq!(result[0], (q, 0.0));
+            // Lattice spacing 0.5: the next neighbors are exactly one step away
+            assert!((result[1].1 - 0.25).abs() < 1e-6);
+            assert!(result[1].1 <= result[2].1);
+        }
+    }
+
+    #[test]
+    fn test_matches_brute_force_for_off_grid_queries() {
+        let cloud = lattice();
+        let queries = vec![0.3, 0.2, 0.1, 3.9, 3.6, 1.7, -1.0, 2.0, 0.7];
+        let k = 5;
+        let neighbors = k_nearest_neighbors(&cloud, &queries, k, &mut ToolRun::default());
+        for (q, result) in queries.chunks_exact(3).zip(neighbors.chunks_exact(k)) {
+            let mut brute: Vec<f32> = cloud
+                .chunks_exact(3)
+                .map(|p| (p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2) + (p[2] - q[2]).powi(2))
+                .collect();
+            brute.sort_by(f32::total_cmp);
+            for (found, expected) in result.iter().zip(&brute) {
+                assert!((found.1 - expected).abs() < 1e-5);
+            }
+        }
+    }
+}
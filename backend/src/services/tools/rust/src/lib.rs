@@ -0,0 +1,20 @@
+// Shared building blocks for the backend Rust tool binaries.
+// Each tool in this crate is a standalone executable speaking a binary stdin/stdout protocol;
+// the modules here hold the pieces several tools share.
+
+pub mod binary_io;
+pub mod colormap;
+pub mod convex_hull;
+pub mod delta_codec;
+pub mod geometry;
+pub mod linalg;
+pub mod morton;
+pub mod normals;
+pub mod pcd_loader;
+pub mod ply_loader;
+pub mod point_attributes;
+pub mod protocol;
+pub mod rng;
+pub mod spatial_grid;
+pub mod voxel_hash;
+pub mod xyz_io;
@@ -0,0 +1,329 @@
+// Small fixed-size linear algebra for neighborhood statistics (3x3 covariance and solves).
+// Accumulation is done in f64: neighborhoods are small, but coordinates can be large
+// relative to their spread and f32 sums of squares lose the covariance.
+
+pub type Mat3 = [[f64; 3]; 3];
+
+/// Centroid and covariance (divided by n) of the points at `indices`
+pub fn covariance(points: &[f32], indices: &[usize]) -> ([f64; 3], Mat3) {
+    let mut centroid = [0.0f64; 3];
+    let mut cov = [[0.0f64; 3]; 3];
+    if indices.is_empty() {
+        return (centroid, cov);
+    }
+    for &j in indices {
+        for axis in 0..3 {
+            centroid[axis] += points[j * 3 + axis] as f64;
+        }
+    }
+    let n = indices.len() as f64;
+    for c in centroid.iter_mut() {
+        *c /= n;
+    }
+    for &j in indices {
+        let d = [
+            points[j * 3] as f64 - centroid[0],
+            points[j * 3 + 1] as f64 - centroid[1],
+            points[j * 3 + 2] as f64 - centroid[2],
+        ];
+        for (r, row) in cov.iter_mut().enumerate() {
+            for (c, v) in row.iter_mut().enumerate().skip(r) {
+                *v += d[r] * d[c];
+            }
+        }
+    }
+    for v in cov.iter_mut().flatten() {
+        *v /= n;
+    }
+    cov[1][0] = cov[0][1];
+    cov[2][0] = cov[0][2];
+    cov[2][1] = cov[1][2];
+    (centroid, cov)
+}
+
+pub fn determinant(m: &Mat3) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+/// Solve m * x = b by Cramer's rule; None when m is (numerically) singular
+pub fn solve3(m: &Mat3, b: [f64; 3]) -> Option<[f64; 3]> {
+    let det = determinant(m);
+    let scale = m.iter().flatten().fold(0.0f64, |acc, v| acc.max(v.abs()));
+    if scale == 0.0 || det.abs() <= 1e-12 * scale * scale * scale {
+        return None;
+    }
+    let mut x = [0.0f64; 3];
+    for (col, xc) in x.iter_mut().enumerate() {
+        let mut mc = *m;
+        for row in 0..3 {
+            mc[row][col] = b[row];
+        }
+        *xc = determinant(&mc) / det;
+    }
+    Some(x)
+}
+
+/// Inverse-transpose (cofactor matrix / determinant), the matrix that maps normals under m;
+/// None when m is singular
+pub fn inverse_transpose(m: &Mat3) -> Option<Mat3> {
+    let det = determinant(m);
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+    let mut out = [[0.0f64; 3]; 3];
+    for (r, row) in out.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            let (r1, r2) = ((r + 1) % 3, (r + 2) % 3);
+            let (c1, c2) = ((c + 1) % 3, (c + 2) % 3);
+            *value = (m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1]) / det;
+        }
+    }
+    Some(out)
+}
+
+/// Solve the n x n system a * x = b (a row-major) by Gaussian elimination with partial pivoting;
+/// None when a is (numerically) singular. Used for small least-squares normal equations.
+pub fn solve_dense(mut a: Vec<f64>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    let scale = a.iter().fold(0.0f64, |acc, v| acc.max(v.abs()));
+    if scale == 0.0 {
+        return None;
+    }
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&r, &s| a[r * n + col].abs().total_cmp(&a[s * n + col].abs()))?;
+        if a[pivot * n + col].abs() <= 1e-12 * scale {
+            return None;
+        }
+        if pivot != col {
+            for k in 0..n {
+                a.swap(pivot * n + k, col * n + k);
+            }
+            b.swap(pivot, col);
+        }
+        for row in col + 1..n {
+            let factor = a[row * n + col] / a[col * n + col];
+            for k in col..n {
+                a[row * n + k] -= factor * a[col * n + k];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = vec![0.0f64; n];
+    for row in (0..n).rev() {
+        let tail: f64 = (row + 1..n).map(|k| a[row * n + k] * x[k]).sum();
+        x[row] = (b[row] - tail) / a[row * n + row];
+    }
+    Some(x)
+}
+
+pub fn trace(m: &Mat3) -> f64 {
+    m[0][0] + m[1][1] + m[2][2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn norm_squared(v: [f64; 3]) -> f64 {
+    v[0] * v[0] + v[1] * v[1] + v[2] * v[2]
+}
+
+/// Eigenvalues of a symmetric 3x3 matrix in ascending order (closed-form trigonometric solution)
+pub fn symmetric_eigenvalues(m: &Mat3) -> [f64; 3] {
+    let p1 = m[0][1] * m[0][1] + m[0][2] * m[0][2] + m[1][2] * m[1][2];
+    if p1 == 0.0 {
+        let mut diagonal = [m[0][0], m[1][1], m[2][2]];
+        diagonal.sort_by(|a, b| a.total_cmp(b));
+        return diagonal;
+    }
+    let q = trace(m) / 3.0;
+    let p2 = (m[0][0] - q).powi(2) + (m[1][1] - q).powi(2) + (m[2][2] - q).powi(2) + 2.0 * p1;
+    let p = (p2 / 6.0).sqrt();
+    let mut b = *m;
+    for (i, row) in b.iter_mut().enumerate() {
+        for v in row.iter_mut() {
+            *v /= p;
+        }
+        row[i] -= q / p;
+    }
+    let r = (determinant(&b) / 2.0).clamp(-1.0, 1.0);
+    let phi = r.acos() / 3.0;
+    let largest = q + 2.0 * p * phi.cos();
+    let smallest = q + 2.0 * p * (phi + 2.0 * std::f64::consts::PI / 3.0).cos();
+    let middle = 3.0 * q - largest - smallest;
+    [smallest, middle, largest]
+}
+
+/// Unit eigenvector of a symmetric 3x3 matrix for a given eigenvalue.
+/// Taken as the largest cross product of two rows of (m - lambda I); when the eigenvalue is
+/// repeated any unit vector of its eigenspace is returned.
+pub fn symmetric_eigenvector(m: &Mat3, eigenvalue: f64) -> [f64; 3] {
+    let mut rows = *m;
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[i] -= eigenvalue;
+    }
+    let candidates = [
+        cross(rows[0], rows[1]),
+        cross(rows[0], rows[2]),
+        cross(rows[1], rows[2]),
+    ];
+    let (best, best_norm) = candidates
+        .iter()
+        .map(|&c| (c, norm_squared(c)))
+        .fold(([0.0; 3], 0.0), |acc, c| if c.1 > acc.1 { c } else { acc });
+
+    let scale = m.iter().flatten().fold(0.0f64, |acc, v| acc.max(v.abs())).max(f64::MIN_POSITIVE);
+    if best_norm > 1e-20 * scale.powi(4) {
+        let inv = 1.0 / best_norm.sqrt();
+        return [best[0] * inv, best[1] * inv, best[2] * inv];
+    }
+
+    // Rank <= 1: eigenspace is the plane orthogonal to the dominant row (or everything)
+    let (row, row_norm) = rows
+        .iter()
+        .map(|&r| (r, norm_squared(r)))
+        .fold(([0.0; 3], 0.0), |acc, r| if r.1 > acc.1 { r } else { acc });
+    if row_norm <= 1e-20 * scale.powi(2) {
+        return [0.0, 0.0, 1.0];
+    }
+    let axis = if row[0].abs() < row[1].abs() && row[0].abs() < row[2].abs() {
+        [1.0, 0.0, 0.0]
+    } else if row[1].abs() < row[2].abs() {
+        [0.0, 1.0, 0.0]
+    } else {
+        [0.0, 0.0, 1.0]
+    };
+    let v = cross(row, axis);
+    let inv = 1.0 / norm_squared(v).sqrt();
+    [v[0] * inv, v[1] * inv, v[2] * inv]
+}
+
+/// Eigen-decomposition of a small symmetric matrix by cyclic Jacobi rotations.
+/// Returns the eigenvalues and the matching unit eigenvectors as the columns of the second
+/// matrix (unsorted). Unlike the closed-form 3x3 solver, the eigenvectors are orthonormal even
+/// when eigenvalues repeat.
+pub fn symmetric_eigen_jacobi<const N: usize>(m: &[[f64; N]; N]) -> ([f64; N], [[f64; N]; N]) {
+    let mut a = *m;
+    let mut v = [[0.0f64; N]; N];
+    for (i, row) in v.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    for _sweep in 0..64 {
+        let off: f64 = (0..N).flat_map(|p| (p + 1..N).map(move |q| (p, q))).map(|(p, q)| a[p][q] * a[p][q]).sum();
+        let scale: f64 = (0..N).map(|i| a[i][i] * a[i][i]).sum::<f64>() + off;
+        if off <= 1e-30 * scale.max(f64::MIN_POSITIVE) {
+            break;
+        }
+        for p in 0..N {
+            for q in p + 1..N {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                // Rotation angle that zeroes a[p][q]
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let t = if theta == 0.0 { 1.0 } else { t };
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (row_p, row_q) = (a[p], a[q]);
+                a[p] = std::array::from_fn(|k| c * row_p[k] - s * row_q[k]);
+                a[q] = std::array::from_fn(|k| s * row_p[k] + c * row_q[k]);
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    let mut values = [0.0f64; N];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = a[i][i];
+    }
+    (values, v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve3() {
+        let m = [[2.0, 1.0, 0.0], [1.0, 3.0, 1.0], [0.0, 1.0, 4.0]];
+        let x = solve3(&m, [3.0, 5.0, 5.0]).unwrap();
+        for (v, e) in x.iter().zip([1.0, 1.0, 1.0]) {
+            assert!((v - e).abs() < 1e-9);
+        }
+        assert!(solve3(&[[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 0.0, 1.0]], [1.0, 1.0, 1.0]).is_none());
+    }
+
+    #[test]
+    fn test_jacobi_repeated_eigenvalues() {
+        // Repeated eigenvalue: the eigenvectors must still be orthonormal
+        let m = [[2.0, 0.0, 0.0], [0.0, 2.0, 0.0], [0.0, 0.0, 5.0]];
+        let (values, vectors) = symmetric_eigen_jacobi(&m);
+        let mut sorted = values;
+        sorted.sort_by(f64::total_cmp);
+        assert_eq!(sorted, [2.0, 2.0, 5.0]);
+
+        let m = [[4.0, 1.0, 0.5, 0.0], [1.0, 3.0, 0.0, 0.2], [0.5, 0.0, 2.0, 0.1], [0.0, 0.2, 0.1, 1.0]];
+        let (values, vectors4) = symmetric_eigen_jacobi(&m);
+        for (col, &lambda) in values.iter().enumerate() {
+            for row in 0..4 {
+                let mv: f64 = (0..4).map(|k| m[row][k] * vectors4[k][col]).sum();
+                assert!((mv - lambda * vectors4[row][col]).abs() < 1e-9);
+            }
+        }
+        for a in 0..3 {
+            for b in 0..3 {
+                let dot: f64 = (0..3).map(|k| vectors[k][a] * vectors[k][b]).sum();
+                assert!((dot - if a == b { 1.0 } else { 0.0 }).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve_dense() {
+        // Needs a row swap: the first pivot is zero
+        let a = vec![0.0, 2.0, 1.0, 1.0, 1.0, 0.0, 2.0, 0.0, 3.0];
+        let x = solve_dense(a, vec![7.0, 3.0, 11.0]).unwrap();
+        for (got, want) in x.iter().zip([1.0, 2.0, 3.0]) {
+            assert!((got - want).abs() < 1e-9, "{:?}", x);
+        }
+        assert!(solve_dense(vec![1.0, 2.0, 2.0, 4.0], vec![1.0, 2.0]).is_none());
+    }
+
+    #[test]
+    fn test_symmetric_eigen() {
+        let m = [[2.0, 1.0, 0.0], [1.0, 2.0, 0.0], [0.0, 0.0, 5.0]];
+        let values = symmetric_eigenvalues(&m);
+        for (v, e) in values.iter().zip([1.0, 3.0, 5.0]) {
+            assert!((v - e).abs() < 1e-9);
+        }
+        let smallest = symmetric_eigenvector(&m, values[0]);
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((smallest[0].abs() - s).abs() < 1e-9);
+        assert!((smallest[0] + smallest[1]).abs() < 1e-9);
+        assert!(smallest[2].abs() < 1e-9);
+
+        // Repeated eigenvalue still yields a unit vector of the eigenspace
+        let flat = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 0.0]];
+        let v = symmetric_eigenvector(&flat, 1.0);
+        assert!(v[2].abs() < 1e-9 && (norm_squared(v) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_covariance_of_line() {
+        let points = [0.0f32, 0.0, 0.0, 2.0, 0.0, 0.0];
+        let (centroid, cov) = covariance(&points, &[0, 1]);
+        assert_eq!(centroid, [1.0, 0.0, 0.0]);
+        assert!((cov[0][0] - 1.0).abs() < 1e-12);
+        assert_eq!(cov[1][1], 0.0);
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, ToolError, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Per-point local density, to drive adaptive effects and find sparse or noisy regions. Mode 0
+// counts the other points within radius; mode 1 sums an Epanechnikov kernel 1 - (d / radius)^2
+// over them instead, so near neighbors weigh more than ones at the edge of the ball. Neighbors
+// come from the shared spatial grid with cell size radius. Points with a NaN or infinite
+// coordinate get NaN and are nobody's neighbor.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 radius][u32 mode][f32* positions]
+// Output format: [u32 pointCount][f32* densities]
+
+const MODE_KERNEL: u32 = 1;
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: u32 + f32 + u32)
+    let (header, mut run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::LOCAL_DENSITY) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let radius = f32_at(&header, 4, run.order);
+    let kernel = u32_at(&header, 8, run.order) == MODE_KERNEL;
+
+    if !(radius > 0.0 && radius.is_finite()) {
+        ToolError::new(ErrorCode::InvalidData, "radius must be positive and finite").exit(run.order);
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let densities = local_density(&positions, radius, kernel, &mut run);
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &densities, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Neighbor count (or kernel sum with `kernel`) within `radius` of each point of a flat xyz array
+fn local_density(positions: &[f32], radius: f32, kernel: bool, run: &mut ToolRun) -> Vec<f32> {
+    let grid = SpatialGrid::new(positions, radius);
+    run.note_map_size(grid.cell_count());
+    let inv_radius_squared = 1.0 / (radius * radius);
+    positions
+        .chunks_exact(3)
+        .enumerate()
+        .map(|(i, p)| {
+            if !is_finite_point(p) {
+                return f32::NAN;
+            }
+            let mut density = 0.0f32;
+            grid.for_each_in_radius(positions, p[0], p[1], p[2], radius, |j, distance_squared| {
+                if j != i {
+                    density += if kernel { 1.0 - distance_squared * inv_radius_squared } else { 1.0 };
+                }
+            });
+            density
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dense_core_outranks_sparse_shell() {
+        // 500 points in a ball of radius 1 and 500 on a shell of radius 10 around it
+        let mut positions = Vec::new();
+        for (scale, count) in [(1.0f32, 500), (10.0, 500)] {
+            for i in 0..count {
+                let z = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+                let r = (1.0 - z * z).sqrt();
+                let phi = i as f32 * 2.399_963;
+                let depth = if scale == 1.0 { ((i % 7) as f32 + 1.0) / 7.0 } else { 1.0 };
+                positions.extend_from_slice(&[scale * depth * r * phi.cos(), scale * depth * r * phi.sin(), scale * depth * z]);
+            }
+        }
+
+        for kernel in [false, true] {
+            let densities = local_density(&positions, 0.75, kernel, &mut ToolRun::default());
+            let mean = |range: std::ops::Range<usize>| densities[range.clone()].iter().sum::<f32>() / range.len() as f32;
+            let (core, shell) = (mean(0..500), mean(500..1000));
+            assert!(core > 5.0 * shell, "kernel {}: core {} shell {}", kernel, core, shell);
+            let min_core = densities[..500].iter().cloned().fold(f32::INFINITY, f32::min);
+            let max_shell = densities[500..].iter().cloned().fold(0.0, f32::max);
+            assert!(min_core > max_shell, "kernel {}: {} vs {}", kernel, min_core, max_shell);
+        }
+    }
+
+    #[test]
+    fn test_counts_exclude_the_point_itself() {
+        let positions = vec![0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.5, 0.0, 5.0, 0.0, 0.0, f32::NAN, 0.0, 0.0];
+        let counts = local_density(&positions, 0.6, false, &mut ToolRun::default());
+        assert_eq!(counts[..4], [2.0, 1.0, 1.0, 0.0]);
+        assert!(counts[4].is_nan());
+
+        let kernel = local_density(&positions, 1.0, true, &mut ToolRun::default());
+        assert!((kernel[1] - (0.75 + (1.0 - 0.5))).abs() < 1e-6, "{}", kernel[1]);
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::rng::Pcg32;
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Typical point spacing, for picking a voxel size or smoothing radius automatically: the mean
+// and median distance from a point to its nearest neighbor. sampleFraction of the finite points
+// (at least one) are probed, chosen with a fixed-seed PCG32 stream so repeated calls agree;
+// fractions <= 0, >= 1 or NaN probe every point. Neighbors are found on the shared spatial grid
+// and may be any finite point, not only sampled ones. Duplicate points count as spacing 0.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 sampleFraction][f32* positions]
+// Output format: [f32 mean][f32 median]
+// Fewer than two finite points give -1 for both.
+
+// Sampling is reproducible rather than configurable
+const SAMPLE_SEED: u64 = 0x5eed;
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: u32 + f32)
+    let (header, mut run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::MEAN_SPACING) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let sample_fraction = f32_at(&header, 4, run.order);
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let (mean, median) = mean_spacing(&positions, sample_fraction, &mut run).unwrap_or((-1.0, -1.0));
+
+    let mut stdout = io::stdout();
+    if write_f32_slice(&mut stdout, &[mean, median], run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, 1).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// (mean, median) nearest-neighbor distance over a sample of the finite points of a flat xyz
+/// array; None with fewer than two finite points
+fn mean_spacing(positions: &[f32], sample_fraction: f32, run: &mut ToolRun) -> Option<(f32, f32)> {
+    let finite: Vec<usize> = (0..positions.len() / 3).filter(|&i| is_finite_point(&positions[i * 3..i * 3 + 3])).collect();
+    if finite.len() < 2 {
+        return None;
+    }
+
+    // Selection sampling: exactly probe_count indices, in input order
+    let probe_count = if sample_fraction > 0.0 && sample_fraction < 1.0 {
+        ((finite.len() as f64 * sample_fraction as f64).ceil() as usize).clamp(1, finite.len())
+    } else {
+        finite.len()
+    };
+    let mut rng = Pcg32::new(SAMPLE_SEED);
+    let mut probes = Vec::with_capacity(probe_count);
+    for (seen, &i) in finite.iter().enumerate() {
+        let needed = probe_count - probes.len();
+        if (rng.next_f64() * (finite.len() - seen) as f64) < needed as f64 {
+            probes.push(i);
+        }
+    }
+
+    let grid = SpatialGrid::new(positions, SpatialGrid::auto_cell_size(positions));
+
+    run.note_map_size(grid.cell_count());
+    let mut distances: Vec<f64> = probes
+        .iter()
+        .map(|&i| {
+            let p = &positions[i * 3..i * 3 + 3];
+            // The closest hit is the probe itself (or a duplicate at distance 0, which is as good)
+            let nearest = grid.k_nearest(positions, p[0], p[1], p[2], 2);
+            (nearest[1].1 as f64).sqrt()
+        })
+        .collect();
+
+    let mean = distances.iter().sum::<f64>() / distances.len() as f64;
+    distances.sort_unstable_by(f64::total_cmp);
+    let middle = distances.len() / 2;
+    let median = if distances.len().is_multiple_of(2) { (distances[middle - 1] + distances[middle]) * 0.5 } else { distances[middle] };
+    Some((mean as f32, median as f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regular_grid_spacing() {
+        let spacing = 0.25f32;
+        let mut positions = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                for k in 0..5 {
+                    positions.extend_from_slice(&[i as f32 * spacing + 3.0, j as f32 * spacing - 1.0, k as f32 * spacing]);
+                }
+            }
+        }
+        for fraction in [1.0, 0.1] {
+            let (mean, median) = mean_spacing(&positions, fraction, &mut ToolRun::default()).unwrap();
+            assert!((mean - spacing).abs() < 1e-5, "fraction {}: mean {}", fraction, mean);
+            assert!((median - spacing).abs() < 1e-5, "fraction {}: median {}", fraction, median);
+        }
+    }
+
+    #[test]
+    fn test_median_resists_an_outlier_and_tiny_clouds_are_rejected() {
+        // Three points 1 apart on a line plus one far away: distances 1, 1, 1, 99
+        let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0, 101.0, 0.0, 0.0, f32::NAN, 0.0, 0.0];
+        let (mean, median) = mean_spacing(&positions, 0.0, &mut ToolRun::default()).unwrap();
+        assert!((mean - 25.5).abs() < 1e-4, "mean {}", mean);
+        assert_eq!(median, 1.0);
+
+        assert_eq!(mean_spacing(&[1.0, 2.0, 3.0], 1.0, &mut ToolRun::default()), None);
+        assert_eq!(mean_spacing(&[], 1.0, &mut ToolRun::default()), None);
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::{self, Read, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError};
+
+// Concatenates several clouds (e.g. scans registered with icp_rust) into one. Points keep their
+// order: all of cloud 0, then all of cloud 1, and so on. The output carries every attribute that
+// at least one input has; clouds without it get a default (white for colors, 0 for intensity
+// and classification) so the blocks stay aligned with the positions.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 cloudCount]
+//               then per cloud [u32 pointCount][u32 flags][f32* positions][optional colors][optional intensities][optional classifications]
+// flags (per cloud): bit0=colors, bit1=intensity, bit2=classification, bit31=drop non-finite points
+// Output format: [u32 pointCount][u32 flags (union of the attribute bits)][f32* positions][optional colors][optional intensities][optional classifications]
+
+const DEFAULT_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+const DEFAULT_INTENSITY: f32 = 0.0;
+const DEFAULT_CLASSIFICATION: u8 = 0;
+
+struct Cloud {
+    positions: Vec<f32>,
+    attributes: PointAttributes,
+}
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (4 bytes: u32)
+    let (header, run): ([u8; 4], _) = match read_tool_header(&mut stdin, tool_id::MERGE_CLOUDS) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let cloud_count = u32_at(&header, 0, run.order) as usize;
+
+    let mut clouds = Vec::new();
+    let mut input_count = 0;
+    for c in 0..cloud_count {
+        let mut cloud_header = [0u8; 8];
+        if stdin.read_exact(&mut cloud_header).is_err() {
+            ToolError::new(ErrorCode::ShortPayload, format!("input ends inside the header of cloud {}", c)).exit(run.order);
+        }
+        let point_count = u32_at(&cloud_header, 0, run.order) as usize;
+        let flags = u32_at(&cloud_header, 4, run.order);
+        input_count += point_count;
+
+        let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+            Ok(v) => v,
+            Err(e) => e.exit(run.order),
+        };
+        let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+            Ok(a) => a,
+            Err(_) => {
+                ToolError::new(ErrorCode::ShortPayload, format!("input ends inside the attribute blocks of cloud {}", c))
+                    .exit(run.order)
+            }
+        };
+        if flags & FLAG_DROP_NON_FINITE != 0 {
+            drop_non_finite(&mut positions, &mut attributes);
+        }
+        clouds.push(Cloud { positions, attributes });
+    }
+
+    let (positions, attributes) = merge_clouds(&clouds);
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, (positions.len() / 3) as u32, run.order).is_err()
+        || write_u32(&mut stdout, attributes.flags(), run.order).is_err()
+        || write_f32_slice(&mut stdout, &positions, run.order).is_err()
+        || attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, input_count, positions.len() / 3).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Positions and attributes of all clouds back to back, in cloud order
+fn merge_clouds(clouds: &[Cloud]) -> (Vec<f32>, PointAttributes) {
+    let total: usize = clouds.iter().map(|c| c.positions.len() / 3).sum();
+    let any = |has: fn(&PointAttributes) -> bool| clouds.iter().any(|c| has(&c.attributes));
+
+    let mut positions = Vec::with_capacity(total * 3);
+    let mut merged = PointAttributes {
+        colors: any(|a| a.colors.is_some()).then(|| Vec::with_capacity(total * 3)),
+        intensities: any(|a| a.intensities.is_some()).then(|| Vec::with_capacity(total)),
+        classifications: any(|a| a.classifications.is_some()).then(|| Vec::with_capacity(total)),
+    };
+
+    for cloud in clouds {
+        let count = cloud.positions.len() / 3;
+        positions.extend_from_slice(&cloud.positions);
+        if let Some(colors) = merged.colors.as_mut() {
+            match &cloud.attributes.colors {
+                Some(c) => colors.extend_from_slice(c),
+                None => colors.extend(DEFAULT_COLOR.iter().cycle().take(count * 3)),
+            }
+        }
+        if let Some(intensities) = merged.intensities.as_mut() {
+            match &cloud.attributes.intensities {
+                Some(v) => intensities.extend_from_slice(v),
+                None => intensities.resize(intensities.len() + count, DEFAULT_INTENSITY),
+            }
+        }
+        if let Some(classifications) = merged.classifications.as_mut() {
+            match &cloud.attributes.classifications {
+                Some(v) => classifications.extend_from_slice(v),
+                None => classifications.resize(classifications.len() + count, DEFAULT_CLASSIFICATION),
+            }
+        }
+    }
+
+    (positions, merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_keeps_order_and_fills_missing_attributes() {
+        let first = Cloud {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            attributes: PointAttributes {
+                colors: Some(vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0]),
+                ..Default::default()
+            },
+        };
+        let second = Cloud {
+            positions: vec![2.0, 2.0, 2.0, 3.0, 3.0, 3.0, 4.0, 4.0, 4.0],
+            attributes: PointAttributes {
+                intensities: Some(vec![10.0, 20.0, 30.0]),
+                ..Default::default()
+            },
+        };
+
+        let (positions, attributes) = merge_clouds(&[first, second]);
+        assert_eq!(positions.len() / 3, 2 + 3);
+        assert_eq!(positions, vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 3.0, 3.0, 3.0, 4.0, 4.0, 4.0]);
+        assert_eq!(
+            attributes.colors.unwrap(),
+            vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0]
+        );
+        assert_eq!(attributes.intensities.unwrap(), vec![0.0, 0.0, 10.0, 20.0, 30.0]);
+        assert_eq!(attributes.classifications, None);
+    }
+
+    #[test]
+    fn test_merging_nothing_is_empty() {
+        let (positions, attributes) = merge_clouds(&[]);
+        assert!(positions.is_empty());
+        assert_eq!(attributes.flags(), 0);
+    }
+}
@@ -0,0 +1,203 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::linalg::solve_dense;
+use pointcloud_tools_backend::normals::fit_local_plane;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Moving-least-squares smoothing: each point is projected onto a surface fitted to its neighbors
+// within the search radius instead of being pulled toward their mean. The reference plane comes
+// from PCA of the neighborhood; heights above it are fitted with a Gaussian-weighted quadratic
+// w = a + bu + cv + du^2 + euv + fv^2 and the point moves to the height of the fit. The quadratic
+// follows curved surfaces, so the cloud does not shrink the way neighbor averaging does.
+// Neighborhoods with fewer than 6 points (or a degenerate fit) fall back to plane projection;
+// with fewer than 3 the point is left in place.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 searchRadius][f32* positions]
+// Output format: [u32 pointCount][f32* positions]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: u32 + f32)
+    let (header, mut run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::MLS_SMOOTH) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let search_radius = f32_at(&header, 4, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || search_radius <= 0.0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let smoothed = mls_smooth(&positions, search_radius, &mut run);
+
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &smoothed, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Two unit vectors spanning the plane perpendicular to the unit vector `n`
+fn tangent_basis(n: [f64; 3]) -> ([f64; 3], [f64; 3]) {
+    // Cross with the axis least aligned with n
+    let axis = if n[0].abs() <= n[1].abs() && n[0].abs() <= n[2].abs() {
+        [1.0, 0.0, 0.0]
+    } else if n[1].abs() <= n[2].abs() {
+        [0.0, 1.0, 0.0]
+    } else {
+        [0.0, 0.0, 1.0]
+    };
+    let t = [n[1] * axis[2] - n[2] * axis[1], n[2] * axis[0] - n[0] * axis[2], n[0] * axis[1] - n[1] * axis[0]];
+    let len = dot(t, t).sqrt();
+    let e1 = [t[0] / len, t[1] / len, t[2] / len];
+    let e2 = [n[1] * e1[2] - n[2] * e1[1], n[2] * e1[0] - n[0] * e1[2], n[0] * e1[1] - n[1] * e1[0]];
+    (e1, e2)
+}
+
+fn mls_smooth(positions: &[f32], search_radius: f32, run: &mut ToolRun) -> Vec<f32> {
+    let grid = SpatialGrid::new(positions, search_radius);
+    run.note_map_size(grid.cell_count());
+    // Gaussian weight exp(-d^2 / h^2) with h = radius / 2
+    let inv_h_squared = 4.0 / (search_radius as f64 * search_radius as f64);
+    let mut smoothed = positions.to_vec();
+    let mut neighbors: Vec<(usize, f32)> = Vec::new();
+
+    for (i, out) in smoothed.chunks_exact_mut(3).enumerate() {
+        let i3 = i * 3;
+        let p = [positions[i3] as f64, positions[i3 + 1] as f64, positions[i3 + 2] as f64];
+        neighbors.clear();
+        grid.for_each_in_radius(positions, positions[i3], positions[i3 + 1], positions[i3 + 2], search_radius, |j, d2| {
+            neighbors.push((j, d2));
+        });
+        let indices: Vec<usize> = neighbors.iter().map(|&(j, _)| j).collect();
+        let plane = match fit_local_plane(positions, &indices) {
+            Some(plane) => plane,
+            None => continue,
+        };
+
+        let n = [plane.normal[0] as f64, plane.normal[1] as f64, plane.normal[2] as f64];
+        let c = [plane.centroid[0] as f64, plane.centroid[1] as f64, plane.centroid[2] as f64];
+        let offset = dot([p[0] - c[0], p[1] - c[1], p[2] - c[2]], n);
+        // Foot of p on the reference plane: origin of the local (u, v, w) frame
+        let q = [p[0] - offset * n[0], p[1] - offset * n[1], p[2] - offset * n[2]];
+
+        let height = if neighbors.len() >= 6 {
+            fit_quadratic_height(positions, &neighbors, q, n, inv_h_squared).unwrap_or(0.0)
+        } else {
+            0.0
+        };
+
+        for a in 0..3 {
+            out[a] = (q[a] + height * n[a]) as f32;
+        }
+    }
+
+    smoothed
+}
+
+/// Weighted least-squares quadratic height field over the plane through `q` with normal `n`;
+/// returns its value at the origin (the constant term)
+fn fit_quadratic_height(
+    positions: &[f32],
+    neighbors: &[(usize, f32)],
+    q: [f64; 3],
+    n: [f64; 3],
+    inv_h_squared: f64,
+) -> Option<f64> {
+    let (e1, e2) = tangent_basis(n);
+    let mut ata = vec![0.0f64; 36];
+    let mut atb = vec![0.0f64; 6];
+    for &(j, d2) in neighbors {
+        let j3 = j * 3;
+        let r = [
+            positions[j3] as f64 - q[0],
+            positions[j3 + 1] as f64 - q[1],
+            positions[j3 + 2] as f64 - q[2],
+        ];
+        let (u, v, w) = (dot(r, e1), dot(r, e2), dot(r, n));
+        let weight = (-(d2 as f64) * inv_h_squared).exp();
+        let basis = [1.0, u, v, u * u, u * v, v * v];
+        for row in 0..6 {
+            for col in 0..6 {
+                ata[row * 6 + col] += weight * basis[row] * basis[col];
+            }
+            atb[row] += weight * basis[row] * w;
+        }
+    }
+    solve_dense(ata, atb).map(|coefficients| coefficients[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pointcloud_tools_backend::rng::Pcg32;
+
+    fn mean_radius_error(positions: &[f32]) -> f64 {
+        let errors: f64 = positions
+            .chunks_exact(3)
+            .map(|p| ((p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt() as f64 - 1.0).abs())
+            .sum();
+        errors / (positions.len() / 3) as f64
+    }
+
+    #[test]
+    fn test_noisy_sphere_moves_toward_true_radius() {
+        // Fibonacci sphere of radius 1 with radial noise in [-0.02, 0.02]
+        let count = 4000;
+        let golden_angle = std::f64::consts::PI * (3.0 - 5.0f64.sqrt());
+        let mut rng = Pcg32::new(7);
+        let mut positions = Vec::with_capacity(count * 3);
+        for i in 0..count {
+            let z = 1.0 - 2.0 * (i as f64 + 0.5) / count as f64;
+            let ring = (1.0 - z * z).sqrt();
+            let theta = golden_angle * i as f64;
+            let radius = 1.0 + (rng.next_f64() - 0.5) * 0.04;
+            positions.extend_from_slice(&[
+                (radius * ring * theta.cos()) as f32,
+                (radius * ring * theta.sin()) as f32,
+                (radius * z) as f32,
+            ]);
+        }
+
+        let smoothed = mls_smooth(&positions, 0.15, &mut ToolRun::default());
+
+        let before = mean_radius_error(&positions);
+        let after = mean_radius_error(&smoothed);
+        assert!(after < before * 0.6, "error before {} after {}", before, after);
+    }
+
+    #[test]
+    fn test_plane_points_stay_in_place() {
+        let mut positions = Vec::new();
+        for i in 0..10 {
+            for j in 0..10 {
+                positions.extend_from_slice(&[i as f32 * 0.1, j as f32 * 0.1, 0.5]);
+            }
+        }
+        let smoothed = mls_smooth(&positions, 0.25, &mut ToolRun::default());
+        for (a, b) in positions.iter().zip(&smoothed) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// 3D Morton (Z-order) codes: the bits of three integer coordinates interleaved into one u64, so
+// sorting by code walks space along a Z curve and points close in space mostly end up close in
+// the sorted order. Used to lay out output for GPU upload and neighbor-query locality.
+
+/// Bits kept per axis (3 * 21 = 63 bits of code)
+pub const MORTON_BITS: u32 = 21;
+const AXIS_MASK: u32 = (1 << MORTON_BITS) - 1;
+
+/// Spread the low 21 bits of `v` so bit i lands on bit 3i
+fn spread_bits(v: u32) -> u64 {
+    let mut x = (v & AXIS_MASK) as u64;
+    x = (x | (x << 32)) & 0x001f_0000_0000_ffff;
+    x = (x | (x << 16)) & 0x001f_0000_ff00_00ff;
+    x = (x | (x << 8)) & 0x100f_00f0_0f00_f00f;
+    x = (x | (x << 4)) & 0x10c3_0c30_c30c_30c3;
+    x = (x | (x << 2)) & 0x1249_2492_4924_9249;
+    x
+}
+
+/// Interleave the low 21 bits of each coordinate, x in the lowest bit of every triple
+pub fn morton_encode(x: u32, y: u32, z: u32) -> u64 {
+    spread_bits(x) | (spread_bits(y) << 1) | (spread_bits(z) << 2)
+}
+
+/// Map a signed voxel index onto the unsigned 21-bit Morton range, preserving order for
+/// indices within +-2^20 and clamping beyond
+pub fn morton_axis(v: i32) -> u32 {
+    (v as i64 + (1 << (MORTON_BITS - 1))).clamp(0, AXIS_MASK as i64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_interleaves_bits() {
+        assert_eq!(morton_encode(0, 0, 0), 0);
+        assert_eq!(morton_encode(1, 0, 0), 0b001);
+        assert_eq!(morton_encode(0, 1, 0), 0b010);
+        assert_eq!(morton_encode(0, 0, 1), 0b100);
+        assert_eq!(morton_encode(3, 5, 6), 0b110_101_011);
+        assert_eq!(morton_encode(AXIS_MASK, AXIS_MASK, AXIS_MASK), (1 << 63) - 1);
+        // Bits above the 21st are dropped
+        assert_eq!(morton_encode(1 << MORTON_BITS, 0, 0), 0);
+
+        // Bit-by-bit reference
+        for (x, y, z) in [(12345, 678, 1_000_000), (AXIS_MASK, 0, 77), (2, 2_000_000, 3)] {
+            let expected = (0..MORTON_BITS as u64).fold(0u64, |code, bit| {
+                code | ((x as u64 >> bit) & 1) << (3 * bit) | ((y as u64 >> bit) & 1) << (3 * bit + 1) | ((z as u64 >> bit) & 1) << (3 * bit + 2)
+            });
+            assert_eq!(morton_encode(x, y, z), expected);
+        }
+    }
+
+    #[test]
+    fn test_axis_mapping_keeps_order() {
+        assert!(morton_axis(-1) < morton_axis(0) && morton_axis(0) < morton_axis(1));
+        assert_eq!(morton_axis(i32::MIN), 0);
+        assert_eq!(morton_axis(i32::MAX), AXIS_MASK);
+    }
+}
@@ -0,0 +1,129 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::{is_finite_point, Bounds};
+use pointcloud_tools_backend::morton::{morton_encode, MORTON_BITS};
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, gather_positions, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError};
+
+// Spatial sort: reorder a cloud and its attributes along a 3D Morton (Z-order) curve, a cheap
+// preprocessing step that speeds up kd-tree builds and other locality-sensitive consumers.
+// Coordinates are quantized to 2^quantizationBits cells per axis over a cube spanning the cloud's
+// largest extent (so cells stay cubic), and points are stably sorted by the interleaved code:
+// points sharing a cell keep their input order. quantizationBits is clamped to 1..=21. Points
+// with a NaN or infinite coordinate go last, in input order, unless bit31 drops them.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 quantizationBits][u32 flags]
+//               [f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit31=drop non-finite points
+// Output format: [u32 pointCount][f32* positions][optional colors][optional intensities][optional classifications]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: 3 * u32)
+    let (header, run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::MORTON_SORT) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let quantization_bits = u32_at(&header, 4, run.order);
+    let flags = u32_at(&header, 8, run.order);
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+
+    let order = morton_order(&positions, quantization_bits);
+    let sorted_positions = gather_positions(&positions, &order);
+    let sorted_attributes = attributes.gather(&order);
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, order.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &sorted_positions, run.order).is_err()
+        || sorted_attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, order.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Input indices in Morton order of the quantized positions; non-finite points last
+fn morton_order(positions: &[f32], quantization_bits: u32) -> Vec<usize> {
+    let bits = quantization_bits.clamp(1, MORTON_BITS);
+    let cells = (1u64 << bits) as f64;
+    let max_cell = cells - 1.0;
+    let (min, scale) = match Bounds::from_points(positions) {
+        Some(b) if b.max_extent() > 0.0 => (b.min, cells / b.max_extent() as f64),
+        Some(b) => (b.min, 0.0),
+        None => ([0.0; 3], 0.0),
+    };
+
+    let codes: Vec<u64> = positions
+        .chunks_exact(3)
+        .map(|p| {
+            if !is_finite_point(p) {
+                return u64::MAX;
+            }
+            let [x, y, z] = [0, 1, 2].map(|a| ((p[a] as f64 - min[a] as f64) * scale).floor().clamp(0.0, max_cell) as u32);
+            morton_encode(x, y, z)
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..codes.len()).collect();
+    order.sort_by_key(|&i| codes[i]);
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pointcloud_tools_backend::rng::Pcg32;
+
+    #[test]
+    fn test_output_is_a_permutation_with_neighbors_adjacent() {
+        let mut rng = Pcg32::new(11);
+        let positions: Vec<f32> = (0..5000 * 3).map(|_| rng.next_f32() * 100.0).collect();
+        let order = morton_order(&positions, 10);
+
+        let mut seen = order.clone();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..5000).collect::<Vec<_>>());
+
+        // Consecutive points are far closer after sorting than in the random input order
+        let mean_step = |indices: &[usize]| {
+            indices
+                .windows(2)
+                .map(|w| (0..3).map(|a| (positions[w[0] * 3 + a] - positions[w[1] * 3 + a]).powi(2)).sum::<f32>().sqrt())
+                .sum::<f32>()
+                / (indices.len() - 1) as f32
+        };
+        let input_order: Vec<usize> = (0..5000).collect();
+        assert!(mean_step(&order) * 4.0 < mean_step(&input_order), "{} vs {}", mean_step(&order), mean_step(&input_order));
+    }
+
+    #[test]
+    fn test_shared_cells_keep_input_order_and_non_finite_points_go_last() {
+        let positions = vec![
+            1.0, 1.0, 1.0, // cell (1, 1, 1) at one bit
+            f32::NAN, 0.0, 0.0,
+            0.0, 0.0, 0.0, // cell (0, 0, 0)
+            0.9, 0.9, 0.9, // cell (1, 1, 1)
+            0.1, 0.2, 0.0, // cell (0, 0, 0)
+            1.0, 0.0, 0.0, // cell (1, 0, 0)
+        ];
+        assert_eq!(morton_order(&positions, 1), vec![2, 4, 5, 0, 3, 1]);
+        // Out-of-range bit counts are clamped rather than rejected
+        assert_eq!(morton_order(&positions, 0), morton_order(&positions, 1));
+        assert_eq!(morton_order(&positions, 64).len(), 6);
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::normals::fit_local_plane;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// PCA normal estimation: each point's normal is the smallest-eigenvalue direction of the
+// covariance of its k nearest neighbors (the point itself included), flipped to face the viewpoint.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 k][f32 viewpointX][f32 viewpointY][f32 viewpointZ][f32* positions]
+// Output format: [u32 pointCount][f32* normals] (3 floats per point; (0,0,0) when fewer than 3 points)
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (20 bytes: 2 * u32 + 3 * f32)
+    let (header, mut run): ([u8; 20], _) = match read_tool_header(&mut stdin, tool_id::NORMAL_ESTIMATION) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let k = u32_at(&header, 4, run.order) as usize;
+    let viewpoint = [f32_at(&header, 8, run.order), f32_at(&header, 12, run.order), f32_at(&header, 16, run.order)];
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || k == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let normals = estimate_normals(&positions, k, viewpoint, &mut run);
+
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &normals, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+fn estimate_normals(positions: &[f32], k: usize, viewpoint: [f32; 3], run: &mut ToolRun) -> Vec<f32> {
+    let point_count = positions.len() / 3;
+    let grid = SpatialGrid::new(positions, SpatialGrid::auto_cell_size(positions));
+    run.note_map_size(grid.cell_count());
+    let mut normals = vec![0.0f32; point_count * 3];
+
+    for (i, normal) in normals.chunks_exact_mut(3).enumerate() {
+        let i3 = i * 3;
+        let position = [positions[i3], positions[i3 + 1], positions[i3 + 2]];
+        let neighbors: Vec<usize> = grid
+            .k_nearest(positions, position[0], position[1], position[2], k)
+            .into_iter()
+            .map(|(j, _)| j)
+            .collect();
+        if let Some(mut plane) = fit_local_plane(positions, &neighbors) {
+            plane.orient_toward(position, viewpoint);
+            normal.copy_from_slice(&plane.normal);
+        }
+    }
+
+    normals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_planar_patch_normals() {
+        // Tilted plane z = 0.5x + 0.2y + 1, viewed from above
+        let mut positions = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                let x = i as f32 * 0.1;
+                let y = j as f32 * 0.1;
+                positions.extend_from_slice(&[x, y, 0.5 * x + 0.2 * y + 1.0]);
+            }
+        }
+        let length = (0.5f32 * 0.5 + 0.2 * 0.2 + 1.0).sqrt();
+        let expected = [-0.5 / length, -0.2 / length, 1.0 / length];
+
+        let normals = estimate_normals(&positions, 10, [1.0, 1.0, 50.0], &mut ToolRun::default());
+
+        for n in normals.chunks_exact(3) {
+            let dot = n[0] * expected[0] + n[1] * expected[1] + n[2] * expected[2];
+            assert!(dot > 0.999, "normal {:?} deviates from {:?}", n, expected);
+        }
+    }
+
+    #[test]
+    fn test_normals_face_viewpoint() {
+        let mut positions = Vec::new();
+        for i in 0..5 {
+            for j in 0..5 {
+                positions.extend_from_slice(&[i as f32, j as f32, 0.0]);
+            }
+        }
+        let normals = estimate_normals(&positions, 8, [2.0, 2.0, -10.0], &mut ToolRun::default());
+        assert!(normals.chunks_exact(3).all(|n| n[2] < -0.999));
+    }
+}
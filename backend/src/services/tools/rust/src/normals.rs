@@ -0,0 +1,66 @@
+use crate::linalg::{covariance, symmetric_eigenvalues, symmetric_eigenvector};
+use crate::protocol::ToolRun;
+use crate::spatial_grid::SpatialGrid;
+
+// Local-plane fitting by PCA of a neighborhood: the normal is the eigenvector of the smallest
+// covariance eigenvalue, and the eigenvalues describe how planar the neighborhood is.
+
+pub struct LocalPlane {
+    pub centroid: [f32; 3],
+    /// Unit normal (sign arbitrary unless oriented)
+    pub normal: [f32; 3],
+    /// Covariance eigenvalues, ascending
+    pub eigenvalues: [f64; 3],
+}
+
+impl LocalPlane {
+    /// Surface variation lambda0 / (lambda0 + lambda1 + lambda2): 0 on a plane, 1/3 when isotropic
+    pub fn curvature(&self) -> f32 {
+        let sum = self.eigenvalues[0] + self.eigenvalues[1] + self.eigenvalues[2];
+        if sum > 0.0 {
+            (self.eigenvalues[0].max(0.0) / sum) as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Flip the normal so it points toward `viewpoint` from `position`
+    pub fn orient_toward(&mut self, position: [f32; 3], viewpoint: [f32; 3]) {
+        let dot = (0..3).map(|a| self.normal[a] * (viewpoint[a] - position[a])).sum::<f32>();
+        if dot < 0.0 {
+            for n in self.normal.iter_mut() {
+                *n = -*n;
+            }
+        }
+    }
+}
+
+/// Fit a plane to the points at `indices`; None with fewer than 3 points
+pub fn fit_local_plane(points: &[f32], indices: &[usize]) -> Option<LocalPlane> {
+    if indices.len() < 3 {
+        return None;
+    }
+    let (centroid, cov) = covariance(points, indices);
+    let eigenvalues = symmetric_eigenvalues(&cov);
+    let normal = symmetric_eigenvector(&cov, eigenvalues[0]);
+    Some(LocalPlane {
+        centroid: [centroid[0] as f32, centroid[1] as f32, centroid[2] as f32],
+        normal: [normal[0] as f32, normal[1] as f32, normal[2] as f32],
+        eigenvalues,
+    })
+}
+
+/// Surface variation of every point's k-nearest neighborhood (the point itself included);
+/// 0 where fewer than 3 neighbors exist. The search grid's size is noted in `run`.
+pub fn estimate_curvature(positions: &[f32], k: usize, run: &mut ToolRun) -> Vec<f32> {
+    let grid = SpatialGrid::new(positions, SpatialGrid::auto_cell_size(positions));
+    run.note_map_size(grid.cell_count());
+    positions
+        .chunks_exact(3)
+        .map(|p| {
+            let neighbors: Vec<usize> =
+                grid.k_nearest(positions, p[0], p[1], p[2], k).into_iter().map(|(j, _)| j).collect();
+            fit_local_plane(positions, &neighbors).map_or(0.0, |plane| plane.curvature())
+        })
+        .collect()
+}
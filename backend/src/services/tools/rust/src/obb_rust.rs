@@ -0,0 +1,184 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::linalg::{covariance, symmetric_eigenvalues, symmetric_eigenvector};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+
+// Oriented bounding box from PCA: the box axes are the eigenvectors of the cloud's covariance
+// (same closed-form solver as normal estimation) and the extents come from projecting every point
+// onto them. Much tighter than the axis-aligned box for tilted, elongated objects. The axes are
+// ordered by decreasing variance and form a right-handed frame. Points with a non-finite
+// coordinate are ignored.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32* positions]
+// Output format: [f32 centerX][f32 centerY][f32 centerZ][f32*9 axes (three unit vectors, major first)]
+//                [f32 halfExtent0][f32 halfExtent1][f32 halfExtent2]
+// An empty cloud (or one without finite points) gives identity axes and half-extents of -1.
+
+struct OrientedBox {
+    center: [f32; 3],
+    axes: [[f32; 3]; 3],
+    half_extents: [f32; 3],
+}
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (4 bytes: u32)
+    let (header, run): ([u8; 4], _) = match read_tool_header(&mut stdin, tool_id::OBB) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let obb = oriented_bounding_box(&positions).unwrap_or(OrientedBox {
+        center: [0.0; 3],
+        axes: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        half_extents: [-1.0; 3],
+    });
+
+    let mut values = Vec::with_capacity(15);
+    values.extend_from_slice(&obb.center);
+    values.extend(obb.axes.iter().flatten());
+    values.extend_from_slice(&obb.half_extents);
+
+    let mut stdout = io::stdout();
+    if write_f32_slice(&mut stdout, &values, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, 1).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// PCA box of a flat xyz array; None without finite points
+fn oriented_bounding_box(positions: &[f32]) -> Option<OrientedBox> {
+    let indices: Vec<usize> = positions
+        .chunks_exact(3)
+        .enumerate()
+        .filter(|(_, p)| is_finite_point(p))
+        .map(|(i, _)| i)
+        .collect();
+    if indices.is_empty() {
+        return None;
+    }
+
+    let (centroid, cov) = covariance(positions, &indices);
+    let eigenvalues = symmetric_eigenvalues(&cov);
+    // Major axis, then the middle one made exactly orthogonal to it (the solver returns an
+    // arbitrary vector of the eigenspace when eigenvalues repeat), then their cross product
+    let major = symmetric_eigenvector(&cov, eigenvalues[2]);
+    let middle = orthogonal_unit(symmetric_eigenvector(&cov, eigenvalues[1]), major);
+    let minor = cross(major, middle);
+    let axes = [major, middle, minor];
+
+    // Extents along each axis, measured from the centroid in f64
+    let mut low = [f64::INFINITY; 3];
+    let mut high = [f64::NEG_INFINITY; 3];
+    for &i in &indices {
+        let d = [0, 1, 2].map(|a| positions[i * 3 + a] as f64 - centroid[a]);
+        for (k, axis) in axes.iter().enumerate() {
+            let t = dot(d, *axis);
+            low[k] = low[k].min(t);
+            high[k] = high[k].max(t);
+        }
+    }
+
+    let mut center = centroid;
+    for (k, axis) in axes.iter().enumerate() {
+        let mid = (low[k] + high[k]) * 0.5;
+        for a in 0..3 {
+            center[a] += axis[a] * mid;
+        }
+    }
+    Some(OrientedBox {
+        center: center.map(|c| c as f32),
+        axes: axes.map(|axis| axis.map(|v| v as f32)),
+        half_extents: [0, 1, 2].map(|k| ((high[k] - low[k]) * 0.5) as f32),
+    })
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// `v` with its component along the unit vector `axis` removed, normalized. Falls back to any
+/// unit vector perpendicular to `axis` when `v` is (nearly) parallel to it.
+fn orthogonal_unit(v: [f64; 3], axis: [f64; 3]) -> [f64; 3] {
+    let along = dot(v, axis);
+    let mut u = [0, 1, 2].map(|a| v[a] - along * axis[a]);
+    let mut length = dot(u, u).sqrt();
+    if length < 1e-6 {
+        let helper = if axis[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+        u = cross(axis, helper);
+        length = dot(u, u).sqrt();
+    }
+    u.map(|c| c / length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_axis_follows_elongation() {
+        // 10 x 2 x 1 box of points, rotated 30 degrees about z and 20 about x, then shifted
+        let (cz, sz) = (30f32.to_radians().cos(), 30f32.to_radians().sin());
+        let (cx, sx) = (20f32.to_radians().cos(), 20f32.to_radians().sin());
+        let rotate = |p: [f32; 3]| {
+            let q = [cz * p[0] - sz * p[1], sz * p[0] + cz * p[1], p[2]];
+            [q[0], cx * q[1] - sx * q[2], sx * q[1] + cx * q[2]]
+        };
+        let offset = [4.0f32, -2.0, 7.0];
+        let mut positions = Vec::new();
+        for i in 0..=40 {
+            for j in 0..=8 {
+                for k in 0..=4 {
+                    let p = rotate([i as f32 * 0.25 - 5.0, j as f32 * 0.25 - 1.0, k as f32 * 0.25 - 0.5]);
+                    positions.extend_from_slice(&[p[0] + offset[0], p[1] + offset[1], p[2] + offset[2]]);
+                }
+            }
+        }
+
+        let obb = oriented_bounding_box(&positions).unwrap();
+        let elongation = rotate([1.0, 0.0, 0.0]);
+        let alignment: f32 = (0..3).map(|a| obb.axes[0][a] * elongation[a]).sum();
+        assert!(alignment.abs() > 0.999, "major axis {:?}", obb.axes[0]);
+
+        assert!(obb.half_extents[0] > obb.half_extents[1] && obb.half_extents[1] > obb.half_extents[2]);
+        for (found, expected) in obb.half_extents.iter().zip([5.0, 1.0, 0.5]) {
+            assert!((found - expected).abs() < 1e-3, "half extents {:?}", obb.half_extents);
+        }
+        for (found, expected) in obb.center.iter().zip(offset) {
+            assert!((found - expected).abs() < 1e-3, "center {:?}", obb.center);
+        }
+    }
+
+    #[test]
+    fn test_axes_are_orthonormal_for_a_round_cloud() {
+        // Cube corners: all eigenvalues equal, so any frame is valid but it must stay orthonormal
+        let mut positions = Vec::new();
+        for corner in 0..8 {
+            positions.extend_from_slice(&[(corner & 1) as f32, ((corner >> 1) & 1) as f32, ((corner >> 2) & 1) as f32]);
+        }
+        let obb = oriented_bounding_box(&positions).unwrap();
+        for i in 0..3 {
+            for j in 0..3 {
+                let d: f32 = (0..3).map(|a| obb.axes[i][a] * obb.axes[j][a]).sum();
+                assert!((d - if i == j { 1.0 } else { 0.0 }).abs() < 1e-5);
+            }
+        }
+        assert!(oriented_bounding_box(&[]).is_none());
+    }
+}
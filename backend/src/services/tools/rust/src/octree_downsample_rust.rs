@@ -0,0 +1,180 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, write_u32_slice, ByteOrder};
+use pointcloud_tools_backend::geometry::{is_finite_point, Bounds};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+
+// Octree downsampling for clouds with extreme dynamic range, where a flat voxel grid would cover
+// mostly empty space. The root is the cube of side max extent at the bounds minimum; a node is
+// split into its eight octants while it is shallower than maxDepth and holds more than
+// maxLeafPoints points, and only octants that receive points are ever created. Each leaf emits
+// the centroid of its points (f64 sums), so dense regions end at maxDepth while isolated points
+// stop early. Leaves are written depth first in octant order (x lowest bit, then y, then z),
+// which is Morton order. Points with a NaN or infinite coordinate are ignored.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 maxDepth][u32 maxLeafPoints][u32 flags][f32* positions]
+// flags: bit5=per-leaf counts (same bit as voxel_downsample_rust)
+// Output format: [u32 leafCount][f32* centroids][optional u32* counts]
+// maxDepth is capped at MAX_DEPTH; maxLeafPoints 0 splits every node down to maxDepth.
+
+const FLAG_COUNTS: u32 = 32;
+// Side of a depth-21 cell is 2^-21 of the cloud extent, past f32 resolution for any real cloud
+const MAX_DEPTH: u32 = 21;
+
+/// One occupied leaf: centroid and number of points
+#[derive(Debug, PartialEq)]
+struct Leaf {
+    centroid: [f32; 3],
+    count: u32,
+}
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (16 bytes: 4 * u32)
+    let (header, run): ([u8; 16], _) = match read_tool_header(&mut stdin, tool_id::OCTREE_DOWNSAMPLE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let max_depth = u32_at(&header, 4, run.order).min(MAX_DEPTH);
+    let max_leaf_points = u32_at(&header, 8, run.order) as usize;
+    let flags = u32_at(&header, 12, run.order);
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let (leaves, _) = octree_downsample(&positions, max_depth, max_leaf_points);
+    let centroids: Vec<f32> = leaves.iter().flat_map(|leaf| leaf.centroid).collect();
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, leaves.len() as u32, run.order).is_err() || write_f32_slice(&mut stdout, &centroids, run.order).is_err() {
+        std::process::exit(1);
+    }
+    if flags & FLAG_COUNTS != 0 {
+        let counts: Vec<u32> = leaves.iter().map(|leaf| leaf.count).collect();
+        if write_u32_slice(&mut stdout, &counts, run.order).is_err() {
+            std::process::exit(1);
+        }
+    }
+    if write_stats(&mut stdout, &run, point_count, leaves.len()).is_err() || stdout.flush().is_err() {
+        std::process::exit(1);
+    }
+}
+
+/// Occupied leaves of the octree over the finite points of a flat xyz array, in Morton order,
+/// plus the number of nodes created (root included)
+fn octree_downsample(positions: &[f32], max_depth: u32, max_leaf_points: usize) -> (Vec<Leaf>, usize) {
+    let mut indices: Vec<usize> = (0..positions.len() / 3).filter(|&i| is_finite_point(&positions[i * 3..i * 3 + 3])).collect();
+    let Some(bounds) = Bounds::from_points(positions) else {
+        return (Vec::new(), 0);
+    };
+
+    let mut leaves = Vec::new();
+    let mut node_count = 0;
+    let side = bounds.max_extent() as f64;
+    let origin = bounds.min.map(|v| v as f64);
+    split_node(positions, &mut indices, origin, side, max_depth, max_leaf_points, &mut leaves, &mut node_count);
+    (leaves, node_count)
+}
+
+/// Emit the node of cube `origin`..`origin + side` holding `indices` as a leaf, or partition the
+/// indices by octant and recurse into the occupied children
+#[allow(clippy::too_many_arguments)]
+fn split_node(
+    positions: &[f32],
+    indices: &mut [usize],
+    origin: [f64; 3],
+    side: f64,
+    depth_left: u32,
+    max_leaf_points: usize,
+    leaves: &mut Vec<Leaf>,
+    node_count: &mut usize,
+) {
+    *node_count += 1;
+    if depth_left == 0 || indices.len() <= max_leaf_points || side <= 0.0 {
+        let mut sum = [0.0f64; 3];
+        for &i in indices.iter() {
+            for (s, &v) in sum.iter_mut().zip(&positions[i * 3..i * 3 + 3]) {
+                *s += v as f64;
+            }
+        }
+        let count = indices.len();
+        leaves.push(Leaf { centroid: sum.map(|s| (s / count as f64) as f32), count: count as u32 });
+        return;
+    }
+
+    // Points on a split plane go to the upper octant
+    let half = side * 0.5;
+    let octant = |i: usize| -> usize {
+        (0..3).map(|a| usize::from(positions[i * 3 + a] as f64 >= origin[a] + half) << a).sum()
+    };
+    indices.sort_unstable_by_key(|&i| octant(i));
+
+    let mut rest = indices;
+    while let Some(&first) = rest.first() {
+        let code = octant(first);
+        let length = rest.iter().take_while(|&&i| octant(i) == code).count();
+        let (child, tail) = rest.split_at_mut(length);
+        let child_origin = [0, 1, 2].map(|a| origin[a] + if code >> a & 1 == 1 { half } else { 0.0 });
+        split_node(positions, child, child_origin, half, depth_left - 1, max_leaf_points, leaves, node_count);
+        rest = tail;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_distant_clusters_without_a_full_grid() {
+        // 100 points near the origin and 100 near (1000, 1000, 1000)
+        let mut positions = Vec::new();
+        for cluster in [[0.0f32, 0.0, 0.0], [1000.0, 1000.0, 1000.0]] {
+            for i in 0..100 {
+                let t = i as f32;
+                positions.extend_from_slice(&[cluster[0] + (t * 0.37).sin() * 0.01, cluster[1] + (t * 0.11).cos() * 0.01, cluster[2] + (t * 0.05).sin() * 0.01]);
+            }
+        }
+        let (leaves, node_count) = octree_downsample(&positions, 3, 0);
+        assert_eq!(leaves.len(), 2);
+        assert_eq!(node_count, 7, "root plus one path of three nodes per cluster");
+        for (leaf, cluster) in leaves.iter().zip(positions.chunks(300)) {
+            assert_eq!(leaf.count, 100);
+            for a in 0..3 {
+                let mean = cluster.iter().skip(a).step_by(3).map(|&v| v as f64).sum::<f64>() / 100.0;
+                assert!((leaf.centroid[a] as f64 - mean).abs() < 1e-4, "{:?}", leaf.centroid);
+            }
+        }
+
+        // At depth 18 a flat grid would have 2^54 cells; the clusters split, but the tree never
+        // holds more than one root-to-leaf path per point
+        let (deep_leaves, deep_nodes) = octree_downsample(&positions, 18, 0);
+        assert!(deep_leaves.len() > 2);
+        assert_eq!(deep_leaves.iter().map(|leaf| leaf.count).sum::<u32>(), 200);
+        assert!(deep_nodes <= 200 * 18 + 1, "{} nodes", deep_nodes);
+    }
+
+    #[test]
+    fn test_isolated_points_stop_early_and_leaves_follow_morton_order() {
+        // Two points in the low corner octant, one alone in the high corner
+        let positions = vec![0.0, 0.0, 0.0, 0.5, 0.5, 0.5, 8.0, 8.0, 8.0, f32::NAN, 1.0, 1.0];
+        let (leaves, _) = octree_downsample(&positions, 5, 1);
+        assert_eq!(
+            leaves,
+            vec![
+                Leaf { centroid: [0.0, 0.0, 0.0], count: 1 },
+                Leaf { centroid: [0.5, 0.5, 0.5], count: 1 },
+                Leaf { centroid: [8.0, 8.0, 8.0], count: 1 },
+            ]
+        );
+        // With room for two points per leaf the low corner is one leaf after a single split
+        let (leaves, node_count) = octree_downsample(&positions, 5, 2);
+        assert_eq!(leaves[0], Leaf { centroid: [0.25, 0.25, 0.25], count: 2 });
+        assert_eq!(node_count, 3);
+        assert_eq!(octree_downsample(&[], 5, 1), (Vec::new(), 0));
+    }
+}
@@ -0,0 +1,323 @@
+use std::io;
+use crate::point_attributes::PointAttributes;
+
+// PCL `.pcd` reader producing the flat layout the tools consume: positions as x, y, z per point
+// plus optional colors (r, g, b in 0..1) and intensities. Supports `DATA ascii` and
+// `DATA binary` (little-endian rows); `DATA binary_compressed` is rejected.
+// Colors come from PCL's packed `rgb`/`rgba` field (0x00RRGGBB, stored either as the bits of an
+// F 4 field or as a U 4 field).
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FieldType {
+    Float,
+    Unsigned,
+    Signed,
+}
+
+#[derive(Debug)]
+struct Field {
+    name: String,
+    size: usize,
+    field_type: FieldType,
+    count: usize,
+}
+
+impl Field {
+    /// Decode the first element of this field from little-endian bytes
+    fn read_le(&self, bytes: &[u8]) -> io::Result<f64> {
+        let b = |n: usize| -> [u8; 8] {
+            let mut buf = [0u8; 8];
+            buf[..n].copy_from_slice(&bytes[..n]);
+            buf
+        };
+        Ok(match (self.field_type, self.size) {
+            (FieldType::Float, 4) => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            (FieldType::Float, 8) => f64::from_le_bytes(b(8)),
+            (FieldType::Unsigned, 1 | 2 | 4 | 8) => u64::from_le_bytes(b(self.size)) as f64,
+            (FieldType::Signed, 1) => bytes[0] as i8 as f64,
+            (FieldType::Signed, 2) => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            (FieldType::Signed, 4) => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            (FieldType::Signed, 8) => i64::from_le_bytes(b(8)) as f64,
+            _ => return Err(invalid(format!("unsupported size {} for field {}", self.size, self.name))),
+        })
+    }
+
+    /// Packed 0x00RRGGBB color from this field's first element
+    fn read_packed_rgb_le(&self, bytes: &[u8]) -> u32 {
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    /// Packed color from an ASCII token: F fields hold the bits of a float, U/I fields an integer
+    fn parse_packed_rgb(&self, token: &str) -> io::Result<u32> {
+        let bad = || invalid(format!("bad rgb value {}", token));
+        match self.field_type {
+            FieldType::Float => token.parse::<f32>().map(f32::to_bits).map_err(|_| bad()),
+            _ => token.parse::<f64>().map(|v| v as u32).map_err(|_| bad()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DataFormat {
+    Ascii,
+    Binary,
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn unpack_rgb(packed: u32) -> [f32; 3] {
+    [
+        ((packed >> 16) & 0xFF) as f32 / 255.0,
+        ((packed >> 8) & 0xFF) as f32 / 255.0,
+        (packed & 0xFF) as f32 / 255.0,
+    ]
+}
+
+/// Parse the header; returns the fields, the point count, the data format and the body offset
+fn parse_header(bytes: &[u8]) -> io::Result<(Vec<Field>, usize, DataFormat, usize)> {
+    let mut names: Vec<String> = Vec::new();
+    let mut sizes: Vec<usize> = Vec::new();
+    let mut types: Vec<FieldType> = Vec::new();
+    let mut counts: Vec<usize> = Vec::new();
+    let mut width = None;
+    let mut height = 1usize;
+    let mut points = None;
+    let mut offset = 0usize;
+
+    let parse_usize = |token: &str| -> io::Result<usize> {
+        token.parse().map_err(|_| invalid(format!("bad header number {}", token)))
+    };
+
+    let data = loop {
+        let line_end = bytes[offset..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| invalid("PCD header has no DATA line"))?;
+        let line = std::str::from_utf8(&bytes[offset..offset + line_end])
+            .map_err(|_| invalid("PCD header is not valid text"))?
+            .trim();
+        offset += line_end + 1;
+
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) if !k.starts_with('#') => k,
+            _ => continue,
+        };
+        let values: Vec<&str> = tokens.collect();
+        match keyword {
+            "FIELDS" => names = values.iter().map(|v| v.to_string()).collect(),
+            "SIZE" => sizes = values.iter().map(|v| parse_usize(v)).collect::<io::Result<_>>()?,
+            "TYPE" => {
+                types = values
+                    .iter()
+                    .map(|v| match *v {
+                        "F" => Ok(FieldType::Float),
+                        "U" => Ok(FieldType::Unsigned),
+                        "I" => Ok(FieldType::Signed),
+                        other => Err(invalid(format!("unknown field type {}", other))),
+                    })
+                    .collect::<io::Result<_>>()?
+            }
+            "COUNT" => counts = values.iter().map(|v| parse_usize(v)).collect::<io::Result<_>>()?,
+            "WIDTH" => width = Some(parse_usize(values.first().copied().unwrap_or(""))?),
+            "HEIGHT" => height = parse_usize(values.first().copied().unwrap_or(""))?,
+            "POINTS" => points = Some(parse_usize(values.first().copied().unwrap_or(""))?),
+            "DATA" => {
+                break match values.first().copied() {
+                    Some("ascii") => DataFormat::Ascii,
+                    Some("binary") => DataFormat::Binary,
+                    Some("binary_compressed") => {
+                        return Err(invalid("DATA binary_compressed is not supported"))
+                    }
+                    other => return Err(invalid(format!("unknown DATA format {:?}", other))),
+                };
+            }
+            // VERSION, VIEWPOINT
+            _ => {}
+        }
+    };
+
+    if counts.is_empty() {
+        counts = vec![1; names.len()];
+    }
+    if sizes.len() != names.len() || types.len() != names.len() || counts.len() != names.len() {
+        return Err(invalid("FIELDS, SIZE, TYPE and COUNT lengths differ"));
+    }
+    let point_count = match (points, width) {
+        (Some(p), _) => p,
+        (None, Some(w)) => w * height,
+        (None, None) => return Err(invalid("PCD header has neither POINTS nor WIDTH")),
+    };
+
+    let fields = names
+        .into_iter()
+        .zip(sizes)
+        .zip(types)
+        .zip(counts)
+        .map(|(((name, size), field_type), count)| Field {
+            name,
+            size,
+            field_type,
+            count,
+        })
+        .collect();
+    Ok((fields, point_count, data, offset))
+}
+
+/// Parse a PCD file into flat positions plus colors/intensities when the points carry them
+pub fn parse_pcd(bytes: &[u8]) -> io::Result<(Vec<f32>, PointAttributes)> {
+    let (fields, point_count, data, body_offset) = parse_header(bytes)?;
+    let find = |name: &str| fields.iter().position(|f| f.name == name);
+    let position = [
+        find("x").ok_or_else(|| invalid("PCD has no x field"))?,
+        find("y").ok_or_else(|| invalid("PCD has no y field"))?,
+        find("z").ok_or_else(|| invalid("PCD has no z field"))?,
+    ];
+    let rgb = find("rgb").or_else(|| find("rgba"));
+    let intensity = find("intensity");
+
+    let mut positions = Vec::with_capacity(point_count * 3);
+    let mut colors = rgb.map(|_| Vec::with_capacity(point_count * 3));
+    let mut intensities = intensity.map(|_| Vec::with_capacity(point_count));
+    let body = &bytes[body_offset..];
+
+    match data {
+        DataFormat::Ascii => {
+            // Token index of each field's first element
+            let mut starts = Vec::with_capacity(fields.len());
+            let mut tokens_per_point = 0usize;
+            for field in &fields {
+                starts.push(tokens_per_point);
+                tokens_per_point += field.count;
+            }
+            let text = std::str::from_utf8(body).map_err(|_| invalid("PCD body is not valid text"))?;
+            let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+            let mut tokens = Vec::with_capacity(tokens_per_point);
+            for _ in 0..point_count {
+                let line = lines.next().ok_or_else(|| invalid("PCD body ends early"))?;
+                tokens.clear();
+                tokens.extend(line.split_whitespace());
+                if tokens.len() < tokens_per_point {
+                    return Err(invalid("PCD point line has too few values"));
+                }
+                for &f in &position {
+                    let token = tokens[starts[f]];
+                    // PCL writes nan for invalid points of organized clouds
+                    let value = token.parse::<f32>().map_err(|_| invalid(format!("bad value {}", token)))?;
+                    positions.push(value);
+                }
+                if let (Some(f), Some(colors)) = (rgb, colors.as_mut()) {
+                    colors.extend_from_slice(&unpack_rgb(fields[f].parse_packed_rgb(tokens[starts[f]])?));
+                }
+                if let (Some(f), Some(intensities)) = (intensity, intensities.as_mut()) {
+                    let token = tokens[starts[f]];
+                    intensities.push(token.parse::<f32>().map_err(|_| invalid(format!("bad value {}", token)))?);
+                }
+            }
+        }
+        DataFormat::Binary => {
+            // Byte offset of each field within a row
+            let mut starts = Vec::with_capacity(fields.len());
+            let mut row_size = 0usize;
+            for field in &fields {
+                starts.push(row_size);
+                row_size += field.size * field.count;
+            }
+            if body.len() < row_size * point_count {
+                return Err(invalid("PCD body ends early"));
+            }
+            for row in body.chunks_exact(row_size.max(1)).take(point_count) {
+                for &f in &position {
+                    positions.push(fields[f].read_le(&row[starts[f]..])? as f32);
+                }
+                if let (Some(f), Some(colors)) = (rgb, colors.as_mut()) {
+                    colors.extend_from_slice(&unpack_rgb(fields[f].read_packed_rgb_le(&row[starts[f]..])));
+                }
+                if let (Some(f), Some(intensities)) = (intensity, intensities.as_mut()) {
+                    intensities.push(fields[f].read_le(&row[starts[f]..])? as f32);
+                }
+            }
+        }
+    }
+
+    Ok((
+        positions,
+        PointAttributes {
+            colors,
+            intensities,
+            classifications: None,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTS: [([f32; 3], u32, f32); 3] = [
+        ([0.5, -1.25, 3.0], 0x00FF0033, 10.0),
+        ([1.0e3, 2.5e-3, -7.75], 0x000080FF, 0.5),
+        ([-0.1, 0.2, 0.3], 0x00112244, 250.0),
+    ];
+
+    const HEADER: &str = "# .PCD v0.7 - Point Cloud Data file format\nVERSION 0.7\n\
+                          FIELDS x y z rgb intensity\nSIZE 4 4 4 4 4\nTYPE F F F U F\nCOUNT 1 1 1 1 1\n\
+                          WIDTH 3\nHEIGHT 1\nVIEWPOINT 0 0 0 1 0 0 0\nPOINTS 3\n";
+
+    fn ascii_pcd() -> Vec<u8> {
+        let mut text = format!("{}DATA ascii\n", HEADER);
+        for (p, rgb, i) in POINTS {
+            text.push_str(&format!("{} {} {} {} {}\n", p[0], p[1], p[2], rgb, i));
+        }
+        text.into_bytes()
+    }
+
+    fn binary_pcd() -> Vec<u8> {
+        let mut bytes = format!("{}DATA binary\n", HEADER).into_bytes();
+        for (p, rgb, i) in POINTS {
+            for v in p {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+            bytes.extend_from_slice(&rgb.to_le_bytes());
+            bytes.extend_from_slice(&i.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_ascii_and_binary_parse_identically() {
+        let (ascii_positions, ascii_attributes) = parse_pcd(&ascii_pcd()).unwrap();
+        let (binary_positions, binary_attributes) = parse_pcd(&binary_pcd()).unwrap();
+
+        assert_eq!(ascii_positions, binary_positions);
+        assert_eq!(ascii_attributes, binary_attributes);
+
+        let expected: Vec<f32> = POINTS.iter().flat_map(|(p, _, _)| *p).collect();
+        assert_eq!(binary_positions, expected);
+        let colors = binary_attributes.colors.unwrap();
+        assert_eq!(&colors[..3], &[1.0, 0.0, 51.0 / 255.0]);
+        assert_eq!(binary_attributes.intensities.unwrap(), vec![10.0, 0.5, 250.0]);
+    }
+
+    #[test]
+    fn test_float_packed_rgb_and_unsupported_data() {
+        // PCL's usual layout: rgb as the bits of a float, plus a padding field with COUNT 2
+        let rgb = f32::from_bits(0x00FF8000);
+        let text = format!(
+            "FIELDS x y z _ rgb\nSIZE 4 4 4 1 4\nTYPE F F F U F\nCOUNT 1 1 1 2 1\nWIDTH 1\nHEIGHT 1\n\
+             DATA ascii\n1 2 3 0 0 {:e}\n",
+            rgb
+        );
+        let (positions, attributes) = parse_pcd(text.as_bytes()).unwrap();
+        assert_eq!(positions, vec![1.0, 2.0, 3.0]);
+        assert_eq!(attributes.colors.unwrap(), vec![1.0, 128.0 / 255.0, 0.0]);
+        assert!(attributes.intensities.is_none());
+
+        let compressed = HEADER.to_string() + "DATA binary_compressed\n";
+        let error = parse_pcd(compressed.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(parse_pcd(&binary_pcd()[..binary_pcd().len() - 1]).is_err());
+    }
+}
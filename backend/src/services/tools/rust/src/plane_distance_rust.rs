@@ -0,0 +1,107 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, ToolError};
+
+// Signed distance of every point to the plane a*x + b*y + c*z + d = 0, e.g. a plane from
+// segmentation, so the frontend can color or threshold by it without a round trip of the cloud.
+// Distances are (a*x + b*y + c*z + d) / |(a, b, c)|, positive on the side the normal (a, b, c)
+// points to, computed in f64. The coefficients need not be normalized. Points with a NaN or
+// infinite coordinate get a NaN distance.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 a][f32 b][f32 c][f32 d][f32* positions]
+// Output format: [u32 pointCount][f32* distances]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (20 bytes: u32 + 4 * f32)
+    let (header, run): ([u8; 20], _) = match read_tool_header(&mut stdin, tool_id::PLANE_DISTANCE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let plane = [0, 1, 2, 3].map(|i| f32_at(&header, 4 + i * 4, run.order));
+
+    let normal_length = (plane[0] as f64).hypot(plane[1] as f64).hypot(plane[2] as f64);
+    if !(normal_length > 0.0 && normal_length.is_finite() && plane[3].is_finite()) {
+        ToolError::new(ErrorCode::InvalidData, "plane needs a finite, non-zero normal (a, b, c) and a finite d").exit(run.order);
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let distances = plane_distances(&positions, plane);
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &distances, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Signed distance of each point of a flat xyz array to the plane (a, b, c, d); NaN for a point
+/// with a non-finite coordinate (an infinite one would otherwise give ±inf, or NaN where its
+/// coefficient is 0)
+fn plane_distances(positions: &[f32], plane: [f32; 4]) -> Vec<f32> {
+    let [a, b, c, d] = plane.map(|v| v as f64);
+    let inv_length = 1.0 / a.hypot(b).hypot(c);
+    positions
+        .chunks_exact(3)
+        .map(|p| {
+            if !is_finite_point(p) {
+                return f32::NAN;
+            }
+            ((a * p[0] as f64 + b * p[1] as f64 + c * p[2] as f64 + d) * inv_length) as f32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_distances_to_a_tilted_plane() {
+        // Plane through (0, 0, 2) with unnormalized normal (0, 3, 4): 3y + 4z - 8 = 0
+        let plane = [0.0, 3.0, 4.0, -8.0];
+        let unit_normal = [0.0, 0.6, 0.8];
+        let on_plane = [5.0, 0.0, 2.0];
+        let mut positions = Vec::new();
+        let offsets = [0.0f32, 1.5, -2.0, 10.0, -0.25];
+        for offset in offsets {
+            positions.extend((0..3).map(|a| on_plane[a] + unit_normal[a] * offset));
+        }
+        let distances = plane_distances(&positions, plane);
+        for (distance, offset) in distances.iter().zip(offsets) {
+            assert!((distance - offset).abs() < 1e-5, "{} vs {}", distance, offset);
+        }
+    }
+
+    #[test]
+    fn test_scaling_the_coefficients_changes_nothing() {
+        let positions = vec![1.0, 2.0, 3.0, -4.0, 0.5, 7.0, f32::NAN, 0.0, 0.0];
+        let distances = plane_distances(&positions, [1.0, -2.0, 2.0, 1.0]);
+        let scaled = plane_distances(&positions, [10.0, -20.0, 20.0, 10.0]);
+        for (a, b) in distances[..2].iter().zip(&scaled[..2]) {
+            assert!((a - b).abs() < 1e-6, "{} vs {}", a, b);
+        }
+        assert!((distances[0] - 4.0 / 3.0).abs() < 1e-6);
+        assert!(distances[2].is_nan());
+    }
+
+    #[test]
+    fn test_infinite_coordinates_give_nan() {
+        let positions = vec![f32::INFINITY, 0.0, 0.0, 0.0, f32::NEG_INFINITY, 0.0, 0.0, 0.0, f32::INFINITY, 0.0, 0.0, 1.0];
+        let distances = plane_distances(&positions, [0.0, 0.0, 1.0, 0.0]);
+        assert!(distances[..3].iter().all(|d| d.is_nan()), "{:?}", distances);
+        assert_eq!(distances[3], 1.0);
+    }
+}
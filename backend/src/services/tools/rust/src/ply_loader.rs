@@ -0,0 +1,334 @@
+use std::io;
+use crate::point_attributes::PointAttributes;
+
+// PLY reader producing the flat layout the tools consume: positions as x, y, z per point plus
+// optional colors (r, g, b in 0..1) and intensities. Supports `format ascii 1.0` and
+// `format binary_little_endian 1.0`. Integer colors are normalized by their type's maximum
+// (uchar -> /255, ushort -> /65535); float colors are taken as-is.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<ScalarType> {
+        match name {
+            "char" | "int8" => Some(ScalarType::Int8),
+            "uchar" | "uint8" => Some(ScalarType::UInt8),
+            "short" | "int16" => Some(ScalarType::Int16),
+            "ushort" | "uint16" => Some(ScalarType::UInt16),
+            "int" | "int32" => Some(ScalarType::Int32),
+            "uint" | "uint32" => Some(ScalarType::UInt32),
+            "float" | "float32" => Some(ScalarType::Float32),
+            "double" | "float64" => Some(ScalarType::Float64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    /// Divisor that maps integer color channels to 0..1
+    fn color_max(self) -> f32 {
+        match self {
+            ScalarType::UInt8 => 255.0,
+            ScalarType::UInt16 => 65535.0,
+            _ => 1.0,
+        }
+    }
+
+    fn read_le(self, bytes: &[u8]) -> f64 {
+        match self {
+            ScalarType::Int8 => bytes[0] as i8 as f64,
+            ScalarType::UInt8 => bytes[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::UInt16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::Int32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::UInt32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::Float32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            ScalarType::Float64 => {
+                let mut b = [0u8; 8];
+                b.copy_from_slice(&bytes[..8]);
+                f64::from_le_bytes(b)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    /// Scalar properties in declaration order; None marks a list property
+    properties: Vec<(String, Option<ScalarType>)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Parse the header; returns the format, the elements and the offset of the body
+fn parse_header(bytes: &[u8]) -> io::Result<(Format, Vec<Element>, usize)> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0usize;
+    let mut first = true;
+
+    loop {
+        let line_end = bytes[offset..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| invalid("PLY header is not terminated by end_header"))?;
+        let line = std::str::from_utf8(&bytes[offset..offset + line_end])
+            .map_err(|_| invalid("PLY header is not valid text"))?
+            .trim_end_matches('\r');
+        offset += line_end + 1;
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if first {
+            if tokens != ["ply"] {
+                return Err(invalid("missing ply magic"));
+            }
+            first = false;
+            continue;
+        }
+        match tokens.as_slice() {
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::BinaryLittleEndian),
+            ["format", other, _] => return Err(invalid(format!("unsupported PLY format {}", other))),
+            ["element", name, count] => {
+                let count = count
+                    .parse()
+                    .map_err(|_| invalid(format!("bad element count {}", count)))?;
+                elements.push(Element {
+                    name: name.to_string(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            ["property", "list", _, _, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid("property before element"))?
+                .properties
+                .push((name.to_string(), None)),
+            ["property", ty, name] => {
+                let ty = ScalarType::parse(ty).ok_or_else(|| invalid(format!("unknown property type {}", ty)))?;
+                elements
+                    .last_mut()
+                    .ok_or_else(|| invalid("property before element"))?
+                    .properties
+                    .push((name.to_string(), Some(ty)));
+            }
+            ["end_header"] => break,
+            // comment, obj_info and blank lines
+            _ => {}
+        }
+    }
+
+    let format = format.ok_or_else(|| invalid("missing PLY format line"))?;
+    Ok((format, elements, offset))
+}
+
+/// Column indices of the vertex properties the tools use
+struct VertexColumns {
+    position: [usize; 3],
+    color: Option<[usize; 3]>,
+    intensity: Option<usize>,
+}
+
+fn vertex_columns(vertex: &Element) -> io::Result<VertexColumns> {
+    let find = |name: &str| vertex.properties.iter().position(|(n, _)| n == name);
+    let position = [
+        find("x").ok_or_else(|| invalid("vertex has no x"))?,
+        find("y").ok_or_else(|| invalid("vertex has no y"))?,
+        find("z").ok_or_else(|| invalid("vertex has no z"))?,
+    ];
+    let color = match (find("red"), find("green"), find("blue")) {
+        (Some(r), Some(g), Some(b)) => Some([r, g, b]),
+        _ => None,
+    };
+    let intensity = find("intensity").or_else(|| find("scalar_intensity"));
+    Ok(VertexColumns {
+        position,
+        color,
+        intensity,
+    })
+}
+
+/// Parse a PLY file into flat positions plus colors/intensities when the vertices carry them
+pub fn parse_ply(bytes: &[u8]) -> io::Result<(Vec<f32>, PointAttributes)> {
+    let (format, elements, body_offset) = parse_header(bytes)?;
+    let vertex_index = elements
+        .iter()
+        .position(|e| e.name == "vertex")
+        .ok_or_else(|| invalid("PLY has no vertex element"))?;
+    let vertex = &elements[vertex_index];
+    if vertex.properties.iter().any(|(_, ty)| ty.is_none()) {
+        return Err(invalid("list properties on vertices are not supported"));
+    }
+    let types: Vec<ScalarType> = vertex.properties.iter().filter_map(|(_, ty)| *ty).collect();
+    let columns = vertex_columns(vertex)?;
+
+    // One f64 row per vertex, whichever the encoding
+    let mut rows: Vec<f64> = Vec::with_capacity(vertex.count * types.len());
+    let body = &bytes[body_offset..];
+    match format {
+        Format::Ascii => {
+            let text = std::str::from_utf8(body).map_err(|_| invalid("PLY body is not valid text"))?;
+            let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+            // Elements declared before the vertices take one line per item
+            for element in &elements[..vertex_index] {
+                for _ in 0..element.count {
+                    lines.next().ok_or_else(|| invalid("PLY body ends early"))?;
+                }
+            }
+            for _ in 0..vertex.count {
+                let line = lines.next().ok_or_else(|| invalid("PLY body ends early"))?;
+                let start = rows.len();
+                for token in line.split_whitespace().take(types.len()) {
+                    rows.push(token.parse().map_err(|_| invalid(format!("bad vertex value {}", token)))?);
+                }
+                if rows.len() - start != types.len() {
+                    return Err(invalid("vertex line has too few values"));
+                }
+            }
+        }
+        Format::BinaryLittleEndian => {
+            let mut offset = 0usize;
+            for element in &elements[..vertex_index] {
+                let mut row_size = 0usize;
+                for (name, ty) in &element.properties {
+                    let ty = ty.ok_or_else(|| {
+                        invalid(format!("cannot skip list property {} before the vertices", name))
+                    })?;
+                    row_size += ty.size();
+                }
+                offset += row_size * element.count;
+            }
+            let row_size: usize = types.iter().map(|t| t.size()).sum();
+            let end = offset + row_size * vertex.count;
+            if body.len() < end {
+                return Err(invalid("PLY body ends early"));
+            }
+            for row in body[offset..end].chunks_exact(row_size) {
+                let mut field = 0usize;
+                for ty in &types {
+                    rows.push(ty.read_le(&row[field..]));
+                    field += ty.size();
+                }
+            }
+        }
+    }
+
+    let stride = types.len();
+    let positions = rows
+        .chunks_exact(stride)
+        .flat_map(|r| columns.position.map(|c| r[c] as f32))
+        .collect();
+    let colors = columns.color.map(|cols| {
+        rows.chunks_exact(stride)
+            .flat_map(|r| cols.map(|c| r[c] as f32 / types[c].color_max()))
+            .collect()
+    });
+    let intensities = columns
+        .intensity
+        .map(|c| rows.chunks_exact(stride).map(|r| r[c] as f32).collect());
+
+    Ok((
+        positions,
+        PointAttributes {
+            colors,
+            intensities,
+            classifications: None,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTS: [([f32; 3], [u8; 3], f32); 3] = [
+        ([0.5, -1.25, 3.0], [255, 0, 51], 10.0),
+        ([1.0e3, 2.5e-3, -7.75], [0, 128, 255], 0.5),
+        ([-0.1, 0.2, 0.3], [17, 34, 68], 250.0),
+    ];
+
+    fn ascii_ply() -> Vec<u8> {
+        let mut text = String::from(
+            "ply\r\nformat ascii 1.0\r\ncomment made by hand\r\nelement vertex 3\r\n\
+             property float x\r\nproperty float y\r\nproperty float z\r\n\
+             property uchar red\r\nproperty uchar green\r\nproperty uchar blue\r\n\
+             property float intensity\r\nelement face 0\r\nproperty list uchar int vertex_indices\r\n\
+             end_header\r\n",
+        );
+        for (p, c, i) in POINTS {
+            text.push_str(&format!("{} {} {} {} {} {} {}\r\n", p[0], p[1], p[2], c[0], c[1], c[2], i));
+        }
+        text.into_bytes()
+    }
+
+    fn binary_ply() -> Vec<u8> {
+        let mut bytes = b"ply\nformat binary_little_endian 1.0\nelement vertex 3\n\
+             property float x\nproperty float y\nproperty float z\n\
+             property uchar red\nproperty uchar green\nproperty uchar blue\n\
+             property float intensity\nend_header\n"
+            .to_vec();
+        for (p, c, i) in POINTS {
+            for v in p {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+            bytes.extend_from_slice(&c);
+            bytes.extend_from_slice(&i.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_ascii_and_binary_parse_identically() {
+        let (ascii_positions, ascii_attributes) = parse_ply(&ascii_ply()).unwrap();
+        let (binary_positions, binary_attributes) = parse_ply(&binary_ply()).unwrap();
+
+        assert_eq!(ascii_positions, binary_positions);
+        assert_eq!(ascii_attributes, binary_attributes);
+
+        let expected: Vec<f32> = POINTS.iter().flat_map(|(p, _, _)| *p).collect();
+        assert_eq!(binary_positions, expected);
+        let colors = binary_attributes.colors.unwrap();
+        assert_eq!(&colors[..3], &[1.0, 0.0, 51.0 / 255.0]);
+        assert_eq!(binary_attributes.intensities.unwrap(), vec![10.0, 0.5, 250.0]);
+        assert!(binary_attributes.classifications.is_none());
+    }
+
+    #[test]
+    fn test_positions_only_and_errors() {
+        let ply = b"ply\nformat ascii 1.0\nelement vertex 2\nproperty double z\nproperty double y\n\
+                    property double x\nend_header\n1 2 3\n4 5 6\n";
+        let (positions, attributes) = parse_ply(ply).unwrap();
+        assert_eq!(positions, vec![3.0, 2.0, 1.0, 6.0, 5.0, 4.0]);
+        assert_eq!(attributes, PointAttributes::default());
+
+        assert!(parse_ply(b"ply\nformat binary_big_endian 1.0\nend_header\n").is_err());
+        assert!(parse_ply(&binary_ply()[..binary_ply().len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::{self, Read, Write};
+use crate::binary_io::{read_f32_vec, read_u8_vec, write_f32_slice, ByteOrder};
+use crate::geometry::sanitize;
+
+// Optional per-point attribute blocks that follow the positions in the extended binary protocol
+// (same layout as voxel_downsample_rust): [f32* colors][f32* intensities][u8* classifications],
+// each present only when its flag bit is set.
+
+pub const FLAG_COLORS: u32 = 1;
+pub const FLAG_INTENSITY: u32 = 2;
+pub const FLAG_CLASSIFICATION: u32 = 4;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PointAttributes {
+    /// r, g, b per point
+    pub colors: Option<Vec<f32>>,
+    pub intensities: Option<Vec<f32>>,
+    pub classifications: Option<Vec<u8>>,
+}
+
+impl PointAttributes {
+    /// Read the attribute blocks selected by `flags` for `point_count` points
+    pub fn read<R: Read>(reader: &mut R, point_count: usize, flags: u32, order: ByteOrder) -> io::Result<PointAttributes> {
+        let colors = if flags & FLAG_COLORS != 0 {
+            Some(read_f32_vec(reader, point_count * 3, order)?)
+        } else {
+            None
+        };
+        let intensities = if flags & FLAG_INTENSITY != 0 {
+            Some(read_f32_vec(reader, point_count, order)?)
+        } else {
+            None
+        };
+        let classifications = if flags & FLAG_CLASSIFICATION != 0 {
+            Some(read_u8_vec(reader, point_count)?)
+        } else {
+            None
+        };
+        Ok(PointAttributes {
+            colors,
+            intensities,
+            classifications,
+        })
+    }
+
+    /// Write the present attribute blocks in protocol order
+    pub fn write<W: Write>(&self, writer: &mut W, order: ByteOrder) -> io::Result<()> {
+        if let Some(colors) = &self.colors {
+            write_f32_slice(writer, colors, order)?;
+        }
+        if let Some(intensities) = &self.intensities {
+            write_f32_slice(writer, intensities, order)?;
+        }
+        if let Some(classifications) = &self.classifications {
+            writer.write_all(classifications)?;
+        }
+        Ok(())
+    }
+
+    /// Flag bits describing which blocks are present
+    pub fn flags(&self) -> u32 {
+        let mut flags = 0;
+        if self.colors.is_some() {
+            flags |= FLAG_COLORS;
+        }
+        if self.intensities.is_some() {
+            flags |= FLAG_INTENSITY;
+        }
+        if self.classifications.is_some() {
+            flags |= FLAG_CLASSIFICATION;
+        }
+        flags
+    }
+
+    /// New attribute set holding the points at `indices`, in that order
+    pub fn gather(&self, indices: &[usize]) -> PointAttributes {
+        PointAttributes {
+            colors: self
+                .colors
+                .as_ref()
+                .map(|c| indices.iter().flat_map(|&i| [c[i * 3], c[i * 3 + 1], c[i * 3 + 2]]).collect()),
+            intensities: self
+                .intensities
+                .as_ref()
+                .map(|v| indices.iter().map(|&i| v[i]).collect()),
+            classifications: self
+                .classifications
+                .as_ref()
+                .map(|v| indices.iter().map(|&i| v[i]).collect()),
+        }
+    }
+}
+
+/// Positions of the points at `indices`, in that order
+pub fn gather_positions(positions: &[f32], indices: &[usize]) -> Vec<f32> {
+    indices
+        .iter()
+        .flat_map(|&i| [positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]])
+        .collect()
+}
+
+/// Remove the points with a NaN or infinite coordinate together with their attributes
+/// (tools call this when FLAG_DROP_NON_FINITE is set)
+pub fn drop_non_finite(positions: &mut Vec<f32>, attributes: &mut PointAttributes) {
+    if let Some(kept) = sanitize(positions) {
+        *attributes = attributes.gather(&kept);
+    }
+}
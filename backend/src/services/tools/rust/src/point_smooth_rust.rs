@@ -1,8 +1,16 @@
 use std::io::{self, Read, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, to_wire_order, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::protocol::{check_payload_size, read_tool_header, tool_id, write_stats, ErrorCode, ToolError, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
 
 // Binary protocol for fast I/O
-// Input format: [u32 pointCount][f32 smoothingRadius][f32 iterations][f32* pointData]
+// Input format: [protocol header][u32 pointCount][f32 smoothingRadius][f32 iterations][f32* pointData]
 // Output format: [u32 pointCount][f32* smoothedPoints]
+// With protocol version 2, [f32 convergenceEpsilon] follows iterations and [u32 iterationsRun]
+// follows the smoothed points: smoothing stops early once no point moves by convergenceEpsilon
+// or more in an iteration (0 runs every iteration). Version 1 input always runs every iteration.
+// Points with a NaN or infinite coordinate are neither smoothed nor used as neighbors; they are
+// passed through unchanged.
 
 fn main() {
     // Read binary input for fast I/O
@@ -11,21 +19,35 @@ fn main() {
     let mut stdin = io::stdin();
     
     // Read binary header (12 bytes: 4 for u32 + 4 for f32 + 4 for f32)
-    let mut header = [0u8; 12];
-    if stdin.read_exact(&mut header).is_err() {
-        std::process::exit(1);
-    }
+    let (header, mut run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::POINT_SMOOTH) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
     
-    let point_count = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
-    let smoothing_radius = f32::from_le_bytes([header[4], header[5], header[6], header[7]]);
-    let iterations = f32::from_le_bytes([header[8], header[9], header[10], header[11]]) as i32;
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let smoothing_radius = f32_at(&header, 4, run.order);
+    let iterations = f32_at(&header, 8, run.order) as i32;
+    let reports_iterations = run.version >= 2;
+    let convergence_epsilon = if reports_iterations {
+        let mut epsilon = [0u8; 4];
+        if stdin.read_exact(&mut epsilon).is_err() {
+            ToolError::new(ErrorCode::ShortHeader, "input ends before convergenceEpsilon").exit(run.order);
+        }
+        f32_at(&epsilon, 0, run.order)
+    } else {
+        0.0
+    };
     
     // Validate input
     if point_count == 0 || smoothing_radius <= 0.0 || iterations <= 0 {
-        // Write empty result (4 bytes: pointCount = 0)
+        // Write empty result (4 bytes: pointCount = 0, plus iterationsRun = 0 for version 2)
         let output_count: u32 = 0;
         let mut stdout = io::stdout();
-        if stdout.write_all(&output_count.to_le_bytes()).is_err() || stdout.flush().is_err() {
+        if write_u32(&mut stdout, output_count, run.order).is_err()
+            || (reports_iterations && write_u32(&mut stdout, 0, run.order).is_err())
+            || write_stats(&mut stdout, &run, point_count, 0).is_err()
+            || stdout.flush().is_err()
+        {
             std::process::exit(1);
         }
         return;
@@ -33,114 +55,79 @@ fn main() {
     
     // Read point data directly into vector (optimized binary read)
     let float_count = point_count * 3;
-    let bytes_to_read = float_count * 4;
+    let bytes_to_read = match check_payload_size(float_count, 4) {
+        Ok(n) => n,
+        Err(e) => e.exit(run.order),
+    };
     let mut buffer = vec![0u8; bytes_to_read];
     
     if stdin.read_exact(&mut buffer).is_err() {
-        std::process::exit(1);
+        ToolError::new(ErrorCode::ShortPayload, "input ends inside the point data").exit(run.order);
     }
     
-    // Convert bytes to floats (little-endian) - optimized conversion
+    // Convert bytes to floats (wire byte order) - optimized conversion
+    to_wire_order(&mut buffer, 4, run.order);
     let point_cloud_data: Vec<f32> = buffer
         .chunks_exact(4)
         .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
         .collect();
     
     // Process point cloud smoothing
-    let smoothed_points = point_cloud_smooth(
+    let (smoothed_points, iterations_run) = point_cloud_smooth(
         &point_cloud_data,
         smoothing_radius,
         iterations,
+        convergence_epsilon,
+        &mut run,
     );
     
     // Write binary output for fast I/O
-    // Binary format: [u32 pointCount][f32* smoothedPoints]
+    // Binary format: [u32 pointCount][f32* smoothedPoints][u32 iterationsRun (version 2)]
     
     let mut stdout = io::stdout();
     
     // Write output count (4 bytes)
     let output_count = smoothed_points.len() / 3;
-    if stdout.write_all(&(output_count as u32).to_le_bytes()).is_err() {
+    if write_u32(&mut stdout, output_count as u32, run.order).is_err() {
         std::process::exit(1);
     }
     
     // Write smoothed points directly (binary, no serialization overhead!)
-    let bytes: Vec<u8> = smoothed_points
-        .iter()
-        .flat_map(|&f| f.to_le_bytes().into_iter())
-        .collect();
-    if stdout.write_all(&bytes).is_err() || stdout.flush().is_err() {
+    if write_f32_slice(&mut stdout, &smoothed_points, run.order).is_err()
+        || (reports_iterations && write_u32(&mut stdout, iterations_run as u32, run.order).is_err())
+        || write_stats(&mut stdout, &run, point_count, output_count).is_err()
+        || stdout.flush().is_err()
+    {
         std::process::exit(1);
     }
 }
 
+/// Smooth `points`, returning the result and the number of iterations run. The loop stops after
+/// the first iteration in which every point moved less than `convergence_epsilon`; a zero,
+/// negative or NaN epsilon disables the early exit.
 fn point_cloud_smooth(
     points: &[f32],
     smoothing_radius: f32,
     iterations: i32,
-) -> Vec<f32> {
-    // OPTIMIZATION: Use O(n) spatial hashing algorithm (same as Rust WASM)
+    convergence_epsilon: f32,
+    run: &mut ToolRun,
+) -> (Vec<f32>, usize) {
     let point_count = points.len() / 3;
     let mut smoothed_points = points.to_vec();
-    let radius_squared = smoothing_radius * smoothing_radius;
-    let cell_size = smoothing_radius;
-    let inv_cell_size = 1.0f32 / cell_size;
-    
-    // Find bounding box - single pass
-    let mut min_x = points[0];
-    let mut max_x = points[0];
-    let mut min_y = points[1];
-    let mut max_y = points[1];
-    let mut min_z = points[2];
-    let mut max_z = points[2];
-    
-    for i in (0..points.len()).step_by(3) {
-        min_x = min_x.min(points[i]);
-        max_x = max_x.max(points[i]);
-        min_y = min_y.min(points[i + 1]);
-        max_y = max_y.max(points[i + 1]);
-        min_z = min_z.min(points[i + 2]);
-        max_z = max_z.max(points[i + 2]);
-    }
+    let epsilon_squared = if convergence_epsilon > 0.0 { convergence_epsilon * convergence_epsilon } else { 0.0 };
     
-    // Calculate grid dimensions
-    let grid_width = ((max_x - min_x) * inv_cell_size) as usize + 1;
-    let grid_height = ((max_y - min_y) * inv_cell_size) as usize + 1;
-    let grid_depth = ((max_z - min_z) * inv_cell_size) as usize + 1;
-    let grid_size = grid_width * grid_height * grid_depth;
-    
-    // Pre-allocate grid with capacity estimation
-    let mut grid: Vec<Vec<usize>> = vec![Vec::with_capacity(8); grid_size];
-    
-    // Hash function to get grid index (same as Rust WASM)
-    let get_grid_index = |x: f32, y: f32, z: f32| -> i32 {
-        let gx = ((x - min_x) * inv_cell_size) as i32;
-        let gy = ((y - min_y) * inv_cell_size) as i32;
-        let gz = ((z - min_z) * inv_cell_size) as i32;
-        gx + gy * grid_width as i32 + gz * grid_width as i32 * grid_height as i32
-    };
-    
-    // Smoothing iterations using spatial hashing (same as Rust WASM)
+    // Smoothing iterations using spatial hashing (cells one radius wide, 27-cell search)
+    let mut iterations_run = 0;
     for _iter in 0..iterations {
+        iterations_run += 1;
+        let mut max_displacement_squared = 0.0f32;
+
         // Copy current state to temp buffer
         let temp_points = smoothed_points.clone();
         
-        // Clear grid efficiently
-        for cell in &mut grid {
-            cell.clear();
-        }
-        
-        // Populate grid with PREVIOUS iteration's point positions
-        for i in 0..point_count {
-            let i3 = i * 3;
-            let x = temp_points[i3];
-            let y = temp_points[i3 + 1];
-            let z = temp_points[i3 + 2];
-            let grid_index = get_grid_index(x, y, z);
-            if grid_index >= 0 && grid_index < grid_size as i32 {
-                grid[grid_index as usize].push(i);
-            }
-        }
+        // Bucket the PREVIOUS iteration's point positions; non-finite points stay out
+        let grid = SpatialGrid::new(&temp_points, smoothing_radius);
+        run.note_map_size(grid.cell_count());
         
         // Process each point using spatial hash
         for i in 0..point_count {
@@ -153,43 +140,15 @@ fn point_cloud_smooth(
             let mut sum_y = 0.0;
             let mut sum_z = 0.0;
             let mut count = 0;
-            
-            // Check neighboring grid cells (3x3x3 = 27 cells) - same as Rust WASM
-            for dx in -1..=1 {
-                for dy in -1..=1 {
-                    for dz in -1..=1 {
-                        let grid_index = get_grid_index(
-                            x + dx as f32 * cell_size,
-                            y + dy as f32 * cell_size,
-                            z + dz as f32 * cell_size
-                        );
-                        
-                        if grid_index >= 0 && grid_index < grid_size as i32 {
-                            for &j in &grid[grid_index as usize] {
-                                if i == j { continue; }
-                                
-                                let j3 = j * 3;
-                                let jx = temp_points[j3];
-                                let jy = temp_points[j3 + 1];
-                                let jz = temp_points[j3 + 2];
-                                
-                                let dx2 = jx - x;
-                                let dy2 = jy - y;
-                                let dz2 = jz - z;
-                                
-                                let distance_squared = dx2 * dx2 + dy2 * dy2 + dz2 * dz2;
-                                
-                                if distance_squared <= radius_squared {
-                                    sum_x += jx;
-                                    sum_y += jy;
-                                    sum_z += jz;
-                                    count += 1;
-                                }
-                            }
-                        }
-                    }
+            grid.for_each_in_radius(&temp_points, x, y, z, smoothing_radius, |j, _| {
+                if i != j {
+                    let j3 = j * 3;
+                    sum_x += temp_points[j3];
+                    sum_y += temp_points[j3 + 1];
+                    sum_z += temp_points[j3 + 2];
+                    count += 1;
                 }
-            }
+            });
             
             // Apply smoothing if neighbors found
             if count > 0 {
@@ -200,9 +159,79 @@ fn point_cloud_smooth(
                 smoothed_points[i3] = new_x;
                 smoothed_points[i3 + 1] = new_y;
                 smoothed_points[i3 + 2] = new_z;
+
+                let (mx, my, mz) = (new_x - x, new_y - y, new_z - z);
+                max_displacement_squared = max_displacement_squared.max(mx * mx + my * my + mz * mz);
             }
         }
+
+        if max_displacement_squared < epsilon_squared {
+            break;
+        }
     }
     
-    smoothed_points
+    (smoothed_points, iterations_run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smoothing_pulls_bump_toward_neighbors() {
+        let mut points: Vec<f32> = (0..10).flat_map(|i| [i as f32 * 0.1, 0.0, 0.0]).collect();
+        points[5 * 3 + 1] = 0.05;
+        let (smoothed, _) = point_cloud_smooth(&points, 0.15, 1, 0.0, &mut ToolRun::default());
+        assert!(smoothed[5 * 3 + 1] > 0.0 && smoothed[5 * 3 + 1] < 0.05);
+        assert_eq!(smoothed.len(), points.len());
+    }
+
+    #[test]
+    fn test_huge_extent_with_tiny_radius() {
+        // (radius, far corner, pair position): 2^20 cells per axis (2^60 in total), then an
+        // extent of 2^24 cells per axis. The grid only stores occupied cells, so both must
+        // complete and still average the close pair while the corner points stay put. Powers of
+        // two keep the cell arithmetic exact.
+        for (radius, far, base) in [(1.0f32 / 1024.0, 1024.0f32, 1.0f32), (1.0, 16_777_216.0, 100.0)] {
+            let gap = radius * 0.5;
+            let points = vec![0.0, 0.0, 0.0, base, base, base, base + gap, base, base, far, far, far];
+            let (smoothed, _) = point_cloud_smooth(&points, radius, 1, 0.0, &mut ToolRun::default());
+            let midpoint = base + gap * 0.5;
+            assert!((smoothed[3] - midpoint).abs() < 1e-5 * base, "{:?}", smoothed);
+            assert!((smoothed[6] - midpoint).abs() < 1e-5 * base, "{:?}", smoothed);
+            assert_eq!(&smoothed[9..], &[far, far, far]);
+        }
+    }
+
+    #[test]
+    fn test_non_finite_points_pass_through_without_affecting_others() {
+        let mut finite: Vec<f32> = (0..10).flat_map(|i| [i as f32 * 0.1, 0.0, 0.0]).collect();
+        finite[5 * 3 + 1] = 0.05;
+        let mut points = finite.clone();
+        points.extend_from_slice(&[f32::NAN, 0.0, 0.0, 0.5, f32::INFINITY, 0.0, 0.45, 0.0, f32::NAN]);
+
+        let (expected, _) = point_cloud_smooth(&finite, 0.15, 2, 0.0, &mut ToolRun::default());
+        let (smoothed, _) = point_cloud_smooth(&points, 0.15, 2, 0.0, &mut ToolRun::default());
+        assert_eq!(&smoothed[..30], &expected[..]);
+        assert!(smoothed[30].is_nan() && smoothed[34] == f32::INFINITY && smoothed[38].is_nan());
+    }
+
+    #[test]
+    fn test_already_smooth_cloud_exits_after_one_iteration() {
+        // Each point's only neighbors sit exactly on top of it, so averaging moves nothing
+        let points: Vec<f32> = (0..15).flat_map(|i| [(i / 3) as f32 * 10.0, 1.0, 2.0]).collect();
+        let (smoothed, iterations_run) = point_cloud_smooth(&points, 0.5, 50, 1e-4, &mut ToolRun::default());
+        assert_eq!(iterations_run, 1);
+        assert_eq!(smoothed, points);
+
+        // Without an epsilon every requested iteration runs
+        let (_, iterations_run) = point_cloud_smooth(&points, 0.5, 50, 0.0, &mut ToolRun::default());
+        assert_eq!(iterations_run, 50);
+
+        // A cloud that still moves keeps iterating past the first pass
+        let mut bumpy: Vec<f32> = (0..10).flat_map(|i| [i as f32 * 0.1, 0.0, 0.0]).collect();
+        bumpy[5 * 3 + 1] = 0.05;
+        let (_, iterations_run) = point_cloud_smooth(&bumpy, 0.15, 50, 1e-4, &mut ToolRun::default());
+        assert!(iterations_run > 1 && iterations_run < 50, "{}", iterations_run);
+    }
 }
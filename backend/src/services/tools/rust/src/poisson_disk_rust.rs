@@ -0,0 +1,132 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, gather_positions, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Poisson-disk (blue-noise) downsampling: walks the points in input order and keeps each one
+// unless an already kept point lies within minDistance of it, so no two kept points are
+// minDistance or closer. Unlike voxel downsampling the result has no grid-aligned structure.
+// Rejection checks scan a spatial grid with cell size minDistance. The output is a subset of the
+// input (no averaging), kept in input order with its attributes. Points with a NaN or infinite
+// coordinate are never kept.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 minDistance][u32 flags]
+//               [f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit31=drop non-finite points
+// Output format: [u32 outputCount][f32* positions][optional colors][optional intensities][optional classifications]
+// minDistance <= 0 keeps every finite point.
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: u32 + f32 + u32)
+    let (header, mut run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::POISSON_DISK) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let min_distance = f32_at(&header, 4, run.order);
+    let flags = u32_at(&header, 8, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+
+    let kept = poisson_disk(&positions, min_distance, &mut run);
+    let kept_positions = gather_positions(&positions, &kept);
+    let kept_attributes = attributes.gather(&kept);
+
+    if write_u32(&mut stdout, kept.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &kept_positions, run.order).is_err()
+        || kept_attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, kept.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Indices of the greedily kept points, in input order
+fn poisson_disk(positions: &[f32], min_distance: f32, run: &mut ToolRun) -> Vec<usize> {
+    let finite = (0..positions.len() / 3).filter(|&i| is_finite_point(&positions[i * 3..i * 3 + 3]));
+    if !(min_distance > 0.0 && min_distance.is_finite()) {
+        return finite.collect();
+    }
+
+    // The grid holds every point; a candidate is rejected if any point already kept is in range
+    let grid = SpatialGrid::new(positions, min_distance);
+    run.note_map_size(grid.cell_count());
+    let mut is_kept = vec![false; positions.len() / 3];
+    let mut kept = Vec::new();
+    for i in finite {
+        let p = &positions[i * 3..i * 3 + 3];
+        let mut blocked = false;
+        grid.for_each_in_radius(positions, p[0], p[1], p[2], min_distance, |j, _| blocked |= is_kept[j]);
+        if !blocked {
+            is_kept[i] = true;
+            kept.push(i);
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kept_points_are_farther_apart_than_min_distance() {
+        let mut positions = Vec::new();
+        for i in 0..2000 {
+            let t = i as f32;
+            positions.extend_from_slice(&[(t * 0.37).sin() * 2.0, (t * 0.11).cos() * 2.0, (t * 0.053).sin() * 0.5]);
+        }
+        let min_distance = 0.3;
+        let kept = poisson_disk(&positions, min_distance, &mut ToolRun::default());
+        assert!(kept.len() > 10 && kept.len() < 2000, "kept {}", kept.len());
+        assert!(kept.windows(2).all(|w| w[0] < w[1]));
+
+        for (a, &i) in kept.iter().enumerate() {
+            for &j in &kept[a + 1..] {
+                let d: f32 = (0..3).map(|k| (positions[i * 3 + k] - positions[j * 3 + k]).powi(2)).sum();
+                assert!(d > min_distance * min_distance, "points {} and {} are {} apart", i, j, d.sqrt());
+            }
+        }
+
+        // Every rejected point is within min_distance of a kept one (the sample is maximal)
+        for i in (0..2000).filter(|i| !kept.contains(i)) {
+            assert!(kept.iter().any(|&j| {
+                let d: f32 = (0..3).map(|k| (positions[i * 3 + k] - positions[j * 3 + k]).powi(2)).sum();
+                d <= min_distance * min_distance
+            }));
+        }
+    }
+
+    #[test]
+    fn test_duplicates_collapse_and_non_finite_points_are_dropped() {
+        let positions = vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, f32::NAN, 0.0, 0.0, 1.0, 0.0, 0.0];
+        assert_eq!(poisson_disk(&positions, 0.5, &mut ToolRun::default()), vec![0, 3]);
+        assert_eq!(poisson_disk(&positions, 0.0, &mut ToolRun::default()), vec![0, 1, 3]);
+    }
+}
@@ -0,0 +1,527 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::Instant;
+use crate::binary_io::{read_f32_vec, read_float_vec, read_u8_vec, u64_at, write_u32, write_u64, ByteOrder, LeFloat};
+
+// Versioned prefix in front of every tool's binary header:
+// [4 bytes magic "PCWT"][u16 version][u16 toolId], little-endian (8 bytes).
+// Tools validate it before touching the rest of the input, so data written for another tool
+// or another protocol revision is rejected instead of silently misparsed.
+//
+// Version 2 extends two tools: point_smooth's header gains convergenceEpsilon and its output
+// the number of iterations run, and adaptive_voxel_downsample's header gains a mode word. Every
+// other tool reads both versions identically, so version-1 producers keep working unchanged.
+//
+// When a tool cannot read or accept its input it writes an error frame instead of a result:
+// [u32 0xFFFFFFFF][u32 errorCode][u32 msgLen][utf8 msg], then exits with status 1.
+// The marker can never be a valid count, so callers check the first u32 of the output.
+// A tool that exits non-zero without a frame failed while writing its output (e.g. broken pipe).
+//
+// Setting STATS_FLAG in the toolId field asks the tool to append a stats block after its regular
+// output: [u64 inputCount][u64 outputCount][u64 elapsedMicros][u64 peakMapSize] (STATS_SIZE
+// bytes). Elapsed time runs from reading the prefix to writing the block; peakMapSize is the
+// largest voxel/cell map the tool built (0 for tools without one). Error frames carry no block.
+//
+// The prefix itself is always little-endian. Everything after it (tool header, payload, output,
+// error frames and stats) is little-endian too unless BIG_ENDIAN_FLAG is set in the toolId
+// field, for producers on big-endian targets. Classification and mask bytes are unaffected.
+
+pub const MAGIC: [u8; 4] = *b"PCWT";
+pub const PROTOCOL_VERSION: u16 = 2;
+/// Oldest version still accepted
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+pub const HEADER_SIZE: usize = 8;
+
+/// Tool identifiers carried in the prefix (stable: never renumber, only append)
+pub mod tool_id {
+    pub const VOXEL_DOWNSAMPLE: u16 = 1;
+    pub const POINT_SMOOTH: u16 = 2;
+    pub const VOXEL_DEBUG: u16 = 3;
+    pub const COLOR_EDGE_MASK: u16 = 4;
+    pub const INTENSITY_TO_COLOR: u16 = 5;
+    pub const SKELETON_RADIUS: u16 = 6;
+    pub const CANONICALIZE_ORDER: u16 = 7;
+    pub const FARTHEST_POINT_SAMPLING: u16 = 8;
+    pub const COLOR_GRADIENT: u16 = 9;
+    pub const RAY_CARVE: u16 = 10;
+    pub const RANDOM_DOWNSAMPLE: u16 = 11;
+    pub const NORMAL_ESTIMATION: u16 = 12;
+    pub const DIFFERENCE_OF_NORMALS: u16 = 13;
+    pub const ITERATIVE_GROUND: u16 = 14;
+    pub const EUCLIDEAN_CLUSTERING: u16 = 15;
+    pub const RELIABILITY: u16 = 16;
+    pub const CLASS_BOUNDARY: u16 = 17;
+    pub const DELTA_ENCODE: u16 = 18;
+    pub const DELTA_DECODE: u16 = 19;
+    pub const MLS_SMOOTH: u16 = 20;
+    pub const TRANSFORM: u16 = 21;
+    pub const ICP: u16 = 22;
+    pub const KNN: u16 = 23;
+    pub const CURVATURE_ESTIMATION: u16 = 24;
+    pub const ADAPTIVE_VOXEL_DOWNSAMPLE: u16 = 25;
+    pub const VOXEL_DENSITY: u16 = 26;
+    pub const DEDUP: u16 = 27;
+    pub const CROP_BOX: u16 = 28;
+    pub const CROP_SPHERE: u16 = 29;
+    pub const INTENSITY_FILTER: u16 = 30;
+    pub const COLOR_CLASSIFY: u16 = 31;
+    pub const REMOVE_GROUND: u16 = 32;
+    pub const MERGE_CLOUDS: u16 = 33;
+    pub const BOUNDING_SPHERE: u16 = 34;
+    pub const OBB: u16 = 35;
+    pub const SNAP_TO_GRID: u16 = 36;
+    pub const POISSON_DISK: u16 = 37;
+    pub const CONVEX_HULL: u16 = 38;
+    pub const HULL_VOLUME: u16 = 39;
+    pub const COLORIZE_BY_HEIGHT: u16 = 40;
+    pub const MORTON_SORT: u16 = 41;
+    pub const PLANE_DISTANCE: u16 = 42;
+    pub const STRIDE_DECIMATE: u16 = 43;
+    pub const CLOUD_SUMMARY: u16 = 44;
+    pub const JITTER: u16 = 45;
+    pub const MEAN_SPACING: u16 = 46;
+    pub const SUGGEST_VOXEL_SIZE: u16 = 47;
+    pub const OCTREE_DOWNSAMPLE: u16 = 48;
+    pub const LOCAL_DENSITY: u16 = 49;
+    pub const RECENTER: u16 = 50;
+    pub const CLOUD_DIFF: u16 = 51;
+    pub const CLOUD_DISTANCE: u16 = 52;
+    pub const GATHER: u16 = 53;
+    pub const DENSITY_VOLUME: u16 = 54;
+    pub const MARCHING_CUBES: u16 = 55;
+    pub const HOLE_DETECTION: u16 = 56;
+    pub const TRANSFER_COLOR: u16 = 57;
+}
+
+/// High bit of the toolId field: append the stats block to the output
+pub const STATS_FLAG: u16 = 0x8000;
+pub const STATS_SIZE: usize = 32;
+/// Second-highest bit of the toolId field: the rest of the stream is big-endian
+pub const BIG_ENDIAN_FLAG: u16 = 0x4000;
+const TOOL_FLAGS: u16 = STATS_FLAG | BIG_ENDIAN_FLAG;
+
+pub const ERROR_MARKER: u32 = 0xFFFF_FFFF;
+
+/// Bit 31 of a tool's flags word, shared by every tool that has one: remove points with a NaN
+/// or infinite coordinate (and their attributes) before processing. Without it such points are
+/// still kept out of bounds and grids, and tools that emit one point per input pass them through.
+pub const FLAG_DROP_NON_FINITE: u32 = 1 << 31;
+
+/// Largest payload block a tool will allocate for (4 GiB); larger counts are rejected up front
+pub const MAX_PAYLOAD_BYTES: usize = 1 << 32;
+
+/// Error codes carried in the error frame (stable: never renumber, only append)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// Input ended inside the protocol prefix or the tool's fixed header
+    ShortHeader = 1,
+    /// Input ended inside a payload block
+    ShortPayload = 2,
+    /// Voxel size is zero, negative or not finite
+    InvalidVoxelSize = 3,
+    /// Declared counts need a payload larger than MAX_PAYLOAD_BYTES
+    AllocationTooLarge = 4,
+    /// Wrong magic, unsupported version or input meant for another tool
+    BadProtocolHeader = 5,
+    /// Payload read fine but its contents are malformed
+    InvalidData = 6,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ToolError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ToolError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> ToolError {
+        ToolError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Write the error frame
+    pub fn write_to<W: Write>(&self, writer: &mut W, order: ByteOrder) -> io::Result<()> {
+        write_u32(writer, ERROR_MARKER, order)?;
+        write_u32(writer, self.code as u32, order)?;
+        write_u32(writer, self.message.len() as u32, order)?;
+        writer.write_all(self.message.as_bytes())
+    }
+
+    /// Report on stderr, write the error frame to stdout in `order` and exit(1). Errors in the
+    /// prefix itself go out little-endian, since the order is not known yet.
+    pub fn exit(&self, order: ByteOrder) -> ! {
+        eprintln!("error {}: {}", self.code as u32, self.message);
+        let mut stdout = io::stdout();
+        let _ = self.write_to(&mut stdout, order).and_then(|_| stdout.flush());
+        std::process::exit(1);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HeaderError {
+    Truncated,
+    BadMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    WrongTool { expected: u16, found: u16 },
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeaderError::Truncated => write!(f, "input ends before the {}-byte protocol header", HEADER_SIZE),
+            HeaderError::BadMagic(magic) => write!(f, "bad protocol magic {:?}, expected {:?}", magic, MAGIC),
+            HeaderError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported protocol version {} (this build speaks versions {} to {})",
+                version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+            ),
+            HeaderError::WrongTool { expected, found } => {
+                write!(f, "input is for tool id {}, this tool is id {}", found, expected)
+            }
+        }
+    }
+}
+
+/// Trailing statistics block requested with STATS_FLAG
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ToolStats {
+    pub input_count: u64,
+    pub output_count: u64,
+    pub elapsed_micros: u64,
+    pub peak_map_size: u64,
+}
+
+impl ToolStats {
+    pub fn write_to<W: Write>(&self, writer: &mut W, order: ByteOrder) -> io::Result<()> {
+        for value in [self.input_count, self.output_count, self.elapsed_micros, self.peak_map_size] {
+            write_u64(writer, value, order)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R, order: ByteOrder) -> io::Result<ToolStats> {
+        let mut bytes = [0u8; STATS_SIZE];
+        reader.read_exact(&mut bytes)?;
+        let field = |i: usize| u64_at(&bytes, i * 8, order);
+        Ok(ToolStats { input_count: field(0), output_count: field(1), elapsed_micros: field(2), peak_map_size: field(3) })
+    }
+}
+
+/// What the prefix set up for one run of a tool: the protocol version, the byte order of the
+/// rest of the stream, and for the stats block (only when STATS_FLAG is set) the start time and
+/// the largest map noted so far. Returned by header parsing and handed back to write_stats once
+/// the output is written.
+#[derive(Clone, Debug)]
+pub struct ToolRun {
+    pub version: u16,
+    pub order: ByteOrder,
+    stats_start: Option<Instant>,
+    peak_map_size: u64,
+}
+
+impl Default for ToolRun {
+    /// Current protocol version, little-endian, no stats block
+    fn default() -> ToolRun {
+        ToolRun { version: PROTOCOL_VERSION, order: ByteOrder::Little, stats_start: None, peak_map_size: 0 }
+    }
+}
+
+impl ToolRun {
+    /// Record the size of a voxel/cell map the tool built; the stats block reports the largest
+    pub fn note_map_size(&mut self, size: usize) {
+        self.peak_map_size = self.peak_map_size.max(size as u64);
+    }
+
+    pub fn peak_map_size(&self) -> u64 {
+        self.peak_map_size
+    }
+}
+
+/// Append the stats block if the input asked for it (STATS_FLAG); otherwise write nothing
+pub fn write_stats<W: Write>(writer: &mut W, run: &ToolRun, input_count: usize, output_count: usize) -> io::Result<()> {
+    match run.stats_start {
+        Some(start) => ToolStats {
+            input_count: input_count as u64,
+            output_count: output_count as u64,
+            elapsed_micros: start.elapsed().as_micros() as u64,
+            peak_map_size: run.peak_map_size,
+        }
+        .write_to(writer, run.order),
+        None => Ok(()),
+    }
+}
+
+/// Write the prefix for `tool` with the current protocol version
+pub fn write_header<W: Write>(writer: &mut W, tool: u16) -> io::Result<()> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&PROTOCOL_VERSION.to_le_bytes())?;
+    writer.write_all(&tool.to_le_bytes())
+}
+
+/// Read and validate the prefix; returns the run it sets up (version, byte order, stats) on success
+pub fn read_header<R: Read>(reader: &mut R, tool: u16) -> Result<ToolRun, HeaderError> {
+    let mut header = [0u8; HEADER_SIZE];
+    reader.read_exact(&mut header).map_err(|_| HeaderError::Truncated)?;
+
+    let magic = [header[0], header[1], header[2], header[3]];
+    if magic != MAGIC {
+        return Err(HeaderError::BadMagic(magic));
+    }
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version) {
+        return Err(HeaderError::UnsupportedVersion(version));
+    }
+    let found = u16::from_le_bytes([header[6], header[7]]);
+    if found & !TOOL_FLAGS != tool {
+        return Err(HeaderError::WrongTool { expected: tool, found: found & !TOOL_FLAGS });
+    }
+    let order = if found & BIG_ENDIAN_FLAG != 0 { ByteOrder::Big } else { ByteOrder::Little };
+    let stats_start = if found & STATS_FLAG != 0 { Some(Instant::now()) } else { None };
+    Ok(ToolRun { version, order, stats_start, peak_map_size: 0 })
+}
+
+/// Validate the prefix for `tool`, then read the tool's fixed `N`-byte header
+pub fn read_tool_header<R: Read, const N: usize>(reader: &mut R, tool: u16) -> Result<([u8; N], ToolRun), ToolError> {
+    let run = read_header(reader, tool).map_err(|e| match e {
+        HeaderError::Truncated => ToolError::new(ErrorCode::ShortHeader, e.to_string()),
+        _ => ToolError::new(ErrorCode::BadProtocolHeader, e.to_string()),
+    })?;
+    let mut header = [0u8; N];
+    reader.read_exact(&mut header).map_err(|_| {
+        ToolError::new(ErrorCode::ShortHeader, format!("input ends before the {}-byte tool header", N))
+    })?;
+    Ok((header, run))
+}
+
+/// Reject payload blocks of `count` elements of `element_size` bytes above MAX_PAYLOAD_BYTES
+pub fn check_payload_size(count: usize, element_size: usize) -> Result<usize, ToolError> {
+    count
+        .checked_mul(element_size)
+        .filter(|&bytes| bytes <= MAX_PAYLOAD_BYTES)
+        .ok_or_else(|| {
+            ToolError::new(
+                ErrorCode::AllocationTooLarge,
+                format!("payload of {} x {} bytes exceeds {} bytes", count, element_size, MAX_PAYLOAD_BYTES),
+            )
+        })
+}
+
+fn short_payload(count: usize, what: &str) -> ToolError {
+    ToolError::new(ErrorCode::ShortPayload, format!("input ends before {} {} values", count, what))
+}
+
+/// Read a payload block of `count` f32 values
+pub fn read_f32_payload<R: Read>(reader: &mut R, count: usize, order: ByteOrder) -> Result<Vec<f32>, ToolError> {
+    check_payload_size(count, 4)?;
+    read_f32_vec(reader, count, order).map_err(|_| short_payload(count, "f32"))
+}
+
+/// Read a payload block of `count` values at the precision selected by the tool's flags
+pub fn read_float_payload<T: LeFloat, R: Read>(reader: &mut R, count: usize, order: ByteOrder) -> Result<Vec<T>, ToolError> {
+    check_payload_size(count, T::SIZE)?;
+    let what = if T::SIZE == 8 { "f64" } else { "f32" };
+    read_float_vec(reader, count, order).map_err(|_| short_payload(count, what))
+}
+
+/// Read a payload block of `count` bytes
+pub fn read_u8_payload<R: Read>(reader: &mut R, count: usize) -> Result<Vec<u8>, ToolError> {
+    check_payload_size(count, 1)?;
+    read_u8_vec(reader, count).map_err(|_| short_payload(count, "u8"))
+}
+
+/// Voxel sizes must be positive and finite
+pub fn check_voxel_size(voxel_size: f32) -> Result<(), ToolError> {
+    if voxel_size.is_finite() && voxel_size > 0.0 {
+        Ok(())
+    } else {
+        Err(ToolError::new(ErrorCode::InvalidVoxelSize, format!("invalid voxel size {}", voxel_size)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary_io::{f32_at, u32_at, write_f32_slice};
+
+    #[test]
+    fn test_valid_header_round_trip() {
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, tool_id::CLASS_BOUNDARY).unwrap();
+        bytes.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(bytes.len(), HEADER_SIZE + 3);
+
+        let mut reader = &bytes[..];
+        assert_eq!(read_header(&mut reader, tool_id::CLASS_BOUNDARY).map(|run| run.version), Ok(PROTOCOL_VERSION));
+        // Payload starts right after the prefix
+        assert_eq!(reader, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_wrong_magic_rejected() {
+        // A legacy header starting directly with the point count
+        let legacy = [4u8, 0, 0, 0, 0, 0, 128, 63];
+        assert_eq!(
+            read_header(&mut &legacy[..], tool_id::VOXEL_DOWNSAMPLE).unwrap_err(),
+            HeaderError::BadMagic([4, 0, 0, 0])
+        );
+        assert_eq!(read_header(&mut &b"PCW"[..], tool_id::VOXEL_DOWNSAMPLE).unwrap_err(), HeaderError::Truncated);
+    }
+
+    #[test]
+    fn test_unsupported_version_and_wrong_tool_rejected() {
+        let mut future = MAGIC.to_vec();
+        future.extend_from_slice(&(PROTOCOL_VERSION + 1).to_le_bytes());
+        future.extend_from_slice(&tool_id::POINT_SMOOTH.to_le_bytes());
+        assert_eq!(
+            read_header(&mut &future[..], tool_id::POINT_SMOOTH).unwrap_err(),
+            HeaderError::UnsupportedVersion(PROTOCOL_VERSION + 1)
+        );
+
+        let mut legacy = MAGIC.to_vec();
+        legacy.extend_from_slice(&MIN_PROTOCOL_VERSION.to_le_bytes());
+        legacy.extend_from_slice(&tool_id::POINT_SMOOTH.to_le_bytes());
+        assert_eq!(read_header(&mut &legacy[..], tool_id::POINT_SMOOTH).unwrap().version, MIN_PROTOCOL_VERSION);
+
+        let mut other_tool = Vec::new();
+        write_header(&mut other_tool, tool_id::VOXEL_DEBUG).unwrap();
+        assert_eq!(
+            read_header(&mut &other_tool[..], tool_id::POINT_SMOOTH).unwrap_err(),
+            HeaderError::WrongTool {
+                expected: tool_id::POINT_SMOOTH,
+                found: tool_id::VOXEL_DEBUG
+            }
+        );
+    }
+
+    fn input(tool: u16, header: &[u8], payload: &[f32]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, tool).unwrap();
+        bytes.extend_from_slice(header);
+        bytes.extend(payload.iter().flat_map(|f| f.to_le_bytes()));
+        bytes
+    }
+
+    /// Decode an error frame into (code, message)
+    fn decode_frame(frame: &[u8]) -> (u32, String) {
+        let word = |i: usize| u32::from_le_bytes([frame[i], frame[i + 1], frame[i + 2], frame[i + 3]]);
+        assert_eq!(word(0), ERROR_MARKER);
+        let length = word(8) as usize;
+        assert_eq!(frame.len(), 12 + length);
+        (word(4), String::from_utf8(frame[12..].to_vec()).unwrap())
+    }
+
+    fn emitted_code(error: ToolError) -> u32 {
+        let mut frame = Vec::new();
+        error.write_to(&mut frame, ByteOrder::Little).unwrap();
+        let (code, message) = decode_frame(&frame);
+        assert_eq!(message, error.message);
+        code
+    }
+
+    #[test]
+    fn test_truncated_header_emits_short_header() {
+        let bytes = input(tool_id::RELIABILITY, &[3, 0, 0, 0, 0, 0, 128, 63], &[]);
+        for cut in [0, 5, HEADER_SIZE, HEADER_SIZE + 7] {
+            let error = read_tool_header::<_, 8>(&mut &bytes[..cut], tool_id::RELIABILITY).unwrap_err();
+            assert_eq!(emitted_code(error), 1, "cut at {}", cut);
+        }
+        let (header, _) = read_tool_header::<_, 8>(&mut &bytes[..], tool_id::RELIABILITY).unwrap();
+        assert_eq!(header, [3, 0, 0, 0, 0, 0, 128, 63]);
+
+        let error = read_tool_header::<_, 8>(&mut &bytes[..], tool_id::VOXEL_DEBUG).unwrap_err();
+        assert_eq!(emitted_code(error), 5);
+    }
+
+    #[test]
+    fn test_truncated_payload_emits_short_payload() {
+        let payload: Vec<u8> = [1.0f32, 2.0, 3.0, 4.0, 5.0].iter().flat_map(|f| f.to_le_bytes()).collect();
+        assert_eq!(read_f32_payload(&mut &payload[..], 5, ByteOrder::Little).unwrap(), vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+        let error = read_f32_payload(&mut &payload[..19], 5, ByteOrder::Little).unwrap_err();
+        assert_eq!(emitted_code(error), 2);
+        let error = read_u8_payload(&mut &payload[..3], 4).unwrap_err();
+        assert_eq!(emitted_code(error), 2);
+    }
+
+    #[test]
+    fn test_invalid_voxel_size_and_oversized_payload() {
+        for voxel_size in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(emitted_code(check_voxel_size(voxel_size).unwrap_err()), 3);
+        }
+        assert!(check_voxel_size(0.05).is_ok());
+
+        // u32::MAX points of xyz would need 48 GiB; nothing is read or allocated
+        let error = read_f32_payload(&mut &[][..], u32::MAX as usize * 3, ByteOrder::Little).unwrap_err();
+        assert_eq!(emitted_code(error), 4);
+        assert_eq!(emitted_code(check_payload_size(usize::MAX, 2).unwrap_err()), 4);
+    }
+
+    #[test]
+    fn test_stats_block_parses_back() {
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, tool_id::DEDUP | STATS_FLAG).unwrap();
+        let mut run = read_header(&mut &bytes[..], tool_id::DEDUP).unwrap();
+
+        run.note_map_size(12);
+        run.note_map_size(7);
+        let mut output = Vec::new();
+        write_stats(&mut output, &run, 1000, 250).unwrap();
+        assert_eq!(output.len(), STATS_SIZE);
+        let stats = ToolStats::read_from(&mut &output[..], ByteOrder::Little).unwrap();
+        assert_eq!(stats.input_count, 1000);
+        assert_eq!(stats.output_count, 250);
+        assert_eq!(stats.peak_map_size, 12);
+
+        // Without STATS_FLAG nothing is appended, and each run starts from its own peak
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, tool_id::DEDUP).unwrap();
+        let run = read_header(&mut &bytes[..], tool_id::DEDUP).unwrap();
+        assert_eq!(run.peak_map_size(), 0);
+        let mut output = Vec::new();
+        write_stats(&mut output, &run, 1000, 250).unwrap();
+        assert!(output.is_empty());
+
+        let block = ToolStats { input_count: 3, output_count: 2, elapsed_micros: 99, peak_map_size: 1 };
+        let mut encoded = Vec::new();
+        block.write_to(&mut encoded, ByteOrder::Big).unwrap();
+        assert_eq!(ToolStats::read_from(&mut &encoded[..], ByteOrder::Big).unwrap(), block);
+    }
+
+    #[test]
+    fn test_header_and_payload_round_trip_in_both_byte_orders() {
+        let positions = [1.5f32, -2.25, 1e6, f32::MIN_POSITIVE, 0.0, -0.0];
+        for big_endian in [false, true] {
+            // [u32 pointCount][f32 voxelSize][u32 flags] tool header, then positions and classes
+            let encode_u32 = |v: u32| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+            let encode_f32 = |v: f32| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+            let mut bytes = Vec::new();
+            let tool = if big_endian { tool_id::VOXEL_DEBUG | BIG_ENDIAN_FLAG } else { tool_id::VOXEL_DEBUG };
+            write_header(&mut bytes, tool).unwrap();
+            bytes.extend_from_slice(&encode_u32(2));
+            bytes.extend_from_slice(&encode_f32(0.125));
+            bytes.extend_from_slice(&encode_u32(FLAG_DROP_NON_FINITE | 5));
+            for &p in &positions {
+                bytes.extend_from_slice(&encode_f32(p));
+            }
+            bytes.extend_from_slice(&[7, 9]);
+
+            let mut reader = &bytes[..];
+            let (header, run): ([u8; 12], _) = read_tool_header(&mut reader, tool_id::VOXEL_DEBUG).unwrap();
+            assert_eq!(run.order, if big_endian { ByteOrder::Big } else { ByteOrder::Little });
+            assert_eq!(u32_at(&header, 0, run.order), 2);
+            assert_eq!(f32_at(&header, 4, run.order), 0.125);
+            assert_eq!(u32_at(&header, 8, run.order), FLAG_DROP_NON_FINITE | 5);
+            let parsed = read_f32_payload(&mut reader, 6, run.order).unwrap();
+            assert_eq!(parsed.iter().map(|f| f.to_bits()).collect::<Vec<_>>(), positions.map(f32::to_bits).to_vec());
+            assert_eq!(read_u8_payload(&mut reader, 2).unwrap(), vec![7, 9]);
+
+            // Output goes out in the same order it came in
+            let mut output = Vec::new();
+            write_u32(&mut output, 2, run.order).unwrap();
+            write_f32_slice(&mut output, &positions, run.order).unwrap();
+            assert_eq!(&output[..4], &encode_u32(2));
+            assert_eq!(&output[4..], &bytes[HEADER_SIZE + 12..bytes.len() - 2]);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, u64_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::sanitize;
+use pointcloud_tools_backend::point_attributes::gather_positions;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+use pointcloud_tools_backend::rng::Pcg32;
+
+// Reproducible random downsampling for benchmarks: the same seed always keeps the same points.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u64 seed][f32 ratio][u32 targetCount][f32* positions]
+// targetCount > 0 keeps exactly targetCount points; otherwise each point is kept with probability ratio
+// Points with a NaN or infinite coordinate are never kept; sampling runs over the finite points.
+// Output format: [u32 outputCount][f32* positions] (kept points in input order)
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (20 bytes: u32 + u64 + f32 + u32)
+    let (header, run): ([u8; 20], _) = match read_tool_header(&mut stdin, tool_id::RANDOM_DOWNSAMPLE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let seed = u64_at(&header, 4, run.order);
+    let ratio = f32_at(&header, 12, run.order);
+    let target_count = u32_at(&header, 16, run.order) as usize;
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || (target_count == 0 && ratio <= 0.0) {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    sanitize(&mut positions);
+    let finite_count = positions.len() / 3;
+
+    let kept = if target_count > 0 {
+        random_sample_exact(finite_count, target_count, seed)
+    } else {
+        random_sample_ratio(finite_count, ratio, seed)
+    };
+    let output = gather_positions(&positions, &kept);
+
+    if write_u32(&mut stdout, kept.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &output, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, kept.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Keep each point independently with probability `ratio`
+fn random_sample_ratio(point_count: usize, ratio: f32, seed: u64) -> Vec<usize> {
+    let mut rng = Pcg32::new(seed);
+    let mut kept = Vec::with_capacity((point_count as f32 * ratio.min(1.0)) as usize + 1);
+    for i in 0..point_count {
+        if rng.next_f32() < ratio {
+            kept.push(i);
+        }
+    }
+    kept
+}
+
+/// Keep exactly min(target_count, point_count) points, uniformly at random, in input order
+/// (selection sampling: point i is taken with probability remaining_needed / remaining_points)
+fn random_sample_exact(point_count: usize, target_count: usize, seed: u64) -> Vec<usize> {
+    let target_count = target_count.min(point_count);
+    let mut rng = Pcg32::new(seed);
+    let mut kept = Vec::with_capacity(target_count);
+    for i in 0..point_count {
+        let needed = target_count - kept.len();
+        if needed == 0 {
+            break;
+        }
+        let remaining = point_count - i;
+        if (rng.next_f64() * remaining as f64) < needed as f64 {
+            kept.push(i);
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_identical_output() {
+        assert_eq!(random_sample_ratio(10_000, 0.3, 1234), random_sample_ratio(10_000, 0.3, 1234));
+        assert_eq!(random_sample_exact(10_000, 777, 99), random_sample_exact(10_000, 777, 99));
+        assert_ne!(random_sample_exact(10_000, 777, 99), random_sample_exact(10_000, 777, 100));
+    }
+
+    #[test]
+    fn test_target_count_within_one() {
+        for (n, target) in [(10_000usize, 777usize), (5, 3), (100, 100), (50, 80)] {
+            let kept = random_sample_exact(n, target, 5);
+            assert!((kept.len() as i64 - target.min(n) as i64).abs() <= 1);
+            assert!(kept.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+
+    #[test]
+    fn test_ratio_keeps_roughly_ratio() {
+        let kept = random_sample_ratio(100_000, 0.25, 42);
+        let fraction = kept.len() as f32 / 100_000.0;
+        assert!((fraction - 0.25).abs() < 0.01);
+    }
+}
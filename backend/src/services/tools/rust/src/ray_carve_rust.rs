@@ -0,0 +1,195 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::voxel_hash::{VoxelKey, VoxelMap};
+
+// Free-space carving by ray casting: every voxel a sensor ray passes through on its way to a
+// point is free, the voxel containing the point is occupied, everything never touched is unknown.
+// Voxels are aligned to the world origin (index = floor(p / voxelSize)) so results from
+// different scans of the same scene share a grid.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 voxelSize][f32 sensorX][f32 sensorY][f32 sensorZ][f32* positions]
+// Output format: [u32 voxelCount][f32* voxelCenters][u8* states]
+// states: 1=free, 2=occupied; voxels not listed are unknown (0)
+// Points with a NaN or infinite coordinate cast no ray (an infinite one would never end), and a
+// non-finite sensor position carves nothing.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum Occupancy {
+    #[allow(dead_code)]
+    Unknown = 0,
+    Free = 1,
+    Occupied = 2,
+}
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (20 bytes: u32 + 4 * f32)
+    let (header, mut run): ([u8; 20], _) = match read_tool_header(&mut stdin, tool_id::RAY_CARVE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let voxel_size = f32_at(&header, 4, run.order);
+    let sensor = [f32_at(&header, 8, run.order), f32_at(&header, 12, run.order), f32_at(&header, 16, run.order)];
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || voxel_size <= 0.0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let voxels = ray_carve(&positions, sensor, voxel_size, &mut run);
+
+    let mut centers = Vec::with_capacity(voxels.len() * 3);
+    let mut states = Vec::with_capacity(voxels.len());
+    for (cell, state) in &voxels {
+        centers.extend(cell.iter().map(|&c| (c as f32 + 0.5) * voxel_size));
+        states.push(*state as u8);
+    }
+
+    if write_u32(&mut stdout, voxels.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &centers, run.order).is_err()
+        || stdout.write_all(&states).is_err()
+        || write_stats(&mut stdout, &run, point_count, voxels.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Visit every voxel the segment start -> end passes through, excluding the voxel containing
+/// `end` (Amanatides-Woo traversal)
+fn traverse_voxels<F: FnMut([i32; 3])>(start: [f32; 3], end: [f32; 3], voxel_size: f32, mut visit: F) {
+    let inv_voxel_size = 1.0 / voxel_size;
+    let mut cell = [0i32; 3];
+    let mut end_cell = [0i32; 3];
+    let mut step = [0i32; 3];
+    let mut t_max = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+
+    for axis in 0..3 {
+        cell[axis] = (start[axis] * inv_voxel_size).floor() as i32;
+        end_cell[axis] = (end[axis] * inv_voxel_size).floor() as i32;
+        let direction = end[axis] - start[axis];
+        if direction > 0.0 {
+            step[axis] = 1;
+            let boundary = (cell[axis] + 1) as f32 * voxel_size;
+            t_max[axis] = (boundary - start[axis]) / direction;
+            t_delta[axis] = voxel_size / direction;
+        } else if direction < 0.0 {
+            step[axis] = -1;
+            let boundary = cell[axis] as f32 * voxel_size;
+            t_max[axis] = (boundary - start[axis]) / direction;
+            t_delta[axis] = -voxel_size / direction;
+        }
+    }
+
+    // A straight segment crosses at most this many voxel boundaries
+    let max_steps: i64 = (0..3).map(|a| (end_cell[a] as i64 - cell[a] as i64).abs()).sum();
+    for _ in 0..=max_steps {
+        if cell == end_cell {
+            return;
+        }
+        visit(cell);
+        let axis = if t_max[0] <= t_max[1] && t_max[0] <= t_max[2] {
+            0
+        } else if t_max[1] <= t_max[2] {
+            1
+        } else {
+            2
+        };
+        cell[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+    }
+}
+
+/// Occupancy state of every voxel touched by a sensor ray, sorted by voxel index.
+/// Endpoint voxels are occupied even if another ray passes through them.
+fn ray_carve(positions: &[f32], sensor: [f32; 3], voxel_size: f32, run: &mut ToolRun) -> Vec<([i32; 3], Occupancy)> {
+    let inv_voxel_size = 1.0 / voxel_size;
+    let mut voxels: VoxelMap<VoxelKey, Occupancy> = VoxelMap::default();
+    if !is_finite_point(&sensor[..]) {
+        return Vec::new();
+    }
+
+    for p in positions.chunks_exact(3).filter(|p| is_finite_point(p)) {
+        let end = [p[0], p[1], p[2]];
+        traverse_voxels(sensor, end, voxel_size, |cell| {
+            voxels.entry(cell).or_insert(Occupancy::Free);
+        });
+    }
+    for p in positions.chunks_exact(3).filter(|p| is_finite_point(p)) {
+        let cell = [
+            (p[0] * inv_voxel_size).floor() as i32,
+            (p[1] * inv_voxel_size).floor() as i32,
+            (p[2] * inv_voxel_size).floor() as i32,
+        ];
+        voxels.insert(cell, Occupancy::Occupied);
+    }
+
+    run.note_map_size(voxels.len());
+    let mut result: Vec<([i32; 3], Occupancy)> = voxels.into_iter().collect();
+    result.sort_unstable_by_key(|&(cell, _)| cell);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_ray_free_then_occupied() {
+        let voxels = ray_carve(&[4.5, 0.5, 0.5], [0.5, 0.5, 0.5], 1.0, &mut ToolRun::default());
+        assert_eq!(
+            voxels,
+            vec![
+                ([0, 0, 0], Occupancy::Free),
+                ([1, 0, 0], Occupancy::Free),
+                ([2, 0, 0], Occupancy::Free),
+                ([3, 0, 0], Occupancy::Free),
+                ([4, 0, 0], Occupancy::Occupied),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diagonal_ray_is_connected() {
+        let voxels = ray_carve(&[-2.3, 3.7, 1.2], [0.1, 0.2, 0.3], 0.5, &mut ToolRun::default());
+        let occupied: Vec<_> = voxels.iter().filter(|(_, s)| *s == Occupancy::Occupied).collect();
+        assert_eq!(occupied.len(), 1);
+        // Each step moves one voxel along one axis, so the free voxels plus the endpoint number
+        // exactly the Manhattan distance between start voxel (0,0,0) and end voxel (-5,7,2) + 1
+        assert_eq!(voxels.len(), 5 + 7 + 2 + 1);
+    }
+
+    #[test]
+    fn test_non_finite_points_and_sensor_cast_no_ray() {
+        let positions = [2.5, 0.5, 0.5, f32::INFINITY, 0.5, 0.5, f32::NAN, 0.0, 0.0, 0.5, f32::NEG_INFINITY, 0.5];
+        let voxels = ray_carve(&positions, [0.5, 0.5, 0.5], 1.0, &mut ToolRun::default());
+        assert_eq!(voxels.len(), 3);
+        assert!(ray_carve(&positions, [f32::NAN, 0.5, 0.5], 1.0, &mut ToolRun::default()).is_empty());
+    }
+
+    #[test]
+    fn test_endpoint_overrides_free() {
+        // Second ray passes through the first ray's endpoint voxel
+        let voxels = ray_carve(&[2.5, 0.5, 0.5, 4.5, 0.5, 0.5], [0.5, 0.5, 0.5], 1.0, &mut ToolRun::default());
+        let state_of = |c: [i32; 3]| voxels.iter().find(|(cell, _)| *cell == c).map(|(_, s)| *s);
+        assert_eq!(state_of([2, 0, 0]), Some(Occupancy::Occupied));
+        assert_eq!(state_of([3, 0, 0]), Some(Occupancy::Free));
+    }
+}
@@ -0,0 +1,136 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, u64_at, write_f32_slice, write_float_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError};
+
+// Move a cloud to the origin before f32 processing, since large coordinates (e.g. georeferenced
+// scans) keep few significant bits. Every point has the offset subtracted, in f64: by default the
+// centroid of the finite points, or with bit3 the origin given in the header. The offset is
+// returned in f64 so callers can add it back to undo the shift. Attributes pass through unchanged
+// and points with a NaN or infinite coordinate are left as they are.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 flags][f64 originX][f64 originY][f64 originZ]
+//               [f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit3=subtract the given origin instead
+//        of the centroid, bit31=drop non-finite points
+// Output format: [f64 offsetX][f64 offsetY][f64 offsetZ][u32 pointCount][f32* positions]
+//                [optional colors][optional intensities][optional classifications]
+// Without finite points the centroid offset is zero.
+
+const FLAG_ORIGIN: u32 = 8;
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (32 bytes: 2 * u32 + 3 * f64)
+    let (header, run): ([u8; 32], _) = match read_tool_header(&mut stdin, tool_id::RECENTER) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let flags = u32_at(&header, 4, run.order);
+    let origin = [0, 1, 2].map(|a| f64::from_bits(u64_at(&header, 8 + a * 8, run.order)));
+
+    if flags & FLAG_ORIGIN != 0 && !origin.iter().all(|v| v.is_finite()) {
+        ToolError::new(ErrorCode::InvalidData, "origin must be finite").exit(run.order);
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+
+    let offset = if flags & FLAG_ORIGIN != 0 { origin } else { centroid(&positions) };
+    recenter(&mut positions, offset);
+
+    let output_count = positions.len() / 3;
+    let mut stdout = io::stdout();
+    if write_float_slice(&mut stdout, &offset, run.order).is_err()
+        || write_u32(&mut stdout, output_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &positions, run.order).is_err()
+        || attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, output_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Mean of the finite points of a flat xyz array in f64; zero without any
+fn centroid(positions: &[f32]) -> [f64; 3] {
+    let mut sum = [0.0f64; 3];
+    let mut count = 0usize;
+    for p in positions.chunks_exact(3).filter(|p| is_finite_point(p)) {
+        for (s, &v) in sum.iter_mut().zip(p) {
+            *s += v as f64;
+        }
+        count += 1;
+    }
+    if count == 0 {
+        return [0.0; 3];
+    }
+    sum.map(|s| s / count as f64)
+}
+
+/// Subtract `offset` from every finite point of a flat xyz array
+fn recenter(positions: &mut [f32], offset: [f64; 3]) {
+    for p in positions.chunks_exact_mut(3).filter(|p| is_finite_point(p)) {
+        for (v, o) in p.iter_mut().zip(offset) {
+            *v = (*v as f64 - o) as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn georeferenced_cloud() -> Vec<f32> {
+        (0..1000)
+            .flat_map(|i| {
+                let t = i as f32;
+                [500_000.0 + (t * 0.37).sin() * 20.0, 4_200_000.0 + (t * 0.11).cos() * 20.0, 150.0 + (t * 0.05).sin() * 5.0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recentered_cloud_has_its_centroid_at_the_origin() {
+        let original = georeferenced_cloud();
+        let mut positions = original.clone();
+        let offset = centroid(&positions);
+        recenter(&mut positions, offset);
+
+        for c in centroid(&positions) {
+            assert!(c.abs() < 1e-4, "centroid {}", c);
+        }
+        // Adding the offset back restores the input (to f32 rounding of the input's magnitude)
+        for (p, q) in positions.chunks_exact(3).zip(original.chunks_exact(3)) {
+            for a in 0..3 {
+                let restored = (p[a] as f64 + offset[a]) as f32;
+                assert!((restored - q[a]).abs() <= q[a].abs() * f32::EPSILON, "{} vs {}", restored, q[a]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_given_origin_and_non_finite_points() {
+        let mut positions = vec![10.0, 20.0, 30.0, f32::NAN, 1.0, 1.0, 12.0, 22.0, 28.0];
+        assert_eq!(centroid(&positions), [11.0, 21.0, 29.0]);
+        recenter(&mut positions, [10.0, 20.0, 30.0]);
+        assert_eq!(positions[..3], [0.0, 0.0, 0.0]);
+        assert!(positions[3].is_nan() && positions[4] == 1.0);
+        assert_eq!(positions[6..], [2.0, 2.0, -2.0]);
+        assert_eq!(centroid(&[]), [0.0; 3]);
+    }
+}
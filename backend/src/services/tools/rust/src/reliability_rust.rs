@@ -0,0 +1,129 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::normals::fit_local_plane;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Per-point reliability in [0, 1] from local consistency, for quality weighting.
+// score = support * fit, where
+//   support = neighbor count / median neighbor count of the cloud (capped at 1)
+//   fit     = exp(-(d / (RESIDUAL_SCALE * radius))^2), d = distance from the point to the plane
+//             fitted through its neighbors (the point itself excluded so it cannot pull the fit)
+// Points with fewer than 3 neighbors have no plane and score 0.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 radius][f32* positions]
+// Output format: [u32 pointCount][f32* reliability]
+
+const RESIDUAL_SCALE: f32 = 0.1;
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: u32 + f32)
+    let (header, mut run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::RELIABILITY) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let radius = f32_at(&header, 4, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 || radius <= 0.0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let scores = reliability(&positions, radius, &mut run);
+
+    if write_u32(&mut stdout, point_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &scores, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, point_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+fn reliability(positions: &[f32], radius: f32, run: &mut ToolRun) -> Vec<f32> {
+    let point_count = positions.len() / 3;
+    let grid = SpatialGrid::new(positions, radius);
+    run.note_map_size(grid.cell_count());
+
+    let mut residuals = vec![f32::INFINITY; point_count];
+    let mut counts = vec![0usize; point_count];
+    for i in 0..point_count {
+        let i3 = i * 3;
+        let (x, y, z) = (positions[i3], positions[i3 + 1], positions[i3 + 2]);
+        let mut neighbors = grid.neighbors_in_radius(positions, x, y, z, radius);
+        neighbors.retain(|&j| j != i);
+        counts[i] = neighbors.len();
+        if let Some(plane) = fit_local_plane(positions, &neighbors) {
+            let d: f32 = (0..3)
+                .map(|a| plane.normal[a] * (positions[i3 + a] - plane.centroid[a]))
+                .sum();
+            residuals[i] = d.abs();
+        }
+    }
+
+    let mut sorted_counts = counts.clone();
+    sorted_counts.sort_unstable();
+    let median_count = sorted_counts[point_count / 2].max(1) as f32;
+    let residual_scale = RESIDUAL_SCALE * radius;
+
+    residuals
+        .iter()
+        .zip(&counts)
+        .map(|(&d, &n)| {
+            let support = (n as f32 / median_count).min(1.0);
+            let fit = if d.is_finite() {
+                (-(d / residual_scale).powi(2)).exp()
+            } else {
+                0.0
+            };
+            support * fit
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isolated_noisy_point_low_surface_point_high() {
+        let mut positions = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                positions.extend_from_slice(&[i as f32 * 0.1, j as f32 * 0.1, 0.0]);
+            }
+        }
+        // Noisy point hovering above the middle of the sheet
+        positions.extend_from_slice(&[1.0, 1.0, 0.15]);
+        let noisy = positions.len() / 3 - 1;
+        let on_surface = 10 * 20 + 10;
+
+        let scores = reliability(&positions, 0.3, &mut ToolRun::default());
+
+        assert!(scores[on_surface] > 0.9, "surface point scored {}", scores[on_surface]);
+        assert!(scores[noisy] < 0.1, "noisy point scored {}", scores[noisy]);
+        assert!(scores.iter().all(|s| (0.0..=1.0).contains(s)));
+    }
+
+    #[test]
+    fn test_lone_point_scores_zero() {
+        let mut positions = vec![0.0, 0.0, 0.0, 0.05, 0.0, 0.0, 0.0, 0.05, 0.0, 0.05, 0.05, 0.0];
+        positions.extend_from_slice(&[10.0, 10.0, 10.0]);
+        let scores = reliability(&positions, 0.2, &mut ToolRun::default());
+        assert_eq!(scores[4], 0.0);
+    }
+}
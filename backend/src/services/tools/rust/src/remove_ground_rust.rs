@@ -0,0 +1,199 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, u64_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, gather_positions, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError};
+use pointcloud_tools_backend::rng::Pcg32;
+
+// Ground removal in one call: RANSAC finds the near-horizontal plane with the most inliers and
+// only the points farther than distanceThreshold from it are kept ("everything above the floor").
+// Each iteration fits a plane through three random points; planes whose normal is more than
+// maxTiltDegrees away from the up (+z) axis are rejected. With no qualifying plane the input is
+// returned unchanged. Kept points stay in input order, with their attributes.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 distanceThreshold][f32 maxTiltDegrees][u32 maxIterations][u64 seed][u32 flags]
+//               [f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit31=drop non-finite points
+// Output format: [u32 outputCount][f32* positions][optional colors][optional intensities][optional classifications]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (28 bytes: u32 + 2 * f32 + u32 + u64 + u32)
+    let (header, run): ([u8; 28], _) = match read_tool_header(&mut stdin, tool_id::REMOVE_GROUND) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let distance_threshold = f32_at(&header, 4, run.order);
+    let max_tilt_degrees = f32_at(&header, 8, run.order);
+    let max_iterations = u32_at(&header, 12, run.order) as usize;
+    let seed = u64_at(&header, 16, run.order);
+    let flags = u32_at(&header, 24, run.order);
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+
+    let settings = RansacSettings {
+        distance_threshold: distance_threshold as f64,
+        min_up_component: (max_tilt_degrees as f64).to_radians().cos(),
+        max_iterations,
+        seed,
+    };
+    let kept = match find_ground_plane(&positions, &settings) {
+        Some(plane) => points_off_plane(&positions, &plane, settings.distance_threshold),
+        None => (0..positions.len() / 3).collect(),
+    };
+    let kept_positions = gather_positions(&positions, &kept);
+    let kept_attributes = attributes.gather(&kept);
+
+    if write_u32(&mut stdout, kept.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &kept_positions, run.order).is_err()
+        || kept_attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, kept.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+struct RansacSettings {
+    distance_threshold: f64,
+    /// cos(max tilt): the smallest |normal.z| a ground plane may have
+    min_up_component: f64,
+    max_iterations: usize,
+    seed: u64,
+}
+
+/// Plane n . p + d = 0 with unit normal n
+#[derive(Clone, Copy, Debug)]
+struct Plane {
+    normal: [f64; 3],
+    d: f64,
+}
+
+impl Plane {
+    /// Plane through three points, or None when they are (nearly) collinear
+    fn through(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> Option<Plane> {
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let n = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+        let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        if len < 1e-12 {
+            return None;
+        }
+        let normal = [n[0] / len, n[1] / len, n[2] / len];
+        Some(Plane { normal, d: -(normal[0] * a[0] + normal[1] * a[1] + normal[2] * a[2]) })
+    }
+
+    fn distance(&self, p: &[f32]) -> f64 {
+        (self.normal[0] * p[0] as f64 + self.normal[1] * p[1] as f64 + self.normal[2] * p[2] as f64 + self.d).abs()
+    }
+}
+
+fn point(positions: &[f32], i: usize) -> [f64; 3] {
+    [positions[i * 3] as f64, positions[i * 3 + 1] as f64, positions[i * 3 + 2] as f64]
+}
+
+/// Near-horizontal plane with the most inliers over the RANSAC iterations (earliest on ties)
+fn find_ground_plane(positions: &[f32], settings: &RansacSettings) -> Option<Plane> {
+    let point_count = positions.len() / 3;
+    if point_count < 3 {
+        return None;
+    }
+    let mut rng = Pcg32::new(settings.seed);
+    let mut best: Option<(Plane, usize)> = None;
+    for _ in 0..settings.max_iterations {
+        let a = rng.below(point_count as u32) as usize;
+        let b = rng.below(point_count as u32) as usize;
+        let c = rng.below(point_count as u32) as usize;
+        if a == b || b == c || a == c {
+            continue;
+        }
+        let plane = match Plane::through(point(positions, a), point(positions, b), point(positions, c)) {
+            Some(p) if p.normal[2].abs() >= settings.min_up_component => p,
+            _ => continue,
+        };
+        let inliers = positions
+            .chunks_exact(3)
+            .filter(|p| plane.distance(p) <= settings.distance_threshold)
+            .count();
+        if best.is_none_or(|(_, count)| inliers > count) {
+            best = Some((plane, inliers));
+        }
+    }
+    best.map(|(plane, _)| plane)
+}
+
+/// Indices of the points farther than `threshold` from the plane, in input order
+fn points_off_plane(positions: &[f32], plane: &Plane, threshold: f64) -> Vec<usize> {
+    positions
+        .chunks_exact(3)
+        .enumerate()
+        .filter(|(_, p)| plane.distance(p) > threshold)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> RansacSettings {
+        RansacSettings {
+            distance_threshold: 0.05,
+            min_up_component: 10.0f64.to_radians().cos(),
+            max_iterations: 200,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn test_floor_removed_object_remains() {
+        // Slightly tilted, slightly bumpy 20 x 20 floor
+        let mut positions = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                let (x, y) = (i as f32 * 0.25, j as f32 * 0.25);
+                let bump = if (i + j) % 2 == 0 { 0.01 } else { -0.01 };
+                positions.extend_from_slice(&[x, y, 0.02 * x + bump]);
+            }
+        }
+        let floor_count = positions.len() / 3;
+        // 5 x 5 x 5 block of points floating from z = 0.5 to 1.5
+        for i in 0..125 {
+            let (a, b, c) = ((i % 5) as f32, ((i / 5) % 5) as f32, (i / 25) as f32);
+            positions.extend_from_slice(&[2.0 + a * 0.2, 2.0 + b * 0.2, 0.5 + c * 0.25]);
+        }
+
+        let plane = find_ground_plane(&positions, &settings()).expect("floor plane");
+        let kept = points_off_plane(&positions, &plane, 0.05);
+        assert_eq!(kept, (floor_count..floor_count + 125).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_no_horizontal_plane_keeps_input() {
+        // A vertical wall only: every candidate plane is rejected by the tilt limit
+        let positions: Vec<f32> = (0..100).flat_map(|i| [(i % 10) as f32 * 0.1, 0.0, (i / 10) as f32 * 0.1]).collect();
+        assert!(find_ground_plane(&positions, &settings()).is_none());
+    }
+}
@@ -0,0 +1,109 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Medial radius annotation for a skeleton / centerline.
+// Takes the skeleton points produced by a skeletonization step together with the surface cloud
+// they were extracted from, and estimates each skeleton point's local radius as the distance
+// to its nearest surface point (cylinders, trunks, pipes).
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 skeletonCount][u32 surfaceCount][f32* skeletonPositions][f32* surfacePositions]
+// Output format: [u32 skeletonCount][f32* skeletonPositions][f32* radii]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: 2 * u32)
+    let (header, mut run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::SKELETON_RADIUS) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let skeleton_count = u32_at(&header, 0, run.order) as usize;
+    let surface_count = u32_at(&header, 4, run.order) as usize;
+
+    let mut stdout = io::stdout();
+
+    if skeleton_count == 0 || surface_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, skeleton_count + surface_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let skeleton = match read_f32_payload(&mut stdin, skeleton_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let surface = match read_f32_payload(&mut stdin, surface_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let radii = skeleton_radii(&skeleton, &surface, &mut run);
+
+    if write_u32(&mut stdout, skeleton_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &skeleton, run.order).is_err()
+        || write_f32_slice(&mut stdout, &radii, run.order).is_err()
+        || write_stats(&mut stdout, &run, skeleton_count + surface_count, skeleton_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Distance from each skeleton point to the nearest surface point
+fn skeleton_radii(skeleton: &[f32], surface: &[f32], run: &mut ToolRun) -> Vec<f32> {
+    let grid = SpatialGrid::new(surface, SpatialGrid::auto_cell_size(surface));
+    run.note_map_size(grid.cell_count());
+    skeleton
+        .chunks_exact(3)
+        .map(|p| {
+            grid.nearest(surface, p[0], p[1], p[2])
+                .map(|(_, distance_squared)| distance_squared.sqrt())
+                .unwrap_or(0.0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cylinder_skeleton_radii() {
+        // Cylinder of radius 0.5 along Z, 64 samples per ring, rings every 0.05
+        let radius = 0.5f32;
+        let mut surface = Vec::new();
+        for ring in 0..40 {
+            let z = ring as f32 * 0.05;
+            for s in 0..64 {
+                let angle = s as f32 / 64.0 * std::f32::consts::TAU;
+                surface.extend_from_slice(&[radius * angle.cos(), radius * angle.sin(), z]);
+            }
+        }
+        // Centerline away from the open ends
+        let skeleton: Vec<f32> = (5..35).flat_map(|i| [0.0, 0.0, i as f32 * 0.05 + 0.01]).collect();
+
+        let radii = skeleton_radii(&skeleton, &surface, &mut ToolRun::default());
+
+        assert_eq!(radii.len(), 30);
+        for r in radii {
+            assert!((r - radius).abs() < 0.01, "radius {} should approximate {}", r, radius);
+        }
+    }
+
+    #[test]
+    fn test_off_axis_skeleton_point_has_smaller_radius() {
+        let mut surface = Vec::new();
+        for s in 0..32 {
+            let angle = s as f32 / 32.0 * std::f32::consts::TAU;
+            surface.extend_from_slice(&[angle.cos(), angle.sin(), 0.0]);
+        }
+        let radii = skeleton_radii(&[0.0, 0.0, 0.0, 0.5, 0.0, 0.0], &surface, &mut ToolRun::default());
+        assert!((radii[0] - 1.0).abs() < 0.01);
+        assert!((radii[1] - 0.5).abs() < 0.01);
+    }
+}
@@ -0,0 +1,147 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::{is_finite_point, Bounds};
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, PointAttributes};
+use pointcloud_tools_backend::protocol::{check_voxel_size, read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError, ToolRun};
+use pointcloud_tools_backend::voxel_hash::VoxelMap;
+
+// Snap every point to the centroid of its voxel without collapsing them: the output has one point
+// per input point, in input order, and points sharing a voxel land on the same location. Voxels
+// are assigned exactly as in voxel_downsample_rust's default floor mode (grid anchored at the
+// bounds minimum, f64 intermediates; there is no round mode here), so the distinct output
+// positions are the downsampler's centroids up to rounding (sums here are f64). Useful for
+// aligning noisy repeated scans onto a common lattice. Attributes pass through unchanged and
+// points with a NaN or infinite coordinate are left where they are.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 voxelSize][f32 minX][f32 minY][f32 minZ][f32 maxX][f32 maxY][f32 maxZ][u32 flags]
+//               [f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit31=drop non-finite points
+// Output format: [u32 pointCount][f32* positions][optional colors][optional intensities][optional classifications]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (36 bytes: u32 + 7 * f32 + u32)
+    let (header, mut run): ([u8; 36], _) = match read_tool_header(&mut stdin, tool_id::SNAP_TO_GRID) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let voxel_size = f32_at(&header, 4, run.order);
+    let bounds = Bounds {
+        min: [0, 1, 2].map(|a| f32_at(&header, 8 + a * 4, run.order)),
+        max: [0, 1, 2].map(|a| f32_at(&header, 20 + a * 4, run.order)),
+    };
+    let flags = u32_at(&header, 32, run.order);
+
+    if let Err(e) = check_voxel_size(voxel_size) {
+        e.exit(run.order);
+    }
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+
+    snap_to_grid(&mut positions, voxel_size, &bounds, &mut run);
+
+    let output_count = positions.len() / 3;
+    if write_u32(&mut stdout, output_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &positions, run.order).is_err()
+        || attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, output_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Replace every finite point of a flat xyz array with the centroid of its voxel
+fn snap_to_grid(positions: &mut [f32], voxel_size: f32, bounds: &Bounds, run: &mut ToolRun) {
+    let inv_voxel_size = 1.0 / voxel_size as f64;
+    let voxel_of = |p: &[f32]| [0, 1, 2].map(|a| ((p[a] as f64 - bounds.min[a] as f64) * inv_voxel_size).floor() as i32);
+
+    // Pass 1: count and position sums per voxel
+    let mut voxels: VoxelMap<[i32; 3], (u32, [f64; 3])> = VoxelMap::default();
+    for p in positions.chunks_exact(3).filter(|p| is_finite_point(p)) {
+        let entry = voxels.entry(voxel_of(p)).or_insert((0, [0.0; 3]));
+        entry.0 += 1;
+        for (sum, &v) in entry.1.iter_mut().zip(p) {
+            *sum += v as f64;
+        }
+    }
+    run.note_map_size(voxels.len());
+
+    // Pass 2: move each point onto its voxel's centroid
+    for p in positions.chunks_exact_mut(3).filter(|p| is_finite_point(p)) {
+        let (count, sum) = voxels[&voxel_of(p)];
+        for (v, s) in p.iter_mut().zip(sum) {
+            *v = (s / count as f64) as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_kept_and_voxel_mates_share_a_location() {
+        let mut positions = Vec::new();
+        for i in 0..300 {
+            let t = i as f32 * 0.29;
+            positions.extend_from_slice(&[t.sin() * 2.0, t.cos() * 2.0, (t * 0.1).sin()]);
+        }
+        let original = positions.clone();
+        let bounds = Bounds::from_points(&positions).unwrap();
+        let voxel_size = 0.5;
+        snap_to_grid(&mut positions, voxel_size, &bounds, &mut ToolRun::default());
+        assert_eq!(positions.len(), original.len());
+
+        let cell = |p: &[f32]| [0, 1, 2].map(|a| ((p[a] as f64 - bounds.min[a] as f64) * 2.0).floor() as i32);
+        for i in 0..300 {
+            for j in i + 1..300 {
+                let same_voxel = cell(&original[i * 3..i * 3 + 3]) == cell(&original[j * 3..j * 3 + 3]);
+                assert_eq!(positions[i * 3..i * 3 + 3] == positions[j * 3..j * 3 + 3], same_voxel, "points {} and {}", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn test_centroid_and_non_finite_pass_through() {
+        let mut positions = vec![0.25, 0.125, 0.5, 0.75, 0.375, 0.25, f32::NAN, 0.0, 0.0, 2.5, 0.5, 0.5];
+        let bounds = Bounds { min: [0.0; 3], max: [3.0, 1.0, 1.0] };
+        snap_to_grid(&mut positions, 1.0, &bounds, &mut ToolRun::default());
+        assert_eq!(&positions[0..3], &[0.5, 0.25, 0.375]);
+        assert_eq!(&positions[3..6], &[0.5, 0.25, 0.375]);
+        assert!(positions[6].is_nan());
+        assert_eq!(&positions[9..12], &[2.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_voxels_a_16_bit_field_apart_stay_separate() {
+        // (1, 0, 0) and (0, 65536, 0) shared a key when y was packed into 16 bits
+        let mut positions = vec![1.5, 0.5, 0.5, 0.5, 65536.5, 0.5];
+        let bounds = Bounds { min: [0.0; 3], max: [2.0, 65537.0, 1.0] };
+        snap_to_grid(&mut positions, 1.0, &bounds, &mut ToolRun::default());
+        assert_eq!(positions, vec![1.5, 0.5, 0.5, 0.5, 65536.5, 0.5]);
+    }
+}
@@ -0,0 +1,412 @@
+use rustc_hash::{FxHashMap, FxHashSet};
+use crate::geometry::{is_finite_point, Bounds};
+
+// Uniform spatial hash over a point cloud for fixed-radius and k-nearest neighbor queries.
+// Same idea as the grid in point_smooth_rust (cell size ~ search radius, scan the surrounding
+// cells), but cells live in an FxHashMap keyed by packed integer coordinates so memory scales
+// with occupied cells rather than with the bounding box volume.
+// Points with a NaN or infinite coordinate are left out of the grid, and queries from such a
+// position find nothing.
+
+const AXIS_BITS: u64 = 21;
+const AXIS_MASK: u64 = (1 << AXIS_BITS) - 1;
+
+// auto_cell_size aims for about this many points per occupied cell, refining the size at most
+// AUTO_MAX_REFINEMENTS times
+const AUTO_POINTS_PER_CELL: f32 = 2.0;
+const AUTO_MAX_REFINEMENTS: usize = 8;
+
+pub struct SpatialGrid {
+    min_x: f32,
+    min_y: f32,
+    min_z: f32,
+    cell_size: f32,
+    inv_cell_size: f32,
+    // Largest occupied cell coordinate per axis, bounds the ring search in k_nearest
+    max_cell: (i64, i64, i64),
+    cells: FxHashMap<u64, Vec<usize>>,
+}
+
+impl SpatialGrid {
+    /// Bucket every point of a flat xyz array into cells of `cell_size`
+    pub fn new(points: &[f32], cell_size: f32) -> SpatialGrid {
+        let point_count = points.len() / 3;
+        let [min_x, min_y, min_z] = Bounds::from_points(points).map_or([0.0; 3], |b| b.min);
+
+        let estimated_cells = (point_count / 8).clamp(16, 1_000_000);
+        let mut grid = SpatialGrid {
+            min_x,
+            min_y,
+            min_z,
+            cell_size,
+            inv_cell_size: 1.0 / cell_size,
+            max_cell: (0, 0, 0),
+            cells: FxHashMap::with_capacity_and_hasher(estimated_cells, Default::default()),
+        };
+
+        for i in 0..point_count {
+            let i3 = i * 3;
+            if !is_finite_point(&points[i3..i3 + 3]) {
+                continue;
+            }
+            let (cx, cy, cz) = grid.cell_coords(points[i3], points[i3 + 1], points[i3 + 2]);
+            if let Some(key) = Self::cell_key(cx, cy, cz) {
+                grid.cells.entry(key).or_insert_with(|| Vec::with_capacity(8)).push(i);
+                grid.max_cell = (grid.max_cell.0.max(cx), grid.max_cell.1.max(cy), grid.max_cell.2.max(cz));
+            }
+        }
+        grid
+    }
+
+    /// Cell size giving a handful of points per occupied cell for nearest-neighbor queries
+    /// when there is no natural search radius. Starts from largest bounding box extent /
+    /// cbrt(point count) and rescales from the points per occupied cell until that lands near
+    /// AUTO_POINTS_PER_CELL, so a far outlier stretching the bounds cannot collapse the cloud
+    /// into a few huge cells.
+    pub fn auto_cell_size(points: &[f32]) -> f32 {
+        let bounds = match Bounds::from_points(points) {
+            Some(b) => b,
+            None => return 1.0,
+        };
+        let finite: Vec<&[f32]> = points.chunks_exact(3).filter(|p| is_finite_point(p)).collect();
+        let extent = bounds.max_extent();
+        if extent <= 0.0 {
+            return 1.0;
+        }
+
+        let mut cell_size = extent / (finite.len() as f32).cbrt();
+        let mut occupied: FxHashSet<[i64; 3]> = FxHashSet::default();
+        for _ in 0..AUTO_MAX_REFINEMENTS {
+            let inv_cell_size = 1.0 / cell_size as f64;
+            occupied.clear();
+            occupied.extend(
+                finite
+                    .iter()
+                    .map(|p| [0, 1, 2].map(|a| ((p[a] as f64 - bounds.min[a] as f64) * inv_cell_size).floor() as i64)),
+            );
+            let points_per_cell = finite.len() as f32 / occupied.len() as f32;
+            if points_per_cell <= AUTO_POINTS_PER_CELL * 2.0 {
+                break;
+            }
+            // Occupied cells grow like 1 / size^3 in solid regions and more slowly on surfaces
+            // and lines, so the cube-root step never shrinks cells past the target
+            cell_size *= (AUTO_POINTS_PER_CELL / points_per_cell).cbrt();
+        }
+        cell_size
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Number of occupied cells
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Integer cell coordinates of a position (floor, relative to the grid minimum)
+    pub fn cell_coords(&self, x: f32, y: f32, z: f32) -> (i64, i64, i64) {
+        (
+            ((x - self.min_x) * self.inv_cell_size).floor() as i64,
+            ((y - self.min_y) * self.inv_cell_size).floor() as i64,
+            ((z - self.min_z) * self.inv_cell_size).floor() as i64,
+        )
+    }
+
+    /// Packed hash key for a cell; None for cells below the grid minimum (always empty).
+    /// Coordinates beyond 21 bits wrap, which can only add candidates that the caller's
+    /// distance test rejects.
+    fn cell_key(cx: i64, cy: i64, cz: i64) -> Option<u64> {
+        if cx < 0 || cy < 0 || cz < 0 {
+            return None;
+        }
+        Some(
+            ((cx as u64 & AXIS_MASK) << (2 * AXIS_BITS))
+                | ((cy as u64 & AXIS_MASK) << AXIS_BITS)
+                | (cz as u64 & AXIS_MASK),
+        )
+    }
+
+    /// Indices of the points stored in one cell
+    pub fn cell_points(&self, cx: i64, cy: i64, cz: i64) -> &[usize] {
+        Self::cell_key(cx, cy, cz)
+            .and_then(|key| self.cells.get(&key))
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Call `f(j, distance_squared)` for every point j within `radius` of (x, y, z),
+    /// including a point located exactly at the query position
+    pub fn for_each_in_radius<F: FnMut(usize, f32)>(
+        &self,
+        points: &[f32],
+        x: f32,
+        y: f32,
+        z: f32,
+        radius: f32,
+        mut f: F,
+    ) {
+        if !is_finite_point(&[x, y, z]) {
+            return;
+        }
+        let radius_squared = radius * radius;
+        let reach = (radius * self.inv_cell_size).ceil().max(1.0) as i64;
+        let (cx, cy, cz) = self.cell_coords(x, y, z);
+        for gx in cx - reach..=cx + reach {
+            for gy in cy - reach..=cy + reach {
+                for gz in cz - reach..=cz + reach {
+                    for &j in self.cell_points(gx, gy, gz) {
+                        let j3 = j * 3;
+                        let dx = points[j3] - x;
+                        let dy = points[j3 + 1] - y;
+                        let dz = points[j3 + 2] - z;
+                        let distance_squared = dx * dx + dy * dy + dz * dz;
+                        if distance_squared <= radius_squared {
+                            f(j, distance_squared);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Indices of all points within `radius` of (x, y, z)
+    pub fn neighbors_in_radius(&self, points: &[f32], x: f32, y: f32, z: f32, radius: f32) -> Vec<usize> {
+        let mut neighbors = Vec::new();
+        self.for_each_in_radius(points, x, y, z, radius, |j, _| neighbors.push(j));
+        neighbors
+    }
+
+    /// The `k` points closest to (x, y, z) as (index, distance_squared), nearest first.
+    /// Searches shells of cells outward, starting at the first shell that reaches the occupied
+    /// cells and visiting only the parts of each shell inside them, and stops once the k-th
+    /// candidate is closer than anything an unvisited shell could contain.
+    pub fn k_nearest(&self, points: &[f32], x: f32, y: f32, z: f32, k: usize) -> Vec<(usize, f32)> {
+        let mut best: Vec<(usize, f32)> = Vec::with_capacity(k + 1);
+        if k == 0 || self.cells.is_empty() || !is_finite_point(&[x, y, z]) {
+            return best;
+        }
+        let center = self.cell_coords(x, y, z);
+        let center = [center.0, center.1, center.2];
+        let max_cell = [self.max_cell.0, self.max_cell.1, self.max_cell.2];
+        // Chebyshev cell distance from the query cell to the nearest and farthest occupied cells
+        // (occupied coordinates run from 0 to max_cell on every axis)
+        let first_ring = (0..3).map(|a| (-center[a]).max(center[a] - max_cell[a]).max(0)).max().unwrap_or(0);
+        let last_ring = (0..3).map(|a| center[a].abs().max((max_cell[a] - center[a]).abs())).max().unwrap_or(0);
+        let (query, grid_min) = ([x, y, z], [self.min_x, self.min_y, self.min_z]);
+        let box_gap: [f32; 3] = [0, 1, 2].map(|a| {
+            let grid_max = grid_min[a] + (max_cell[a] + 1) as f32 * self.cell_size;
+            (grid_min[a] - query[a]).max(query[a] - grid_max).max(0.0)
+        });
+
+        for ring in first_ring..=last_ring {
+            self.for_each_shell_cell(center, ring, |gx, gy, gz| {
+                for &j in self.cell_points(gx, gy, gz) {
+                    let j3 = j * 3;
+                    let dx = points[j3] - x;
+                    let dy = points[j3 + 1] - y;
+                    let dz = points[j3 + 2] - z;
+                    let distance_squared = dx * dx + dy * dy + dz * dz;
+                    if best.len() < k || distance_squared < best[best.len() - 1].1 {
+                        let pos = best.partition_point(|&(_, d)| d <= distance_squared);
+                        best.insert(pos, (j, distance_squared));
+                        best.truncate(k);
+                    }
+                }
+            });
+            if best.len() == k && best[k - 1].1 <= self.unvisited_bound_squared(&box_gap, ring) {
+                break;
+            }
+        }
+        best
+    }
+
+    /// Squared lower bound on the distance from the query to any point in a shell beyond
+    /// `ring`. Such a point is at least `ring` whole cells away along some axis, and along every
+    /// axis at least `box_gap` (the query's distance to the occupied cell range) away.
+    fn unvisited_bound_squared(&self, box_gap: &[f32; 3], ring: i64) -> f32 {
+        let reach = ring as f32 * self.cell_size;
+        let gap_squared: f32 = box_gap.iter().map(|g| g * g).sum();
+        (0..3)
+            .map(|a| {
+                let along = reach.max(box_gap[a]);
+                gap_squared - box_gap[a] * box_gap[a] + along * along
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// Call `f` for every cell at Chebyshev distance exactly `ring` from `center` that lies
+    /// within the occupied cell range: the six faces of the shell, each clipped to the range
+    /// and with shared edges visited once
+    fn for_each_shell_cell<F: FnMut(i64, i64, i64)>(&self, center: [i64; 3], ring: i64, mut f: F) {
+        let max_cell = [self.max_cell.0, self.max_cell.1, self.max_cell.2];
+        // Clipped span of the shell along an axis, with or without its two face planes
+        let span = |a: usize, inset: i64| ((center[a] - ring + inset).max(0), (center[a] + ring - inset).min(max_cell[a]));
+        // The face planes along an axis that fall inside the occupied range
+        let planes = |a: usize| {
+            let sides = if ring == 0 { vec![center[a]] } else { vec![center[a] - ring, center[a] + ring] };
+            sides.into_iter().filter(move |&g| g >= 0 && g <= max_cell[a])
+        };
+        let ((y0, y1), (z0, z1)) = (span(1, 0), span(2, 0));
+        let ((xi0, xi1), (yi0, yi1)) = (span(0, 1), span(1, 1));
+
+        // x faces in full, y faces without the x edges, z faces without the x and y edges
+        for gx in planes(0) {
+            for gy in y0..=y1 {
+                for gz in z0..=z1 {
+                    f(gx, gy, gz);
+                }
+            }
+        }
+        for gy in planes(1) {
+            for gx in xi0..=xi1 {
+                for gz in z0..=z1 {
+                    f(gx, gy, gz);
+                }
+            }
+        }
+        for gz in planes(2) {
+            for gx in xi0..=xi1 {
+                for gy in yi0..=yi1 {
+                    f(gx, gy, gz);
+                }
+            }
+        }
+    }
+
+    /// Nearest point to (x, y, z) as (index, distance_squared)
+    pub fn nearest(&self, points: &[f32], x: f32, y: f32, z: f32) -> Option<(usize, f32)> {
+        self.k_nearest(points, x, y, z, 1).into_iter().next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neighbors_in_radius_matches_brute_force() {
+        let mut points = Vec::new();
+        for i in 0..10 {
+            for j in 0..10 {
+                points.extend_from_slice(&[i as f32 * 0.3, j as f32 * 0.3, (i + j) as f32 * 0.05]);
+            }
+        }
+        let grid = SpatialGrid::new(&points, 0.5);
+        let radius = 0.7;
+        for q in [0usize, 17, 55, 99] {
+            let (x, y, z) = (points[q * 3], points[q * 3 + 1], points[q * 3 + 2]);
+            let mut found = grid.neighbors_in_radius(&points, x, y, z, radius);
+            found.sort_unstable();
+            let expected: Vec<usize> = (0..100)
+                .filter(|&j| {
+                    let dx = points[j * 3] - x;
+                    let dy = points[j * 3 + 1] - y;
+                    let dz = points[j * 3 + 2] - z;
+                    dx * dx + dy * dy + dz * dz <= radius * radius
+                })
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn test_k_nearest_matches_brute_force() {
+        let mut points = Vec::new();
+        for i in 0..200 {
+            let t = i as f32 * 0.37;
+            points.extend_from_slice(&[t.sin() * 3.0, t.cos() * 2.0, (t * 0.5).sin()]);
+        }
+        let grid = SpatialGrid::new(&points, SpatialGrid::auto_cell_size(&points));
+        // Query from inside and from well outside the cloud
+        for (x, y, z) in [(0.1f32, 0.2f32, 0.0f32), (10.0, -4.0, 2.0)] {
+            let found: Vec<usize> = grid.k_nearest(&points, x, y, z, 6).into_iter().map(|(j, _)| j).collect();
+            let mut all: Vec<(usize, f32)> = (0..200)
+                .map(|j| {
+                    let dx = points[j * 3] - x;
+                    let dy = points[j * 3 + 1] - y;
+                    let dz = points[j * 3 + 2] - z;
+                    (j, dx * dx + dy * dy + dz * dz)
+                })
+                .collect();
+            all.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            let expected: Vec<usize> = all[..6].iter().map(|&(j, _)| j).collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn test_k_nearest_from_far_outside_the_cloud() {
+        // 20 x 20 x 20 lattice with a small cell size: queries 20 and 1e5 units away start at
+        // the first shell touching the cloud instead of scanning every shell out to it
+        let mut points = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                for k in 0..20 {
+                    points.extend_from_slice(&[i as f32 * 0.05, j as f32 * 0.05, k as f32 * 0.05]);
+                }
+            }
+        }
+        let grid = SpatialGrid::new(&points, 0.05);
+        for (x, y, z) in [(20.5f32, 0.3f32, -20.0f32), (-1e5, 1e5, 0.4), (0.47, 0.52, 1e5)] {
+            let found: Vec<usize> = grid.k_nearest(&points, x, y, z, 4).into_iter().map(|(j, _)| j).collect();
+            let mut all: Vec<(usize, f32)> = (0..points.len() / 3)
+                .map(|j| {
+                    let dx = points[j * 3] - x;
+                    let dy = points[j * 3 + 1] - y;
+                    let dz = points[j * 3 + 2] - z;
+                    (j, dx * dx + dy * dy + dz * dz)
+                })
+                .collect();
+            all.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            assert_eq!(found.len(), 4);
+            for (&j, &(_, d)) in found.iter().zip(&all[..4]) {
+                let (dx, dy, dz) = (points[j * 3] - x, points[j * 3 + 1] - y, points[j * 3 + 2] - z);
+                assert_eq!(dx * dx + dy * dy + dz * dz, d);
+            }
+        }
+    }
+
+    #[test]
+    fn test_auto_cell_size_ignores_a_far_outlier() {
+        let mut points = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                for k in 0..20 {
+                    points.extend_from_slice(&[i as f32 * 0.5, j as f32 * 0.5, k as f32 * 0.5]);
+                }
+            }
+        }
+        let size = SpatialGrid::auto_cell_size(&points);
+        points.extend_from_slice(&[1000.0, 0.0, 0.0]);
+        let with_outlier = SpatialGrid::auto_cell_size(&points);
+        assert!(with_outlier < size * 2.0, "{} vs {}", with_outlier, size);
+
+        // The grid still spreads the lattice over many cells and answers queries exactly
+        let grid = SpatialGrid::new(&points, with_outlier);
+        assert!(grid.cell_count() > 500, "{} cells", grid.cell_count());
+        assert_eq!(grid.nearest(&points, 3.1, 2.4, 0.125).map(|(j, _)| j), Some(6 * 400 + 5 * 20));
+    }
+
+    #[test]
+    fn test_non_finite_points_stay_out_of_the_grid() {
+        let points = vec![
+            0.0, 0.0, 0.0,
+            f32::NAN, 0.0, 0.0,
+            0.5, 0.0, 0.0,
+            f32::INFINITY, 0.0, 0.0,
+            0.0, 0.5, f32::NEG_INFINITY,
+            1.0, 1.0, 1.0,
+        ];
+        let grid = SpatialGrid::new(&points, 0.5);
+        let mut everything = grid.neighbors_in_radius(&points, 0.5, 0.5, 0.5, 10.0);
+        everything.sort();
+        assert_eq!(everything, vec![0, 2, 5]);
+        let nearest: Vec<usize> = grid.k_nearest(&points, 0.0, 0.0, 0.0, 6).into_iter().map(|(j, _)| j).collect();
+        assert_eq!(nearest, vec![0, 2, 5]);
+
+        // Queries from non-finite positions return nothing instead of scanning without end
+        assert!(grid.k_nearest(&points, f32::INFINITY, 0.0, 0.0, 3).is_empty());
+        assert!(grid.neighbors_in_radius(&points, 0.0, f32::NAN, 0.0, 1.0).is_empty());
+    }
+}
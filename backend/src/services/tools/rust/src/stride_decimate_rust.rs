@@ -0,0 +1,86 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::point_attributes::{drop_non_finite, gather_positions, PointAttributes};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError};
+
+// The cheapest possible downsample: keep every stride-th point (indices 0, stride, 2 * stride, ...)
+// with its attributes, for quick LOD previews where spatial fairness doesn't matter. Doing it here
+// rather than in JS avoids copying the whole buffer. The output has ceil(pointCount / stride)
+// points in input order; with bit31 set the stride applies to the points left after dropping
+// non-finite ones.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 stride][u32 flags]
+//               [f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit31=drop non-finite points
+// Output format: [u32 outputCount][f32* positions][optional colors][optional intensities][optional classifications]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (12 bytes: 3 * u32)
+    let (header, run): ([u8; 12], _) = match read_tool_header(&mut stdin, tool_id::STRIDE_DECIMATE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let stride = u32_at(&header, 4, run.order) as usize;
+    let flags = u32_at(&header, 8, run.order);
+
+    if stride == 0 {
+        ToolError::new(ErrorCode::InvalidData, "stride must be at least 1").exit(run.order);
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut attributes = match PointAttributes::read(&mut stdin, point_count, flags, run.order) {
+        Ok(a) => a,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside the attribute blocks").exit(run.order),
+    };
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        drop_non_finite(&mut positions, &mut attributes);
+    }
+
+    let kept = stride_indices(positions.len() / 3, stride);
+    let kept_positions = gather_positions(&positions, &kept);
+    let kept_attributes = attributes.gather(&kept);
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, kept.len() as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &kept_positions, run.order).is_err()
+        || kept_attributes.write(&mut stdout, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, kept.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Indices 0, stride, 2 * stride, ... below point_count
+fn stride_indices(point_count: usize, stride: usize) -> Vec<usize> {
+    (0..point_count).step_by(stride).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_third_point_of_ten() {
+        let kept = stride_indices(10, 3);
+        assert_eq!(kept, vec![0, 3, 6, 9]);
+
+        let positions: Vec<f32> = (0..30).map(|v| v as f32).collect();
+        assert_eq!(gather_positions(&positions, &kept), vec![0.0, 1.0, 2.0, 9.0, 10.0, 11.0, 18.0, 19.0, 20.0, 27.0, 28.0, 29.0]);
+    }
+
+    #[test]
+    fn test_output_count_is_rounded_up() {
+        for (count, stride) in [(0, 4), (1, 4), (8, 4), (9, 4), (7, 1), (5, 100)] {
+            assert_eq!(stride_indices(count, stride).len(), count.div_ceil(stride), "{} / {}", count, stride);
+        }
+    }
+}
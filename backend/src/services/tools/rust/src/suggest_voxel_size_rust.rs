@@ -0,0 +1,137 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::{is_finite_point, Bounds};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats};
+use pointcloud_tools_backend::voxel_hash::VoxelSet;
+
+// Suggest a voxel size for voxel_downsample_rust that yields roughly targetCount output points.
+// The voxel count falls as the size grows, so the size is bisected geometrically between a
+// millionth of the largest extent and twice it, counting occupied voxels (grid anchored at the
+// bounds minimum, voxel indices as in the downsampler) each step. The search stops once a count is
+// within SEARCH_TOLERANCE of the target or after MAX_ITERATIONS, and returns the size whose
+// count came closest. Points with a NaN or infinite coordinate are ignored.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 targetCount][f32* positions]
+// Output format: [f32 voxelSize][u32 voxelCount]
+// No finite points, a zero target or a single location give voxelSize 0 and voxelCount 0.
+
+// Relative count error accepted before the search stops early
+const SEARCH_TOLERANCE: f64 = 0.05;
+const MAX_ITERATIONS: usize = 32;
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: 2 * u32)
+    let (header, run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::SUGGEST_VOXEL_SIZE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let target_count = u32_at(&header, 4, run.order) as usize;
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let (voxel_size, voxel_count) = suggest_voxel_size(&positions, target_count).unwrap_or((0.0, 0));
+
+    let mut stdout = io::stdout();
+    if write_f32_slice(&mut stdout, &[voxel_size], run.order).is_err()
+        || write_u32(&mut stdout, voxel_count as u32, run.order).is_err()
+        || write_stats(&mut stdout, &run, point_count, 1).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Number of occupied voxels of size `voxel_size` in a grid anchored at `min`
+fn voxel_count(positions: &[f32], voxel_size: f32, min: [f32; 3]) -> usize {
+    let inv_voxel_size = 1.0 / voxel_size as f64;
+    // Keyed by the full coordinate: fine sizes reach about a million voxels per axis, past what a
+    // packed 16-bit field per axis can tell apart
+    let voxels: VoxelSet<[i32; 3]> = positions
+        .chunks_exact(3)
+        .filter(|p| is_finite_point(p))
+        .map(|p| [0, 1, 2].map(|a| ((p[a] as f64 - min[a] as f64) * inv_voxel_size).floor() as i32))
+        .collect();
+    voxels.len()
+}
+
+/// (voxel size, resulting voxel count) closest to `target_count` found by geometric bisection;
+/// None without a search range (no finite points, zero target, zero extent)
+fn suggest_voxel_size(positions: &[f32], target_count: usize) -> Option<(f32, usize)> {
+    let bounds = Bounds::from_points(positions)?;
+    let extent = bounds.max_extent();
+    if target_count == 0 || extent <= 0.0 {
+        return None;
+    }
+
+    let mut low = extent as f64 * 1e-6;
+    let mut high = extent as f64 * 2.0;
+    let mut best = (high as f32, 1usize);
+    for _ in 0..MAX_ITERATIONS {
+        let size = (low * high).sqrt() as f32;
+        let count = voxel_count(positions, size, bounds.min);
+        if count.abs_diff(target_count) < best.1.abs_diff(target_count) {
+            best = (size, count);
+        }
+        if count.abs_diff(target_count) as f64 <= target_count as f64 * SEARCH_TOLERANCE {
+            break;
+        }
+        if count > target_count {
+            low = size as f64;
+        } else {
+            high = size as f64;
+        }
+    }
+    Some(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn surface_cloud() -> Vec<f32> {
+        (0..40_000)
+            .flat_map(|i| {
+                let t = i as f32;
+                let (u, v) = ((t * 0.618_034) % 1.0, (t * 0.754_878) % 1.0);
+                [u * 10.0, v * 6.0, (u * 7.0).sin() * 0.5 + v]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_suggested_size_lands_near_the_target() {
+        let positions = surface_cloud();
+        let bounds = Bounds::from_points(&positions).unwrap();
+        for target in [50, 1_000, 12_000] {
+            let (size, count) = suggest_voxel_size(&positions, target).unwrap();
+            assert_eq!(voxel_count(&positions, size, bounds.min), count);
+            let error = count.abs_diff(target) as f64 / target as f64;
+            assert!(error <= 0.2, "target {}: size {} gives {}", target, size, count);
+        }
+    }
+
+    #[test]
+    fn test_voxels_a_16_bit_field_apart_are_counted_separately() {
+        // y and z indices of 65536 and beyond, as the finest sizes of the search produce
+        let positions = [0.0, 0.0, 0.0, 0.0, 65536.5, 0.0, 0.0, 0.0, 131072.5, 0.0, 65536.5, 65536.5];
+        assert_eq!(voxel_count(&positions, 1.0, [0.0; 3]), 4);
+        assert_eq!(voxel_count(&positions, 1.0, [0.0, 65536.5, 65536.5]), 4);
+    }
+
+    #[test]
+    fn test_degenerate_inputs_have_no_suggestion() {
+        assert_eq!(suggest_voxel_size(&[], 10), None);
+        assert_eq!(suggest_voxel_size(&surface_cloud(), 0), None);
+        assert_eq!(suggest_voxel_size(&[1.0, 2.0, 3.0, 1.0, 2.0, 3.0, f32::NAN, 0.0, 0.0], 1), None);
+        // A target of one voxel is met by a size spanning the whole cloud
+        assert_eq!(suggest_voxel_size(&[0.0, 0.0, 0.0, 4.0, 1.0, 0.0], 1).map(|(_, count)| count), Some(1));
+    }
+}
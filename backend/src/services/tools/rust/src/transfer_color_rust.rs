@@ -0,0 +1,99 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::spatial_grid::SpatialGrid;
+
+// Re-colorize geometry that was processed without its colors (e.g. downsampled separately) from
+// a colored reference: each target point takes the color of its nearest reference point. The
+// reference is bucketed into a spatial grid sized by SpatialGrid::auto_cell_size. Target points
+// with a NaN or infinite coordinate, and every target point when the reference has no finite
+// point, get black (0, 0, 0); reference points with one are never picked.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 targetCount][u32 referenceCount]
+//               [f32* targetPositions][f32* referencePositions][f32* referenceColors]
+// Output format: [u32 targetCount][f32* colors] (r, g, b per target point, in target order)
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (8 bytes: 2 * u32)
+    let (header, mut run): ([u8; 8], _) = match read_tool_header(&mut stdin, tool_id::TRANSFER_COLOR) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let target_count = u32_at(&header, 0, run.order) as usize;
+    let reference_count = u32_at(&header, 4, run.order) as usize;
+
+    let target = match read_f32_payload(&mut stdin, target_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let reference = match read_f32_payload(&mut stdin, reference_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let reference_colors = match read_f32_payload(&mut stdin, reference_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let colors = transfer_color(&target, &reference, &reference_colors, &mut run);
+
+    let mut stdout = io::stdout();
+    if write_u32(&mut stdout, target_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &colors, run.order).is_err()
+        || write_stats(&mut stdout, &run, target_count + reference_count, target_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Color of the nearest reference point for every target point (flat rgb, black when none)
+fn transfer_color(target: &[f32], reference: &[f32], reference_colors: &[f32], run: &mut ToolRun) -> Vec<f32> {
+    let grid = SpatialGrid::new(reference, SpatialGrid::auto_cell_size(reference));
+    run.note_map_size(grid.cell_count());
+    let mut colors = vec![0.0f32; target.len()];
+    for (p, color) in target.chunks_exact(3).zip(colors.chunks_exact_mut(3)) {
+        if let Some((nearest, _)) = grid.nearest(reference, p[0], p[1], p[2]) {
+            color.copy_from_slice(&reference_colors[nearest * 3..nearest * 3 + 3]);
+        }
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coincident_points_take_reference_colors_exactly() {
+        let mut reference = Vec::new();
+        let mut reference_colors = Vec::new();
+        for i in 0..50 {
+            let t = i as f32 * 0.37;
+            reference.extend_from_slice(&[t.sin() * 3.0, t.cos() * 2.0, t * 0.1]);
+            reference_colors.extend_from_slice(&[i as f32 / 50.0, 1.0 - i as f32 / 50.0, 0.5]);
+        }
+        // Every third reference point, in reverse order
+        let picked: Vec<usize> = (0..50).rev().step_by(3).collect();
+        let target: Vec<f32> = picked.iter().flat_map(|&i| reference[i * 3..i * 3 + 3].to_vec()).collect();
+
+        let colors = transfer_color(&target, &reference, &reference_colors, &mut ToolRun::default());
+        let expected: Vec<f32> = picked.iter().flat_map(|&i| reference_colors[i * 3..i * 3 + 3].to_vec()).collect();
+        assert_eq!(colors, expected);
+    }
+
+    #[test]
+    fn test_non_finite_targets_and_empty_reference_get_black() {
+        let reference = [0.0, 0.0, 0.0, 10.0, 0.0, 0.0, f32::NAN, 0.0, 0.0];
+        let reference_colors = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let target = [9.0, 1.0, 0.0, f32::INFINITY, 0.0, 0.0, 0.5, 0.0, 0.0];
+        let colors = transfer_color(&target, &reference, &reference_colors, &mut ToolRun::default());
+        assert_eq!(colors, vec![0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+        assert_eq!(transfer_color(&target, &[], &[], &mut ToolRun::default()), vec![0.0; 9]);
+    }
+}
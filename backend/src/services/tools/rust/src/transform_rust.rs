@@ -0,0 +1,166 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_f32_slice, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::sanitize;
+use pointcloud_tools_backend::linalg::{inverse_transpose, Mat3};
+use pointcloud_tools_backend::point_attributes::gather_positions;
+use pointcloud_tools_backend::protocol::{read_f32_payload, read_tool_header, tool_id, write_stats, ErrorCode, FLAG_DROP_NON_FINITE, ToolError};
+
+// Applies a 4x4 affine transform to every point (alignment, unit conversion, axis swaps) before
+// other tools run. The matrix is column-major, as in WebGL/three.js: element (row r, column c)
+// is at index c * 4 + r, and the translation is in elements 12..15. Normals are transformed by the
+// inverse-transpose of the top-left 3x3 and renormalized, so they stay perpendicular to the
+// surface under non-uniform scaling. The projective row is ignored.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][u32 flags][f32*16 matrix][f32* positions][optional f32* normals]
+// flags: bit0=normals, bit31=drop points with a NaN or infinite coordinate (and their normals);
+// without it such points are transformed like any other and stay non-finite
+// Output format: [u32 pointCount][f32* positions][optional f32* normals]
+
+const FLAG_NORMALS: u32 = 1;
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (72 bytes: 2 * u32 + 16 * f32)
+    let (header, run): ([u8; 72], _) = match read_tool_header(&mut stdin, tool_id::TRANSFORM) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let flags = u32_at(&header, 4, run.order);
+    let mut matrix = [0.0f32; 16];
+    for (i, m) in matrix.iter_mut().enumerate() {
+        *m = f32_at(&header, 8 + i * 4, run.order);
+    }
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+    let mut normals = if flags & FLAG_NORMALS != 0 {
+        match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+            Ok(v) => Some(v),
+            Err(e) => e.exit(run.order),
+        }
+    } else {
+        None
+    };
+
+    if flags & FLAG_DROP_NON_FINITE != 0 {
+        if let Some(kept) = sanitize(&mut positions) {
+            normals = normals.map(|n| gather_positions(&n, &kept));
+        }
+    }
+    let output_count = positions.len() / 3;
+
+    transform_points(&mut positions, &matrix);
+    if let Some(normals) = normals.as_mut() {
+        if !transform_normals(normals, &matrix) {
+            ToolError::new(ErrorCode::InvalidData, "transform is singular; normals cannot be transformed").exit(run.order);
+        }
+    }
+
+    if write_u32(&mut stdout, output_count as u32, run.order).is_err()
+        || write_f32_slice(&mut stdout, &positions, run.order).is_err()
+        || normals.is_some_and(|n| write_f32_slice(&mut stdout, &n, run.order).is_err())
+        || write_stats(&mut stdout, &run, point_count, output_count).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// p' = M * [p, 1] for a column-major 4x4 matrix
+fn transform_points(positions: &mut [f32], m: &[f32; 16]) {
+    let m: Vec<f64> = m.iter().map(|&v| v as f64).collect();
+    for p in positions.chunks_exact_mut(3) {
+        let (x, y, z) = (p[0] as f64, p[1] as f64, p[2] as f64);
+        for (r, out) in p.iter_mut().enumerate() {
+            *out = (m[r] * x + m[4 + r] * y + m[8 + r] * z + m[12 + r]) as f32;
+        }
+    }
+}
+
+/// n' = normalize(inverse-transpose(M3) * n); false when the 3x3 part is singular
+fn transform_normals(normals: &mut [f32], m: &[f32; 16]) -> bool {
+    let linear: Mat3 = [
+        [m[0] as f64, m[4] as f64, m[8] as f64],
+        [m[1] as f64, m[5] as f64, m[9] as f64],
+        [m[2] as f64, m[6] as f64, m[10] as f64],
+    ];
+    let normal_matrix = match inverse_transpose(&linear) {
+        Some(n) => n,
+        None => return false,
+    };
+    for n in normals.chunks_exact_mut(3) {
+        let v = [n[0] as f64, n[1] as f64, n[2] as f64];
+        let t: Vec<f64> = normal_matrix.iter().map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2]).collect();
+        let len = (t[0] * t[0] + t[1] * t[1] + t[2] * t[2]).sqrt();
+        if len > 0.0 {
+            for a in 0..3 {
+                n[a] = (t[a] / len) as f32;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Column-major matrix for a rotation of 90 degrees about z followed by a translation
+    fn rotate_z_then_translate(tx: f32, ty: f32, tz: f32) -> [f32; 16] {
+        [
+            0.0, 1.0, 0.0, 0.0, // column 0: image of x axis
+            -1.0, 0.0, 0.0, 0.0, // column 1: image of y axis
+            0.0, 0.0, 1.0, 0.0, // column 2: image of z axis
+            tx, ty, tz, 1.0, // column 3: translation
+        ]
+    }
+
+    #[test]
+    fn test_rotation_and_translation() {
+        let mut positions = vec![1.0, 2.0, 3.0, 0.0, 0.0, 0.0];
+        transform_points(&mut positions, &rotate_z_then_translate(10.0, 20.0, 30.0));
+        let expected = [8.0, 21.0, 33.0, 10.0, 20.0, 30.0];
+        for (got, want) in positions.iter().zip(expected) {
+            assert!((got - want).abs() < 1e-5, "{:?}", positions);
+        }
+
+        let mut normals = vec![1.0, 0.0, 0.0];
+        assert!(transform_normals(&mut normals, &rotate_z_then_translate(10.0, 20.0, 30.0)));
+        assert!((normals[0]).abs() < 1e-6 && (normals[1] - 1.0).abs() < 1e-6 && normals[2].abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_normals_stay_perpendicular_under_nonuniform_scale() {
+        // Plane x + y = 0 has normal (1, 1, 0)/sqrt(2); scaling x by 2 maps it to x/2 + y = 0
+        let mut scale = [0.0f32; 16];
+        scale[0] = 2.0;
+        scale[5] = 1.0;
+        scale[10] = 1.0;
+        scale[15] = 1.0;
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+        let mut normals = vec![s, s, 0.0];
+        assert!(transform_normals(&mut normals, &scale));
+        // Tangent (1, -1, 0) maps to (2, -1, 0); the normal must stay orthogonal to it
+        let dot = normals[0] * 2.0 - normals[1];
+        assert!(dot.abs() < 1e-6);
+        let len = (normals[0] * normals[0] + normals[1] * normals[1] + normals[2] * normals[2]).sqrt();
+        assert!((len - 1.0).abs() < 1e-6);
+
+        assert!(!transform_normals(&mut normals, &[0.0; 16]));
+    }
+}
@@ -1,37 +1,48 @@
 use std::io::{self, Read, Write};
-use rustc_hash::FxHashSet;
+use pointcloud_tools_backend::binary_io::{f32_at, u32_at, write_float_slice, write_u32, ByteOrder, LeFloat};
+use pointcloud_tools_backend::geometry::{is_finite_point, Bounds};
+use pointcloud_tools_backend::protocol::{check_voxel_size, read_float_payload, read_tool_header, tool_id, write_stats, ErrorCode, ToolError, ToolRun};
+use pointcloud_tools_backend::voxel_hash::{VoxelKey, VoxelSet};
 
 // Binary protocol for fast I/O
-// Input format: [u32 pointCount][f32 voxelSize][f32 minX][f32 minY][f32 minZ][f32 maxX][f32 maxY][f32 maxZ][f32* pointData]
+// Input format: [protocol header][u32 pointCount][f32 voxelSize][f32 minX][f32 minY][f32 minZ][f32 maxX][f32 maxY][f32 maxZ][u32 flags][f32* pointData]
+// flags: bit3=f64 positions (same bit as voxel_downsample_rust; other bits are ignored)
+// Points with a NaN or infinite coordinate are skipped.
 // Output format: [u32 voxelCount][f32* voxelGridPositions]
+// With bit3 set, points are f64 and the flags are echoed: [u32 voxelCount][u32 flags][f64* voxelGridPositions]
+
+const FLAG_F64: u32 = 8;
 
 fn main() {
     // Read binary input for fast I/O
-    // Binary format: [u32 pointCount][f32 voxelSize][f32 minX][f32 minY][f32 minZ][f32 maxX][f32 maxY][f32 maxZ][f32* pointData]
+    // Binary format: [u32 pointCount][f32 voxelSize][f32 minX][f32 minY][f32 minZ][f32 maxX][f32 maxY][f32 maxZ][u32 flags][pointData]
     
     let mut stdin = io::stdin();
     
-    // Read binary header (32 bytes: 4 for u32 + 7*4 for floats)
-    let mut header = [0u8; 32];
-    if stdin.read_exact(&mut header).is_err() {
-        std::process::exit(1);
+    // Read binary header (36 bytes: 4 for u32 + 7*4 for floats + 4 for flags)
+    let (header, mut tool_run): ([u8; 36], _) = match read_tool_header(&mut stdin, tool_id::VOXEL_DEBUG) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+    
+    let point_count = u32_at(&header, 0, tool_run.order) as usize;
+    let voxel_size = f32_at(&header, 4, tool_run.order);
+    let bounds = Bounds {
+        min: [0, 1, 2].map(|a| f32_at(&header, 8 + a * 4, tool_run.order)),
+        max: [0, 1, 2].map(|a| f32_at(&header, 20 + a * 4, tool_run.order)),
+    };
+    let flags = u32_at(&header, 32, tool_run.order);
+    
+    if let Err(e) = check_voxel_size(voxel_size) {
+        e.exit(tool_run.order);
     }
     
-    let point_count = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
-    let voxel_size = f32::from_le_bytes([header[4], header[5], header[6], header[7]]);
-    let min_x = f32::from_le_bytes([header[8], header[9], header[10], header[11]]);
-    let min_y = f32::from_le_bytes([header[12], header[13], header[14], header[15]]);
-    let min_z = f32::from_le_bytes([header[16], header[17], header[18], header[19]]);
-    let _max_x = f32::from_le_bytes([header[20], header[21], header[22], header[23]]);
-    let _max_y = f32::from_le_bytes([header[24], header[25], header[26], header[27]]);
-    let _max_z = f32::from_le_bytes([header[28], header[29], header[30], header[31]]);
-    
     // Validate input
-    if point_count == 0 || voxel_size <= 0.0 {
+    if point_count == 0 {
         // Write empty result (4 bytes: voxelCount = 0)
         let voxel_count: u32 = 0;
         let mut stdout = io::stdout();
-        if stdout.write_all(&voxel_count.to_le_bytes()).is_err() || stdout.flush().is_err() {
+        if write_u32(&mut stdout, voxel_count, tool_run.order).is_err() || write_stats(&mut stdout, &tool_run, point_count, 0).is_err() || stdout.flush().is_err() {
             std::process::exit(1);
         }
         return;
@@ -40,84 +51,78 @@ fn main() {
     // Safety check: prevent unreasonable allocations (max 100M points = ~1.2GB)
     const MAX_POINTS: usize = 100_000_000;
     if point_count > MAX_POINTS {
-        eprintln!("Error: point_count {} exceeds maximum {}", point_count, MAX_POINTS);
-        std::process::exit(1);
-    }
-    
-    // Read point data directly into vector (optimized binary read)
-    let float_count = point_count * 3;
-    let bytes_to_read = float_count * 4;
-    
-    // Additional safety check for bytes_to_read
-    if bytes_to_read > 2_000_000_000 { // 2GB max
-        eprintln!("Error: bytes_to_read {} exceeds maximum 2GB", bytes_to_read);
-        std::process::exit(1);
+        ToolError::new(
+            ErrorCode::AllocationTooLarge,
+            format!("point_count {} exceeds maximum {}", point_count, MAX_POINTS),
+        )
+        .exit(tool_run.order);
     }
     
-    let mut buffer = vec![0u8; bytes_to_read];
-    
-    if stdin.read_exact(&mut buffer).is_err() {
-        std::process::exit(1);
+    if (flags & FLAG_F64) != 0 {
+        run::<f64>(&mut stdin, point_count, voxel_size, bounds, flags, &mut tool_run);
+    } else {
+        run::<f32>(&mut stdin, point_count, voxel_size, bounds, flags, &mut tool_run);
     }
-    
-    // Convert bytes to floats (little-endian) - safe conversion
-    let point_cloud_data: Vec<f32> = buffer
-        .chunks_exact(4)
-        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-        .collect();
+}
+
+/// Read the points at precision `T`, generate the voxel centers and write them at the same precision
+fn run<T: LeFloat>(stdin: &mut impl Read, point_count: usize, voxel_size: f32, bounds: Bounds, flags: u32, tool_run: &mut ToolRun) {
+    // Read point data directly into vector (optimized binary read)
+    let point_cloud_data: Vec<T> = match read_float_payload(stdin, point_count * 3, tool_run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(tool_run.order),
+    };
     
     // Process voxel debug generation
     let voxel_grid_positions = generate_voxel_centers(
         &point_cloud_data,
         point_count,
         voxel_size,
-        min_x,
-        min_y,
-        min_z,
+        &bounds,
+        tool_run,
     );
     
     // Write binary output for fast I/O
-    // Binary format: [u32 voxelCount][f32* voxelGridPositions]
+    // Binary format: [u32 voxelCount][u32 flags if f64][voxelGridPositions]
     
     let mut stdout = io::stdout();
     
     // Write voxel count (4 bytes)
     let voxel_count = voxel_grid_positions.len() / 3;
-    if stdout.write_all(&(voxel_count as u32).to_le_bytes()).is_err() {
+    if write_u32(&mut stdout, voxel_count as u32, tool_run.order).is_err() {
+        std::process::exit(1);
+    }
+    if (flags & FLAG_F64) != 0 && write_u32(&mut stdout, flags, tool_run.order).is_err() {
         std::process::exit(1);
     }
     
     // Write voxel grid positions directly (binary, no serialization overhead!)
-    // Convert floats to bytes in bulk for better performance
-    let bytes: Vec<u8> = voxel_grid_positions
-        .iter()
-        .flat_map(|&f| f.to_le_bytes().into_iter())
-        .collect();
-    if stdout.write_all(&bytes).is_err() || stdout.flush().is_err() {
+    if write_float_slice(&mut stdout, &voxel_grid_positions, tool_run.order).is_err() || write_stats(&mut stdout, tool_run, point_count, voxel_count).is_err() || stdout.flush().is_err() {
         std::process::exit(1);
     }
 }
 
-fn generate_voxel_centers(
-    points: &[f32],
+fn generate_voxel_centers<T: LeFloat>(
+    points: &[T],
     point_count: usize,
     voxel_size: f32,
-    min_x: f32,
-    min_y: f32,
-    min_z: f32,
-) -> Vec<f32> {
+    bounds: &Bounds,
+    run: &mut ToolRun,
+) -> Vec<T> {
     // Pre-calculate constants at the start for efficiency
+    // Voxel math runs in f64 so large coordinates (e.g. UTM) keep neighboring voxels apart
+    let voxel_size = voxel_size as f64;
+    let [min_x, min_y, min_z] = bounds.min.map(|v| v as f64);
     let inv_voxel_size = 1.0 / voxel_size;
     let half_voxel_size = voxel_size * 0.5;
     let offset_x = min_x + half_voxel_size;
     let offset_y = min_y + half_voxel_size;
     let offset_z = min_z + half_voxel_size;
     
-    // Use FxHashSet with integer keys for fast hashing
-    // Integer keys are faster to hash than tuples (same optimization as downsampling)
+    // Keyed by the full voxel index, the same key as downsampling (see VoxelKey)
     // Pre-allocate with estimated capacity to avoid reallocations (same as downsampling)
     let estimated_voxels = (point_count / 100).min(100_000);
-    let mut voxel_keys: FxHashSet<u64> = FxHashSet::with_capacity_and_hasher(estimated_voxels, Default::default());
+    let mut voxel_keys: VoxelSet<VoxelKey> = VoxelSet::with_capacity_and_hasher(estimated_voxels, Default::default());
     
     // Process points in chunks for better cache locality
     const CHUNK_SIZE: usize = 1024;
@@ -127,42 +132,72 @@ fn generate_voxel_centers(
         
         for i in chunk_start..chunk_end {
             let i3 = i * 3;
-            let x = points[i3];
-            let y = points[i3 + 1];
-            let z = points[i3 + 2];
+            if !is_finite_point(&points[i3..i3 + 3]) {
+                continue;
+            }
+            let x = points[i3].to_f64();
+            let y = points[i3 + 1].to_f64();
+            let z = points[i3 + 2].to_f64();
             
             // OPTIMIZATION 4: Use multiplication instead of division
             let voxel_x = ((x - min_x) * inv_voxel_size).floor() as i32;
             let voxel_y = ((y - min_y) * inv_voxel_size).floor() as i32;
             let voxel_z = ((z - min_z) * inv_voxel_size).floor() as i32;
             
-            // Use integer hash key for fast lookup
-            let voxel_key = ((voxel_x as u64) << 32) | ((voxel_y as u64) << 16) | (voxel_z as u64);
-            
-            voxel_keys.insert(voxel_key);
+            voxel_keys.insert([voxel_x, voxel_y, voxel_z]);
         }
     }
     
+    run.note_map_size(voxel_keys.len());
+
     // OPTIMIZATION 6: Pre-allocate result vector with exact capacity
     let voxel_count = voxel_keys.len();
     let mut voxel_grid_positions = Vec::with_capacity(voxel_count * 3);
     
     // OPTIMIZATION 7: Single pass conversion with direct grid position calculation
-    for voxel_key in voxel_keys {
-        // Extract voxel coordinates from integer key (same as C++/WASM)
-        let voxel_x = (voxel_key >> 32) as i32;
-        let voxel_y = ((voxel_key >> 16) & 0xFFFF) as i16 as i32; // Sign-extend 16-bit
-        let voxel_z = (voxel_key & 0xFFFF) as i16 as i32; // Sign-extend 16-bit
-        
+    for [voxel_x, voxel_y, voxel_z] in voxel_keys {
         // Calculate voxel grid position (center of voxel grid cell)
-        let center_x = offset_x + voxel_x as f32 * voxel_size;
-        let center_y = offset_y + voxel_y as f32 * voxel_size;
-        let center_z = offset_z + voxel_z as f32 * voxel_size;
+        let center_x = offset_x + voxel_x as f64 * voxel_size;
+        let center_y = offset_y + voxel_y as f64 * voxel_size;
+        let center_z = offset_z + voxel_z as f64 * voxel_size;
         
-        voxel_grid_positions.push(center_x);
-        voxel_grid_positions.push(center_y);
-        voxel_grid_positions.push(center_z);
+        voxel_grid_positions.push(T::from_f64(center_x));
+        voxel_grid_positions.push(T::from_f64(center_y));
+        voxel_grid_positions.push(T::from_f64(center_z));
     }
     
     voxel_grid_positions
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f64_points_keep_voxels_that_f32_merges() {
+        // Two points 0.15 apart near UTM northing 5,000,000; f32 spacing there is 0.5
+        let points: Vec<f64> = vec![5_000_000.05, 0.0, 0.0, 5_000_000.20, 0.0, 0.0];
+        let points_f32: Vec<f32> = points.iter().map(|&v| v as f32).collect();
+        let origin = Bounds { min: [5_000_000.0, 0.0, 0.0], max: [5_000_000.5, 0.0, 0.0] };
+
+        let merged = generate_voxel_centers(&points_f32, 2, 0.1, &origin, &mut ToolRun::default());
+        assert_eq!(merged.len(), 3);
+
+        let separate = generate_voxel_centers(&points, 2, 0.1, &origin, &mut ToolRun::default());
+        assert_eq!(separate.len(), 6);
+        let mut xs = [separate[0], separate[3]];
+        xs.sort_by(f64::total_cmp);
+        assert!((xs[0] - 5_000_000.05).abs() < 1e-6);
+        assert!((xs[1] - 5_000_000.15).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_voxels_a_16_bit_field_apart_or_negative_stay_separate() {
+        let points: Vec<f32> = vec![1.5, 0.5, 0.5, 0.5, 65536.5, 0.5, 0.5, -0.5, 0.5, -0.5, -0.5, 0.5];
+        let origin = Bounds { min: [0.0; 3], max: [0.0; 3] };
+        let centers = generate_voxel_centers(&points, 4, 1.0, &origin, &mut ToolRun::default());
+        let mut centers: Vec<[f32; 3]> = centers.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect();
+        centers.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(centers, vec![[-0.5, -0.5, 0.5], [0.5, -0.5, 0.5], [0.5, 65536.5, 0.5], [1.5, 0.5, 0.5]]);
+    }
+}
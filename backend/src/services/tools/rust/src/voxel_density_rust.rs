@@ -0,0 +1,140 @@
+use std::io::{self, Write};
+use pointcloud_tools_backend::binary_io::{f32_at, to_wire_order, u32_at, write_u32, ByteOrder};
+use pointcloud_tools_backend::geometry::is_finite_point;
+use pointcloud_tools_backend::protocol::{check_voxel_size, read_f32_payload, read_tool_header, tool_id, write_stats, ToolRun};
+use pointcloud_tools_backend::voxel_hash::{VoxelKey, VoxelMap};
+
+// Voxel occupancy export for density heatmaps: the integer grid coordinate and point count of
+// every occupied voxel, without computing centroids. Voxel indices are computed exactly as in
+// voxel_downsample_rust (grid anchored at the given minimum, f64 intermediates), so each entry
+// corresponds to one output point of the downsampler for the same voxel size. Voxels are keyed
+// by their full (vx, vy, vz) coordinate, so voxels below the anchor or far from it never merge.
+// Voxels are listed in ascending (vx, vy, vz) order. Points with a NaN or infinite coordinate are
+// not counted.
+//
+// Binary protocol for fast I/O
+// Input format: [protocol header][u32 pointCount][f32 voxelSize][f32 minX][f32 minY][f32 minZ][f32* positions]
+// Output format: [u32 voxelCount] then per voxel [i32 vx][i32 vy][i32 vz][u32 count]
+
+fn main() {
+    let mut stdin = io::stdin();
+
+    // Read binary header (20 bytes: u32 + 4 * f32)
+    let (header, mut run): ([u8; 20], _) = match read_tool_header(&mut stdin, tool_id::VOXEL_DENSITY) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, run.order) as usize;
+    let voxel_size = f32_at(&header, 4, run.order);
+    let min = [f32_at(&header, 8, run.order), f32_at(&header, 12, run.order), f32_at(&header, 16, run.order)];
+
+    if let Err(e) = check_voxel_size(voxel_size) {
+        e.exit(run.order);
+    }
+
+    let mut stdout = io::stdout();
+
+    if point_count == 0 {
+        if write_u32(&mut stdout, 0, run.order).is_err() || write_stats(&mut stdout, &run, point_count, 0).is_err() || stdout.flush().is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let positions = match read_f32_payload(&mut stdin, point_count * 3, run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(run.order),
+    };
+
+    let voxels = voxel_density(&positions, voxel_size, min, &mut run);
+
+    let mut bytes = Vec::with_capacity(voxels.len() * 16);
+    for (cell, count) in &voxels {
+        for c in cell {
+            bytes.extend_from_slice(&c.to_le_bytes());
+        }
+        bytes.extend_from_slice(&count.to_le_bytes());
+    }
+    to_wire_order(&mut bytes, 4, run.order);
+    if write_u32(&mut stdout, voxels.len() as u32, run.order).is_err()
+        || stdout.write_all(&bytes).is_err()
+        || write_stats(&mut stdout, &run, point_count, voxels.len()).is_err()
+        || stdout.flush().is_err()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Occupied voxels as (grid coordinate, point count), sorted by coordinate
+fn voxel_density(positions: &[f32], voxel_size: f32, min: [f32; 3], run: &mut ToolRun) -> Vec<([i32; 3], u32)> {
+    let inv_voxel_size = 1.0 / voxel_size as f64;
+    let estimated_voxels = (positions.len() / 300).min(100_000);
+    let mut voxel_map: VoxelMap<VoxelKey, u32> =
+        VoxelMap::with_capacity_and_hasher(estimated_voxels, Default::default());
+
+    for p in positions.chunks_exact(3).filter(|p| is_finite_point(p)) {
+        let mut cell = [0i32; 3];
+        for a in 0..3 {
+            cell[a] = ((p[a] as f64 - min[a] as f64) * inv_voxel_size).floor() as i32;
+        }
+        *voxel_map.entry(cell).or_insert(0) += 1;
+    }
+
+    run.note_map_size(voxel_map.len());
+    let mut voxels: Vec<([i32; 3], u32)> = voxel_map.into_iter().collect();
+    voxels.sort_unstable_by_key(|&(cell, _)| cell);
+    voxels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_counts_sum_to_input_and_coordinates_are_unique() {
+        let mut positions = Vec::new();
+        for i in 0..40 {
+            let t = i as f32 * 0.173;
+            positions.extend_from_slice(&[t.sin() * 2.0, t.cos() * 1.5, t * 0.05 - 1.0]);
+        }
+        let voxels = voxel_density(&positions, 0.5, [-2.0, -1.5, -1.0], &mut ToolRun::default());
+
+        let total: u32 = voxels.iter().map(|&(_, count)| count).sum();
+        assert_eq!(total as usize, positions.len() / 3);
+
+        let unique: HashSet<[i32; 3]> = voxels.iter().map(|&(cell, _)| cell).collect();
+        assert_eq!(unique.len(), voxels.len());
+        assert!(voxels.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn test_known_voxel_counts() {
+        let positions = vec![
+            0.1, 0.1, 0.1, 0.9, 0.9, 0.9, // both in voxel (0, 0, 0)
+            1.5, 0.2, 0.3, // voxel (1, 0, 0)
+            -0.5, 0.0, 0.0, // below the anchor: voxel (-1, 0, 0)
+        ];
+        let voxels = voxel_density(&positions, 1.0, [0.0, 0.0, 0.0], &mut ToolRun::default());
+        assert_eq!(voxels, vec![([-1, 0, 0], 1), ([0, 0, 0], 2), ([1, 0, 0], 1)]);
+    }
+
+    #[test]
+    fn test_negative_y_and_z_voxels_stay_distinct() {
+        // A packed key sign-extending y or z over the other fields would merge these
+        let positions = vec![
+            0.5, -0.5, 0.5, // voxel (0, -1, 0)
+            3.5, -0.5, 0.5, // voxel (3, -1, 0)
+            0.5, 0.5, -0.5, // voxel (0, 0, -1)
+            0.5, -0.5, -0.5, // voxel (0, -1, -1)
+            0.5, 65536.5, 0.5, // voxel (0, 65536, 0), one 16-bit field width from (0, 0, 0)
+            0.5, 0.5, 0.5, // voxel (0, 0, 0)
+        ];
+        let voxels = voxel_density(&positions, 1.0, [0.0, 0.0, 0.0], &mut ToolRun::default());
+        assert_eq!(
+            voxels,
+            vec![([0, -1, -1], 1), ([0, -1, 0], 1), ([0, 0, -1], 1), ([0, 0, 0], 1), ([0, 65536, 0], 1), ([3, -1, 0], 1)]
+        );
+    }
+}
@@ -1,187 +1,639 @@
 use std::io::{self, Read, Write};
-use rustc_hash::FxHashMap;
+use pointcloud_tools_backend::binary_io::{f32_at, read_float_chunk, u32_at, write_f32_slice, write_float_slice, write_u32, write_u32_slice, ByteOrder, LeFloat};
+use pointcloud_tools_backend::geometry::{is_finite_point, Bounds};
+use pointcloud_tools_backend::morton::{morton_axis, morton_encode};
+use pointcloud_tools_backend::protocol::{check_voxel_size, read_f32_payload, read_float_payload, read_tool_header, tool_id, write_stats, ErrorCode, ToolError, ToolRun};
+use pointcloud_tools_backend::voxel_hash::{VoxelKey, VoxelMap, VoxelSet};
 
 // Binary protocol: extended same as C++ BE
-// Input: [u32 pointCount][f32 voxelSize][f32 minX..maxZ][u32 flags][f32* positions][optional colors][optional intensities][optional classifications]
-// flags: bit0=colors, bit1=intensity, bit2=classification
-// Output: [u32 outputCount][f32* positions][optional colors][optional intensities][optional classifications]
+// Input: [protocol header][u32 pointCount][f32 voxelSize][f32 minX..maxZ][u32 flags][u32 minPointsPerVoxel][f32* positions][optional colors][optional intensities][optional classifications]
+// flags: bit0=colors, bit1=intensity, bit2=classification, bit3=f64 positions, bit4=nearest to center,
+//        bit5=per-voxel counts, bit6=round voxel indices (Open3D convention), bit7=streaming,
+//        bit8=exact map capacity (two passes), bit9=sorted output, bit10=max intensity, bit11=first point,
+//        bit12=per-axis voxel size, bit13=Morton order, bit14=per-voxel spread
+// With bit4 set, each voxel emits the input point closest to the voxel's geometric center with its
+// exact attributes instead of the centroid and averaged attributes (output layout unchanged).
+// Voxels holding fewer than minPointsPerVoxel input points are dropped (0 and 1 keep every voxel),
+// which doubles as a cheap outlier filter for isolated points.
+// bit6 switches voxel indices from floor((p - min) / size) to floor((p - min) / size + 0.5),
+// Open3D's rounding (ties go up, also below the origin), so results can be compared against
+// Open3D exactly. Voxels are then centered on the grid points instead of spanning them, which
+// moves assignments only for points on boundaries. Only this tool has bit6: the other voxel tools
+// (snap_to_grid, voxel_debug, voxel_density, the adaptive and octree downsamplers and the
+// frontend WASM downsampler) always floor.
+// Output: [u32 outputCount][f32* positions][optional colors][optional intensities][optional classifications][optional u32* counts]
+// counts (bit5) holds the number of input points in each output voxel, in output order.
+// With bit3 set, positions are f64 on both sides and the flags are echoed:
+// Output: [u32 outputCount][u32 flags][f64* positions][optional attributes as above]
+// The f32 bounds only anchor the voxel grid, so their rounding shifts the grid but never merges voxels.
+// With bit7 set, pointCount is ignored and positions are read until end of input in chunks of
+// STREAM_CHUNK_POINTS, each folded into the voxel map before the next is read, so peak memory is
+// one chunk plus the map. Output is unchanged. Streaming takes positions only (no attributes,
+// no bit4).
+// Points with a NaN or infinite coordinate are never assigned to a voxel (bit31, the shared
+// drop-non-finite flag, is accepted but changes nothing here).
+// bit8 sizes the voxel map from a first pass that only collects voxel keys instead of estimating
+// it from pointCount (positions-only path); see voxel_downsample_exact_capacity.
+// Output order otherwise follows voxel map iteration, which changes with map capacity and
+// insertion history. bit9 sorts the output by voxel index (x, then y, then z) so repeated runs
+// emit byte-identical output, at the cost of a sort over the output points. Every path honors it.
+// bit10 and bit11 pick the reduction mode: instead of averaging, each voxel emits one input point
+// with its exact attributes, the one with the highest intensity (bit10, needs bit1; ties keep the
+// earlier point) or the first one in input order (bit11). Neither combines with bit4 or bit7.
+// With bit12 set, [f32 voxelSizeY][f32 voxelSizeZ] follow the header and voxelSize applies to x
+// only, so voxels can be e.g. coarse in x/y and fine in z. Every path honors it.
+// bit13 orders the output along a Morton (Z-order) curve over the voxel indices instead, so
+// spatially adjacent voxels mostly sit next to each other in memory (GPU upload locality, later
+// neighbor queries). Output stays deterministic; bit13 takes precedence over bit9.
+// bit14 appends [f32* spread] after the counts: the RMS distance of each voxel's points from its
+// centroid, ~0 for a tight cluster and large for voxels straddling an edge or two surfaces.
+// The extra sums are only tracked when it is set. Positions only (no attributes, bit4, bit7 or
+// bit8).
+
+const FLAG_COLORS: u32 = 1;
+const FLAG_INTENSITY: u32 = 2;
+const FLAG_CLASSIFICATION: u32 = 4;
+const ATTRIBUTE_FLAGS: u32 = FLAG_COLORS | FLAG_INTENSITY | FLAG_CLASSIFICATION;
+const FLAG_F64: u32 = 8;
+const FLAG_NEAREST: u32 = 16;
+const FLAG_COUNTS: u32 = 32;
+const FLAG_ROUND: u32 = 64;
+const FLAG_STREAM: u32 = 128;
+const FLAG_EXACT_CAPACITY: u32 = 256;
+const FLAG_SORTED: u32 = 512;
+const FLAG_MAX_INTENSITY: u32 = 1024;
+const FLAG_FIRST_POINT: u32 = 2048;
+const FLAG_PER_AXIS_SIZE: u32 = 4096;
+const FLAG_MORTON: u32 = 8192;
+const FLAG_SPREAD: u32 = 16384;
+
+const STREAM_CHUNK_POINTS: usize = 1 << 20;
+
+/// How a coordinate maps to its voxel index along each axis
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum BoundaryMode {
+    /// floor((p - min) / size): voxel i spans [i, i + 1) voxel sizes from the origin
+    #[default]
+    Floor,
+    /// floor((p - min) / size + 0.5), as Open3D does: voxel i spans [i - 0.5, i + 0.5)
+    Round,
+}
+
+/// What each voxel of the attribute downsampler emits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum ReductionMode {
+    /// Centroid, averaged colors and intensity, majority classification
+    #[default]
+    Average,
+    /// The input point with the highest intensity
+    MaxIntensity,
+    /// The first input point in the voxel
+    FirstPoint,
+}
+
+/// Voxel edge length along x, y and z
+type VoxelSize = [f32; 3];
 
 #[derive(Clone, Copy)]
-struct Voxel {
+struct VoxelGrid {
+    voxel_size: VoxelSize,
+    bounds: Bounds,
+    min_points_per_voxel: u32,
+    boundary_mode: BoundaryMode,
+}
+
+/// Per-point attribute arrays for the attribute path; None (or the wrong length) skips one
+#[derive(Clone, Copy)]
+struct AttributeInput<'a> {
+    colors: Option<&'a Vec<f32>>,
+    intensities: Option<&'a Vec<f32>>,
+    classifications: Option<&'a Vec<u8>>,
+}
+
+/// Positions, colors, intensities, classifications and per-voxel counts, in output order
+type AttributeOutput<T> = (Vec<T>, Vec<f32>, Vec<f32>, Vec<u8>, Vec<u32>);
+
+#[derive(Clone, Copy)]
+struct Voxel<T> {
     count: i32,
-    sum_x: f32,
-    sum_y: f32,
-    sum_z: f32,
+    sum_x: T,
+    sum_y: T,
+    sum_z: T,
+}
+
+/// Voxel plus the f64 sums of the offsets from the grid origin and of their squares, for the
+/// spread output; offsets keep the squares small for far-from-origin clouds
+#[derive(Clone, Copy)]
+struct VoxelMoments<T> {
+    voxel: Voxel<T>,
+    sum: [f64; 3],
+    sum_squares: [f64; 3],
 }
 
 #[derive(Clone)]
-struct VoxelFull {
+struct VoxelFull<T> {
     count: i32,
-    sum_x: f32,
-    sum_y: f32,
-    sum_z: f32,
+    sum_x: T,
+    sum_y: T,
+    sum_z: T,
     sum_r: f32,
     sum_g: f32,
     sum_b: f32,
     sum_intensity: f32,
-    class_counts: FxHashMap<u8, i32>,
+    class_counts: VoxelMap<u8, i32>,
+    // Point emitted by the MaxIntensity and FirstPoint reductions
+    best_index: usize,
+    best_intensity: f32,
 }
 
 fn main() {
     let mut stdin = io::stdin();
 
-    // Extended header: 36 bytes (32 + 4 for flags)
-    let mut header = [0u8; 36];
-    if stdin.read_exact(&mut header).is_err() {
-        std::process::exit(1);
+    // Extended header: 40 bytes (32 + 4 for flags + 4 for minPointsPerVoxel)
+    let (header, mut tool_run): ([u8; 40], _) = match read_tool_header(&mut stdin, tool_id::VOXEL_DOWNSAMPLE) {
+        Ok(h) => h,
+        Err(e) => e.exit(ByteOrder::Little),
+    };
+
+    let point_count = u32_at(&header, 0, tool_run.order) as usize;
+    let voxel_size = f32_at(&header, 4, tool_run.order);
+    let bounds = Bounds {
+        min: [0, 1, 2].map(|a| f32_at(&header, 8 + a * 4, tool_run.order)),
+        max: [0, 1, 2].map(|a| f32_at(&header, 20 + a * 4, tool_run.order)),
+    };
+    let flags = u32_at(&header, 32, tool_run.order);
+    let min_points_per_voxel = u32_at(&header, 36, tool_run.order);
+
+    let voxel_size = if (flags & FLAG_PER_AXIS_SIZE) != 0 {
+        match read_f32_payload(&mut stdin, 2, tool_run.order) {
+            Ok(yz) => [voxel_size, yz[0], yz[1]],
+            Err(e) => e.exit(tool_run.order),
+        }
+    } else {
+        [voxel_size; 3]
+    };
+    for size in voxel_size {
+        if let Err(e) = check_voxel_size(size) {
+            e.exit(tool_run.order);
+        }
     }
 
-    let point_count = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
-    let voxel_size = f32::from_le_bytes([header[4], header[5], header[6], header[7]]);
-    let min_x = f32::from_le_bytes([header[8], header[9], header[10], header[11]]);
-    let min_y = f32::from_le_bytes([header[12], header[13], header[14], header[15]]);
-    let min_z = f32::from_le_bytes([header[16], header[17], header[18], header[19]]);
-    let _max_x = f32::from_le_bytes([header[20], header[21], header[22], header[23]]);
-    let _max_y = f32::from_le_bytes([header[24], header[25], header[26], header[27]]);
-    let _max_z = f32::from_le_bytes([header[28], header[29], header[30], header[31]]);
-    let flags = u32::from_le_bytes([header[32], header[33], header[34], header[35]]);
+    let boundary_mode = if (flags & FLAG_ROUND) != 0 { BoundaryMode::Round } else { BoundaryMode::Floor };
+    let grid = VoxelGrid { voxel_size, bounds, min_points_per_voxel, boundary_mode };
 
-    let use_colors = (flags & 1) != 0;
-    let use_intensity = (flags & 2) != 0;
-    let use_classification = (flags & 4) != 0;
+    let reduction = match flags & (FLAG_MAX_INTENSITY | FLAG_FIRST_POINT) {
+        0 => ReductionMode::Average,
+        FLAG_MAX_INTENSITY if (flags & FLAG_INTENSITY) != 0 => ReductionMode::MaxIntensity,
+        FLAG_MAX_INTENSITY => ToolError::new(ErrorCode::InvalidData, "max-intensity reduction needs intensities (bit1)").exit(tool_run.order),
+        FLAG_FIRST_POINT => ReductionMode::FirstPoint,
+        _ => ToolError::new(ErrorCode::InvalidData, "max-intensity and first-point reductions are exclusive").exit(tool_run.order),
+    };
+    if let Err(e) = check_spread_flags(flags) {
+        e.exit(tool_run.order);
+    }
+    if reduction != ReductionMode::Average && (flags & (FLAG_NEAREST | FLAG_STREAM)) != 0 {
+        ToolError::new(ErrorCode::InvalidData, "reduction modes do not combine with nearest or streaming mode").exit(tool_run.order);
+    }
 
-    if point_count == 0 || voxel_size <= 0.0 {
+    if (flags & FLAG_STREAM) != 0 {
+        if (flags & (ATTRIBUTE_FLAGS | FLAG_NEAREST)) != 0 {
+            ToolError::new(ErrorCode::InvalidData, "streaming mode takes positions only (no attributes or nearest mode)").exit(tool_run.order);
+        }
+        if (flags & FLAG_F64) != 0 {
+            run_streaming::<f64>(&mut stdin, grid, flags, &mut tool_run);
+        } else {
+            run_streaming::<f32>(&mut stdin, grid, flags, &mut tool_run);
+        }
+        return;
+    }
+
+    if point_count == 0 {
         let output_count: u32 = 0;
         let mut stdout = io::stdout();
-        if stdout.write_all(&output_count.to_le_bytes()).is_err() || stdout.flush().is_err() {
+        if write_u32(&mut stdout, output_count, tool_run.order).is_err()
+            || write_stats(&mut stdout, &tool_run, point_count, 0).is_err()
+            || stdout.flush().is_err()
+        {
             std::process::exit(1);
         }
         return;
     }
 
-    let float_count = point_count * 3;
-    let mut buf = vec![0u8; float_count * 4];
-    if stdin.read_exact(&mut buf).is_err() {
+    if (flags & FLAG_F64) != 0 {
+        run::<f64>(&mut stdin, point_count, grid, reduction, flags, &mut tool_run);
+    } else {
+        run::<f32>(&mut stdin, point_count, grid, reduction, flags, &mut tool_run);
+    }
+}
+
+/// Spread output (bit14) only exists on the single-pass positions-only path, so every flag that
+/// selects another path conflicts with it
+fn check_spread_flags(flags: u32) -> Result<(), ToolError> {
+    if (flags & FLAG_SPREAD) != 0 && (flags & (ATTRIBUTE_FLAGS | FLAG_NEAREST | FLAG_STREAM | FLAG_EXACT_CAPACITY)) != 0 {
+        return Err(ToolError::new(
+            ErrorCode::InvalidData,
+            "spread output takes positions only (no attributes, nearest, streaming or exact-capacity mode)",
+        ));
+    }
+    Ok(())
+}
+
+/// Fold positions at precision `T` into the voxel map chunk by chunk until end of input, then
+/// write the result
+fn run_streaming<T: LeFloat>(stdin: &mut impl Read, grid: VoxelGrid, flags: u32, tool_run: &mut ToolRun) {
+    let (mut downsampled_points, mut counts, point_count) = match voxel_downsample_streaming::<T, _>(stdin, STREAM_CHUNK_POINTS, grid, tool_run) {
+        Ok(result) => result,
+        Err(_) => ToolError::new(ErrorCode::ShortPayload, "input ends inside a point").exit(tool_run.order),
+    };
+    if let Some(order) = output_order(&downsampled_points, &grid, flags) {
+        downsampled_points = reorder(&downsampled_points, &order, 3);
+        counts = reorder(&counts, &order, 1);
+    }
+    let mut stdout = io::stdout();
+    if write_count_and_positions(&mut stdout, &downsampled_points, flags, tool_run.order).is_err()
+        || ((flags & FLAG_COUNTS) != 0 && write_u32_slice(&mut stdout, &counts, tool_run.order).is_err())
+        || write_stats(&mut stdout, tool_run, point_count, counts.len()).is_err()
+        || stdout.flush().is_err()
+    {
         std::process::exit(1);
     }
-    let point_cloud_data: Vec<f32> = buf
-        .chunks_exact(4)
-        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
-        .collect();
+}
 
+/// Read the payload at precision `T`, downsample and write the result
+fn run<T: LeFloat>(stdin: &mut impl Read, point_count: usize, grid: VoxelGrid, reduction: ReductionMode, flags: u32, tool_run: &mut ToolRun) {
+    let use_colors = (flags & FLAG_COLORS) != 0;
+    let use_intensity = (flags & FLAG_INTENSITY) != 0;
+    let use_classification = (flags & FLAG_CLASSIFICATION) != 0;
+    let use_counts = (flags & FLAG_COUNTS) != 0;
+
+    let float_count = point_count * 3;
+    let point_cloud_data: Vec<T> = match read_float_payload(stdin, float_count, tool_run.order) {
+        Ok(v) => v,
+        Err(e) => e.exit(tool_run.order),
+    };
+
+    let mut buf: Vec<u8> = vec![];
     let mut input_colors: Vec<f32> = vec![];
     let mut input_intensities: Vec<f32> = vec![];
     let mut input_classifications: Vec<u8> = vec![];
     if use_colors {
         buf.resize(float_count * 4, 0);
         if stdin.read_exact(&mut buf).is_err() {
-            std::process::exit(1);
+            ToolError::new(ErrorCode::ShortPayload, "input ends inside the colors").exit(tool_run.order);
         }
-        input_colors = buf.chunks_exact(4).map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect();
+        input_colors = buf.chunks_exact(4).map(|c| f32_at(c, 0, tool_run.order)).collect();
     }
     if use_intensity {
         buf.resize(point_count * 4, 0);
         if stdin.read_exact(&mut buf).is_err() {
-            std::process::exit(1);
+            ToolError::new(ErrorCode::ShortPayload, "input ends inside the intensities").exit(tool_run.order);
         }
-        input_intensities = buf.chunks_exact(4).map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect();
+        input_intensities = buf.chunks_exact(4).map(|c| f32_at(c, 0, tool_run.order)).collect();
     }
     if use_classification {
         input_classifications.resize(point_count, 0);
         if stdin.read_exact(&mut input_classifications).is_err() {
-            std::process::exit(1);
+            ToolError::new(ErrorCode::ShortPayload, "input ends inside the classifications").exit(tool_run.order);
         }
     }
 
     let mut stdout = io::stdout();
 
-    if !use_colors && !use_intensity && !use_classification {
-        let downsampled_points = voxel_downsample_internal(
-            &point_cloud_data,
-            point_count,
-            voxel_size,
-            min_x,
-            min_y,
-            min_z,
-        );
-        let output_count = downsampled_points.len() / 3;
-        if stdout.write_all(&(output_count as u32).to_le_bytes()).is_err() {
+    if (flags & FLAG_NEAREST) != 0 {
+        let (mut kept, mut counts) = voxel_downsample_nearest(&point_cloud_data, point_count, grid, tool_run);
+        // Gathered once for the output; a Morton or sorted order permutes it in place of a regather
+        let mut positions: Vec<T> = kept.iter().flat_map(|&i| point_cloud_data[i * 3..i * 3 + 3].iter().copied()).collect();
+        if let Some(order) = output_order(&positions, &grid, flags) {
+            kept = reorder(&kept, &order, 1);
+            counts = reorder(&counts, &order, 1);
+            positions = reorder(&positions, &order, 3);
+        }
+        if write_count_and_positions(&mut stdout, &positions, flags, tool_run.order).is_err() {
             std::process::exit(1);
         }
-        let bytes: Vec<u8> = downsampled_points.iter().flat_map(|&f| f.to_le_bytes()).collect();
-        if stdout.write_all(&bytes).is_err() || stdout.flush().is_err() {
+        if use_colors {
+            let colors: Vec<f32> = kept.iter().flat_map(|&i| input_colors[i * 3..i * 3 + 3].iter().copied()).collect();
+            let _ = write_f32_slice(&mut stdout, &colors, tool_run.order);
+        }
+        if use_intensity {
+            let intensities: Vec<f32> = kept.iter().map(|&i| input_intensities[i]).collect();
+            let _ = write_f32_slice(&mut stdout, &intensities, tool_run.order);
+        }
+        if use_classification {
+            let classes: Vec<u8> = kept.iter().map(|&i| input_classifications[i]).collect();
+            let _ = stdout.write_all(&classes);
+        }
+        if use_counts {
+            let _ = write_u32_slice(&mut stdout, &counts, tool_run.order);
+        }
+        let _ = write_stats(&mut stdout, tool_run, point_count, kept.len());
+        let _ = stdout.flush();
+        return;
+    }
+
+    if !use_colors && !use_intensity && !use_classification && reduction == ReductionMode::Average {
+        let downsample = if (flags & FLAG_EXACT_CAPACITY) != 0 {
+            voxel_downsample_exact_capacity
+        } else {
+            voxel_downsample_per_axis
+        };
+        let (mut downsampled_points, mut counts, mut spread) = if (flags & FLAG_SPREAD) != 0 {
+            voxel_downsample_with_spread(&point_cloud_data, point_count, grid, tool_run)
+        } else {
+            let (points, counts) = downsample(
+                &point_cloud_data,
+                point_count,
+                grid.voxel_size,
+                &grid.bounds,
+                grid.min_points_per_voxel,
+                grid.boundary_mode,
+                tool_run,
+            );
+            (points, counts, Vec::new())
+        };
+        if let Some(order) = output_order(&downsampled_points, &grid, flags) {
+            downsampled_points = reorder(&downsampled_points, &order, 3);
+            counts = reorder(&counts, &order, 1);
+            spread = reorder(&spread, &order, 1);
+        }
+        if write_count_and_positions(&mut stdout, &downsampled_points, flags, tool_run.order).is_err()
+            || (use_counts && write_u32_slice(&mut stdout, &counts, tool_run.order).is_err())
+            || ((flags & FLAG_SPREAD) != 0 && write_f32_slice(&mut stdout, &spread, tool_run.order).is_err())
+            || write_stats(&mut stdout, tool_run, point_count, counts.len()).is_err()
+            || stdout.flush().is_err()
+        {
             std::process::exit(1);
         }
         return;
     }
 
-    let (downsampled_points, downsampled_colors, downsampled_intensities, downsampled_classifications) =
+    let (mut downsampled_points, mut downsampled_colors, mut downsampled_intensities, mut downsampled_classifications, mut counts) =
         voxel_downsample_with_attributes(
             &point_cloud_data,
-            if use_colors { Some(&input_colors) } else { None },
-            if use_intensity { Some(&input_intensities) } else { None },
-            if use_classification { Some(&input_classifications) } else { None },
+            AttributeInput {
+                colors: if use_colors { Some(&input_colors) } else { None },
+                intensities: if use_intensity { Some(&input_intensities) } else { None },
+                classifications: if use_classification { Some(&input_classifications) } else { None },
+            },
             point_count,
-            voxel_size,
-            min_x,
-            min_y,
-            min_z,
+            grid,
+            reduction,
+            tool_run,
         );
-
-    let output_count = downsampled_points.len() / 3;
-    if stdout.write_all(&(output_count as u32).to_le_bytes()).is_err() {
-        std::process::exit(1);
+    if let Some(order) = output_order(&downsampled_points, &grid, flags) {
+        downsampled_points = reorder(&downsampled_points, &order, 3);
+        downsampled_colors = reorder(&downsampled_colors, &order, 3);
+        downsampled_intensities = reorder(&downsampled_intensities, &order, 1);
+        downsampled_classifications = reorder(&downsampled_classifications, &order, 1);
+        counts = reorder(&counts, &order, 1);
     }
-    let bytes: Vec<u8> = downsampled_points.iter().flat_map(|&f| f.to_le_bytes()).collect();
-    if stdout.write_all(&bytes).is_err() {
+
+    if write_count_and_positions(&mut stdout, &downsampled_points, flags, tool_run.order).is_err() {
         std::process::exit(1);
     }
     if use_colors {
-        let bytes: Vec<u8> = downsampled_colors.iter().flat_map(|&f| f.to_le_bytes()).collect();
-        let _ = stdout.write_all(&bytes);
+        let _ = write_f32_slice(&mut stdout, &downsampled_colors, tool_run.order);
     }
     if use_intensity {
-        let bytes: Vec<u8> = downsampled_intensities.iter().flat_map(|&f| f.to_le_bytes()).collect();
-        let _ = stdout.write_all(&bytes);
+        let _ = write_f32_slice(&mut stdout, &downsampled_intensities, tool_run.order);
     }
     if use_classification {
         let _ = stdout.write_all(&downsampled_classifications);
     }
+    if use_counts {
+        let _ = write_u32_slice(&mut stdout, &counts, tool_run.order);
+    }
+    let _ = write_stats(&mut stdout, tool_run, point_count, counts.len());
     let _ = stdout.flush();
 }
 
-fn voxel_downsample_with_attributes(
-    points: &[f32],
-    colors: Option<&Vec<f32>>,
-    intensities: Option<&Vec<f32>>,
-    classifications: Option<&Vec<u8>>,
+/// [u32 outputCount], the echoed flags when positions are f64, then the positions
+fn write_count_and_positions<T: LeFloat, W: Write>(out: &mut W, points: &[T], flags: u32, order: ByteOrder) -> io::Result<()> {
+    write_u32(out, (points.len() / 3) as u32, order)?;
+    if (flags & FLAG_F64) != 0 {
+        write_u32(out, flags, order)?;
+    }
+    write_float_slice(out, points, order)
+}
+
+/// Voxel index along one axis. Intermediates are f64 so large coordinates (e.g. UTM) do not
+/// collapse neighboring voxels when subtracting the grid origin.
+#[inline]
+fn voxel_coord(value: f64, min: f32, inv_voxel_size: f64, mode: BoundaryMode) -> i32 {
+    let scaled = (value - min as f64) * inv_voxel_size;
+    match mode {
+        BoundaryMode::Floor => scaled.floor() as i32,
+        // Not f64::round, which sends -0.5 to -1 (ties away from zero)
+        BoundaryMode::Round => (scaled + 0.5).floor() as i32,
+    }
+}
+
+/// 1 / size per axis, in f64 like the rest of the index arithmetic
+fn inverse_voxel_size(voxel_size: VoxelSize) -> [f64; 3] {
+    voxel_size.map(|size| 1.0 / size as f64)
+}
+
+/// Reordering requested by the flags (Morton or sorted), or None to keep map order
+fn output_order<T: LeFloat>(positions: &[T], grid: &VoxelGrid, flags: u32) -> Option<Vec<usize>> {
+    if (flags & FLAG_MORTON) != 0 {
+        Some(morton_order(positions, grid))
+    } else if (flags & FLAG_SORTED) != 0 {
+        Some(voxel_order(positions, grid))
+    } else {
+        None
+    }
+}
+
+/// Output order for FLAG_MORTON: indices of the output points sorted by the Morton code of the
+/// voxel each one lies in, ties broken by position as in voxel_order
+fn morton_order<T: LeFloat>(positions: &[T], grid: &VoxelGrid) -> Vec<usize> {
+    let mut cells = Vec::with_capacity(positions.len());
+    voxel_cells(positions, grid.bounds.min, inverse_voxel_size(grid.voxel_size), grid.boundary_mode, &mut cells);
+    let codes: Vec<u64> = cells.chunks_exact(3).map(|c| morton_encode(morton_axis(c[0]), morton_axis(c[1]), morton_axis(c[2]))).collect();
+    let mut order: Vec<usize> = (0..positions.len() / 3).collect();
+    order.sort_unstable_by(|&a, &b| codes[a].cmp(&codes[b]).then_with(|| position_cmp(positions, a, b)));
+    order
+}
+
+/// Output order for FLAG_SORTED: indices of the output points sorted by the voxel each one lies
+/// in, ties (possible only when a centroid rounds onto a voxel face) broken by position
+fn voxel_order<T: LeFloat>(positions: &[T], grid: &VoxelGrid) -> Vec<usize> {
+    let inv_voxel_size = inverse_voxel_size(grid.voxel_size);
+    let mut cells = Vec::with_capacity(positions.len());
+    voxel_cells(positions, grid.bounds.min, inv_voxel_size, grid.boundary_mode, &mut cells);
+    let mut order: Vec<usize> = (0..positions.len() / 3).collect();
+    order.sort_unstable_by(|&a, &b| {
+        cells[a * 3..a * 3 + 3].cmp(&cells[b * 3..b * 3 + 3]).then_with(|| position_cmp(positions, a, b))
+    });
+    order
+}
+
+/// Total order of points a and b by x, then y, then z
+fn position_cmp<T: LeFloat>(positions: &[T], a: usize, b: usize) -> std::cmp::Ordering {
+    let (pa, pb) = (&positions[a * 3..a * 3 + 3], &positions[b * 3..b * 3 + 3]);
+    (0..3).map(|axis| pa[axis].to_f64().total_cmp(&pb[axis].to_f64())).find(|o| o.is_ne()).unwrap_or(std::cmp::Ordering::Equal)
+}
+
+/// Records of `stride` values taken in `order`; an empty (skipped) attribute stays empty
+fn reorder<V: Copy>(values: &[V], order: &[usize], stride: usize) -> Vec<V> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+    order.iter().flat_map(|&i| values[i * stride..(i + 1) * stride].iter().copied()).collect()
+}
+
+/// Voxel index of every point of a flat xyz slice, appended to `cells` as flat (vx, vy, vz).
+/// With the `simd` feature on x86_64, floor mode runs four points per step on AVX when the CPU
+/// has it; everything else takes the scalar loop. Both produce identical indices.
+fn voxel_cells<T: LeFloat>(points: &[T], min: [f32; 3], inv_voxel_size: [f64; 3], mode: BoundaryMode, cells: &mut Vec<i32>) {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    {
+        if mode == BoundaryMode::Floor && is_x86_feature_detected!("avx") {
+            // SAFETY: AVX support was checked just above
+            unsafe { simd::floor_voxel_cells(points, min, inv_voxel_size, cells) };
+            return;
+        }
+    }
+    voxel_cells_scalar(points, min, inv_voxel_size, mode, cells);
+}
+
+fn voxel_cells_scalar<T: LeFloat>(points: &[T], min: [f32; 3], inv_voxel_size: [f64; 3], mode: BoundaryMode, cells: &mut Vec<i32>) {
+    for p in points.chunks_exact(3) {
+        for axis in 0..3 {
+            cells.push(voxel_coord(p[axis].to_f64(), min[axis], inv_voxel_size[axis], mode));
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd {
+    use std::arch::x86_64::*;
+    use pointcloud_tools_backend::binary_io::LeFloat;
+    use super::{voxel_cells_scalar, BoundaryMode};
+
+    /// floor((p - min) * inv_voxel_size) as i32 for four points (twelve interleaved coordinates,
+    /// three AVX vectors) per step; the remaining points go through the scalar loop.
+    /// Lanes are clamped to the i32 range and NaN lanes zeroed, so the result matches the
+    /// saturating `as i32` of the scalar path exactly.
+    #[target_feature(enable = "avx")]
+    pub(super) unsafe fn floor_voxel_cells<T: LeFloat>(points: &[T], min: [f32; 3], inv_voxel_size: [f64; 3], cells: &mut Vec<i32>) {
+        let m = min.map(|v| v as f64);
+        // Axis pattern of the three vectors: [x y z x] [y z x y] [z x y z]
+        let mins = [
+            _mm256_setr_pd(m[0], m[1], m[2], m[0]),
+            _mm256_setr_pd(m[1], m[2], m[0], m[1]),
+            _mm256_setr_pd(m[2], m[0], m[1], m[2]),
+        ];
+        let v = inv_voxel_size;
+        let invs = [
+            _mm256_setr_pd(v[0], v[1], v[2], v[0]),
+            _mm256_setr_pd(v[1], v[2], v[0], v[1]),
+            _mm256_setr_pd(v[2], v[0], v[1], v[2]),
+        ];
+        let lowest = _mm256_set1_pd(i32::MIN as f64);
+        let highest = _mm256_set1_pd(i32::MAX as f64);
+
+        let mut blocks = points.chunks_exact(12);
+        let mut lanes = [0.0f64; 4];
+        let mut indices = [0i32; 4];
+        for block in &mut blocks {
+            for ((coords, &min_v), &inv) in block.chunks_exact(4).zip(&mins).zip(&invs) {
+                for (lane, c) in lanes.iter_mut().zip(coords) {
+                    *lane = c.to_f64();
+                }
+                let scaled = _mm256_mul_pd(_mm256_sub_pd(_mm256_loadu_pd(lanes.as_ptr()), min_v), inv);
+                let floored = _mm256_floor_pd(scaled);
+                // max_pd returns its second operand for NaN lanes; the ordered mask then zeroes them
+                let ordered = _mm256_cmp_pd::<_CMP_ORD_Q>(floored, floored);
+                let clamped = _mm256_and_pd(_mm256_min_pd(_mm256_max_pd(floored, lowest), highest), ordered);
+                _mm_storeu_si128(indices.as_mut_ptr() as *mut __m128i, _mm256_cvttpd_epi32(clamped));
+                cells.extend_from_slice(&indices);
+            }
+        }
+        voxel_cells_scalar(blocks.remainder(), min, inv_voxel_size, BoundaryMode::Floor, cells);
+    }
+}
+
+/// Index of the input point closest to each occupied voxel's center, tracked during the
+/// insertion pass (ties keep the earlier point); voxels below the point threshold are skipped.
+/// Also returns each kept voxel's point count.
+fn voxel_downsample_nearest<T: LeFloat>(points: &[T], point_count: usize, grid: VoxelGrid, tool_run: &mut ToolRun) -> (Vec<usize>, Vec<u32>) {
+    let voxel_size = grid.voxel_size.map(|size| size as f64);
+    let inv_voxel_size = inverse_voxel_size(grid.voxel_size);
+    let min = grid.bounds.min;
+
+    let estimated_voxels = (point_count / 100).min(100_000);
+    // (closest index, its squared distance to the center, points in the voxel)
+    let mut best: VoxelMap<VoxelKey, (usize, f64, u32)> =
+        VoxelMap::with_capacity_and_hasher(estimated_voxels, Default::default());
+
+    for i in 0..point_count {
+        let i3 = i * 3;
+        if !is_finite_point(&points[i3..i3 + 3]) {
+            continue;
+        }
+        let mut cell = [0i32; 3];
+        let mut distance_squared = 0.0f64;
+        for axis in 0..3 {
+            let value = points[i3 + axis].to_f64();
+            cell[axis] = voxel_coord(value, min[axis], inv_voxel_size[axis], grid.boundary_mode);
+            let center_offset = if grid.boundary_mode == BoundaryMode::Floor { 0.5 } else { 0.0 };
+            let center = min[axis] as f64 + (cell[axis] as f64 + center_offset) * voxel_size[axis];
+            distance_squared += (value - center) * (value - center);
+        }
+        best.entry(cell)
+            .and_modify(|entry| {
+                entry.2 += 1;
+                if distance_squared < entry.1 {
+                    entry.0 = i;
+                    entry.1 = distance_squared;
+                }
+            })
+            .or_insert((i, distance_squared, 1));
+    }
+
+    tool_run.note_map_size(best.len());
+    best.into_values()
+        .filter(|&(_, _, count)| count >= grid.min_points_per_voxel)
+        .map(|(i, _, count)| (i, count))
+        .unzip()
+}
+
+fn voxel_downsample_with_attributes<T: LeFloat>(
+    points: &[T],
+    attributes: AttributeInput,
     point_count: usize,
-    voxel_size: f32,
-    min_x: f32,
-    min_y: f32,
-    min_z: f32,
-) -> (Vec<f32>, Vec<f32>, Vec<f32>, Vec<u8>) {
-    let inv_voxel_size = 1.0 / voxel_size;
+    grid: VoxelGrid,
+    reduction: ReductionMode,
+    tool_run: &mut ToolRun,
+) -> AttributeOutput<T> {
+    let AttributeInput { colors, intensities, classifications } = attributes;
+    let VoxelGrid { voxel_size, bounds, min_points_per_voxel, boundary_mode } = grid;
+    let inv_voxel_size = inverse_voxel_size(voxel_size);
+    let [min_x, min_y, min_z] = bounds.min;
     let use_colors = colors.map(|c| c.len() == point_count * 3).unwrap_or(false);
     let use_intensity = intensities.map(|i| i.len() == point_count).unwrap_or(false);
     let use_classification = classifications.map(|c| c.len() == point_count).unwrap_or(false);
 
     let estimated_voxels = (point_count / 100).max(100).min(100_000);
-    let mut voxel_map: FxHashMap<u64, VoxelFull> =
-        FxHashMap::with_capacity_and_hasher(estimated_voxels, Default::default());
+    let mut voxel_map: VoxelMap<VoxelKey, VoxelFull<T>> =
+        VoxelMap::with_capacity_and_hasher(estimated_voxels, Default::default());
 
     const CHUNK_SIZE: usize = 1024;
     for chunk_start in (0..point_count).step_by(CHUNK_SIZE) {
         let chunk_end = (chunk_start + CHUNK_SIZE).min(point_count);
         for i in chunk_start..chunk_end {
             let i3 = i * 3;
+            if !is_finite_point(&points[i3..i3 + 3]) {
+                continue;
+            }
             let x = points[i3];
             let y = points[i3 + 1];
             let z = points[i3 + 2];
-            let voxel_x = ((x - min_x) * inv_voxel_size).floor() as i32;
-            let voxel_y = ((y - min_y) * inv_voxel_size).floor() as i32;
-            let voxel_z = ((z - min_z) * inv_voxel_size).floor() as i32;
-            let voxel_key = ((voxel_x as u64) << 32) | ((voxel_y as u64) << 16) | (voxel_z as u64);
+            let voxel_x = voxel_coord(x.to_f64(), min_x, inv_voxel_size[0], boundary_mode);
+            let voxel_y = voxel_coord(y.to_f64(), min_y, inv_voxel_size[1], boundary_mode);
+            let voxel_z = voxel_coord(z.to_f64(), min_z, inv_voxel_size[2], boundary_mode);
+            let voxel_key: VoxelKey = [voxel_x, voxel_y, voxel_z];
 
             let (sum_r, sum_g, sum_b) = if use_colors {
                 let c = colors.unwrap();
@@ -218,9 +670,13 @@ fn voxel_downsample_with_attributes(
                     if use_classification {
                         *v.class_counts.entry(class_byte).or_insert(0) += 1;
                     }
+                    if reduction == ReductionMode::MaxIntensity && sum_intensity > v.best_intensity {
+                        v.best_index = i;
+                        v.best_intensity = sum_intensity;
+                    }
                 })
                 .or_insert_with(|| {
-                    let mut class_counts = FxHashMap::default();
+                    let mut class_counts = VoxelMap::default();
                     if use_classification {
                         class_counts.insert(class_byte, 1);
                     }
@@ -234,23 +690,47 @@ fn voxel_downsample_with_attributes(
                         sum_b,
                         sum_intensity,
                         class_counts,
+                        best_index: i,
+                        best_intensity: sum_intensity,
                     }
                 });
         }
     }
 
+    tool_run.note_map_size(voxel_map.len());
+    voxel_map.retain(|_, voxel| voxel.count as u32 >= min_points_per_voxel);
     let output_count = voxel_map.len();
-    let mut downsampled_points = vec![0.0f32; output_count * 3];
+    let mut downsampled_points = vec![T::default(); output_count * 3];
     let mut downsampled_colors = vec![0.0f32; if use_colors { output_count * 3 } else { 0 }];
     let mut downsampled_intensities = vec![0.0f32; if use_intensity { output_count } else { 0 }];
     let mut downsampled_classifications = vec![0u8; if use_classification { output_count } else { 0 }];
+    let mut counts = vec![0u32; output_count];
 
     let mut output_index = 0;
     for (_k, voxel) in voxel_map {
+        if reduction != ReductionMode::Average {
+            // Copy the chosen input point unchanged
+            let (i, o) = (voxel.best_index, output_index);
+            counts[o] = voxel.count as u32;
+            downsampled_points[o * 3..o * 3 + 3].copy_from_slice(&points[i * 3..i * 3 + 3]);
+            if use_colors {
+                downsampled_colors[o * 3..o * 3 + 3].copy_from_slice(&colors.unwrap()[i * 3..i * 3 + 3]);
+            }
+            if use_intensity {
+                downsampled_intensities[o] = intensities.unwrap()[i];
+            }
+            if use_classification {
+                downsampled_classifications[o] = classifications.unwrap()[i];
+            }
+            output_index += 1;
+            continue;
+        }
         let count_f = voxel.count as f32;
-        downsampled_points[output_index * 3] = voxel.sum_x / count_f;
-        downsampled_points[output_index * 3 + 1] = voxel.sum_y / count_f;
-        downsampled_points[output_index * 3 + 2] = voxel.sum_z / count_f;
+        let count_d = voxel.count as f64;
+        counts[output_index] = voxel.count as u32;
+        downsampled_points[output_index * 3] = T::from_f64(voxel.sum_x.to_f64() / count_d);
+        downsampled_points[output_index * 3 + 1] = T::from_f64(voxel.sum_y.to_f64() / count_d);
+        downsampled_points[output_index * 3 + 2] = T::from_f64(voxel.sum_z.to_f64() / count_d);
         if use_colors {
             downsampled_colors[output_index * 3] = voxel.sum_r / count_f;
             downsampled_colors[output_index * 3 + 1] = voxel.sum_g / count_f;
@@ -270,44 +750,142 @@ fn voxel_downsample_with_attributes(
         output_index += 1;
     }
 
-    (downsampled_points, downsampled_colors, downsampled_intensities, downsampled_classifications)
+    (downsampled_points, downsampled_colors, downsampled_intensities, downsampled_classifications, counts)
 }
 
-pub(crate) fn voxel_downsample_internal(
-    points: &[f32],
+/// `voxel_downsample_per_axis` for cubic voxels (the scalar signature the tests were written against)
+#[cfg(test)]
+pub(crate) fn voxel_downsample_internal<T: LeFloat>(
+    points: &[T],
     point_count: usize,
     voxel_size: f32,
-    min_x: f32,
-    min_y: f32,
-    min_z: f32,
-) -> Vec<f32> {
-    // OPTIMIZATION 1: Pre-calculate inverse voxel size to avoid division
-    let inv_voxel_size = 1.0 / voxel_size;
-    
-    // Use FxHashMap for fast integer key hashing with struct for better cache locality
+    bounds: &Bounds,
+    min_points_per_voxel: u32,
+    boundary_mode: BoundaryMode,
+) -> (Vec<T>, Vec<u32>) {
+    voxel_downsample_per_axis(points, point_count, [voxel_size; 3], bounds, min_points_per_voxel, boundary_mode, &mut ToolRun::default())
+}
+
+/// Centroids plus the number of input points behind each one, in the same order; voxels may have
+/// a different size along each axis
+fn voxel_downsample_per_axis<T: LeFloat>(
+    points: &[T],
+    point_count: usize,
+    voxel_size: VoxelSize,
+    bounds: &Bounds,
+    min_points_per_voxel: u32,
+    boundary_mode: BoundaryMode,
+    tool_run: &mut ToolRun,
+) -> (Vec<T>, Vec<u32>) {
+    // Use VoxelMap for fast integer key hashing with struct for better cache locality
     // Pre-allocate with estimated capacity to avoid reallocations
     let estimated_voxels = (point_count / 100).min(100_000);
-    let mut voxel_map: FxHashMap<u64, Voxel> = FxHashMap::with_capacity_and_hasher(estimated_voxels, Default::default());
+    let mut voxel_map: VoxelMap<VoxelKey, Voxel<T>> = VoxelMap::with_capacity_and_hasher(estimated_voxels, Default::default());
+    
+    accumulate_voxels(&mut voxel_map, &points[..point_count * 3], voxel_size, bounds, boundary_mode);
+    collect_voxels(voxel_map, min_points_per_voxel, tool_run)
+}
+
+/// Same result as `voxel_downsample_per_axis`, but the voxel map is allocated at exactly the
+/// number of occupied voxels, learned from a first pass that inserts only the voxel keys into a
+/// VoxelSet. The `point_count / 100` estimate is off by orders of magnitude when the voxel size
+/// is small (many voxels: the map rehashes repeatedly, moving every accumulated entry each time)
+/// or large (few voxels: most of the allocation is never used). The extra pass pays off for
+/// clouds with many more voxels than the estimate; when the estimate is close, it is pure
+/// overhead, so this is opt-in (bit8) rather than the default.
+fn voxel_downsample_exact_capacity<T: LeFloat>(
+    points: &[T],
+    point_count: usize,
+    voxel_size: VoxelSize,
+    bounds: &Bounds,
+    min_points_per_voxel: u32,
+    boundary_mode: BoundaryMode,
+    tool_run: &mut ToolRun,
+) -> (Vec<T>, Vec<u32>) {
+    let points = &points[..point_count * 3];
+    let occupied = occupied_voxel_count(points, voxel_size, bounds, boundary_mode);
+    let mut voxel_map: VoxelMap<VoxelKey, Voxel<T>> = VoxelMap::with_capacity_and_hasher(occupied, Default::default());
+    accumulate_voxels(&mut voxel_map, points, voxel_size, bounds, boundary_mode);
+    collect_voxels(voxel_map, min_points_per_voxel, tool_run)
+}
+
+/// First pass of `voxel_downsample_exact_capacity`: the number of distinct voxels the finite
+/// points fall in, keyed exactly as `accumulate_voxels` keys them
+fn occupied_voxel_count<T: LeFloat>(points: &[T], voxel_size: VoxelSize, bounds: &Bounds, boundary_mode: BoundaryMode) -> usize {
+    let inv_voxel_size = inverse_voxel_size(voxel_size);
+    let mut keys: VoxelSet<VoxelKey> = VoxelSet::default();
+    let mut cells: Vec<i32> = Vec::new();
+    for chunk in points.chunks(1024 * 3) {
+        cells.clear();
+        voxel_cells(chunk, bounds.min, inv_voxel_size, boundary_mode, &mut cells);
+        keys.extend(
+            cells
+                .chunks_exact(3)
+                .zip(chunk.chunks_exact(3))
+                .filter(|(_, p)| is_finite_point(p))
+                .map(|(c, _)| [c[0], c[1], c[2]]),
+        );
+    }
+    keys.len()
+}
+
+/// Same result as `voxel_downsample_per_axis` over every position in `reader`, read
+/// `chunk_points` points at a time so only one chunk is in memory next to the voxel map.
+/// Also returns the number of points read. Fails if the input ends inside a point.
+fn voxel_downsample_streaming<T: LeFloat, R: Read>(
+    reader: &mut R,
+    chunk_points: usize,
+    grid: VoxelGrid,
+    tool_run: &mut ToolRun,
+) -> io::Result<(Vec<T>, Vec<u32>, usize)> {
+    let mut voxel_map: VoxelMap<VoxelKey, Voxel<T>> = VoxelMap::default();
+    let mut chunk: Vec<T> = Vec::new();
+    let mut point_count = 0;
+    while read_float_chunk(reader, chunk_points * 3, &mut chunk, tool_run.order)? > 0 {
+        if !chunk.len().is_multiple_of(3) {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "input ends inside a point"));
+        }
+        point_count += chunk.len() / 3;
+        accumulate_voxels(&mut voxel_map, &chunk, grid.voxel_size, &grid.bounds, grid.boundary_mode);
+    }
+    let (points, counts) = collect_voxels(voxel_map, grid.min_points_per_voxel, tool_run);
+    Ok((points, counts, point_count))
+}
+
+/// Add every point of a flat xyz slice to its voxel's count and position sums
+fn accumulate_voxels<T: LeFloat>(
+    voxel_map: &mut VoxelMap<VoxelKey, Voxel<T>>,
+    points: &[T],
+    voxel_size: VoxelSize,
+    bounds: &Bounds,
+    boundary_mode: BoundaryMode,
+) {
+    // OPTIMIZATION 1: Pre-calculate inverse voxel size to avoid division
+    let inv_voxel_size = inverse_voxel_size(voxel_size);
+    let point_count = points.len() / 3;
     
     // OPTIMIZATION 3: Process points in chunks for better cache locality
     const CHUNK_SIZE: usize = 1024;
+    let mut cells: Vec<i32> = Vec::with_capacity(CHUNK_SIZE * 3);
     
     for chunk_start in (0..point_count).step_by(CHUNK_SIZE) {
         let chunk_end = (chunk_start + CHUNK_SIZE).min(point_count);
         
-        for i in chunk_start..chunk_end {
+        // OPTIMIZATION 4: Voxel indices for the whole chunk at once (vectorized with the simd feature)
+        cells.clear();
+        voxel_cells(&points[chunk_start * 3..chunk_end * 3], bounds.min, inv_voxel_size, boundary_mode, &mut cells);
+        
+        for (i, cell) in (chunk_start..chunk_end).zip(cells.chunks_exact(3)) {
             let i3 = i * 3;
+            if !is_finite_point(&points[i3..i3 + 3]) {
+                continue;
+            }
             let x = points[i3];
             let y = points[i3 + 1];
             let z = points[i3 + 2];
                 
-            // OPTIMIZATION 4: Use multiplication instead of division
-            let voxel_x = ((x - min_x) * inv_voxel_size).floor() as i32;
-            let voxel_y = ((y - min_y) * inv_voxel_size).floor() as i32;
-            let voxel_z = ((z - min_z) * inv_voxel_size).floor() as i32;
-                
-            // OPTIMIZATION 5: Use integer hash key
-            let voxel_key = ((voxel_x as u64) << 32) | ((voxel_y as u64) << 16) | (voxel_z as u64);
+            // OPTIMIZATION 5: Key by the full voxel index (see VoxelKey)
+            let voxel_key: VoxelKey = [cell[0], cell[1], cell[2]];
                 
             // OPTIMIZATION 6: Use entry() API (like C++ try_emplace) - single hash lookup
             // Use struct for better cache locality (matches WASM implementation)
@@ -324,33 +902,118 @@ pub(crate) fn voxel_downsample_internal(
             });
         }
     }
+}
+
+/// `voxel_downsample_per_axis` plus the RMS distance of each voxel's points from its centroid.
+/// Both paths key voxels by VoxelKey and sum points in input order, so centroids and counts are
+/// identical to the plain path.
+fn voxel_downsample_with_spread<T: LeFloat>(points: &[T], point_count: usize, grid: VoxelGrid, tool_run: &mut ToolRun) -> (Vec<T>, Vec<u32>, Vec<f32>) {
+    let points = &points[..point_count * 3];
+    let inv_voxel_size = inverse_voxel_size(grid.voxel_size);
+    let min = grid.bounds.min;
+    let mut voxel_map: VoxelMap<VoxelKey, VoxelMoments<T>> = VoxelMap::default();
+    let mut cells: Vec<i32> = Vec::new();
+    for chunk in points.chunks(1024 * 3) {
+        cells.clear();
+        voxel_cells(chunk, min, inv_voxel_size, grid.boundary_mode, &mut cells);
+        for (p, cell) in chunk.chunks_exact(3).zip(cells.chunks_exact(3)) {
+            if !is_finite_point(p) {
+                continue;
+            }
+            let entry = voxel_map.entry([cell[0], cell[1], cell[2]]).or_insert(VoxelMoments {
+                voxel: Voxel { count: 0, sum_x: T::default(), sum_y: T::default(), sum_z: T::default() },
+                sum: [0.0; 3],
+                sum_squares: [0.0; 3],
+            });
+            entry.voxel.count += 1;
+            entry.voxel.sum_x += p[0];
+            entry.voxel.sum_y += p[1];
+            entry.voxel.sum_z += p[2];
+            for axis in 0..3 {
+                let offset = p[axis].to_f64() - min[axis] as f64;
+                entry.sum[axis] += offset;
+                entry.sum_squares[axis] += offset * offset;
+            }
+        }
+    }
+
+    tool_run.note_map_size(voxel_map.len());
+    voxel_map.retain(|_, moments| moments.voxel.count as u32 >= grid.min_points_per_voxel);
+    let mut downsampled_points = Vec::with_capacity(voxel_map.len() * 3);
+    let mut counts = Vec::with_capacity(voxel_map.len());
+    let mut spread = Vec::with_capacity(voxel_map.len());
+    for moments in voxel_map.into_values() {
+        let voxel = moments.voxel;
+        let count_d = voxel.count as f64;
+        downsampled_points.extend_from_slice(&[voxel.sum_x, voxel.sum_y, voxel.sum_z].map(|sum| T::from_f64(sum.to_f64() / count_d)));
+        counts.push(voxel.count as u32);
+        // Variance per axis is E[x^2] - E[x]^2; rounding can take it slightly below zero
+        let variance: f64 = (0..3)
+            .map(|axis| (moments.sum_squares[axis] / count_d - (moments.sum[axis] / count_d).powi(2)).max(0.0))
+            .sum();
+        spread.push(variance.sqrt() as f32);
+    }
+    (downsampled_points, counts, spread)
+}
+
+/// Centroids and counts of the voxels holding at least `min_points_per_voxel` points
+fn collect_voxels<T: LeFloat>(mut voxel_map: VoxelMap<VoxelKey, Voxel<T>>, min_points_per_voxel: u32, tool_run: &mut ToolRun) -> (Vec<T>, Vec<u32>) {
+    tool_run.note_map_size(voxel_map.len());
+    // Drop sparse voxels (a threshold of 0 or 1 keeps all of them)
+    voxel_map.retain(|_, voxel| voxel.count as u32 >= min_points_per_voxel);
     
     // Pre-allocate output vector and write directly using indexing for efficiency
     // Use direct indexing instead of push() for better performance (like C++ does)
     let output_count = voxel_map.len();
-    let mut downsampled_points = vec![0.0f32; output_count * 3];
+    let mut downsampled_points = vec![T::default(); output_count * 3];
+    let mut counts = vec![0u32; output_count];
     
     // Write results directly to pre-allocated vector using indexing (faster than push)
     let mut output_index = 0;
     for (_voxel_key, voxel) in voxel_map {
-        let count_f = voxel.count as f32;
-        downsampled_points[output_index * 3] = voxel.sum_x / count_f;
-        downsampled_points[output_index * 3 + 1] = voxel.sum_y / count_f;
-        downsampled_points[output_index * 3 + 2] = voxel.sum_z / count_f;
+        let count_d = voxel.count as f64;
+        counts[output_index] = voxel.count as u32;
+        downsampled_points[output_index * 3] = T::from_f64(voxel.sum_x.to_f64() / count_d);
+        downsampled_points[output_index * 3 + 1] = T::from_f64(voxel.sum_y.to_f64() / count_d);
+        downsampled_points[output_index * 3 + 2] = T::from_f64(voxel.sum_z.to_f64() / count_d);
         output_index += 1;
     }
     
-    downsampled_points
+    (downsampled_points, counts)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Bounds for a grid anchored at `min`; only the minimum positions the voxels
+    fn anchored(min: [f32; 3]) -> Bounds {
+        Bounds { min, max: min }
+    }
+
+    /// Attribute arrays for voxel_downsample_with_attributes
+    fn attributes<'a>(colors: Option<&'a Vec<f32>>, intensities: Option<&'a Vec<f32>>, classifications: Option<&'a Vec<u8>>) -> AttributeInput<'a> {
+        AttributeInput { colors, intensities, classifications }
+    }
+
+    /// Floor-mode grid of `voxel_size` over `bounds`
+    fn floor_grid(voxel_size: VoxelSize, bounds: Bounds, min_points_per_voxel: u32) -> VoxelGrid {
+        VoxelGrid { voxel_size, bounds, min_points_per_voxel, boundary_mode: BoundaryMode::Floor }
+    }
+
+    /// (centroid, count) pairs sorted by centroid: voxel order follows the hash map, which
+    /// depends on its capacity history
+    fn sorted_voxels((positions, counts): (Vec<f32>, Vec<u32>)) -> Vec<([f32; 3], u32)> {
+        let mut voxels: Vec<([f32; 3], u32)> =
+            positions.chunks_exact(3).map(|p| [p[0], p[1], p[2]]).zip(counts).collect();
+        voxels.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        voxels
+    }
+
     #[test]
     fn test_voxel_downsample_simple() {
         // Simple test: 4 points forming a square, should downsample to 1 point
-        let points = vec![
+        let points: Vec<f32> = vec![
             0.0, 0.0, 0.0,  // Point 1
             1.0, 0.0, 0.0,  // Point 2
             0.0, 1.0, 0.0,  // Point 3
@@ -358,11 +1021,9 @@ mod tests {
         ];
         let point_count = 4;
         let voxel_size = 2.0; // Large enough to contain all points
-        let min_x = 0.0;
-        let min_y = 0.0;
-        let min_z = 0.0;
+        let bounds = Bounds::from_points(&points).unwrap();
 
-        let result = voxel_downsample_internal(&points, point_count, voxel_size, min_x, min_y, min_z);
+        let (result, _) = voxel_downsample_internal(&points, point_count, voxel_size, &bounds, 1, BoundaryMode::Floor);
 
         // Should produce 1 voxel (all points in same voxel)
         assert_eq!(result.len(), 3);
@@ -374,15 +1035,15 @@ mod tests {
 
     #[test]
     fn test_voxel_downsample_empty() {
-        let points = vec![];
-        let result = voxel_downsample_internal(&points, 0, 1.0, 0.0, 0.0, 0.0);
+        let points: Vec<f32> = vec![];
+        let (result, _) = voxel_downsample_internal(&points, 0, 1.0, &anchored([0.0, 0.0, 0.0]), 1, BoundaryMode::Floor);
         assert_eq!(result.len(), 0);
     }
 
     #[test]
     fn test_voxel_downsample_single_point() {
-        let points = vec![1.0, 2.0, 3.0];
-        let result = voxel_downsample_internal(&points, 1, 1.0, 0.0, 0.0, 0.0);
+        let points: Vec<f32> = vec![1.0, 2.0, 3.0];
+        let (result, _) = voxel_downsample_internal(&points, 1, 1.0, &anchored([0.0, 0.0, 0.0]), 1, BoundaryMode::Floor);
         assert_eq!(result.len(), 3);
         assert!((result[0] - 1.0).abs() < 0.001);
         assert!((result[1] - 2.0).abs() < 0.001);
@@ -392,12 +1053,466 @@ mod tests {
     #[test]
     fn test_voxel_downsample_separate_voxels() {
         // Two points in separate voxels
-        let points = vec![
+        let points: Vec<f32> = vec![
             0.0, 0.0, 0.0,  // Voxel (0,0,0)
             2.0, 0.0, 0.0,  // Voxel (2,0,0) - different voxel
         ];
-        let result = voxel_downsample_internal(&points, 2, 1.0, 0.0, 0.0, 0.0);
+        let (result, _) = voxel_downsample_internal(&points, 2, 1.0, &anchored([0.0, 0.0, 0.0]), 1, BoundaryMode::Floor);
         // Should produce 2 voxels
         assert_eq!(result.len(), 6);
     }
+
+    #[test]
+    fn test_f64_positions_keep_voxels_that_f32_merges() {
+        // Two points 0.15 apart near UTM northing 5,000,000; f32 spacing there is 0.5
+        let points: Vec<f64> = vec![
+            5_000_000.05, 0.0, 0.0,
+            5_000_000.20, 0.0, 0.0,
+        ];
+        let points_f32: Vec<f32> = points.iter().map(|&v| v as f32).collect();
+        let min_x = 5_000_000.0;
+
+        let (merged, _) = voxel_downsample_internal(&points_f32, 2, 0.1, &anchored([min_x, 0.0, 0.0]), 1, BoundaryMode::Floor);
+        assert_eq!(merged.len(), 3);
+
+        let (separate, _) = voxel_downsample_internal(&points, 2, 0.1, &anchored([min_x, 0.0, 0.0]), 1, BoundaryMode::Floor);
+        assert_eq!(separate.len(), 6);
+        let mut xs = [separate[0], separate[3]];
+        xs.sort_by(f64::total_cmp);
+        assert!((xs[0] - 5_000_000.05).abs() < 1e-6);
+        assert!((xs[1] - 5_000_000.20).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_nearest_keeps_input_points_closest_to_centers() {
+        let mut points: Vec<f32> = Vec::new();
+        for i in 0..200 {
+            let t = i as f32;
+            points.extend_from_slice(&[(t * 0.37).sin() * 2.0, (t * 0.71).cos() * 2.0, (t * 0.13).sin()]);
+        }
+        let grid = VoxelGrid { voxel_size: [0.5; 3], bounds: anchored([-2.0, -2.0, -1.0]), min_points_per_voxel: 1, boundary_mode: BoundaryMode::Floor };
+        let (kept, _) = voxel_downsample_nearest(&points, 200, grid, &mut ToolRun::default());
+
+        // One point per occupied voxel, and every kept point is an input point
+        assert_eq!(kept.len() * 3, voxel_downsample_internal(&points, 200, 0.5, &anchored([-2.0, -2.0, -1.0]), 1, BoundaryMode::Floor).0.len());
+        let cell_of = |i: usize| -> [i32; 3] {
+            let min = [-2.0f32, -2.0, -1.0];
+            [0, 1, 2].map(|a| ((points[i * 3 + a] - min[a]) / 0.5).floor() as i32)
+        };
+        let center_distance = |i: usize| -> f32 {
+            let c = cell_of(i);
+            let min = [-2.0f32, -2.0, -1.0];
+            (0..3).map(|a| (points[i * 3 + a] - (min[a] + (c[a] as f32 + 0.5) * 0.5)).powi(2)).sum()
+        };
+        for &k in &kept {
+            for j in 0..200 {
+                if cell_of(j) == cell_of(k) {
+                    assert!(center_distance(k) <= center_distance(j) + 1e-6);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_voxels_a_16_bit_field_apart_or_negative_stay_separate() {
+        // (1, 0, 0) and (0, 65536, 0), and (0, -1, 0) and (-1, -1, 0), shared a key when the
+        // indices were packed into one u64 with 16 bits for y and z
+        let points: Vec<f32> = vec![1.5, 0.5, 0.5, 0.5, 65536.5, 0.5, 0.5, -0.5, 0.5, -0.5, -0.5, 0.5];
+        let expected = vec![([-0.5, -0.5, 0.5], 1), ([0.5, -0.5, 0.5], 1), ([0.5, 65536.5, 0.5], 1), ([1.5, 0.5, 0.5], 1)];
+        let plain = voxel_downsample_internal(&points, 4, 1.0, &anchored([0.0; 3]), 1, BoundaryMode::Floor);
+        assert_eq!(sorted_voxels(plain), expected);
+
+        let (positions, _, _, _, counts) =
+            voxel_downsample_with_attributes(&points, attributes(None, None, None), 4, floor_grid([1.0; 3], anchored([0.0; 3]), 1), ReductionMode::Average, &mut ToolRun::default());
+        assert_eq!(sorted_voxels((positions, counts)), expected);
+    }
+
+    #[test]
+    fn test_min_points_per_voxel_drops_sparse_voxels() {
+        // Four points in voxel (0,0,0) and a lone point in voxel (5,0,0)
+        let points: Vec<f32> = vec![
+            0.1, 0.1, 0.1,
+            0.2, 0.3, 0.1,
+            0.4, 0.2, 0.3,
+            0.3, 0.4, 0.2,
+            5.5, 0.5, 0.5,
+        ];
+        let (kept_all, _) = voxel_downsample_internal(&points, 5, 1.0, &anchored([0.0, 0.0, 0.0]), 1, BoundaryMode::Floor);
+        assert_eq!(kept_all.len(), 6);
+
+        let (dense_only, _) = voxel_downsample_internal(&points, 5, 1.0, &anchored([0.0, 0.0, 0.0]), 2, BoundaryMode::Floor);
+        assert_eq!(dense_only.len(), 3);
+        assert!((dense_only[0] - 0.25).abs() < 1e-6);
+
+        let grid = VoxelGrid { voxel_size: [1.0; 3], bounds: anchored([0.0, 0.0, 0.0]), min_points_per_voxel: 2, boundary_mode: BoundaryMode::Floor };
+        let (nearest, _) = voxel_downsample_nearest(&points, 5, grid, &mut ToolRun::default());
+        assert_eq!(nearest.len(), 1);
+        assert!(nearest[0] < 4);
+
+        let (attr_points, _, _, classes, _) =
+            voxel_downsample_with_attributes(&points, attributes(None, None, Some(&vec![1, 1, 2, 1, 6])), 5, floor_grid([1.0; 3], anchored([0.0, 0.0, 0.0]), 2), ReductionMode::Average, &mut ToolRun::default());
+        assert_eq!(attr_points.len(), 3);
+        assert_eq!(classes, vec![1]);
+    }
+
+    #[test]
+    fn test_counts_sum_to_input_point_count() {
+        let mut points: Vec<f32> = Vec::new();
+        for i in 0..500 {
+            let t = i as f32;
+            points.extend_from_slice(&[(t * 0.37).sin() * 3.0, (t * 0.11).cos() * 3.0, t * 0.004]);
+        }
+        let intensities: Vec<f32> = (0..500).map(|i| i as f32).collect();
+
+        let (positions, counts) = voxel_downsample_internal(&points, 500, 0.4, &anchored([-3.0, -3.0, 0.0]), 1, BoundaryMode::Floor);
+        assert_eq!(counts.len() * 3, positions.len());
+        assert_eq!(counts.iter().sum::<u32>(), 500);
+
+        let (_, _, _, _, counts) =
+            voxel_downsample_with_attributes(&points, attributes(None, Some(&intensities), None), 500, floor_grid([0.4; 3], anchored([-3.0, -3.0, 0.0]), 1), ReductionMode::Average, &mut ToolRun::default());
+        assert_eq!(counts.iter().sum::<u32>(), 500);
+
+        let grid = VoxelGrid { voxel_size: [0.4; 3], bounds: anchored([-3.0, -3.0, 0.0]), min_points_per_voxel: 1, boundary_mode: BoundaryMode::Floor };
+        let (kept, counts) = voxel_downsample_nearest(&points, 500, grid, &mut ToolRun::default());
+        assert_eq!(kept.len(), counts.len());
+        assert_eq!(counts.iter().sum::<u32>(), 500);
+    }
+
+    #[test]
+    fn test_round_boundary_mode_matches_open3d_convention() {
+        // x on and around voxel boundaries (size 1, origin 0); y and z stay inside voxel 0 / 0
+        let points: Vec<f32> = [0.4f32, 1.0, 1.5, 1.6].iter().flat_map(|&x| [x, 0.2, 0.2]).collect();
+        let sorted_xs = |positions: &[f32]| {
+            let mut xs: Vec<f32> = positions.chunks_exact(3).map(|p| p[0]).collect();
+            xs.sort_by(f32::total_cmp);
+            xs
+        };
+
+        // floor: {0.4} and {1.0, 1.5, 1.6}
+        let (floor, floor_counts) = voxel_downsample_internal(&points, 4, 1.0, &anchored([0.0, 0.0, 0.0]), 1, BoundaryMode::Floor);
+        assert_eq!(sorted_xs(&floor), vec![0.4, (1.0 + 1.5 + 1.6) / 3.0]);
+        assert_eq!(floor_counts.iter().sum::<u32>(), 4);
+
+        // round: {0.4}, {1.0} and {1.5, 1.6} (1.5 rounds up into the next voxel)
+        let (round, round_counts) = voxel_downsample_internal(&points, 4, 1.0, &anchored([0.0, 0.0, 0.0]), 1, BoundaryMode::Round);
+        assert_eq!(sorted_xs(&round), vec![0.4, 1.0, (1.5 + 1.6) / 2.0]);
+        assert_eq!(round_counts.iter().sum::<u32>(), 4);
+
+        // Nearest-to-center uses the matching centers: 1.0 and 2.0 in round mode
+        let grid = VoxelGrid {
+            voxel_size: [1.0; 3],
+            bounds: anchored([0.0, 0.0, 0.0]),
+            min_points_per_voxel: 1,
+            boundary_mode: BoundaryMode::Round,
+        };
+        let (mut kept, _) = voxel_downsample_nearest(&points, 4, grid, &mut ToolRun::default());
+        kept.sort();
+        assert_eq!(kept, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn test_round_mode_sends_ties_up_on_both_sides_of_the_origin() {
+        for (value, floor, round) in [(0.5, 0, 1), (-0.5, -1, 0), (-1.5, -2, -1), (-0.6, -1, -1), (2.49, 2, 2)] {
+            assert_eq!(voxel_coord(value, 0.0, 1.0, BoundaryMode::Floor), floor, "floor of {}", value);
+            assert_eq!(voxel_coord(value, 0.0, 1.0, BoundaryMode::Round), round, "round of {}", value);
+        }
+
+        // -0.5 joins 0.4 in voxel 0 instead of getting a voxel of its own
+        let points = [-0.5f32, 0.2, 0.2, 0.4, 0.2, 0.2];
+        let (_, counts) = voxel_downsample_internal(&points, 2, 1.0, &anchored([0.0, 0.0, 0.0]), 1, BoundaryMode::Round);
+        assert_eq!(counts, vec![2]);
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[test]
+    fn test_simd_voxel_cells_match_scalar() {
+        use pointcloud_tools_backend::rng::Pcg32;
+
+        // 1001 points: not a multiple of four, so the scalar tail runs too
+        let mut rng = Pcg32::new(7);
+        let mut points: Vec<f32> = (0..1001 * 3).map(|_| rng.next_f32() * 200.0 - 100.0).collect();
+        // Exact voxel boundaries, far out-of-range values and NaN
+        points[..12].copy_from_slice(&[0.0, 0.25, -0.25, 1e12, -1e12, f32::NAN, f32::INFINITY, 5.0, -5.0, 2.5, 2.5, 2.5]);
+        let min = [-100.0, -100.0, -100.0];
+
+        let mut scalar = Vec::new();
+        voxel_cells_scalar(&points, min, [4.0; 3], BoundaryMode::Floor, &mut scalar);
+        let mut vectorized = Vec::new();
+        voxel_cells(&points, min, [4.0; 3], BoundaryMode::Floor, &mut vectorized);
+        assert_eq!(vectorized, scalar);
+
+        let points_f64: Vec<f64> = points.iter().map(|&v| v as f64 * 1.001).collect();
+        scalar.clear();
+        vectorized.clear();
+        // Different inverse size per axis, so each lane must pick up its own axis
+        voxel_cells_scalar(&points_f64, min, [0.37, 2.0, 0.05], BoundaryMode::Floor, &mut scalar);
+        voxel_cells(&points_f64, min, [0.37, 2.0, 0.05], BoundaryMode::Floor, &mut vectorized);
+        assert_eq!(vectorized, scalar);
+    }
+
+    #[test]
+    fn test_streaming_chunks_match_single_block() {
+        let mut points: Vec<f32> = Vec::new();
+        for i in 0..2500 {
+            let t = i as f32;
+            points.extend_from_slice(&[(t * 0.37).sin() * 3.0, (t * 0.11).cos() * 3.0, t * 0.001]);
+        }
+        let bytes: Vec<u8> = points.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let grid = VoxelGrid { voxel_size: [0.3; 3], bounds: anchored([-3.0, -3.0, 0.0]), min_points_per_voxel: 2, boundary_mode: BoundaryMode::Floor };
+
+        let block = sorted_voxels(voxel_downsample_internal(&points, 2500, 0.3, &grid.bounds, 2, BoundaryMode::Floor));
+        // 2500 points: 333-point chunks leave a short last chunk
+        for chunk_points in [2500, 1000, 333, 1] {
+            let (points, counts, read) = voxel_downsample_streaming::<f32, _>(&mut bytes.as_slice(), chunk_points, grid, &mut ToolRun::default()).unwrap();
+            assert_eq!(read, 2500);
+            assert_eq!(sorted_voxels((points, counts)), block);
+        }
+
+        // Input ending inside a point is an error
+        assert!(voxel_downsample_streaming::<f32, _>(&mut &bytes[..bytes.len() - 4], 1000, grid, &mut ToolRun::default()).is_err());
+    }
+
+    #[test]
+    fn test_exact_capacity_matches_estimated_capacity() {
+        let mut points: Vec<f32> = Vec::new();
+        for i in 0..3000 {
+            let t = i as f32;
+            points.extend_from_slice(&[(t * 0.37).sin() * 3.0, (t * 0.11).cos() * 3.0, t * 0.001]);
+        }
+        let bounds = Bounds::from_points(&points).unwrap();
+        // Small voxels (far more voxels than the estimate) and large ones (far fewer)
+        for (voxel_size, mode) in [(0.05, BoundaryMode::Floor), (2.0, BoundaryMode::Floor), (0.3, BoundaryMode::Round)] {
+            let estimated = voxel_downsample_internal(&points, 3000, voxel_size, &bounds, 1, mode);
+            let exact = voxel_downsample_exact_capacity(&points, 3000, [voxel_size; 3], &bounds, 1, mode, &mut ToolRun::default());
+            assert_eq!(sorted_voxels(exact), sorted_voxels(estimated));
+        }
+    }
+
+    #[test]
+    fn test_exact_capacity_counts_voxels_a_16_bit_field_apart() {
+        // (1, 0, 0) and (0, 65536, 0), and (0, -1, 0) and (-1, -1, 0), shared a packed u64 key,
+        // so the first pass counted 2 voxels for these 4
+        let points: Vec<f32> = vec![1.5, 0.5, 0.5, 0.5, 65536.5, 0.5, 0.5, -0.5, 0.5, -0.5, -0.5, 0.5];
+        assert_eq!(occupied_voxel_count(&points, [1.0; 3], &anchored([0.0; 3]), BoundaryMode::Floor), 4);
+        let exact = voxel_downsample_exact_capacity(&points, 4, [1.0; 3], &anchored([0.0; 3]), 1, BoundaryMode::Floor, &mut ToolRun::default());
+        assert_eq!(exact.1.len(), 4);
+    }
+
+    #[test]
+    fn test_sorted_output_is_byte_identical() {
+        let mut points: Vec<f32> = Vec::new();
+        for i in 0..4000 {
+            let t = i as f32;
+            points.extend_from_slice(&[(t * 0.37).sin() * 3.0, (t * 0.11).cos() * 3.0, t * 0.001]);
+        }
+        let bounds = Bounds::from_points(&points).unwrap();
+        let grid = VoxelGrid { voxel_size: [0.2; 3], bounds, min_points_per_voxel: 1, boundary_mode: BoundaryMode::Floor };
+        let sorted_bytes = |(positions, counts): (Vec<f32>, Vec<u32>)| {
+            let order = voxel_order(&positions, &grid);
+            let mut bytes = Vec::new();
+            write_float_slice(&mut bytes, &reorder(&positions, &order, 3), ByteOrder::Little).unwrap();
+            write_u32_slice(&mut bytes, &reorder(&counts, &order, 1), ByteOrder::Little).unwrap();
+            bytes
+        };
+
+        let first = sorted_bytes(voxel_downsample_internal(&points, 4000, 0.2, &bounds, 1, BoundaryMode::Floor));
+        let second = sorted_bytes(voxel_downsample_internal(&points, 4000, 0.2, &bounds, 1, BoundaryMode::Floor));
+        assert_eq!(first, second);
+        // A differently sized map iterates in a different order but sorts to the same bytes
+        let exact = sorted_bytes(voxel_downsample_exact_capacity(&points, 4000, [0.2; 3], &bounds, 1, BoundaryMode::Floor, &mut ToolRun::default()));
+        assert_eq!(first, exact);
+
+        // Sorted by voxel index
+        let (positions, _) = voxel_downsample_internal(&points, 4000, 0.2, &bounds, 1, BoundaryMode::Floor);
+        let order = voxel_order(&positions, &grid);
+        let cells: Vec<[i32; 3]> = order
+            .iter()
+            .map(|&i| [0, 1, 2].map(|a| voxel_coord(positions[i * 3 + a] as f64, bounds.min[a], 1.0 / 0.2f32 as f64, BoundaryMode::Floor)))
+            .collect();
+        assert!(cells.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_non_finite_points_are_excluded() {
+        let finite: Vec<f32> = vec![0.1, 0.1, 0.1, 0.3, 0.2, 0.1, 1.5, 0.5, 0.5];
+        let mut points = finite.clone();
+        points.extend_from_slice(&[f32::NAN, 0.2, 0.2, 0.4, f32::INFINITY, 0.4, 0.6, 0.6, f32::NEG_INFINITY]);
+        let bounds = Bounds::from_points(&points).unwrap();
+        assert_eq!(bounds, Bounds::from_points(&finite).unwrap());
+
+        let expected = sorted_voxels(voxel_downsample_internal(&finite, 3, 1.0, &bounds, 1, BoundaryMode::Floor));
+        let (positions, counts) = voxel_downsample_internal(&points, 6, 1.0, &bounds, 1, BoundaryMode::Floor);
+        assert!(positions.iter().all(|v| v.is_finite()));
+        assert_eq!(counts.iter().sum::<u32>(), 3);
+        assert_eq!(sorted_voxels((positions, counts)), expected);
+        assert_eq!(
+            sorted_voxels(voxel_downsample_exact_capacity(&points, 6, [1.0; 3], &bounds, 1, BoundaryMode::Floor, &mut ToolRun::default())),
+            expected
+        );
+
+        let grid = VoxelGrid { voxel_size: [1.0; 3], bounds, min_points_per_voxel: 1, boundary_mode: BoundaryMode::Floor };
+        let (mut kept, _) = voxel_downsample_nearest(&points, 6, grid, &mut ToolRun::default());
+        kept.sort();
+        // Point 1 is closer than point 0 to the center (0.6, 0.6, 0.6) of their voxel
+        assert_eq!(kept, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_max_intensity_keeps_the_strongest_return_per_voxel() {
+        let mut points: Vec<f32> = Vec::new();
+        let mut intensities: Vec<f32> = Vec::new();
+        let mut colors: Vec<f32> = Vec::new();
+        for i in 0..400 {
+            let t = i as f32;
+            points.extend_from_slice(&[(t * 0.31).sin() * 2.0, (t * 0.17).cos() * 2.0, (t * 0.07).sin()]);
+            intensities.push(((t * 1.3).sin() * 1000.0).abs());
+            colors.extend_from_slice(&[t, t + 0.25, t + 0.5]);
+        }
+        let bounds = Bounds::from_points(&points).unwrap();
+        let (positions, out_colors, out_intensities, _, counts) = voxel_downsample_with_attributes(
+            &points, attributes(Some(&colors), Some(&intensities), None), 400, floor_grid([0.5; 3], bounds, 1), ReductionMode::MaxIntensity,
+            &mut ToolRun::default(),
+        );
+        assert_eq!(counts.iter().sum::<u32>(), 400);
+
+        let cell_of = |p: &[f32]| [0, 1, 2].map(|a| voxel_coord(p[a] as f64, bounds.min[a], 1.0 / 0.5f32 as f64, BoundaryMode::Floor));
+        for (o, p) in positions.chunks_exact(3).enumerate() {
+            // The output is an input point carried over whole, with the highest intensity in its voxel
+            let i = (0..400).find(|&i| points[i * 3..i * 3 + 3] == *p).unwrap();
+            assert_eq!(out_intensities[o], intensities[i]);
+            assert_eq!(out_colors[o * 3..o * 3 + 3], colors[i * 3..i * 3 + 3]);
+            let strongest = (0..400)
+                .filter(|&j| cell_of(&points[j * 3..j * 3 + 3]) == cell_of(p))
+                .map(|j| intensities[j])
+                .fold(f32::MIN, f32::max);
+            assert_eq!(out_intensities[o], strongest);
+        }
+
+        let (first, _, _, _, _) =
+            voxel_downsample_with_attributes(&points, attributes(None, Some(&intensities), None), 400, floor_grid([0.5; 3], bounds, 1), ReductionMode::FirstPoint, &mut ToolRun::default());
+        for p in first.chunks_exact(3) {
+            let i = (0..400).find(|&i| points[i * 3..i * 3 + 3] == *p).unwrap();
+            assert!((0..i).all(|j| cell_of(&points[j * 3..j * 3 + 3]) != cell_of(p)));
+        }
+    }
+
+    #[test]
+    fn test_per_axis_voxel_size_merges_along_one_axis_only() {
+        // Coarse in x and y, fine in z: a step of 0.5 merges along x but not along z
+        let points: Vec<f32> = vec![
+            0.125, 0.125, 0.125,
+            0.625, 0.125, 0.125,
+            0.125, 0.125, 0.625,
+            0.625, 0.125, 0.625,
+        ];
+        let bounds = anchored([0.0, 0.0, 0.0]);
+        let (positions, counts) = voxel_downsample_per_axis(&points, 4, [1.0, 1.0, 0.25], &bounds, 1, BoundaryMode::Floor, &mut ToolRun::default());
+        assert_eq!(sorted_voxels((positions, counts)), vec![([0.375, 0.125, 0.125], 2), ([0.375, 0.125, 0.625], 2)]);
+
+        // Swapping the sizes of x and z flips which pairs merge
+        let (positions, counts) = voxel_downsample_per_axis(&points, 4, [0.25, 1.0, 1.0], &bounds, 1, BoundaryMode::Floor, &mut ToolRun::default());
+        assert_eq!(sorted_voxels((positions, counts)), vec![([0.125, 0.125, 0.375], 2), ([0.625, 0.125, 0.375], 2)]);
+
+        // Every path agrees on the anisotropic grid
+        let grid = VoxelGrid { voxel_size: [1.0, 1.0, 0.25], bounds, min_points_per_voxel: 1, boundary_mode: BoundaryMode::Floor };
+        assert_eq!(voxel_downsample_nearest(&points, 4, grid, &mut ToolRun::default()).0.len(), 2);
+        assert_eq!(voxel_downsample_exact_capacity(&points, 4, grid.voxel_size, &bounds, 1, BoundaryMode::Floor, &mut ToolRun::default()).1, vec![2, 2]);
+        let (attr_points, ..) =
+            voxel_downsample_with_attributes(&points, attributes(None, None, None), 4, floor_grid(grid.voxel_size, bounds, 1), ReductionMode::Average, &mut ToolRun::default());
+        assert_eq!(attr_points.len(), 6);
+    }
+
+    #[test]
+    fn test_morton_order_is_a_monotonic_permutation() {
+        let mut points: Vec<f32> = Vec::new();
+        for i in 0..3000 {
+            let t = i as f32;
+            points.extend_from_slice(&[(t * 0.37).sin() * 4.0, (t * 0.11).cos() * 4.0, (t * 0.053).sin() * 2.0]);
+        }
+        let bounds = Bounds::from_points(&points).unwrap();
+        let grid = VoxelGrid { voxel_size: [0.3; 3], bounds, min_points_per_voxel: 1, boundary_mode: BoundaryMode::Floor };
+        let (positions, counts) = voxel_downsample_per_axis(&points, 3000, grid.voxel_size, &bounds, 1, BoundaryMode::Floor, &mut ToolRun::default());
+
+        let order = output_order(&positions, &grid, FLAG_MORTON | FLAG_SORTED).unwrap();
+        let mut seen = order.clone();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..counts.len()).collect::<Vec<_>>());
+        let reordered = reorder(&positions, &order, 3);
+        assert_eq!(sorted_voxels((reordered.clone(), reorder(&counts, &order, 1))), sorted_voxels((positions, counts)));
+
+        let codes: Vec<u64> = reordered
+            .chunks_exact(3)
+            .map(|p| {
+                let c = [0, 1, 2].map(|a| voxel_coord(p[a] as f64, bounds.min[a], 1.0 / 0.3f32 as f64, BoundaryMode::Floor));
+                morton_encode(morton_axis(c[0]), morton_axis(c[1]), morton_axis(c[2]))
+            })
+            .collect();
+        assert!(codes.windows(2).all(|w| w[0] <= w[1]));
+        assert!(output_order(&reordered, &grid, 0).is_none());
+    }
+
+    #[test]
+    fn test_spread_separates_tight_and_scattered_voxels() {
+        let mut points: Vec<f32> = Vec::new();
+        // Voxel (0, 0, 0): eight points within 1e-4 of (0.5, 0.5, 0.5)
+        for i in 0..8 {
+            let d = if i % 2 == 0 { 1e-4 } else { -1e-4 };
+            points.extend_from_slice(&[0.5 + d, 0.5 - d, 0.5]);
+        }
+        // Voxel (2, 0, 0): two points 0.8 apart along x, RMS distance 0.4 from their centroid
+        points.extend_from_slice(&[2.1, 0.5, 0.5, 2.9, 0.5, 0.5]);
+        let grid = VoxelGrid { voxel_size: [1.0; 3], bounds: anchored([0.0, 0.0, 0.0]), min_points_per_voxel: 1, boundary_mode: BoundaryMode::Floor };
+
+        let (positions, counts, spread) = voxel_downsample_with_spread(&points, 10, grid, &mut ToolRun::default());
+        assert_eq!(sorted_voxels((positions.clone(), counts.clone())), sorted_voxels(voxel_downsample_per_axis(&points, 10, [1.0; 3], &grid.bounds, 1, BoundaryMode::Floor, &mut ToolRun::default())));
+        for (p, s) in positions.chunks_exact(3).zip(&spread) {
+            if p[0] < 1.0 {
+                assert!(*s < 2e-4, "tight voxel spread {}", s);
+            } else {
+                assert!((s - 0.4).abs() < 1e-6, "scattered voxel spread {}", s);
+            }
+        }
+
+        // Far from the origin the offsets keep the result exact enough
+        let shifted: Vec<f32> = points.iter().map(|v| v + 100_000.0).collect();
+        let bounds = anchored([100_000.0; 3]);
+        let (_, _, spread) = voxel_downsample_with_spread(&shifted, 10, VoxelGrid { bounds, ..grid }, &mut ToolRun::default());
+        assert!(spread.iter().any(|s| (s - 0.4).abs() < 0.01));
+    }
+
+    #[test]
+    fn test_spread_keeps_voxels_a_16_bit_field_apart_separate() {
+        // (1, 0, 0) and (0, 65536, 0) shared a key when y was packed into 16 bits
+        let points = [1.5f32, 0.5, 0.5, 0.5, 65536.5, 0.5];
+        let grid = VoxelGrid { voxel_size: [1.0; 3], bounds: anchored([0.0; 3]), min_points_per_voxel: 1, boundary_mode: BoundaryMode::Floor };
+        let (positions, counts, spread) = voxel_downsample_with_spread(&points, 2, grid, &mut ToolRun::default());
+        let spread_voxels = sorted_voxels((positions, counts));
+        assert_eq!(spread_voxels, vec![([0.5, 65536.5, 0.5], 1), ([1.5, 0.5, 0.5], 1)]);
+        assert_eq!(spread, vec![0.0, 0.0]);
+
+        // Turning spread on must not change which points merge
+        let plain = voxel_downsample_internal(&points, 2, 1.0, &anchored([0.0; 3]), 1, BoundaryMode::Floor);
+        assert_eq!(sorted_voxels(plain), spread_voxels);
+    }
+
+    #[test]
+    fn test_nearest_keeps_voxels_a_16_bit_field_apart_separate() {
+        // (1, 0, 0) and (0, 65536, 0) shared a key when y was packed into 16 bits, so only the
+        // point nearer its own center survived
+        let points = [1.5f32, 0.5, 0.5, 0.2, 65536.2, 0.2];
+        let grid = VoxelGrid { voxel_size: [1.0; 3], bounds: anchored([0.0; 3]), min_points_per_voxel: 1, boundary_mode: BoundaryMode::Floor };
+        let (mut kept, counts) = voxel_downsample_nearest(&points, 2, grid, &mut ToolRun::default());
+        kept.sort_unstable();
+        assert_eq!(kept, vec![0, 1]);
+        assert_eq!(counts, vec![1, 1]);
+    }
+
+    #[test]
+    fn test_spread_rejects_flags_that_select_another_path() {
+        assert!(check_spread_flags(FLAG_SPREAD | FLAG_COUNTS | FLAG_SORTED).is_ok());
+        assert!(check_spread_flags(FLAG_EXACT_CAPACITY).is_ok());
+        for conflicting in [FLAG_COLORS, FLAG_INTENSITY, FLAG_CLASSIFICATION, FLAG_NEAREST, FLAG_STREAM, FLAG_EXACT_CAPACITY] {
+            assert!(check_spread_flags(FLAG_SPREAD | conflicting).is_err(), "flag {}", conflicting);
+        }
+    }
 }
@@ -0,0 +1,65 @@
+// Hash map and set types for voxel keys, shared by the voxel tools so the hashing strategy can be
+// A/B tested without editing call sites. FxHash by default; the `ahash` feature switches to
+// aHash, which can be faster for u64 keys on some targets. Both are built through
+// BuildHasherDefault, so every map hashes with the same fixed keys: iteration order (and with it
+// the output order of the tools) is reproducible from run to run, and `VoxelMap::default()` and
+// `with_capacity_and_hasher(n, Default::default())` work either way.
+
+use std::collections::{HashMap, HashSet};
+
+#[cfg(not(feature = "ahash"))]
+pub type VoxelHasher = std::hash::BuildHasherDefault<rustc_hash::FxHasher>;
+#[cfg(feature = "ahash")]
+pub type VoxelHasher = std::hash::BuildHasherDefault<ahash::AHasher>;
+
+pub type VoxelMap<K, V> = HashMap<K, V, VoxelHasher>;
+pub type VoxelSet<T> = HashSet<T, VoxelHasher>;
+
+/// Key of one voxel: its full signed (x, y, z) grid index. Packing the indices into a single
+/// integer aliases distinct voxels as soon as one index leaves its bit field (e.g. y = 65536 with
+/// 16 bits for y), so every voxel tool keys by all three coordinates and counts the same voxels.
+pub type VoxelKey = [i32; 3];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_hash::FxHashMap;
+
+    /// Voxel coordinates of 100k synthetic points, including negative ones
+    fn cells() -> Vec<[i32; 3]> {
+        (0..100_000u32)
+            .map(|i| {
+                let t = i as f32;
+                [(t * 0.0137).sin() * 300.0, (t * 0.0071).cos() * 300.0, (t * 0.00031).sin() * 40.0].map(|v| v.floor() as i32)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_selected_backend_matches_fx() {
+        let cells = cells();
+        let mut fx: FxHashMap<[i32; 3], u32> = FxHashMap::default();
+        let mut selected: VoxelMap<[i32; 3], u32> = VoxelMap::default();
+        for cell in &cells {
+            *fx.entry(*cell).or_insert(0) += 1;
+            *selected.entry(*cell).or_insert(0) += 1;
+        }
+        assert_eq!(selected.len(), fx.len());
+        assert!(fx.iter().all(|(cell, count)| selected.get(cell) == Some(count)));
+
+        let set: VoxelSet<[i32; 3]> = cells.iter().copied().collect();
+        assert_eq!(set.len(), fx.len());
+    }
+
+    #[test]
+    fn test_separately_built_maps_iterate_in_the_same_order() {
+        let build = || {
+            let mut map: VoxelMap<[i32; 3], u32> = VoxelMap::with_capacity_and_hasher(16, Default::default());
+            for cell in cells() {
+                *map.entry(cell).or_insert(0) += 1;
+            }
+            map.into_iter().collect::<Vec<_>>()
+        };
+        assert_eq!(build(), build());
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::{self, Write};
+use crate::point_attributes::PointAttributes;
+
+// Plain-text point dumps (.xyz / .csv): one point per line as `x y z` or `x y z r g b`, values
+// separated by whitespace and/or commas. Blank lines and `#` comments are skipped, `\r\n` line
+// endings are accepted, and a non-numeric first line (a CSV column header) is ignored.
+// Colors become 0..1 floats: files whose color values exceed 1 are taken as 0-255 and scaled.
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Parse XYZ/CSV text into flat positions plus colors when every line has them
+pub fn parse_xyz(text: &str) -> io::Result<(Vec<f32>, PointAttributes)> {
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let mut has_colors = None;
+    let mut values = Vec::with_capacity(6);
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        values.clear();
+        let mut parsed = true;
+        for token in line.split(|c: char| c == ',' || c.is_whitespace()).filter(|t| !t.is_empty()) {
+            match token.parse::<f32>() {
+                Ok(v) => values.push(v),
+                Err(_) => {
+                    parsed = false;
+                    break;
+                }
+            }
+        }
+        if !parsed {
+            if has_colors.is_none() && positions.is_empty() {
+                continue; // column header
+            }
+            return Err(invalid(format!("line {}: non-numeric value", line_index + 1)));
+        }
+        if values.len() < 3 {
+            return Err(invalid(format!("line {}: expected x y z", line_index + 1)));
+        }
+
+        let line_has_colors = values.len() >= 6;
+        if *has_colors.get_or_insert(line_has_colors) != line_has_colors {
+            return Err(invalid(format!("line {}: color columns on some lines only", line_index + 1)));
+        }
+        positions.extend_from_slice(&values[..3]);
+        if line_has_colors {
+            colors.extend_from_slice(&values[3..6]);
+        }
+    }
+
+    let colors = if has_colors == Some(true) {
+        if colors.iter().any(|&c| c > 1.0) {
+            for c in colors.iter_mut() {
+                *c /= 255.0;
+            }
+        }
+        Some(colors)
+    } else {
+        None
+    };
+
+    Ok((
+        positions,
+        PointAttributes {
+            colors,
+            intensities: None,
+            classifications: None,
+        },
+    ))
+}
+
+/// Write points as `x y z` or `x y z r g b` lines (`separator` is typically b' ' or b',').
+/// Values use the shortest representation that parses back to the same f32.
+pub fn write_xyz<W: Write>(writer: &mut W, positions: &[f32], colors: Option<&[f32]>, separator: u8) -> io::Result<()> {
+    let separator = separator as char;
+    let mut out = String::with_capacity(positions.len() * 10);
+    for (i, p) in positions.chunks_exact(3).enumerate() {
+        out.push_str(&format!("{}{}{}{}{}", p[0], separator, p[1], separator, p[2]));
+        if let Some(colors) = colors {
+            let c = &colors[i * 3..i * 3 + 3];
+            out.push_str(&format!("{}{}{}{}{}{}", separator, c[0], separator, c[1], separator, c[2]));
+        }
+        out.push('\n');
+    }
+    writer.write_all(out.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_preserves_full_precision() {
+        let positions = vec![0.1f32, -1.0e-7, 123456.79, f32::MAX, f32::MIN_POSITIVE, -0.0, 1.0 / 3.0, 2.0, -7.25];
+        let colors = vec![0.0f32, 0.5, 1.0, 0.2, 0.4, 0.6, 1.0 / 255.0, 0.999, 0.25];
+
+        for separator in [b' ', b','] {
+            let mut text = Vec::new();
+            write_xyz(&mut text, &positions, Some(&colors), separator).unwrap();
+            let (parsed, attributes) = parse_xyz(std::str::from_utf8(&text).unwrap()).unwrap();
+            assert_eq!(parsed, positions);
+            assert_eq!(attributes.colors.unwrap(), colors);
+        }
+    }
+
+    #[test]
+    fn test_comments_blank_lines_crlf_and_byte_colors() {
+        let text = "x,y,z,r,g,b\r\n# exported scan\r\n\r\n1, 2, 3, 255, 0, 51\r\n4 5 6 0 255 0\r\n";
+        let (positions, attributes) = parse_xyz(text).unwrap();
+        assert_eq!(positions, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(attributes.colors.unwrap(), vec![1.0, 0.0, 51.0 / 255.0, 0.0, 1.0, 0.0]);
+
+        let (positions, attributes) = parse_xyz("0 0 0\n1 1 1\n").unwrap();
+        assert_eq!(positions.len(), 6);
+        assert!(attributes.colors.is_none());
+
+        assert!(parse_xyz("0 0 0\n1 1 1 0 0 0\n").is_err());
+        assert!(parse_xyz("0 0 0\n1 one 1\n").is_err());
+    }
+}
@@ -147,7 +147,24 @@ function createVoxelDownsampleInput(
   return buffer;
 }
 
+// Rust tools expect the versioned protocol prefix before the legacy header:
+// [4 bytes "PCWT"][u16 version][u16 toolId]
+// and the Rust voxel debug header carries a trailing u32 flags word (0 = f32 positions).
+const RUST_TOOL_ID = 3;
+
+function withRustProtocolHeader(input) {
+  const header = Buffer.allocUnsafe(8);
+  header.write('PCWT', 0, 'ascii');
+  header.writeUInt16LE(1, 4);
+  header.writeUInt16LE(RUST_TOOL_ID, 6);
+  const flags = Buffer.alloc(4);
+  return Buffer.concat([header, input.subarray(0, 32), flags, input.subarray(32)]);
+}
+
 function runTool(executable, input) {
+  if (executable.endsWith('_rust')) {
+    input = withRustProtocolHeader(input);
+  }
   return new Promise((resolve, reject) => {
     const process = spawn(executable);
     const chunks = [];
@@ -169,7 +169,30 @@ function createVoxelDownsampleInput(
   return buffer;
 }
 
+// Rust tools expect the versioned protocol prefix before the legacy header:
+// [4 bytes "PCWT"][u16 version][u16 toolId]
+// and the Rust header appends a u32 minPointsPerVoxel after the flags (1 = keep every voxel).
+const RUST_TOOL_ID = 1;
+
+function withRustProtocolHeader(input) {
+  const header = Buffer.allocUnsafe(8);
+  header.write('PCWT', 0, 'ascii');
+  header.writeUInt16LE(1, 4);
+  header.writeUInt16LE(RUST_TOOL_ID, 6);
+  const minPointsPerVoxel = Buffer.allocUnsafe(4);
+  minPointsPerVoxel.writeUInt32LE(1, 0);
+  return Buffer.concat([
+    header,
+    input.subarray(0, 36),
+    minPointsPerVoxel,
+    input.subarray(36),
+  ]);
+}
+
 function runTool(executable, input) {
+  if (executable.endsWith('_rust')) {
+    input = withRustProtocolHeader(input);
+  }
   return new Promise((resolve, reject) => {
     const process = spawn(executable);
     const chunks = [];
@@ -0,0 +1,92 @@
+use crate::common::is_finite_point;
+
+/// Axis-aligned bounds of a flat xyz array in one pass: [min_x, min_y, min_z, max_x, max_y, max_z].
+/// Points with a non-finite coordinate are ignored. Input without a finite point returns all zeros.
+pub fn compute_bounds_internal(points: &[f32]) -> [f32; 6] {
+    let mut finite = points.chunks_exact(3).filter(|p| is_finite_point(p));
+    let Some(first) = finite.next() else {
+        return [0.0; 6];
+    };
+
+    let mut min_x = first[0];
+    let mut max_x = first[0];
+    let mut min_y = first[1];
+    let mut max_y = first[1];
+    let mut min_z = first[2];
+    let mut max_z = first[2];
+
+    for p in finite {
+        min_x = min_x.min(p[0]);
+        max_x = max_x.max(p[0]);
+        min_y = min_y.min(p[1]);
+        max_y = max_y.max(p[1]);
+        min_z = min_z.min(p[2]);
+        max_z = max_z.max(p[2]);
+    }
+
+    [min_x, min_y, min_z, max_x, max_y, max_z]
+}
+
+/// Outlier-robust bounds: per axis, the `percentile` and `1 - percentile` quantiles instead of the
+/// absolute min/max, so a few stray points cannot drag the grid origin away from the data.
+/// `percentile` is a fraction in [0, 0.5); 0 gives the absolute bounds. Points with a non-finite
+/// coordinate are ignored, as in compute_bounds_internal.
+pub fn compute_robust_bounds_internal(points: &[f32], percentile: f32) -> [f32; 6] {
+    let point_count = points.chunks_exact(3).filter(|p| is_finite_point(p)).count();
+    if point_count == 0 {
+        return [0.0; 6];
+    }
+    if percentile.is_nan() || percentile <= 0.0 {
+        return compute_bounds_internal(points);
+    }
+
+    let percentile = percentile.min(0.49);
+    let low_rank = ((point_count - 1) as f32 * percentile).round() as usize;
+    let high_rank = ((point_count - 1) as f32 * (1.0 - percentile)).round() as usize;
+    let mut bounds = [0.0f32; 6];
+    let mut axis_values = Vec::with_capacity(point_count);
+    for axis in 0..3 {
+        axis_values.clear();
+        axis_values.extend(points.chunks_exact(3).filter(|p| is_finite_point(p)).map(|p| p[axis]));
+        bounds[axis] = *axis_values.select_nth_unstable_by(low_rank, |a, b| a.total_cmp(b)).1;
+        bounds[axis + 3] = *axis_values.select_nth_unstable_by(high_rank, |a, b| a.total_cmp(b)).1;
+    }
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_bounds() {
+        let points = [1.0, -2.0, 3.0, -4.0, 5.0, 0.5, 2.0, 0.0, -1.0];
+        assert_eq!(compute_bounds_internal(&points), [-4.0, -2.0, -1.0, 2.0, 5.0, 3.0]);
+        assert_eq!(compute_bounds_internal(&[]), [0.0; 6]);
+    }
+
+    #[test]
+    fn test_robust_bounds_ignore_outlier() {
+        let mut points: Vec<f32> = (0..1000)
+            .flat_map(|i| [(i % 10) as f32 * 0.1, ((i / 10) % 10) as f32 * 0.1, (i / 100) as f32 * 0.1])
+            .collect();
+        points.extend_from_slice(&[-1000.0, 5000.0, -1000.0]);
+        let robust = compute_robust_bounds_internal(&points, 0.01);
+        assert_eq!(robust, [0.0, 0.0, 0.0, 0.90000004, 0.90000004, 0.90000004]);
+        assert_eq!(compute_robust_bounds_internal(&points, 0.0), compute_bounds_internal(&points));
+    }
+
+    #[test]
+    fn test_bounds_ignore_non_finite_points() {
+        let points = [
+            f32::NAN, 0.0, 0.0,
+            1.0, -2.0, 3.0,
+            f32::INFINITY, 9.0, 9.0,
+            -4.0, 5.0, 0.5,
+            0.0, f32::NEG_INFINITY, 0.0,
+        ];
+        assert_eq!(compute_bounds_internal(&points), [-4.0, -2.0, 0.5, 1.0, 5.0, 3.0]);
+        assert_eq!(compute_robust_bounds_internal(&points, 0.1), [-4.0, -2.0, 0.5, 1.0, 5.0, 3.0]);
+        assert_eq!(compute_bounds_internal(&[f32::NAN, 1.0, 1.0]), [0.0; 6]);
+    }
+}
@@ -19,15 +19,79 @@ pub struct VoxelFull {
     pub sum_b: f32,
     pub sum_intensity: f32,
     pub class_counts: rustc_hash::FxHashMap<u8, i32>,
+    // Weight-scaled position sums and total weight; zero when no weights were supplied
+    pub weighted_sum: [f32; 3],
+    pub weight_sum: f32,
+}
+
+impl VoxelFull {
+    /// Weighted mean position when the voxel's total weight is positive, else the plain mean
+    pub fn centroid(&self) -> [f32; 3] {
+        if self.weight_sum > 0.0 {
+            self.weighted_sum.map(|s| s / self.weight_sum)
+        } else {
+            let count_f = self.count as f32;
+            [self.sum_x / count_f, self.sum_y / count_f, self.sum_z / count_f]
+        }
+    }
+}
+
+/// Whether all three coordinates of a point are finite. Points with a NaN or infinite coordinate
+/// are left out of bounds, voxels and neighbor searches: binning them would land them in an
+/// arbitrary cell, since float-to-int casts saturate.
+#[inline]
+pub fn is_finite_point(p: &[f32]) -> bool {
+    p[0].is_finite() && p[1].is_finite() && p[2].is_finite()
+}
+
+/// Hash key of a voxel: its full signed grid index. Packing the indices into one integer (e.g.
+/// 16 bits for y and z) aliases voxels whose indices differ by a multiple of the field width,
+/// so keys keep all three coordinates, as the backend tools do.
+pub type VoxelKey = [i32; 3];
+
+#[inline]
+pub fn voxel_key(voxel_x: i32, voxel_y: i32, voxel_z: i32) -> VoxelKey {
+    [voxel_x, voxel_y, voxel_z]
+}
+
+// Renderer buffers store points as [x, y, z, r, g, b] records
+pub const XYZRGB_STRIDE: usize = 6;
+
+/// Split an interleaved XYZRGB array into separate position and color arrays.
+/// A trailing partial record is ignored.
+pub fn deinterleave_xyzrgb(interleaved: &[f32]) -> (Vec<f32>, Vec<f32>) {
+    let point_count = interleaved.len() / XYZRGB_STRIDE;
+    let mut positions = Vec::with_capacity(point_count * 3);
+    let mut colors = Vec::with_capacity(point_count * 3);
+    for record in interleaved.chunks_exact(XYZRGB_STRIDE) {
+        positions.extend_from_slice(&record[..3]);
+        colors.extend_from_slice(&record[3..]);
+    }
+    (positions, colors)
+}
+
+/// Inverse of deinterleave_xyzrgb; both inputs hold three values per point
+pub fn interleave_xyzrgb(positions: &[f32], colors: &[f32]) -> Vec<f32> {
+    let mut interleaved = Vec::with_capacity(positions.len() * 2);
+    for (p, c) in positions.chunks_exact(3).zip(colors.chunks_exact(3)) {
+        interleaved.extend_from_slice(p);
+        interleaved.extend_from_slice(c);
+    }
+    interleaved
 }
 
 // Import the `console.log` function from the browser
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen::prelude::wasm_bindgen]
 extern "C" {
     #[wasm_bindgen::prelude::wasm_bindgen(js_namespace = console)]
     pub fn log(s: &str);
 }
 
+// Native builds (cargo test) have no browser console, so log messages are dropped
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log(_s: &str) {}
+
 // Define a macro to make console.log work like in JavaScript
 #[macro_export]
 macro_rules! console_log {
@@ -5,13 +5,32 @@ mod common;
 mod voxel_downsample;
 mod point_cloud_smoothing;
 mod voxel_debug;
+mod bounds;
 
-use voxel_downsample::{voxel_downsample_internal, voxel_downsample_with_attributes_internal};
-use point_cloud_smoothing::point_cloud_smooth_internal;
-use voxel_debug::generate_voxel_centers_internal;
+use voxel_downsample::{
+    check_direct_args, suggest_voxel_size_internal, voxel_assignments_internal, voxel_count_internal,
+    voxel_downsample_interleaved_internal, voxel_downsample_internal, voxel_downsample_with_attributes_internal,
+    voxel_downsample_with_attributes_vec_internal, voxel_downsample_with_index_map_internal, AttributeOutputs,
+    VoxelDownsampleResult, VoxelGrid, VoxelGridSpec,
+};
+use point_cloud_smoothing::{
+    bilateral_smooth_internal, point_cloud_smooth_interleaved_internal, point_cloud_smooth_internal,
+    point_cloud_smooth_with_attributes_internal, point_cloud_smooth_with_progress_internal, point_cloud_smooth_with_search_cells_internal,
+    SmoothingResult,
+};
+use voxel_debug::{generate_voxel_centers_internal, generate_voxel_centers_with_counts_internal, VoxelCentersResult};
+use bounds::{compute_bounds_internal, compute_robust_bounds_internal};
+
+// Standalone grid type for repeated neighbor queries from JS
+pub use point_cloud_smoothing::SpatialGrid;
+
+// Percentile used for the grid origin when voxel_downsample_auto_bounds runs with robust bounds
+const ROBUST_BOUNDS_PERCENTILE: f32 = 0.01;
 
 #[wasm_bindgen]
 pub struct PointCloudToolsRust {
+    // Voxel grid kept between calls for voxel_grid_* queries
+    voxel_grid: VoxelGrid,
 }
 
 #[wasm_bindgen]
@@ -19,7 +38,9 @@ impl PointCloudToolsRust {
     #[wasm_bindgen(constructor)]
     pub fn new() -> PointCloudToolsRust {
         console_log!("Rust WASM: PointCloudToolsRust initialized");
-        PointCloudToolsRust {}
+        PointCloudToolsRust {
+            voxel_grid: VoxelGrid::default(),
+        }
     }
     
     /// Get WASM memory for direct access
@@ -58,7 +79,7 @@ impl PointCloudToolsRust {
             return 0;
         }
         
-        if input_ptr % 4 != 0 || output_ptr % 4 != 0 {
+        if !input_ptr.is_multiple_of(4) || !output_ptr.is_multiple_of(4) {
             return 0;
         }
         
@@ -75,11 +96,234 @@ impl PointCloudToolsRust {
                 min_x,
                 min_y,
                 min_z,
+                1,
                 output_ptr_f32,
             )
         }
     }
 
+    /// voxel_downsample_direct_static that reports why it could not run instead of returning 0:
+    /// throws in JavaScript for a zero point count, a voxel size that is not positive and
+    /// finite, or a misaligned pointer. Ok(0) cannot happen, so any Ok value is a real count.
+    /// Same safety contract as voxel_downsample_direct_static.
+    #[wasm_bindgen]
+    pub fn voxel_downsample_direct_checked(
+        input_ptr: usize,
+        point_count: usize,
+        voxel_size: f32,
+        min_x: f32,
+        min_y: f32,
+        min_z: f32,
+        output_ptr: usize,
+    ) -> Result<usize, JsValue> {
+        check_direct_args(point_count, voxel_size, &[input_ptr, output_ptr]).map_err(JsValue::from_str)?;
+        Ok(Self::voxel_downsample_direct_static(input_ptr, point_count, voxel_size, min_x, min_y, min_z, output_ptr))
+    }
+
+    /// voxel_downsample_count_only with the same errors as voxel_downsample_direct_checked
+    #[wasm_bindgen]
+    pub fn voxel_downsample_count_only_checked(
+        input_ptr: usize,
+        point_count: usize,
+        voxel_size: f32,
+        min_x: f32,
+        min_y: f32,
+        min_z: f32,
+    ) -> Result<usize, JsValue> {
+        check_direct_args(point_count, voxel_size, &[input_ptr]).map_err(JsValue::from_str)?;
+        Ok(Self::voxel_downsample_count_only(input_ptr, point_count, voxel_size, min_x, min_y, min_z))
+    }
+
+    /// Same as voxel_downsample_direct_static, and additionally writes to `index_output_ptr` the
+    /// input index of one representative point (the last inserted) per output point, so the
+    /// caller can gather colors or other attributes while keeping the position-only fast path.
+    ///
+    /// # Safety
+    /// Same contract as voxel_downsample_direct_static, plus: index_output_ptr must point to
+    /// valid, 4-byte aligned WASM memory with room for at least point_count u32 values.
+    #[wasm_bindgen]
+    pub fn voxel_downsample_direct_with_index_map_static(
+        input_ptr: usize,
+        point_count: usize,
+        voxel_size: f32,
+        min_x: f32,
+        min_y: f32,
+        min_z: f32,
+        output_ptr: usize,
+        index_output_ptr: usize,
+    ) -> usize {
+        if point_count == 0 || voxel_size <= 0.0 {
+            return 0;
+        }
+
+        if !input_ptr.is_multiple_of(4) || !output_ptr.is_multiple_of(4) || !index_output_ptr.is_multiple_of(4) {
+            return 0;
+        }
+
+        unsafe {
+            let points = std::slice::from_raw_parts(input_ptr as *const f32, point_count * 3);
+            voxel_downsample_with_index_map_internal(
+                points,
+                voxel_size,
+                min_x,
+                min_y,
+                min_z,
+                output_ptr as *mut f32,
+                index_output_ptr as *mut u32,
+            )
+        }
+    }
+
+    /// Exact number of points voxel_downsample_direct_static will write for the same inputs,
+    /// so JavaScript can allocate the output buffer precisely before the real call.
+    /// Runs only the voxel insertion pass; same input contract as voxel_downsample_direct_static.
+    #[wasm_bindgen]
+    pub fn voxel_downsample_count_only(
+        input_ptr: usize,
+        point_count: usize,
+        voxel_size: f32,
+        min_x: f32,
+        min_y: f32,
+        min_z: f32,
+    ) -> usize {
+        if point_count == 0 || voxel_size <= 0.0 {
+            return 0;
+        }
+        
+        if !input_ptr.is_multiple_of(4) {
+            return 0;
+        }
+        
+        unsafe {
+            let points = std::slice::from_raw_parts(input_ptr as *const f32, point_count * 3);
+            voxel_count_internal(points, voxel_size, min_x, min_y, min_z)
+        }
+    }
+
+    /// For each input point, the index of the output point it contributes to in
+    /// voxel_downsample_direct_static with the same arguments, so the UI can highlight every
+    /// original point behind a selected voxel
+    #[wasm_bindgen]
+    pub fn voxel_assignments(&self, points: &[f32], voxel_size: f32, min_x: f32, min_y: f32, min_z: f32) -> Vec<u32> {
+        if voxel_size <= 0.0 {
+            return Vec::new();
+        }
+        voxel_assignments_internal(points, voxel_size, min_x, min_y, min_z)
+    }
+
+    /// Voxel size for which voxel downsampling with the grid anchored at the input bounds
+    /// minimum (as in voxel_downsample_auto_bounds without robust bounds) gives roughly
+    /// `target_count` points, within a few percent for typical clouds. Returns 0 when no size
+    /// applies (empty input, zero target, all points at one location).
+    #[wasm_bindgen]
+    pub fn suggest_voxel_size(&self, points: &[f32], target_count: u32) -> f32 {
+        let bounds = compute_bounds_internal(points);
+        suggest_voxel_size_internal(points, target_count as usize, [bounds[0], bounds[1], bounds[2]], [bounds[3], bounds[4], bounds[5]])
+    }
+
+    /// Build the stored voxel grid from `points`, replacing any previous grid.
+    /// Returns the number of occupied voxels.
+    #[wasm_bindgen]
+    pub fn voxel_grid_build(&mut self, points: &[f32], voxel_size: f32, min_x: f32, min_y: f32, min_z: f32) -> usize {
+        self.voxel_grid.build(points, voxel_size, min_x, min_y, min_z);
+        self.voxel_grid.occupied_count()
+    }
+
+    /// Probe one cell of the stored grid: [centroid_x, centroid_y, centroid_z, count] when the
+    /// cell is occupied, an empty array otherwise
+    #[wasm_bindgen]
+    pub fn voxel_grid_query_cell(&self, voxel_x: i32, voxel_y: i32, voxel_z: i32) -> Vec<f32> {
+        match self.voxel_grid.query_cell(voxel_x, voxel_y, voxel_z) {
+            Some((centroid, count)) => vec![centroid[0], centroid[1], centroid[2], count as f32],
+            None => Vec::new(),
+        }
+    }
+
+    /// Number of occupied voxels in the stored grid
+    #[wasm_bindgen]
+    pub fn voxel_grid_occupied_count(&self) -> usize {
+        self.voxel_grid.occupied_count()
+    }
+
+    /// Fold a new batch of points (e.g. from a live capture) into the stored grid, using the voxel
+    /// size and origin of the last voxel_grid_build. Returns the number of occupied voxels.
+    #[wasm_bindgen]
+    pub fn voxel_grid_add_points(&mut self, points: &[f32]) -> usize {
+        self.voxel_grid.add_points(points);
+        self.voxel_grid.occupied_count()
+    }
+
+    /// Current centroid of every occupied voxel in the stored grid (flat xyz)
+    #[wasm_bindgen]
+    pub fn voxel_grid_current_centroids(&self) -> Vec<f32> {
+        self.voxel_grid.current_centroids()
+    }
+
+    /// Empty the stored grid but keep its voxel size and origin for further voxel_grid_add_points
+    #[wasm_bindgen]
+    pub fn voxel_grid_clear(&mut self) {
+        self.voxel_grid.clear();
+    }
+
+    /// Release the stored grid's cells; its voxel size and origin stay, as with voxel_grid_clear
+    #[wasm_bindgen]
+    pub fn voxel_grid_reset(&mut self) {
+        self.voxel_grid.reset();
+    }
+
+    /// Bounding box in one pass: [min_x, min_y, min_z, max_x, max_y, max_z] (zeros for empty input)
+    #[wasm_bindgen]
+    pub fn compute_bounds(&self, points: &[f32]) -> Vec<f32> {
+        compute_bounds_internal(points).to_vec()
+    }
+
+    /// Percentile bounds per axis (fraction in [0, 0.5), e.g. 0.01 for 1st/99th percentile),
+    /// same layout as compute_bounds
+    #[wasm_bindgen]
+    pub fn compute_robust_bounds(&self, points: &[f32], percentile: f32) -> Vec<f32> {
+        compute_robust_bounds_internal(points, percentile).to_vec()
+    }
+
+    /// Same as voxel_downsample_direct_static, but computes the grid origin from the input
+    /// bounds internally so JavaScript does not have to scan the points first.
+    /// With `robust_bounds` the origin comes from the 1st-percentile bounds, so a stray point
+    /// far from the data does not shift the grid; points below it get negative voxel indices.
+    /// Same safety contract as voxel_downsample_direct_static.
+    #[wasm_bindgen]
+    pub fn voxel_downsample_auto_bounds(
+        input_ptr: usize,
+        point_count: usize,
+        voxel_size: f32,
+        robust_bounds: bool,
+        output_ptr: usize,
+    ) -> usize {
+        if point_count == 0 || voxel_size <= 0.0 {
+            return 0;
+        }
+        
+        if !input_ptr.is_multiple_of(4) || !output_ptr.is_multiple_of(4) {
+            return 0;
+        }
+        
+        unsafe {
+            let points = std::slice::from_raw_parts(input_ptr as *const f32, point_count * 3);
+            let bounds = if robust_bounds {
+                compute_robust_bounds_internal(points, ROBUST_BOUNDS_PERCENTILE)
+            } else {
+                compute_bounds_internal(points)
+            };
+            voxel_downsample_internal(
+                points,
+                voxel_size,
+                bounds[0],
+                bounds[1],
+                bounds[2],
+                1,
+                output_ptr as *mut f32,
+            )
+        }
+    }
+
     /// Direct pointer-based voxel downsampling with optional colors, intensity, classification.
     /// Pass 0 for any input or output pointer to skip that attribute.
     #[wasm_bindgen]
@@ -101,7 +345,7 @@ impl PointCloudToolsRust {
         if point_count == 0 || voxel_size <= 0.0 {
             return 0;
         }
-        if input_ptr % 4 != 0 || output_ptr % 4 != 0 {
+        if !input_ptr.is_multiple_of(4) || !output_ptr.is_multiple_of(4) {
             return 0;
         }
         let input_len = point_count * 3;
@@ -152,18 +396,76 @@ impl PointCloudToolsRust {
                 colors,
                 intensities,
                 classifications,
-                voxel_size,
-                min_x,
-                min_y,
-                min_z,
+                &VoxelGridSpec::new(voxel_size, min_x, min_y, min_z),
                 output_ptr as *mut f32,
-                out_colors,
-                out_int,
-                out_cls,
+                AttributeOutputs {
+                    colors: out_colors,
+                    intensities: out_int,
+                    classifications: out_cls,
+                },
             )
         }
     }
 
+    /// Voxel downsampling with attributes from regular typed arrays (no manual memory management).
+    /// Pass an empty array for colors or classifications and undefined for intensities to skip them.
+    /// Averaging and majority-vote classification match the backend voxel_downsample_rust tool.
+    /// `grid` carries the voxel size and origin.
+    #[wasm_bindgen]
+    pub fn voxel_downsample_with_attributes(
+        &self,
+        points: &[f32],
+        colors: &[f32],
+        intensities: Option<Vec<f32>>,
+        classifications: &[u8],
+        grid: &VoxelGridSpec,
+    ) -> VoxelDownsampleResult {
+        let points = if grid.voxel_size() > 0.0 { points } else { &[] };
+        voxel_downsample_with_attributes_vec_internal(points, colors, intensities.as_deref(), classifications, None, grid)
+    }
+
+    /// voxel_downsample_with_attributes with a per-point confidence weight: each voxel's position
+    /// is the weighted mean of its points (the plain mean when its weights sum to zero), while
+    /// attributes are averaged as usual. Negative weights count as zero. A weights array whose
+    /// length is not the point count is ignored. `grid` carries the voxel size and origin.
+    #[wasm_bindgen]
+    pub fn voxel_downsample_weighted(
+        &self,
+        points: &[f32],
+        colors: &[f32],
+        intensities: Option<Vec<f32>>,
+        classifications: &[u8],
+        weights: &[f32],
+        grid: &VoxelGridSpec,
+    ) -> VoxelDownsampleResult {
+        let points = if grid.voxel_size() > 0.0 { points } else { &[] };
+        voxel_downsample_with_attributes_vec_internal(
+            points,
+            colors,
+            intensities.as_deref(),
+            classifications,
+            Some(weights),
+            grid,
+        )
+    }
+
+    /// Voxel downsampling of an interleaved [x, y, z, r, g, b] array (the renderer's layout),
+    /// averaging RGB per voxel. Returns interleaved records, one per occupied voxel.
+    #[wasm_bindgen]
+    pub fn voxel_downsample_interleaved(
+        &self,
+        interleaved: &[f32],
+        voxel_size: f32,
+        min_x: f32,
+        min_y: f32,
+        min_z: f32,
+    ) -> Vec<f32> {
+        if voxel_size <= 0.0 {
+            return Vec::new();
+        }
+        voxel_downsample_interleaved_internal(interleaved, voxel_size, min_x, min_y, min_z)
+    }
+
     /// Point cloud smoothing implementation in Rust
     /// This matches the algorithm used in TS, WASM C++, and BE C++
     #[wasm_bindgen]
@@ -176,6 +478,123 @@ impl PointCloudToolsRust {
         point_cloud_smooth_internal(points, smoothing_radius, iterations)
     }
 
+    /// Point cloud smoothing with a neighbor search spanning `search_cells` grid cells per side
+    /// (cell size = smoothing_radius / search_cells); 1 matches point_cloud_smooth
+    #[wasm_bindgen]
+    pub fn point_cloud_smooth_with_search_cells(
+        &self,
+        points: &[f32],
+        smoothing_radius: f32,
+        iterations: i32,
+        search_cells: i32,
+    ) -> Vec<f32> {
+        point_cloud_smooth_with_search_cells_internal(points, smoothing_radius, iterations, search_cells)
+    }
+
+    /// Point cloud smoothing with progress reporting and cancellation.
+    /// `on_iteration(iteration, total)` is called after each pass (iteration counts from 0);
+    /// returning false, or throwing, stops early and returns the partially smoothed points.
+    #[wasm_bindgen]
+    pub fn point_cloud_smooth_with_progress(
+        &self,
+        points: &[f32],
+        smoothing_radius: f32,
+        iterations: i32,
+        on_iteration: Option<js_sys::Function>,
+    ) -> Vec<f32> {
+        point_cloud_smooth_with_progress_internal(points, smoothing_radius, iterations, |iteration, total| {
+            match &on_iteration {
+                Some(callback) => callback
+                    .call2(&JsValue::NULL, &JsValue::from(iteration), &JsValue::from(total))
+                    .is_ok_and(|keep_going| keep_going.as_bool() != Some(false)),
+                None => true,
+            }
+        })
+    }
+
+    /// Direct pointer-based smoothing, avoiding the Vec copies across the WASM boundary
+    /// Same safety contract as voxel_downsample_direct_static: input_ptr must point to
+    /// point_count * 3 f32 values and output_ptr must have room for as many.
+    /// Returns the number of points written (point_count, or 0 on empty or misaligned input).
+    #[wasm_bindgen]
+    pub fn point_cloud_smooth_direct_static(
+        input_ptr: usize,
+        point_count: usize,
+        smoothing_radius: f32,
+        iterations: i32,
+        output_ptr: usize,
+    ) -> usize {
+        if point_count == 0 {
+            return 0;
+        }
+        
+        if !input_ptr.is_multiple_of(4) || !output_ptr.is_multiple_of(4) {
+            return 0;
+        }
+        
+        let len = point_count * 3;
+        
+        unsafe {
+            // Input is only borrowed until the result is computed, so input and output may alias
+            let smoothed = {
+                let points = std::slice::from_raw_parts(input_ptr as *const f32, len);
+                point_cloud_smooth_internal(points, smoothing_radius, iterations)
+            };
+            let output = std::slice::from_raw_parts_mut(output_ptr as *mut f32, len);
+            output.copy_from_slice(&smoothed[..len]);
+        }
+        
+        point_count
+    }
+
+    /// Point cloud smoothing that carries colors and intensities along with the positions.
+    /// Pass an empty array for colors and undefined for intensities to skip them.
+    #[wasm_bindgen]
+    pub fn point_cloud_smooth_with_attributes(
+        &self,
+        points: &[f32],
+        colors: &[f32],
+        intensities: Option<Vec<f32>>,
+        smoothing_radius: f32,
+        iterations: i32,
+    ) -> SmoothingResult {
+        let colors = if colors.is_empty() { None } else { Some(colors) };
+        point_cloud_smooth_with_attributes_internal(
+            points,
+            colors,
+            intensities.as_deref(),
+            smoothing_radius,
+            iterations,
+        )
+    }
+
+    /// Point cloud smoothing of an interleaved [x, y, z, r, g, b] array, averaging RGB over the
+    /// same neighborhoods as the positions. Returns the same interleaved layout.
+    #[wasm_bindgen]
+    pub fn point_cloud_smooth_interleaved(
+        &self,
+        interleaved: &[f32],
+        smoothing_radius: f32,
+        iterations: i32,
+    ) -> Vec<f32> {
+        point_cloud_smooth_interleaved_internal(interleaved, smoothing_radius, iterations)
+    }
+
+    /// Edge-preserving bilateral smoothing (see bilateral_smooth_internal).
+    /// Pass an empty array for normals to use spatial weights only.
+    #[wasm_bindgen]
+    pub fn bilateral_smooth(
+        &self,
+        points: &[f32],
+        normals: &[f32],
+        sigma_s: f32,
+        sigma_n: f32,
+        iterations: i32,
+    ) -> Vec<f32> {
+        let normals = if normals.is_empty() { None } else { Some(normals) };
+        bilateral_smooth_internal(points, normals, sigma_s, sigma_n, iterations)
+    }
+
     /// Generate voxel centers for debug visualization
     /// Returns unique voxel center positions for rendering wireframe cubes
     #[wasm_bindgen]
@@ -189,4 +608,172 @@ impl PointCloudToolsRust {
     ) -> Vec<f32> {
         generate_voxel_centers_internal(points, voxel_size, min_x, min_y, min_z)
     }
+
+    /// Voxel centers plus the number of points in each voxel, for coloring cubes by density
+    #[wasm_bindgen]
+    pub fn generate_voxel_centers_with_counts(
+        &mut self,
+        points: &[f32],
+        voxel_size: f32,
+        min_x: f32,
+        min_y: f32,
+        min_z: f32,
+    ) -> VoxelCentersResult {
+        generate_voxel_centers_with_counts_internal(points, voxel_size, min_x, min_y, min_z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_triples(values: &[f32]) -> Vec<[f32; 3]> {
+        let mut triples: Vec<[f32; 3]> = values.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect();
+        triples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        triples
+    }
+
+    #[test]
+    fn test_auto_bounds_matches_explicit_bounds() {
+        let points: Vec<f32> = (0..500)
+            .flat_map(|i| {
+                let t = i as f32 * 0.13;
+                [t.sin() * 4.0 - 1.0, t.cos() * 3.0 + 2.0, (t * 0.3).sin() * 2.0]
+            })
+            .collect();
+        let point_count = points.len() / 3;
+        let bounds = compute_bounds_internal(&points);
+
+        let mut explicit_output = vec![0.0f32; points.len()];
+        let explicit_count = PointCloudToolsRust::voxel_downsample_direct_static(
+            points.as_ptr() as usize,
+            point_count,
+            0.5,
+            bounds[0],
+            bounds[1],
+            bounds[2],
+            explicit_output.as_mut_ptr() as usize,
+        );
+
+        let mut auto_output = vec![0.0f32; points.len()];
+        let auto_count = PointCloudToolsRust::voxel_downsample_auto_bounds(
+            points.as_ptr() as usize,
+            point_count,
+            0.5,
+            false,
+            auto_output.as_mut_ptr() as usize,
+        );
+
+        assert_eq!(auto_count, explicit_count);
+        assert_eq!(
+            sorted_triples(&auto_output[..auto_count * 3]),
+            sorted_triples(&explicit_output[..explicit_count * 3])
+        );
+    }
+
+    #[test]
+    fn test_robust_origin_ignores_far_outlier() {
+        // 10 x 10 x 10 lattice in [0, 0.9] plus one stray point far below it
+        let mut points: Vec<f32> = (0..1000)
+            .flat_map(|i| [(i % 10) as f32 * 0.1, ((i / 10) % 10) as f32 * 0.1, (i / 100) as f32 * 0.1])
+            .collect();
+        points.extend_from_slice(&[-500.0, -500.0, -500.0]);
+        let point_count = points.len() / 3;
+
+        let absolute = compute_bounds_internal(&points);
+        let robust = compute_robust_bounds_internal(&points, ROBUST_BOUNDS_PERCENTILE);
+        assert_eq!(absolute[0], -500.0);
+        assert_eq!(&robust[..3], &[0.0, 0.0, 0.0]);
+
+        // Lattice voxel indices stay in 0..=9 from the robust origin, but start near 2000 from
+        // the outlier-dragged one
+        let voxel_size = 0.25;
+        let max_index = |origin: &[f32]| {
+            points[..3000]
+                .chunks_exact(3)
+                .flat_map(|p| (0..3).map(move |a| ((p[a] - origin[a]) / voxel_size).floor() as i32))
+                .max()
+                .unwrap()
+        };
+        assert!(max_index(&robust[..3]) <= 3);
+        assert!(max_index(&absolute[..3]) >= 2000);
+
+        // Downsampling with the robust origin keeps the outlier as its own voxel
+        let mut output = vec![0.0f32; points.len()];
+        let count = PointCloudToolsRust::voxel_downsample_auto_bounds(
+            points.as_ptr() as usize,
+            point_count,
+            voxel_size,
+            true,
+            output.as_mut_ptr() as usize,
+        );
+        assert_eq!(count, 4 * 4 * 4 + 1);
+        let outputs = sorted_triples(&output[..count * 3]);
+        assert_eq!(outputs[0], [-500.0, -500.0, -500.0]);
+    }
+
+    #[test]
+    fn test_count_only_matches_downsample_output() {
+        let points: Vec<f32> = (0..2000)
+            .flat_map(|i| {
+                let f = i as f32;
+                [(f * 0.37).sin() * 3.0, (f * 0.11).cos() * 2.0, (f * 0.05) % 1.7]
+            })
+            .collect();
+        let point_count = points.len() / 3;
+
+        for voxel_size in [0.05f32, 0.3, 1.0, 10.0] {
+            let expected = PointCloudToolsRust::voxel_downsample_count_only(
+                points.as_ptr() as usize,
+                point_count,
+                voxel_size,
+                -3.0,
+                -2.0,
+                0.0,
+            );
+            let mut output = vec![0.0f32; expected * 3];
+            let count = PointCloudToolsRust::voxel_downsample_direct_static(
+                points.as_ptr() as usize,
+                point_count,
+                voxel_size,
+                -3.0,
+                -2.0,
+                0.0,
+                output.as_mut_ptr() as usize,
+            );
+            assert_eq!(count, expected, "voxel size {}", voxel_size);
+        }
+    }
+
+    #[test]
+    fn test_smooth_direct_matches_slice_api() {
+        let points: Vec<f32> = (0..500)
+            .flat_map(|i| {
+                let f = i as f32;
+                [(i % 25) as f32 * 0.1, (i / 25) as f32 * 0.1, (f * 0.7).sin() * 0.05]
+            })
+            .collect();
+        let point_count = points.len() / 3;
+        let expected = point_cloud_smooth_internal(&points, 0.25, 3);
+
+        let mut output = vec![0.0f32; points.len()];
+        let written = PointCloudToolsRust::point_cloud_smooth_direct_static(
+            points.as_ptr() as usize,
+            point_count,
+            0.25,
+            3,
+            output.as_mut_ptr() as usize,
+        );
+        assert_eq!(written, point_count);
+        assert_eq!(output, expected);
+
+        let misaligned = PointCloudToolsRust::point_cloud_smooth_direct_static(
+            points.as_ptr() as usize + 1,
+            point_count,
+            0.25,
+            3,
+            output.as_mut_ptr() as usize,
+        );
+        assert_eq!(misaligned, 0);
+    }
 }
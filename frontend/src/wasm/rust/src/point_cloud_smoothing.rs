@@ -1,67 +1,287 @@
+use wasm_bindgen::prelude::*;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+use crate::bounds::compute_bounds_internal;
+use crate::common::{deinterleave_xyzrgb, interleave_xyzrgb, is_finite_point, voxel_key, VoxelKey};
+
+// Neighbor sums for one point within one iteration (the point itself excluded)
+#[derive(Clone, Copy, Default)]
+struct NeighborSums {
+    count: i32,
+    position: [f32; 3],
+    color: [f32; 3],
+    intensity: f32,
+}
+
+/// Smoothed positions plus whichever attributes were supplied.
+/// Attribute arrays are empty when the matching input was not provided.
+#[wasm_bindgen]
+pub struct SmoothingResult {
+    positions: Vec<f32>,
+    colors: Vec<f32>,
+    intensities: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl SmoothingResult {
+    #[wasm_bindgen(getter)]
+    pub fn positions(&self) -> Vec<f32> {
+        self.positions.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn colors(&self) -> Vec<f32> {
+        self.colors.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn intensities(&self) -> Vec<f32> {
+        self.intensities.clone()
+    }
+}
+
+// Dense grids above this many cells switch to a sparse hash map of occupied cells
+const MAX_DENSE_CELLS: usize = 50_000_000;
+// Cells per axis are capped (by growing the cell size) so flat indices always fit in i64
+const MAX_GRID_AXIS_CELLS: f32 = (1u32 << 20) as f32;
+
+/// Smoothing grid dimensions. Cell indices are computed in i64 with every axis
+/// bounds-checked, so grids with more than i32::MAX cells cannot wrap into wrong cells.
+#[derive(Clone, Copy)]
+struct GridDims {
+    width: usize,
+    height: usize,
+    depth: usize,
+}
+
+impl GridDims {
+    /// Total cell count, or None if it overflows usize
+    fn cell_count(&self) -> Option<usize> {
+        self.width.checked_mul(self.height)?.checked_mul(self.depth)
+    }
+
+    /// Flat index of cell (gx, gy, gz), or None outside the grid
+    #[inline]
+    fn index(&self, gx: i64, gy: i64, gz: i64) -> Option<i64> {
+        let (width, height, depth) = (self.width as i64, self.height as i64, self.depth as i64);
+        if gx < 0 || gy < 0 || gz < 0 || gx >= width || gy >= height || gz >= depth {
+            return None;
+        }
+        Some(gx + width * (gy + height * gz))
+    }
+}
+
+/// Point lists per cell: a dense vector while the grid has at most MAX_DENSE_CELLS cells,
+/// otherwise a hash map holding only the occupied ones
+enum CellGrid {
+    Dense(Vec<Vec<usize>>),
+    Sparse(FxHashMap<i64, Vec<usize>>),
+}
+
+impl CellGrid {
+    fn new(dims: GridDims) -> CellGrid {
+        match dims.cell_count() {
+            Some(cells) if cells <= MAX_DENSE_CELLS => CellGrid::Dense(vec![Vec::with_capacity(8); cells]),
+            _ => CellGrid::Sparse(FxHashMap::default()),
+        }
+    }
+
+    /// Empty every cell, keeping allocations for the next iteration
+    fn clear(&mut self) {
+        match self {
+            CellGrid::Dense(cells) => cells.iter_mut().for_each(Vec::clear),
+            CellGrid::Sparse(cells) => cells.values_mut().for_each(Vec::clear),
+        }
+    }
+
+    fn push(&mut self, index: i64, point: usize) {
+        match self {
+            CellGrid::Dense(cells) => cells[index as usize].push(point),
+            CellGrid::Sparse(cells) => cells.entry(index).or_default().push(point),
+        }
+    }
+
+    fn cell(&self, index: i64) -> &[usize] {
+        match self {
+            CellGrid::Dense(cells) => &cells[index as usize],
+            CellGrid::Sparse(cells) => cells.get(&index).map_or(&[], Vec::as_slice),
+        }
+    }
+}
+
+/// Neighbor radius, pass count, search extent and threading of one smoothing run
+#[derive(Clone, Copy)]
+struct SmoothingOptions {
+    radius: f32,
+    iterations: i32,
+    search_cells: i32,
+    parallel: bool,
+}
+
+impl SmoothingOptions {
+    /// The default 3x3x3 cell search, parallel when built with the `parallel` feature
+    fn new(radius: f32, iterations: i32) -> SmoothingOptions {
+        SmoothingOptions { radius, iterations, search_cells: 1, parallel: cfg!(feature = "parallel") }
+    }
+}
+
+/// Position-only smoothing (original signature, kept for existing callers)
 pub fn point_cloud_smooth_internal(
     points: &[f32],
     smoothing_radius: f32,
     iterations: i32,
 ) -> Vec<f32> {
+    point_cloud_smooth_with_attributes_internal(points, None, None, smoothing_radius, iterations).positions
+}
+
+/// Smoothing that also averages colors and intensities over the same neighbor set used for
+/// the positions, so attributes follow the geometry instead of staying pinned to the input.
+/// Attributes whose length does not match the point count are ignored.
+/// Uses the rayon pool for the per-point neighbor phase when built with the `parallel` feature.
+pub fn point_cloud_smooth_with_attributes_internal(
+    points: &[f32],
+    colors: Option<&[f32]>,
+    intensities: Option<&[f32]>,
+    smoothing_radius: f32,
+    iterations: i32,
+) -> SmoothingResult {
+    smooth_with_attributes(
+        points,
+        colors,
+        intensities,
+        SmoothingOptions::new(smoothing_radius, iterations),
+        &mut |_, _| true,
+    )
+}
+
+/// Smoothing of an interleaved [x, y, z, r, g, b] array: neighbors come from the XYZ channels
+/// and RGB is averaged over the same neighborhoods. Returns the same interleaved layout.
+pub fn point_cloud_smooth_interleaved_internal(interleaved: &[f32], smoothing_radius: f32, iterations: i32) -> Vec<f32> {
+    let (points, colors) = deinterleave_xyzrgb(interleaved);
+    let result =
+        point_cloud_smooth_with_attributes_internal(&points, Some(&colors), None, smoothing_radius, iterations);
+    interleave_xyzrgb(&result.positions, &result.colors)
+}
+
+/// Position-only smoothing that calls `on_iteration(iteration, iterations)` after each pass
+/// (iteration counts from 0). Returning false stops early with the partially smoothed points.
+pub fn point_cloud_smooth_with_progress_internal(
+    points: &[f32],
+    smoothing_radius: f32,
+    iterations: i32,
+    mut on_iteration: impl FnMut(i32, i32) -> bool,
+) -> Vec<f32> {
+    smooth_with_attributes(
+        points,
+        None,
+        None,
+        SmoothingOptions::new(smoothing_radius, iterations),
+        &mut on_iteration,
+    )
+    .positions
+}
+
+/// Position-only smoothing whose grid cells are `smoothing_radius / search_cells` wide, with
+/// neighbors gathered from the (2 * search_cells + 1)^3 surrounding cells so the whole radius
+/// is always covered. search_cells = 1 is the default 3x3x3 search; values below 1 act as 1.
+pub fn point_cloud_smooth_with_search_cells_internal(
+    points: &[f32],
+    smoothing_radius: f32,
+    iterations: i32,
+    search_cells: i32,
+) -> Vec<f32> {
+    let options = SmoothingOptions {
+        search_cells: search_cells.max(1),
+        ..SmoothingOptions::new(smoothing_radius, iterations)
+    };
+    smooth_with_attributes(points, None, None, options, &mut |_, _| true)
+    .positions
+}
+
+fn smooth_with_attributes(
+    points: &[f32],
+    colors: Option<&[f32]>,
+    intensities: Option<&[f32]>,
+    options: SmoothingOptions,
+    on_iteration: &mut dyn FnMut(i32, i32) -> bool,
+) -> SmoothingResult {
+    let SmoothingOptions { radius: smoothing_radius, iterations, search_cells, parallel } = options;
     console_log!("Rust WASM: Starting O(n) spatial hashing point cloud smoothing with {} points, radius: {}, iterations: {}", 
                 points.len() / 3, smoothing_radius, iterations);
+
+    let point_count = points.len() / 3;
+    let colors = colors.filter(|c| c.len() == point_count * 3);
+    let intensities = intensities.filter(|i| i.len() == point_count);
+    let mut smoothed_colors = colors.map(|c| c.to_vec()).unwrap_or_default();
+    let mut smoothed_intensities = intensities.map(|i| i.to_vec()).unwrap_or_default();
+    let use_colors = colors.is_some();
+    let use_intensity = intensities.is_some();
     
     // Validate input
-    if points.len() % 3 != 0 {
+    if !points.len().is_multiple_of(3) {
         console_log!("Rust WASM: Error - points array length {} is not divisible by 3", points.len());
-        return points.to_vec();
+        return SmoothingResult {
+            positions: points.to_vec(),
+            colors: smoothed_colors,
+            intensities: smoothed_intensities,
+        };
+    }
+    if point_count == 0 {
+        return SmoothingResult {
+            positions: Vec::new(),
+            colors: smoothed_colors,
+            intensities: smoothed_intensities,
+        };
     }
     
-    let point_count = points.len() / 3;
-    let length = points.len();
     let mut smoothed_points = points.to_vec();
     let radius_squared = smoothing_radius * smoothing_radius;
-    let cell_size = smoothing_radius;
-    let inv_cell_size = 1.0f32 / cell_size;
     
-    // Find bounding box - single pass
-    let mut min_x = points[0];
-    let mut max_x = points[0];
-    let mut min_y = points[1];
-    let mut max_y = points[1];
-    let mut min_z = points[2];
-    let mut max_z = points[2];
+    // Find bounding box - single pass, over the finite points only. Points with a non-finite
+    // coordinate stay out of the grid, neither move nor move their neighbors.
+    let [min_x, min_y, min_z, max_x, max_y, max_z] = compute_bounds_internal(points);
     
-    for i in (0..length).step_by(3) {
-        min_x = min_x.min(points[i]);
-        max_x = max_x.max(points[i]);
-        min_y = min_y.min(points[i + 1]);
-        max_y = max_y.max(points[i + 1]);
-        min_z = min_z.min(points[i + 2]);
-        max_z = max_z.max(points[i + 2]);
-    }
+    // Cells are radius / search_cells wide unless the extent would need more than
+    // MAX_GRID_AXIS_CELLS per axis; larger cells still cover the radius, so the search stays exact
+    let max_extent = (max_x - min_x).max(max_y - min_y).max(max_z - min_z);
+    let cell_size = (smoothing_radius / search_cells as f32).max(max_extent / MAX_GRID_AXIS_CELLS);
+    let inv_cell_size = 1.0f32 / cell_size;
     
     // Calculate grid dimensions
-    let grid_width = ((max_x - min_x) * inv_cell_size) as usize + 1;
-    let grid_height = ((max_y - min_y) * inv_cell_size) as usize + 1;
-    let grid_depth = ((max_z - min_z) * inv_cell_size) as usize + 1;
-    let grid_size = grid_width * grid_height * grid_depth;
+    let dims = GridDims {
+        width: ((max_x - min_x) * inv_cell_size) as usize + 1,
+        height: ((max_y - min_y) * inv_cell_size) as usize + 1,
+        depth: ((max_z - min_z) * inv_cell_size) as usize + 1,
+    };
     
-    // Pre-allocate grid with capacity estimation
-    let mut grid: Vec<Vec<usize>> = vec![Vec::with_capacity(8); grid_size];
+    // Pre-allocate grid with capacity estimation (sparse when the dense grid would be too large)
+    let mut grid = CellGrid::new(dims);
     
-    // Hash function to get grid index (same as C++ WASM - truncate toward zero)
-    let get_grid_index = |x: f32, y: f32, z: f32| -> i32 {
-        let gx = ((x - min_x) * inv_cell_size) as i32;
-        let gy = ((y - min_y) * inv_cell_size) as i32;
-        let gz = ((z - min_z) * inv_cell_size) as i32;
-        gx + gy * grid_width as i32 + gz * grid_width as i32 * grid_height as i32
+    // Grid cell of a position (same as C++ WASM - truncate toward zero)
+    let get_grid_cell = |x: f32, y: f32, z: f32| -> [i64; 3] {
+        [
+            ((x - min_x) * inv_cell_size) as i64,
+            ((y - min_y) * inv_cell_size) as i64,
+            ((z - min_z) * inv_cell_size) as i64,
+        ]
+    };
+    let get_grid_index = |x: f32, y: f32, z: f32| -> Option<i64> {
+        let [gx, gy, gz] = get_grid_cell(x, y, z);
+        dims.index(gx, gy, gz)
     };
     
+    let mut neighbor_sums = vec![NeighborSums::default(); point_count];
+    
     // Smoothing iterations using spatial hashing (same as C++ WASM)
-    for _iter in 0..iterations {
+    for iter in 0..iterations {
         // Copy current state to temp buffer (same as C++ WASM)
         let temp_points = smoothed_points.clone();
+        let temp_colors = smoothed_colors.clone();
+        let temp_intensities = smoothed_intensities.clone();
         
         // Clear grid efficiently
-        for cell in &mut grid {
-            cell.clear();
-        }
+        grid.clear();
         
         // Populate grid with PREVIOUS iteration's point positions (same as C++ WASM)
         for i in 0..point_count {
@@ -69,36 +289,37 @@ pub fn point_cloud_smooth_internal(
             let x = temp_points[i3];
             let y = temp_points[i3 + 1];
             let z = temp_points[i3 + 2];
-            let grid_index = get_grid_index(x, y, z);
-            if grid_index >= 0 && grid_index < grid_size as i32 {
-                grid[grid_index as usize].push(i);
+            if !is_finite_point(&temp_points[i3..i3 + 3]) {
+                continue;
+            }
+            if let Some(grid_index) = get_grid_index(x, y, z) {
+                grid.push(grid_index, i);
             }
         }
         
-        // Process each point using spatial hash (same as C++ WASM)
-        for i in 0..point_count {
+        // Gather each point's neighbor sums from the read-only grid (same as C++ WASM).
+        // Every point only reads temp_* and grid and writes its own slot, so this phase can
+        // run on the rayon pool; the grid build above stays serial.
+        let gather_neighbors = |i: usize| -> NeighborSums {
             let i3 = i * 3;
             let x = temp_points[i3];
             let y = temp_points[i3 + 1];
             let z = temp_points[i3 + 2];
+            let mut sums = NeighborSums::default();
+            if !is_finite_point(&temp_points[i3..i3 + 3]) {
+                return sums;
+            }
             
-            let mut sum_x = 0.0;
-            let mut sum_y = 0.0;
-            let mut sum_z = 0.0;
-            let mut count = 0;
-            
-            // Check neighboring grid cells (3x3x3 = 27 cells) - same as C++ WASM
-            for dx in -1..=1 {
-                for dy in -1..=1 {
-                    for dz in -1..=1 {
-                        let grid_index = get_grid_index(
-                            x + dx as f32 * cell_size,
-                            y + dy as f32 * cell_size,
-                            z + dz as f32 * cell_size
-                        );
-                        
-                        if grid_index >= 0 && grid_index < grid_size as i32 {
-                            for &j in &grid[grid_index as usize] {
+            // Check neighboring grid cells (3x3x3 = 27 cells with search_cells = 1). Offsets are
+            // applied to the integer cell, so float rounding can neither skip a cell nor visit
+            // one twice near the grid origin.
+            let [gx, gy, gz] = get_grid_cell(x, y, z);
+            let search = search_cells as i64;
+            for dx in -search..=search {
+                for dy in -search..=search {
+                    for dz in -search..=search {
+                        if let Some(grid_index) = dims.index(gx + dx, gy + dy, gz + dz) {
+                            for &j in grid.cell(grid_index) {
                                 if i == j { continue; }
                                 
                                 let j3 = j * 3;
@@ -113,27 +334,571 @@ pub fn point_cloud_smooth_internal(
                                 let distance_squared = dx2 * dx2 + dy2 * dy2 + dz2 * dz2;
                                 
                                 if distance_squared <= radius_squared {
-                                    sum_x += jx;
-                                    sum_y += jy;
-                                    sum_z += jz;
-                                    count += 1;
+                                    sums.position[0] += jx;
+                                    sums.position[1] += jy;
+                                    sums.position[2] += jz;
+                                    if use_colors {
+                                        sums.color[0] += temp_colors[j3];
+                                        sums.color[1] += temp_colors[j3 + 1];
+                                        sums.color[2] += temp_colors[j3 + 2];
+                                    }
+                                    if use_intensity {
+                                        sums.intensity += temp_intensities[j];
+                                    }
+                                    sums.count += 1;
                                 }
                             }
                         }
                     }
                 }
             }
-            
-            // Apply smoothing if neighbors found (same as C++ WASM)
-            if count > 0 {
-                smoothed_points[i3] = (x + sum_x) / (count + 1) as f32;
-                smoothed_points[i3 + 1] = (y + sum_y) / (count + 1) as f32;
-                smoothed_points[i3 + 2] = (z + sum_z) / (count + 1) as f32;
+            sums
+        };
+        
+        if parallel {
+            #[cfg(feature = "parallel")]
+            neighbor_sums
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(i, sums)| *sums = gather_neighbors(i));
+        } else {
+            for (i, sums) in neighbor_sums.iter_mut().enumerate() {
+                *sums = gather_neighbors(i);
             }
         }
+        
+        // Apply smoothing if neighbors found (same as C++ WASM)
+        for (i, sums) in neighbor_sums.iter().enumerate() {
+            if sums.count > 0 {
+                let i3 = i * 3;
+                let divisor = (sums.count + 1) as f32;
+                for axis in 0..3 {
+                    smoothed_points[i3 + axis] = (temp_points[i3 + axis] + sums.position[axis]) / divisor;
+                    if use_colors {
+                        smoothed_colors[i3 + axis] = (temp_colors[i3 + axis] + sums.color[axis]) / divisor;
+                    }
+                }
+                if use_intensity {
+                    smoothed_intensities[i] = (temp_intensities[i] + sums.intensity) / divisor;
+                }
+            }
+        }
+        
+        // Report progress; the caller can cancel between iterations
+        if !on_iteration(iter, iterations) {
+            console_log!("Rust WASM: smoothing cancelled after iteration {}", iter);
+            break;
+        }
     }
     
     console_log!("Rust WASM: O(n) spatial hashing point cloud smoothing completed");
-    smoothed_points
+    SmoothingResult {
+        positions: smoothed_points,
+        colors: smoothed_colors,
+        intensities: smoothed_intensities,
+    }
+}
+
+/// Edge-preserving bilateral smoothing.
+/// Each neighbor within 2 * sigma_s is weighted by exp(-d^2 / 2 sigma_s^2). When normals
+/// (3 per point, matching the point count) are supplied, the weight also includes
+/// exp(-h^2 / 2 sigma_n^2), where h is the neighbor's offset along the point's normal, and the
+/// point only moves along its normal by the weighted mean offset. Points across a crease have
+/// large offsets, so corners stay sharp. Without normals, the result is the spatially weighted
+/// mean of the neighborhood (the point included). Points with a non-finite coordinate are left
+/// unchanged and are nobody's neighbor.
+pub fn bilateral_smooth_internal(
+    points: &[f32],
+    normals: Option<&[f32]>,
+    sigma_s: f32,
+    sigma_n: f32,
+    iterations: i32,
+) -> Vec<f32> {
+    let point_count = points.len() / 3;
+    if !points.len().is_multiple_of(3) || point_count == 0 || sigma_s.is_nan() || sigma_s <= 0.0 {
+        return points.to_vec();
+    }
+    let normals = normals.filter(|n| n.len() == points.len() && sigma_n > 0.0);
+
+    let radius = 2.0 * sigma_s;
+    let radius_squared = radius * radius;
+    let inv_cell_size = 1.0 / radius;
+    let inv_two_sigma_s_squared = 1.0 / (2.0 * sigma_s * sigma_s);
+    let inv_two_sigma_n_squared = 1.0 / (2.0 * sigma_n * sigma_n);
+    let cell_of = |x: f32, y: f32, z: f32| {
+        (
+            (x * inv_cell_size).floor() as i32,
+            (y * inv_cell_size).floor() as i32,
+            (z * inv_cell_size).floor() as i32,
+        )
+    };
+
+    let mut smoothed = points.to_vec();
+    let mut cells: FxHashMap<VoxelKey, Vec<usize>> = FxHashMap::default();
+
+    for _iter in 0..iterations {
+        let current = smoothed.clone();
+        cells.clear();
+        for (i, p) in current.chunks_exact(3).enumerate().filter(|(_, p)| is_finite_point(p)) {
+            let (cx, cy, cz) = cell_of(p[0], p[1], p[2]);
+            cells.entry(voxel_key(cx, cy, cz)).or_default().push(i);
+        }
+
+        for (i, out) in smoothed.chunks_exact_mut(3).enumerate() {
+            let i3 = i * 3;
+            if !is_finite_point(&current[i3..i3 + 3]) {
+                continue;
+            }
+            let (x, y, z) = (current[i3], current[i3 + 1], current[i3 + 2]);
+            let (cx, cy, cz) = cell_of(x, y, z);
+            let normal = normals.map(|n| [n[i3], n[i3 + 1], n[i3 + 2]]);
+            let mut weight_sum = 0.0f32;
+            let mut offset_sum = 0.0f32;
+            let mut position_sum = [0.0f32; 3];
+
+            for gx in cx - 1..=cx + 1 {
+                for gy in cy - 1..=cy + 1 {
+                    for gz in cz - 1..=cz + 1 {
+                        let Some(cell) = cells.get(&voxel_key(gx, gy, gz)) else { continue };
+                        for &j in cell {
+                            let j3 = j * 3;
+                            let d = [current[j3] - x, current[j3 + 1] - y, current[j3 + 2] - z];
+                            let distance_squared = d[0] * d[0] + d[1] * d[1] + d[2] * d[2];
+                            if distance_squared > radius_squared {
+                                continue;
+                            }
+                            let spatial = (-distance_squared * inv_two_sigma_s_squared).exp();
+                            match normal {
+                                Some(n) => {
+                                    let h = n[0] * d[0] + n[1] * d[1] + n[2] * d[2];
+                                    let weight = spatial * (-h * h * inv_two_sigma_n_squared).exp();
+                                    weight_sum += weight;
+                                    offset_sum += weight * h;
+                                }
+                                None => {
+                                    weight_sum += spatial;
+                                    for axis in 0..3 {
+                                        position_sum[axis] += spatial * current[j3 + axis];
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            // The point itself is always in its own neighborhood, so weight_sum >= 1
+            match normal {
+                Some(n) => {
+                    let shift = offset_sum / weight_sum;
+                    for axis in 0..3 {
+                        out[axis] = current[i3 + axis] + shift * n[axis];
+                    }
+                }
+                None => {
+                    for axis in 0..3 {
+                        out[axis] = position_sum[axis] / weight_sum;
+                    }
+                }
+            }
+        }
+    }
+
+    smoothed
+}
+
+/// Reusable uniform grid over a point cloud for repeated radius queries (lasso select,
+/// measuring) without rebuilding between them. Cells hold point indices in the same dense or
+/// sparse storage as the smoothing grid; queries visit every cell the radius overlaps, so the
+/// radius may be larger than the cell size.
+#[wasm_bindgen]
+pub struct SpatialGrid {
+    points: Vec<f32>,
+    min: [f32; 3],
+    inv_cell_size: f32,
+    dims: GridDims,
+    cells: CellGrid,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl SpatialGrid {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SpatialGrid {
+        let dims = GridDims { width: 0, height: 0, depth: 0 };
+        SpatialGrid { points: Vec::new(), min: [0.0; 3], inv_cell_size: 0.0, dims, cells: CellGrid::new(dims) }
+    }
+
+    /// Replace the grid contents with `points` (flat xyz). Points with a non-finite coordinate
+    /// are left out. Returns the number of points indexed; 0 when cell_size is not positive.
+    pub fn build(&mut self, points: &[f32], cell_size: f32) -> usize {
+        *self = SpatialGrid::new();
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for p in points.chunks_exact(3).filter(|p| is_finite_point(p)) {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(p[axis]);
+                max[axis] = max[axis].max(p[axis]);
+            }
+        }
+        if !(cell_size > 0.0 && cell_size.is_finite()) || min[0] > max[0] {
+            return 0;
+        }
+
+        // Same cap on cells per axis as the smoothing grid
+        let max_extent = (max[0] - min[0]).max(max[1] - min[1]).max(max[2] - min[2]);
+        let inv_cell_size = 1.0 / cell_size.max(max_extent / MAX_GRID_AXIS_CELLS);
+        let [width, height, depth] = [0, 1, 2].map(|axis| ((max[axis] - min[axis]) * inv_cell_size) as usize + 1);
+        self.dims = GridDims { width, height, depth };
+        self.cells = CellGrid::new(self.dims);
+        self.min = min;
+        self.inv_cell_size = inv_cell_size;
+
+        let mut indexed = 0;
+        for (i, p) in points.chunks_exact(3).enumerate().filter(|(_, p)| is_finite_point(p)) {
+            let [gx, gy, gz] = self.cell_of(p[0], p[1], p[2]);
+            if let Some(index) = self.dims.index(gx, gy, gz) {
+                self.cells.push(index, i);
+                indexed += 1;
+            }
+        }
+        self.points = points.to_vec();
+        indexed
+    }
+
+    /// Indices of the points within `radius` of (x, y, z), ascending. A point at the query
+    /// location itself is included.
+    pub fn neighbors(&self, x: f32, y: f32, z: f32, radius: f32) -> Vec<u32> {
+        let mut found = Vec::new();
+        if radius.is_nan() || radius < 0.0 || self.points.is_empty() {
+            return found;
+        }
+
+        // Cell range covered by the query box, clamped to the grid
+        let low = self.cell_of(x - radius, y - radius, z - radius);
+        let high = self.cell_of(x + radius, y + radius, z + radius);
+        let limits = [self.dims.width, self.dims.height, self.dims.depth].map(|n| n as i64 - 1);
+        let [lx, ly, lz] = [0, 1, 2].map(|axis| low[axis].max(0));
+        let [hx, hy, hz] = [0, 1, 2].map(|axis| high[axis].min(limits[axis]));
+
+        let radius_squared = radius * radius;
+        for gz in lz..=hz {
+            for gy in ly..=hy {
+                for gx in lx..=hx {
+                    let Some(index) = self.dims.index(gx, gy, gz) else { continue };
+                    for &j in self.cells.cell(index) {
+                        let p = &self.points[j * 3..j * 3 + 3];
+                        let distance_squared = (p[0] - x).powi(2) + (p[1] - y).powi(2) + (p[2] - z).powi(2);
+                        if distance_squared <= radius_squared {
+                            found.push(j as u32);
+                        }
+                    }
+                }
+            }
+        }
+        found.sort_unstable();
+        found
+    }
+}
+
+impl SpatialGrid {
+    /// Grid cell of a position, floored so positions below the grid origin land outside it
+    fn cell_of(&self, x: f32, y: f32, z: f32) -> [i64; 3] {
+        let position = [x, y, z];
+        [0, 1, 2].map(|axis| ((position[axis] - self.min[axis]) * self.inv_cell_size).floor() as i64)
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_cloud(count: usize, spacing: f32) -> Vec<f32> {
+        (0..count).flat_map(|i| [i as f32 * spacing, 0.0, 0.0]).collect()
+    }
+
+    #[test]
+    fn test_points_binned_beyond_i32_cell_range() {
+        // 2000 x 2000 x 1000 one-unit cells: 4e9 in total, past i32::MAX, so the grid is sparse
+        // and the two stacked points near the top land in cells whose flat indices exceed i32
+        let points = [0.0, 0.0, 0.0, 1999.5, 1999.5, 999.5, 7.5, 3.5, 900.5, 7.5, 3.5, 899.5];
+        let mut grid = SpatialGrid::new();
+        assert_eq!(grid.build(&points, 1.0), 4);
+        assert_eq!((grid.dims.width, grid.dims.height, grid.dims.depth), (2000, 2000, 1000));
+        assert!(matches!(grid.cells, CellGrid::Sparse(_)));
+
+        let high = grid.dims.index(7, 3, 900).unwrap();
+        assert!(high > i32::MAX as i64);
+        assert_eq!(grid.cells.cell(high), &[2]);
+        assert_eq!(grid.cells.cell(grid.dims.index(7, 3, 899).unwrap()), &[3]);
+        assert_eq!(grid.cells.cell(grid.dims.index(1999, 1999, 999).unwrap()), &[1]);
+        assert_eq!(grid.cells.cell(grid.dims.index(0, 0, 0).unwrap()), &[0]);
+        assert_eq!(grid.dims.index(2000, 0, 0), None);
+        assert_eq!(grid.dims.index(0, -1, 0), None);
+
+        assert_eq!(grid.neighbors(7.5, 3.5, 900.0, 0.6), vec![2, 3]);
+        assert_eq!(grid.neighbors(7.5, 3.5, 900.5, 0.5), vec![2]);
+
+        // A small cloud keeps dense storage and bins the same way
+        let mut grid = SpatialGrid::new();
+        assert_eq!(grid.build(&[0.5, 0.5, 0.5, 2.5, 1.5, 0.5], 1.0), 2);
+        assert!(matches!(grid.cells, CellGrid::Dense(_)));
+        assert_eq!(grid.cells.cell(grid.dims.index(2, 1, 0).unwrap()), &[1]);
+        assert!(grid.cells.cell(grid.dims.index(1, 1, 0).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_huge_extent_with_tiny_radius() {
+        // (radius, far corner, pair position): 2^20 cells per axis (2^60 in total), then an
+        // extent needing 2^24 cells per axis, past MAX_GRID_AXIS_CELLS. Both must complete and
+        // still average the close pair while the corner points stay put. Powers of two keep
+        // the cell arithmetic exact.
+        for (radius, far, base) in [(1.0f32 / 1024.0, 1024.0f32, 1.0f32), (1.0, 16_777_216.0, 100.0)] {
+            let gap = radius * 0.5;
+            let points = vec![0.0, 0.0, 0.0, base, base, base, base + gap, base, base, far, far, far];
+            let smoothed = point_cloud_smooth_internal(&points, radius, 1);
+            let midpoint = base + gap * 0.5;
+            assert!((smoothed[3] - midpoint).abs() < 1e-5 * base, "{:?}", smoothed);
+            assert!((smoothed[6] - midpoint).abs() < 1e-5 * base, "{:?}", smoothed);
+            assert_eq!(&smoothed[9..], &[far, far, far]);
+        }
+    }
+
+    #[test]
+    fn test_progress_callback_cancels_after_requested_iteration() {
+        let mut points = line_cloud(20, 0.1);
+        points[10 * 3 + 1] = 0.3;
+
+        let mut calls = Vec::new();
+        let cancelled = point_cloud_smooth_with_progress_internal(&points, 0.25, 10, |iteration, total| {
+            calls.push((iteration, total));
+            iteration < 2
+        });
+        assert_eq!(calls, vec![(0, 10), (1, 10), (2, 10)]);
+        // Partial result equals a full run with as many iterations as completed
+        assert_eq!(cancelled, point_cloud_smooth_internal(&points, 0.25, 3));
+
+        let mut count = 0;
+        let full = point_cloud_smooth_with_progress_internal(&points, 0.25, 4, |_, _| {
+            count += 1;
+            true
+        });
+        assert_eq!(count, 4);
+        assert_eq!(full, point_cloud_smooth_internal(&points, 0.25, 4));
+    }
+
+    #[test]
+    fn test_search_cells_cover_full_radius() {
+        // Two points 0.9 radius apart. With cells of radius / 2 they sit two cells apart, which a
+        // fixed 3x3x3 search would miss; search_cells = 2 widens the search to match.
+        let points = vec![0.05, 0.05, 0.05, 0.05 + 0.9 * 0.4, 0.05, 0.05];
+        let smoothed = point_cloud_smooth_with_search_cells_internal(&points, 0.4, 1, 2);
+        let midpoint = 0.05 + 0.45 * 0.4;
+        assert!((smoothed[0] - midpoint).abs() < 1e-6 && (smoothed[3] - midpoint).abs() < 1e-6);
+
+        // Any search_cells gives the brute-force radius neighborhood
+        let cloud: Vec<f32> = (0..200)
+            .flat_map(|i| {
+                let t = i as f32 * 0.37;
+                [t.sin() * 0.8, (t * 1.3).cos() * 0.8, (t * 0.7).sin() * 0.3]
+            })
+            .collect();
+        let radius = 0.2f32;
+        let mut expected = cloud.clone();
+        for (i, p) in cloud.chunks_exact(3).enumerate() {
+            let (mut sum, mut count) = ([p[0], p[1], p[2]], 1);
+            for (j, q) in cloud.chunks_exact(3).enumerate() {
+                let d2 = (p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2) + (p[2] - q[2]).powi(2);
+                if i != j && d2 <= radius * radius {
+                    (0..3).for_each(|a| sum[a] += q[a]);
+                    count += 1;
+                }
+            }
+            (0..3).for_each(|a| expected[i * 3 + a] = sum[a] / count as f32);
+        }
+        for search_cells in 1..=3 {
+            let smoothed = point_cloud_smooth_with_search_cells_internal(&cloud, radius, 1, search_cells);
+            for (got, want) in smoothed.iter().zip(&expected) {
+                assert!((got - want).abs() < 1e-5, "search_cells {}", search_cells);
+            }
+        }
+    }
+
+    #[test]
+    fn test_uniform_color_unchanged() {
+        let points = line_cloud(20, 0.1);
+        let colors: Vec<f32> = (0..20).flat_map(|_| [0.2, 0.4, 0.6]).collect();
+        let result = point_cloud_smooth_with_attributes_internal(&points, Some(&colors), None, 0.25, 3);
+        for (a, b) in result.colors().iter().zip(&colors) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_color_gradient_blurred_with_positions() {
+        // Red channel and intensity both equal x, so averaging them over the same neighbor
+        // set as the positions must keep them equal to the smoothed x
+        let mut points = line_cloud(20, 0.1);
+        points[10 * 3 + 1] = 0.3; // bump one point so positions actually move
+        let colors: Vec<f32> = (0..20).flat_map(|i| [i as f32 * 0.1, 0.0, 1.0]).collect();
+        let intensities: Vec<f32> = (0..20).map(|i| i as f32 * 0.1).collect();
+        let result = point_cloud_smooth_with_attributes_internal(
+            &points,
+            Some(&colors),
+            Some(&intensities),
+            0.35,
+            2,
+        );
+        let positions = result.positions();
+        let smoothed_colors = result.colors();
+        let smoothed_intensities = result.intensities();
+        for i in 0..20 {
+            assert!((smoothed_colors[i * 3] - positions[i * 3]).abs() < 1e-5);
+            assert!((smoothed_colors[i * 3 + 2] - 1.0).abs() < 1e-6);
+            assert!((smoothed_intensities[i] - positions[i * 3]).abs() < 1e-5);
+        }
+        // The bumped point's neighbors pulled toward it, so the gradient was blurred
+        assert!(positions[9 * 3 + 1] > 0.0);
+    }
+
+    #[test]
+    fn test_wrapper_matches_attribute_version() {
+        let points = line_cloud(15, 0.07);
+        let plain = point_cloud_smooth_internal(&points, 0.2, 2);
+        let with_attributes = point_cloud_smooth_with_attributes_internal(&points, None, None, 0.2, 2);
+        assert_eq!(plain, with_attributes.positions());
+        assert!(with_attributes.colors().is_empty());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_matches_serial() {
+        let points: Vec<f32> = (0..5000)
+            .flat_map(|i| {
+                let f = i as f32;
+                [(i % 70) as f32 * 0.05, (i / 70) as f32 * 0.05, (f * 0.3).sin() * 0.02]
+            })
+            .collect();
+        let colors: Vec<f32> = (0..5000).flat_map(|i| [(i % 7) as f32 / 7.0, 0.5, 1.0]).collect();
+        let intensities: Vec<f32> = (0..5000).map(|i| (i % 13) as f32).collect();
+
+        let options = SmoothingOptions::new(0.12, 4);
+        let serial_options = SmoothingOptions { parallel: false, ..options };
+        let parallel_options = SmoothingOptions { parallel: true, ..options };
+        let serial = smooth_with_attributes(&points, Some(&colors), Some(&intensities), serial_options, &mut |_, _| true);
+        let parallel = smooth_with_attributes(&points, Some(&colors), Some(&intensities), parallel_options, &mut |_, _| true);
+
+        assert_eq!(serial.positions, parallel.positions);
+        assert_eq!(serial.colors, parallel.colors);
+        assert_eq!(serial.intensities, parallel.intensities);
+    }
+
+    #[test]
+    fn test_bilateral_keeps_crease_sharper_than_averaging() {
+        // L-shaped cross-section extruded along y: floor z = 0 for x < 1, wall x = 1 for z > 0
+        let spacing = 0.025;
+        let mut points = Vec::new();
+        let mut normals = Vec::new();
+        for k in 0..10 {
+            let y = k as f32 * spacing;
+            for i in 0..=40 {
+                points.extend_from_slice(&[i as f32 * spacing, y, 0.0]);
+                normals.extend_from_slice(&[0.0, 0.0, 1.0]);
+            }
+            for i in 1..=40 {
+                points.extend_from_slice(&[1.0, y, i as f32 * spacing]);
+                normals.extend_from_slice(&[1.0, 0.0, 0.0]);
+            }
+        }
+        // Distance to the L: floor points are judged by z, wall points by x
+        let max_error = |smoothed: &[f32]| -> f32 {
+            smoothed
+                .chunks_exact(3)
+                .zip(normals.chunks_exact(3))
+                .map(|(p, n)| if n[2] == 1.0 { p[2].abs() } else { (p[0] - 1.0).abs() })
+                .fold(0.0, f32::max)
+        };
+
+        let averaged = point_cloud_smooth_internal(&points, 0.1, 2);
+        let bilateral = bilateral_smooth_internal(&points, Some(&normals), 0.05, 0.02, 2);
+
+        let averaged_error = max_error(&averaged);
+        let bilateral_error = max_error(&bilateral);
+        assert!(averaged_error > 0.01, "averaging should round the crease: {}", averaged_error);
+        assert!(
+            bilateral_error < averaged_error * 0.5,
+            "bilateral {} vs averaging {}",
+            bilateral_error,
+            averaged_error
+        );
+    }
+
+    #[test]
+    fn test_bilateral_without_normals_is_identity_on_a_single_point() {
+        let points = vec![1.0, 2.0, 3.0];
+        assert_eq!(bilateral_smooth_internal(&points, None, 0.1, 0.1, 3), points);
+    }
+
+    #[test]
+    fn test_non_finite_points_neither_move_nor_pull_neighbors() {
+        let points = vec![
+            0.0, 0.0, 0.0,
+            0.2, 0.0, 0.0,
+            f32::NAN, 0.1, 0.0,
+            0.1, f32::INFINITY, 0.0,
+            0.1, 0.0, f32::NEG_INFINITY,
+        ];
+        let smoothed = point_cloud_smooth_internal(&points, 0.5, 1);
+        assert_eq!(&smoothed[..6], &[0.1, 0.0, 0.0, 0.1, 0.0, 0.0]);
+        assert!(smoothed[6].is_nan());
+        assert_eq!(&smoothed[7..], &points[7..]);
+
+        let bilateral = bilateral_smooth_internal(&points, None, 0.5, 0.1, 1);
+        assert!(bilateral[0] > 0.05 && (bilateral[0] + bilateral[3] - 0.2).abs() < 1e-6);
+        assert!(bilateral[6].is_nan());
+        assert_eq!(&bilateral[7..], &points[7..]);
+    }
+
+    #[test]
+    fn test_spatial_grid_neighbors_match_brute_force() {
+        let points: Vec<f32> = (0..800)
+            .flat_map(|i| {
+                let f = i as f32;
+                [(f * 0.37).sin() * 3.0, (f * 0.11).cos() * 3.0, (f * 0.053).sin()]
+            })
+            .collect();
+        let mut grid = SpatialGrid::new();
+        assert_eq!(grid.build(&points, 0.25), 800);
+
+        let brute_force = |q: [f32; 3], radius: f32| -> Vec<u32> {
+            (0..800u32)
+                .filter(|&j| {
+                    let p = &points[j as usize * 3..j as usize * 3 + 3];
+                    (p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2) + (p[2] - q[2]).powi(2) <= radius * radius
+                })
+                .collect()
+        };
+        // Radii below and well above the cell size, queries on a point and off the cloud
+        for (q, radius) in [
+            ([points[30], points[31], points[32]], 0.1),
+            ([points[300], points[301], points[302]], 0.8),
+            ([0.0, 0.0, 0.0], 2.0),
+            ([-3.5, 3.5, -1.5], 1.0),
+            ([50.0, 0.0, 0.0], 1.0),
+        ] {
+            let found = grid.neighbors(q[0], q[1], q[2], radius);
+            assert_eq!(found, brute_force(q, radius), "query {:?} radius {}", q, radius);
+        }
+        assert!(grid.neighbors(points[30], points[31], points[32], 0.0).contains(&10));
+
+        assert_eq!(grid.build(&[f32::NAN, 0.0, 0.0, 1.0, 1.0, 1.0], 0.5), 1);
+        assert_eq!(grid.neighbors(1.0, 1.0, 1.0, 5.0), vec![1]);
+        assert_eq!(grid.build(&points, 0.0), 0);
+        assert!(grid.neighbors(0.0, 0.0, 0.0, 10.0).is_empty());
+    }
+}
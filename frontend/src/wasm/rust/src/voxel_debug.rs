@@ -1,4 +1,26 @@
-use rustc_hash::FxHashSet;
+use crate::common::{is_finite_point, voxel_key, VoxelKey};
+use rustc_hash::FxHashMap;
+use wasm_bindgen::prelude::*;
+
+/// Voxel centers with the number of input points in each voxel, in the same order
+#[wasm_bindgen]
+pub struct VoxelCentersResult {
+    centers: Vec<f32>,
+    counts: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl VoxelCentersResult {
+    #[wasm_bindgen(getter)]
+    pub fn centers(&self) -> Vec<f32> {
+        self.centers.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn counts(&self) -> Vec<u32> {
+        self.counts.clone()
+    }
+}
 
 pub fn generate_voxel_centers_internal(
     points: &[f32],
@@ -7,6 +29,18 @@ pub fn generate_voxel_centers_internal(
     min_y: f32,
     min_z: f32,
 ) -> Vec<f32> {
+    generate_voxel_centers_with_counts_internal(points, voxel_size, min_x, min_y, min_z).centers
+}
+
+/// Unique voxel centers plus per-voxel occupancy counts (for coloring debug cubes by density).
+/// Points with a non-finite coordinate are not counted.
+pub fn generate_voxel_centers_with_counts_internal(
+    points: &[f32],
+    voxel_size: f32,
+    min_x: f32,
+    min_y: f32,
+    min_z: f32,
+) -> VoxelCentersResult {
     // Pre-calculate constants to avoid repeated calculations
     let inv_voxel_size = 1.0 / voxel_size;
     let half_voxel_size = voxel_size * 0.5;
@@ -14,8 +48,8 @@ pub fn generate_voxel_centers_internal(
     let offset_y = min_y + half_voxel_size;
     let offset_z = min_z + half_voxel_size;
     
-    // Use fast hash set with integer keys to track unique voxels
-    let mut voxel_keys: FxHashSet<u64> = FxHashSet::default();
+    // Use fast hash map with integer keys to count points per unique voxel
+    let mut voxel_counts: FxHashMap<VoxelKey, u32> = FxHashMap::default();
     
     // Process points in chunks for better CPU cache performance
     const CHUNK_SIZE: usize = 1024;
@@ -26,6 +60,9 @@ pub fn generate_voxel_centers_internal(
         
         for i in chunk_start..chunk_end {
             let i3 = i * 3;
+            if !is_finite_point(&points[i3..i3 + 3]) {
+                continue;
+            }
             let x = points[i3];
             let y = points[i3 + 1];
             let z = points[i3 + 2];
@@ -36,23 +73,19 @@ pub fn generate_voxel_centers_internal(
             let voxel_z = ((z - min_z) * inv_voxel_size).floor() as i32;
             
             // Combine coordinates into single integer hash key
-            let voxel_key = ((voxel_x as u64) << 32) | ((voxel_y as u64) << 16) | (voxel_z as u64);
+            let voxel_key = voxel_key(voxel_x, voxel_y, voxel_z);
             
-            voxel_keys.insert(voxel_key);
+            *voxel_counts.entry(voxel_key).or_insert(0) += 1;
         }
     }
     
     // Pre-allocate result vector with exact capacity
-    let voxel_count = voxel_keys.len();
+    let voxel_count = voxel_counts.len();
     let mut centers = Vec::with_capacity(voxel_count * 3);
+    let mut counts = Vec::with_capacity(voxel_count);
     
     // Convert unique voxel keys to center positions
-    for voxel_key in voxel_keys {
-        // Extract voxel coordinates from integer key
-        let voxel_x = (voxel_key >> 32) as i32;
-        let voxel_y = ((voxel_key >> 16) & 0xFFFF) as i16 as i32;
-        let voxel_z = (voxel_key & 0xFFFF) as i16 as i32;
-        
+    for ([voxel_x, voxel_y, voxel_z], count) in voxel_counts {
         // Calculate voxel center position
         let center_x = offset_x + voxel_x as f32 * voxel_size;
         let center_y = offset_y + voxel_y as f32 * voxel_size;
@@ -61,8 +94,55 @@ pub fn generate_voxel_centers_internal(
         centers.push(center_x);
         centers.push(center_y);
         centers.push(center_z);
+        counts.push(count);
     }
     
-    centers
+    VoxelCentersResult { centers, counts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_match_points_per_voxel() {
+        // 3 points in voxel (0, 0, 0), 1 in (1, 0, 0), 2 in (0, -1, 2)
+        let points = vec![
+            0.1, 0.1, 0.1, 0.5, 0.2, 0.9, 0.9, 0.9, 0.9, //
+            1.5, 0.5, 0.5, //
+            0.2, -0.5, 2.1, 0.8, -0.1, 2.9,
+        ];
+        let result = generate_voxel_centers_with_counts_internal(&points, 1.0, 0.0, 0.0, 0.0);
+        assert_eq!(result.centers.len(), result.counts.len() * 3);
+
+        let mut voxels: Vec<([i32; 3], u32)> = result
+            .centers
+            .chunks_exact(3)
+            .zip(&result.counts)
+            .map(|(c, &n)| ([0, 1, 2].map(|a| (c[a] - 0.5).round() as i32), n))
+            .collect();
+        voxels.sort();
+        assert_eq!(voxels, vec![([0, -1, 2], 2), ([0, 0, 0], 3), ([1, 0, 0], 1)]);
+
+        let centers_only = generate_voxel_centers_internal(&points, 1.0, 0.0, 0.0, 0.0);
+        assert_eq!(centers_only.len(), result.centers.len());
+    }
+
+    #[test]
+    fn test_non_finite_points_are_not_counted() {
+        let points = vec![0.5, 0.5, 0.5, f32::NAN, 0.5, 0.5, 0.5, f32::INFINITY, 0.5, 0.2, 0.2, 0.2];
+        let result = generate_voxel_centers_with_counts_internal(&points, 1.0, 0.0, 0.0, 0.0);
+        assert_eq!(result.centers, vec![0.5, 0.5, 0.5]);
+        assert_eq!(result.counts, vec![2]);
+    }
+
+    #[test]
+    fn test_voxels_65536_cells_apart_in_y_are_counted_separately() {
+        let points = vec![0.5, 0.5, 0.5, 0.5, 65536.5, 0.5, 0.5, 65536.2, 0.5];
+        let result = generate_voxel_centers_with_counts_internal(&points, 1.0, 0.0, 0.0, 0.0);
+        let mut voxels: Vec<(f32, u32)> = result.centers.chunks_exact(3).map(|c| c[1]).zip(result.counts).collect();
+        voxels.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(voxels, vec![(0.5, 1), (65536.5, 2)]);
+    }
 }
 
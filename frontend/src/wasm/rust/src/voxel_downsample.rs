@@ -1,36 +1,30 @@
-use crate::common::{Voxel, VoxelFull};
+use crate::common::{deinterleave_xyzrgb, interleave_xyzrgb, is_finite_point, voxel_key, Voxel, VoxelFull, VoxelKey};
 use rustc_hash::FxHashMap;
+use wasm_bindgen::prelude::*;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
-/// Voxel downsampling with optional colors (average per voxel), intensity (average), classification (mode).
-/// Pass None for any attribute to skip it. Output pointers can be null (0) to skip writing that attribute.
-pub fn voxel_downsample_with_attributes_internal(
+/// Accumulate per-voxel sums for positions and whichever attributes are enabled, plus
+/// weight-scaled position sums when per-point weights are given. Points with a non-finite
+/// coordinate are skipped, together with their attributes.
+/// Shared by the pointer-based and slice-based attribute downsampling paths so both agree.
+fn accumulate_voxels_full(
     points: &[f32],
     colors: Option<&[f32]>,
     intensities: Option<&[f32]>,
     classifications: Option<&[u8]>,
-    voxel_size: f32,
-    min_x: f32,
-    min_y: f32,
-    min_z: f32,
-    output_ptr: *mut f32,
-    output_colors: Option<*mut f32>,
-    output_intensities: Option<*mut f32>,
-    output_classifications: Option<*mut u8>,
-) -> usize {
-    let inv_voxel_size = 1.0 / voxel_size;
+    weights: Option<&[f32]>,
+    grid: &VoxelGridSpec,
+) -> FxHashMap<VoxelKey, VoxelFull> {
+    let inv_voxel_size = 1.0 / grid.voxel_size;
+    let [min_x, min_y, min_z] = grid.min;
     let point_count = points.len() / 3;
-    if points.len() < point_count * 3 {
-        return 0;
-    }
-    let use_colors = output_colors.is_some()
-        && colors.map(|c| c.len() == point_count * 3).unwrap_or(false);
-    let use_intensity = output_intensities.is_some()
-        && intensities.map(|i| i.len() == point_count).unwrap_or(false);
-    let use_classification = output_classifications.is_some()
-        && classifications.map(|c| c.len() == point_count).unwrap_or(false);
+    let use_colors = colors.is_some();
+    let use_intensity = intensities.is_some();
+    let use_classification = classifications.is_some();
 
     let estimated_voxels = (point_count / 100).max(100).min(100_000);
-    let mut voxel_map: FxHashMap<u64, VoxelFull> =
+    let mut voxel_map: FxHashMap<VoxelKey, VoxelFull> =
         FxHashMap::with_capacity_and_hasher(estimated_voxels, Default::default());
 
     const CHUNK_SIZE: usize = 1024;
@@ -38,31 +32,34 @@ pub fn voxel_downsample_with_attributes_internal(
         let chunk_end = (chunk_start + CHUNK_SIZE).min(point_count);
         for i in chunk_start..chunk_end {
             let i3 = i * 3;
+            if !is_finite_point(&points[i3..i3 + 3]) {
+                continue;
+            }
             let x = points[i3];
             let y = points[i3 + 1];
             let z = points[i3 + 2];
             let voxel_x = ((x - min_x) * inv_voxel_size).floor() as i32;
             let voxel_y = ((y - min_y) * inv_voxel_size).floor() as i32;
             let voxel_z = ((z - min_z) * inv_voxel_size).floor() as i32;
-            let voxel_key =
-                ((voxel_x as u64) << 32) | ((voxel_y as u64) << 16) | (voxel_z as u64);
+            let voxel_key = voxel_key(voxel_x, voxel_y, voxel_z);
 
-            let (sum_r, sum_g, sum_b) = if use_colors {
-                let c = colors.unwrap();
+            let (sum_r, sum_g, sum_b) = if let Some(c) = colors {
                 (c[i3], c[i3 + 1], c[i3 + 2])
             } else {
                 (0.0f32, 0.0f32, 0.0f32)
             };
-            let sum_intensity = if use_intensity {
-                intensities.unwrap()[i]
+            let sum_intensity = if let Some(intensities) = intensities {
+                intensities[i]
             } else {
                 0.0f32
             };
-            let class_byte = if use_classification {
-                classifications.unwrap()[i]
+            let class_byte = if let Some(classifications) = classifications {
+                classifications[i]
             } else {
                 0u8
             };
+            // Negative (and NaN) weights count as zero; see voxel_downsample_with_attributes_vec_internal
+            let weight = weights.map_or(0.0, |w| w[i].max(0.0));
 
             voxel_map
                 .entry(voxel_key)
@@ -82,6 +79,10 @@ pub fn voxel_downsample_with_attributes_internal(
                     if use_classification {
                         *v.class_counts.entry(class_byte).or_insert(0) += 1;
                     }
+                    v.weighted_sum[0] += weight * x;
+                    v.weighted_sum[1] += weight * y;
+                    v.weighted_sum[2] += weight * z;
+                    v.weight_sum += weight;
                 })
                 .or_insert_with(|| {
                     let mut class_counts = FxHashMap::default();
@@ -93,24 +94,73 @@ pub fn voxel_downsample_with_attributes_internal(
                         sum_x: x,
                         sum_y: y,
                         sum_z: z,
-                        sum_r: sum_r,
-                        sum_g: sum_g,
-                        sum_b: sum_b,
-                        sum_intensity: sum_intensity,
+                        sum_r,
+                        sum_g,
+                        sum_b,
+                        sum_intensity,
                         class_counts,
+                        weighted_sum: [weight * x, weight * y, weight * z],
+                        weight_sum: weight,
                     }
                 });
         }
     }
+    voxel_map
+}
+
+/// Majority-vote classification of a voxel (same selection as the backend voxel_downsample_rust)
+fn majority_class(class_counts: &FxHashMap<u8, i32>) -> u8 {
+    class_counts
+        .iter()
+        .max_by_key(|(_, &c)| c)
+        .map(|(&cls, _)| cls)
+        .unwrap_or(0)
+}
+
+/// Attribute output buffers of voxel_downsample_with_attributes_internal; None skips that attribute
+#[derive(Clone, Copy)]
+pub struct AttributeOutputs {
+    pub colors: Option<*mut f32>,
+    pub intensities: Option<*mut f32>,
+    pub classifications: Option<*mut u8>,
+}
+
+/// Voxel downsampling with optional colors (average per voxel), intensity (average), classification (mode).
+/// Pass None for any attribute to skip it. Attribute outputs can be None to skip writing that attribute.
+pub fn voxel_downsample_with_attributes_internal(
+    points: &[f32],
+    colors: Option<&[f32]>,
+    intensities: Option<&[f32]>,
+    classifications: Option<&[u8]>,
+    grid: &VoxelGridSpec,
+    output_ptr: *mut f32,
+    outputs: AttributeOutputs,
+) -> usize {
+    let AttributeOutputs {
+        colors: output_colors,
+        intensities: output_intensities,
+        classifications: output_classifications,
+    } = outputs;
+    let point_count = points.len() / 3;
+    if points.len() < point_count * 3 {
+        return 0;
+    }
+    let colors = colors.filter(|c| output_colors.is_some() && c.len() == point_count * 3);
+    let intensities = intensities.filter(|i| output_intensities.is_some() && i.len() == point_count);
+    let classifications =
+        classifications.filter(|c| output_classifications.is_some() && c.len() == point_count);
+
+    let voxel_map = accumulate_voxels_full(points, colors, intensities, classifications, None, grid);
 
     let mut output_index = 0;
     for (_k, voxel) in voxel_map {
         let count_f = voxel.count as f32;
+        let centroid = voxel.centroid();
         unsafe {
             let base = output_index * 3;
-            *output_ptr.add(base) = voxel.sum_x / count_f;
-            *output_ptr.add(base + 1) = voxel.sum_y / count_f;
-            *output_ptr.add(base + 2) = voxel.sum_z / count_f;
+            *output_ptr.add(base) = centroid[0];
+            *output_ptr.add(base + 1) = centroid[1];
+            *output_ptr.add(base + 2) = centroid[2];
         }
         if let Some(out_colors) = output_colors {
             unsafe {
@@ -126,12 +176,7 @@ pub fn voxel_downsample_with_attributes_internal(
             }
         }
         if let Some(out_cls) = output_classifications {
-            let mode = voxel
-                .class_counts
-                .iter()
-                .max_by_key(|(_, &c)| c)
-                .map(|(&cls, _)| cls)
-                .unwrap_or(0);
+            let mode = majority_class(&voxel.class_counts);
             unsafe {
                 *out_cls.add(output_index) = mode;
             }
@@ -141,29 +186,219 @@ pub fn voxel_downsample_with_attributes_internal(
     output_index
 }
 
-pub fn voxel_downsample_internal(
+/// Downsampled positions plus whichever attributes were supplied.
+/// Attribute arrays are empty when the matching input was not provided.
+#[wasm_bindgen]
+pub struct VoxelDownsampleResult {
+    positions: Vec<f32>,
+    colors: Vec<f32>,
+    intensities: Vec<f32>,
+    classifications: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl VoxelDownsampleResult {
+    /// Number of output points (one per occupied voxel)
+    #[wasm_bindgen(getter)]
+    pub fn count(&self) -> usize {
+        self.positions.len() / 3
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn positions(&self) -> Vec<f32> {
+        self.positions.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn colors(&self) -> Vec<f32> {
+        self.colors.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn intensities(&self) -> Vec<f32> {
+        self.intensities.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn classifications(&self) -> Vec<u8> {
+        self.classifications.clone()
+    }
+}
+
+/// Voxel size and grid origin, passed as one value to the attribute downsamplers (and their wasm
+/// entry points) that already take several arrays
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub struct VoxelGridSpec {
+    voxel_size: f32,
+    min: [f32; 3],
+}
+
+#[wasm_bindgen]
+impl VoxelGridSpec {
+    #[wasm_bindgen(constructor)]
+    pub fn new(voxel_size: f32, min_x: f32, min_y: f32, min_z: f32) -> VoxelGridSpec {
+        VoxelGridSpec { voxel_size, min: [min_x, min_y, min_z] }
+    }
+}
+
+impl VoxelGridSpec {
+    pub fn voxel_size(&self) -> f32 {
+        self.voxel_size
+    }
+
+    pub fn min(&self) -> [f32; 3] {
+        self.min
+    }
+}
+
+/// Slice-based voxel downsampling with attributes, returning owned arrays.
+/// Empty `colors` / `classifications` slices (or a `None` intensity) skip that attribute;
+/// slices whose length does not match the point count are ignored, same as the backend.
+/// With `weights` (one per point) each voxel's position is the weighted mean of its points,
+/// falling back to the plain mean when the voxel's total weight is not positive; attributes
+/// are still averaged unweighted. Negative and NaN weights are clamped to 0, so a weighted
+/// mean always lies inside its voxel.
+pub fn voxel_downsample_with_attributes_vec_internal(
     points: &[f32],
+    colors: &[f32],
+    intensities: Option<&[f32]>,
+    classifications: &[u8],
+    weights: Option<&[f32]>,
+    grid: &VoxelGridSpec,
+) -> VoxelDownsampleResult {
+    let point_count = points.len() / 3;
+    let colors = Some(colors).filter(|c| c.len() == point_count * 3);
+    let intensities = intensities.filter(|i| i.len() == point_count);
+    let classifications = Some(classifications).filter(|c| c.len() == point_count);
+    let weights = weights.filter(|w| w.len() == point_count);
+
+    let voxel_map = accumulate_voxels_full(points, colors, intensities, classifications, weights, grid);
+
+    let output_count = voxel_map.len();
+    let mut result = VoxelDownsampleResult {
+        positions: Vec::with_capacity(output_count * 3),
+        colors: Vec::with_capacity(if colors.is_some() { output_count * 3 } else { 0 }),
+        intensities: Vec::with_capacity(if intensities.is_some() { output_count } else { 0 }),
+        classifications: Vec::with_capacity(if classifications.is_some() { output_count } else { 0 }),
+    };
+    for (_k, voxel) in voxel_map {
+        let count_f = voxel.count as f32;
+        result.positions.extend_from_slice(&voxel.centroid());
+        if colors.is_some() {
+            result.colors.extend_from_slice(&[
+                voxel.sum_r / count_f,
+                voxel.sum_g / count_f,
+                voxel.sum_b / count_f,
+            ]);
+        }
+        if intensities.is_some() {
+            result.intensities.push(voxel.sum_intensity / count_f);
+        }
+        if classifications.is_some() {
+            result.classifications.push(majority_class(&voxel.class_counts));
+        }
+    }
+    result
+}
+
+/// Voxel downsampling of an interleaved [x, y, z, r, g, b] array: voxels come from the XYZ
+/// channels, RGB is averaged per voxel, and the result is interleaved the same way.
+/// Same output as voxel_downsample_with_attributes_vec_internal with the channels split.
+pub fn voxel_downsample_interleaved_internal(
+    interleaved: &[f32],
     voxel_size: f32,
     min_x: f32,
     min_y: f32,
     min_z: f32,
-    output_ptr: *mut f32,
-) -> usize {
+) -> Vec<f32> {
+    let (points, colors) = deinterleave_xyzrgb(interleaved);
+    let grid = VoxelGridSpec::new(voxel_size, min_x, min_y, min_z);
+    let result = voxel_downsample_with_attributes_vec_internal(&points, &colors, None, &[], None, &grid);
+    interleave_xyzrgb(&result.positions, &result.colors)
+}
+
+// Clouds below this size stay on the serial path even with the `parallel` feature;
+// splitting them costs more than the insertion loop itself
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_POINTS: usize = 1_000_000;
+
+// Points per chunk in the parallel path, each chunk building its own voxel map
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_POINTS: usize = 65_536;
+
+/// Voxel insertion pass shared by voxel_downsample_internal and voxel_count_internal,
+/// so the count reported ahead of a downsample always matches its output length.
+/// Points with a non-finite coordinate are skipped.
+fn accumulate_voxels(
+    points: &[f32],
+    voxel_size: f32,
+    min_x: f32,
+    min_y: f32,
+    min_z: f32,
+) -> FxHashMap<VoxelKey, Voxel> {
+    #[cfg(feature = "parallel")]
+    if points.len() / 3 >= PARALLEL_MIN_POINTS {
+        return accumulate_voxels_parallel(points, voxel_size, min_x, min_y, min_z);
+    }
+    accumulate_voxels_serial(points, voxel_size, min_x, min_y, min_z)
+}
+
+/// Parallel insertion: per-chunk voxel maps built on the rayon pool, then merged in chunk
+/// order by summing count and coordinates. Voxel keys and counts match the serial path
+/// exactly; the averaged positions only differ by float summation order.
+#[cfg(feature = "parallel")]
+fn accumulate_voxels_parallel(
+    points: &[f32],
+    voxel_size: f32,
+    min_x: f32,
+    min_y: f32,
+    min_z: f32,
+) -> FxHashMap<VoxelKey, Voxel> {
+    let chunk_maps: Vec<FxHashMap<VoxelKey, Voxel>> = points
+        .par_chunks(PARALLEL_CHUNK_POINTS * 3)
+        .map(|chunk| accumulate_voxels_serial(chunk, voxel_size, min_x, min_y, min_z))
+        .collect();
+
+    let mut chunk_maps = chunk_maps.into_iter();
+    let mut voxel_map = chunk_maps.next().unwrap_or_default();
+    for chunk_map in chunk_maps {
+        for (voxel_key, chunk_voxel) in chunk_map {
+            voxel_map.entry(voxel_key).and_modify(|voxel| {
+                voxel.count += chunk_voxel.count;
+                voxel.sum_x += chunk_voxel.sum_x;
+                voxel.sum_y += chunk_voxel.sum_y;
+                voxel.sum_z += chunk_voxel.sum_z;
+            }).or_insert(chunk_voxel);
+        }
+    }
+    voxel_map
+}
+
+fn accumulate_voxels_serial(
+    points: &[f32],
+    voxel_size: f32,
+    min_x: f32,
+    min_y: f32,
+    min_z: f32,
+) -> FxHashMap<VoxelKey, Voxel> {
+    // Use fast hash map with integer keys for voxel lookup
+    // Pre-allocate with estimated capacity to minimize reallocations
+    let estimated_voxels = (points.len() / 300).min(100_000);
+    let mut voxel_map: FxHashMap<VoxelKey, Voxel> = FxHashMap::with_capacity_and_hasher(estimated_voxels, Default::default());
+    accumulate_voxels_into(&mut voxel_map, points, voxel_size, [min_x, min_y, min_z]);
+    voxel_map
+}
+
+/// Fold `points` into an existing voxel map in input order (the serial insertion loop, also
+/// used by VoxelGrid::add_points). Points with a non-finite coordinate are skipped.
+fn accumulate_voxels_into(voxel_map: &mut FxHashMap<VoxelKey, Voxel>, points: &[f32], voxel_size: f32, min: [f32; 3]) {
     // Pre-calculate inverse voxel size to avoid division operations
     let inv_voxel_size = 1.0 / voxel_size;
+    let [min_x, min_y, min_z] = min;
     
     let point_count = points.len() / 3;
     
-    // Validate slice length
-    if points.len() < point_count * 3 {
-        return 0;
-    }
-    
-    // Use fast hash map with integer keys for voxel lookup
-    // Pre-allocate with estimated capacity to minimize reallocations
-    let estimated_voxels = (point_count / 100).min(100_000);
-    let mut voxel_map: FxHashMap<u64, Voxel> = FxHashMap::with_capacity_and_hasher(estimated_voxels, Default::default());
-    
     // Process points in chunks for better CPU cache performance
     const CHUNK_SIZE: usize = 1024;
     
@@ -172,6 +407,9 @@ pub fn voxel_downsample_internal(
         
         for i in chunk_start..chunk_end {
             let i3 = i * 3;
+            if !is_finite_point(&points[i3..i3 + 3]) {
+                continue;
+            }
             let x = points[i3];
             let y = points[i3 + 1];
             let z = points[i3 + 2];
@@ -182,7 +420,7 @@ pub fn voxel_downsample_internal(
             let voxel_z = ((z - min_z) * inv_voxel_size).floor() as i32;
             
             // Combine coordinates into single integer hash key
-            let voxel_key = ((voxel_x as u64) << 32) | ((voxel_y as u64) << 16) | (voxel_z as u64);
+            let voxel_key = voxel_key(voxel_x, voxel_y, voxel_z);
             
             // Update or insert voxel data using single hash lookup
             voxel_map.entry(voxel_key).and_modify(|voxel| {
@@ -198,11 +436,82 @@ pub fn voxel_downsample_internal(
             });
         }
     }
+}
+
+/// Argument check behind the `_checked` pointer-based entry points: the reason a call cannot
+/// run, instead of the plain variants' silent 0. Every pointer must be 4-byte aligned.
+pub fn check_direct_args(point_count: usize, voxel_size: f32, pointers: &[usize]) -> Result<(), &'static str> {
+    if point_count == 0 {
+        return Err("point count is zero");
+    }
+    if !(voxel_size > 0.0 && voxel_size.is_finite()) {
+        return Err("voxel size must be positive and finite");
+    }
+    if pointers.iter().any(|&ptr| !ptr.is_multiple_of(4)) {
+        return Err("buffer pointer is not 4-byte aligned");
+    }
+    Ok(())
+}
+
+/// Number of voxels (= output points) voxel_downsample_internal would produce, without
+/// writing anything, so callers can size the output buffer exactly
+pub fn voxel_count_internal(points: &[f32], voxel_size: f32, min_x: f32, min_y: f32, min_z: f32) -> usize {
+    accumulate_voxels(points, voxel_size, min_x, min_y, min_z).len()
+}
+
+// Relative count error at which suggest_voxel_size_internal stops searching
+const SUGGEST_TOLERANCE: f32 = 0.05;
+const SUGGEST_MAX_ITERATIONS: usize = 32;
+
+/// Voxel size for which voxel_downsample_internal (grid anchored at `min`) produces roughly
+/// `target_count` points: geometric bisection between a millionth of the largest extent and
+/// twice it, returning the size whose voxel count came closest. 0 when there is nothing to
+/// search (no target, or the extent is zero or not finite).
+pub fn suggest_voxel_size_internal(points: &[f32], target_count: usize, min: [f32; 3], max: [f32; 3]) -> f32 {
+    let extent = (max[0] - min[0]).max(max[1] - min[1]).max(max[2] - min[2]);
+    if target_count == 0 || !(extent > 0.0 && extent.is_finite()) {
+        return 0.0;
+    }
+
+    let mut low = extent * 1e-6;
+    let mut high = extent * 2.0;
+    let mut best = (high, 1usize);
+    for _ in 0..SUGGEST_MAX_ITERATIONS {
+        let size = (low * high).sqrt();
+        let count = voxel_count_internal(points, size, min[0], min[1], min[2]);
+        if count.abs_diff(target_count) < best.1.abs_diff(target_count) {
+            best = (size, count);
+        }
+        if count.abs_diff(target_count) as f32 <= target_count as f32 * SUGGEST_TOLERANCE {
+            break;
+        }
+        if count > target_count {
+            low = size;
+        } else {
+            high = size;
+        }
+    }
+    best.0
+}
+
+/// Averaged voxel centers written to `output_ptr`, one per voxel holding at least
+/// `min_points_per_voxel` points (same threshold as the backend voxel_downsample_rust tool;
+/// 0 or 1 keeps every voxel). Returns the number of points written.
+pub fn voxel_downsample_internal(
+    points: &[f32],
+    voxel_size: f32,
+    min_x: f32,
+    min_y: f32,
+    min_z: f32,
+    min_points_per_voxel: u32,
+    output_ptr: *mut f32,
+) -> usize {
+    let voxel_map = accumulate_voxels(points, voxel_size, min_x, min_y, min_z);
     
     // Write averaged voxel centers directly to output buffer
     let mut output_index = 0;
     
-    for (_voxel_key, voxel) in voxel_map {
+    for (_voxel_key, voxel) in voxel_map.into_iter().filter(|(_, voxel)| voxel.count as u32 >= min_points_per_voxel) {
         let count_f = voxel.count as f32;
         unsafe {
             let base_idx = output_index * 3;
@@ -216,3 +525,499 @@ pub fn voxel_downsample_internal(
     output_index
 }
 
+/// voxel_downsample_internal plus an index map: for each output point, the input index of one
+/// representative point of its voxel (the last one inserted), written to `index_output_ptr`, so
+/// callers of the position-only path can gather colors or other attributes themselves. Output
+/// points come in the same order as voxel_downsample_internal.
+pub fn voxel_downsample_with_index_map_internal(
+    points: &[f32],
+    voxel_size: f32,
+    min_x: f32,
+    min_y: f32,
+    min_z: f32,
+    output_ptr: *mut f32,
+    index_output_ptr: *mut u32,
+) -> usize {
+    let voxel_map = accumulate_voxels(points, voxel_size, min_x, min_y, min_z);
+
+    // Write averaged voxel centers and remember each voxel's output slot
+    let mut output_slot: FxHashMap<VoxelKey, usize> = FxHashMap::with_capacity_and_hasher(voxel_map.len(), Default::default());
+    for (slot, (&voxel_key, voxel)) in voxel_map.iter().enumerate() {
+        let count_f = voxel.count as f32;
+        unsafe {
+            *output_ptr.add(slot * 3) = voxel.sum_x / count_f;
+            *output_ptr.add(slot * 3 + 1) = voxel.sum_y / count_f;
+            *output_ptr.add(slot * 3 + 2) = voxel.sum_z / count_f;
+        }
+        output_slot.insert(voxel_key, slot);
+    }
+
+    // Second pass in input order, so the last point of each voxel ends up as its representative
+    let inv_voxel_size = 1.0 / voxel_size;
+    for (i, p) in points.chunks_exact(3).enumerate().filter(|(_, p)| is_finite_point(p)) {
+        let voxel_x = ((p[0] - min_x) * inv_voxel_size).floor() as i32;
+        let voxel_y = ((p[1] - min_y) * inv_voxel_size).floor() as i32;
+        let voxel_z = ((p[2] - min_z) * inv_voxel_size).floor() as i32;
+        let slot = output_slot[&voxel_key(voxel_x, voxel_y, voxel_z)];
+        unsafe {
+            *index_output_ptr.add(slot) = i as u32;
+        }
+    }
+
+    voxel_map.len()
+}
+
+/// Index of the output point each input point contributes to in voxel_downsample_internal.
+/// Rebuilds the same voxel map and numbers its voxels in iteration order, which is the order
+/// voxel_downsample_internal writes them, so assignment j refers to its j-th output point.
+/// Points with a non-finite coordinate belong to no voxel and get u32::MAX.
+pub fn voxel_assignments_internal(points: &[f32], voxel_size: f32, min_x: f32, min_y: f32, min_z: f32) -> Vec<u32> {
+    let voxel_map = accumulate_voxels(points, voxel_size, min_x, min_y, min_z);
+    let output_index: FxHashMap<VoxelKey, u32> = voxel_map
+        .keys()
+        .enumerate()
+        .map(|(index, &voxel_key)| (voxel_key, index as u32))
+        .collect();
+
+    let inv_voxel_size = 1.0 / voxel_size;
+    points
+        .chunks_exact(3)
+        .map(|p| {
+            if !is_finite_point(p) {
+                return u32::MAX;
+            }
+            let voxel_x = ((p[0] - min_x) * inv_voxel_size).floor() as i32;
+            let voxel_y = ((p[1] - min_y) * inv_voxel_size).floor() as i32;
+            let voxel_z = ((p[2] - min_z) * inv_voxel_size).floor() as i32;
+            output_index[&voxel_key(voxel_x, voxel_y, voxel_z)]
+        })
+        .collect()
+}
+
+/// Voxel map kept after a build so cells can be probed repeatedly (e.g. interactive picking)
+/// without downsampling again, or grown batch by batch while streaming a capture. Cells use the
+/// same keying as voxel_downsample_internal.
+#[derive(Default)]
+pub struct VoxelGrid {
+    voxel_map: FxHashMap<VoxelKey, Voxel>,
+    // Voxel size and grid origin of the last build, reused by add_points (size 0 until built)
+    voxel_size: f32,
+    min: [f32; 3],
+}
+
+impl VoxelGrid {
+    /// Replace the grid contents with the voxels of `points`
+    pub fn build(&mut self, points: &[f32], voxel_size: f32, min_x: f32, min_y: f32, min_z: f32) {
+        self.voxel_map = accumulate_voxels(points, voxel_size, min_x, min_y, min_z);
+        self.voxel_size = voxel_size;
+        self.min = [min_x, min_y, min_z];
+    }
+
+    /// Fold another batch of points into the grid with the voxel size and origin of the last
+    /// build. Cells and counts match one build over all batches concatenated; centroids match it
+    /// up to float summation order, since a large build may have summed in parallel chunks.
+    /// Does nothing before the first build.
+    pub fn add_points(&mut self, points: &[f32]) {
+        if self.voxel_size <= 0.0 {
+            return;
+        }
+        accumulate_voxels_into(&mut self.voxel_map, points, self.voxel_size, self.min);
+    }
+
+    /// Centroid of every occupied voxel as a flat xyz array, in map iteration order
+    pub fn current_centroids(&self) -> Vec<f32> {
+        let mut centroids = Vec::with_capacity(self.voxel_map.len() * 3);
+        for voxel in self.voxel_map.values() {
+            let count_f = voxel.count as f32;
+            centroids.extend_from_slice(&[voxel.sum_x / count_f, voxel.sum_y / count_f, voxel.sum_z / count_f]);
+        }
+        centroids
+    }
+
+    /// Centroid and point count of cell (vx, vy, vz), or None if no point fell in it
+    pub fn query_cell(&self, voxel_x: i32, voxel_y: i32, voxel_z: i32) -> Option<([f32; 3], u32)> {
+        self.voxel_map.get(&voxel_key(voxel_x, voxel_y, voxel_z)).map(|voxel| {
+            let count_f = voxel.count as f32;
+            (
+                [voxel.sum_x / count_f, voxel.sum_y / count_f, voxel.sum_z / count_f],
+                voxel.count as u32,
+            )
+        })
+    }
+
+    pub fn occupied_count(&self) -> usize {
+        self.voxel_map.len()
+    }
+
+    /// Drop every cell and the memory behind them. Like clear, the voxel size and origin of the
+    /// last build are kept, so add_points still folds into the same grid afterwards.
+    pub fn reset(&mut self) {
+        self.voxel_map = FxHashMap::default();
+    }
+
+    /// Empty every cell but keep their allocation, along with the voxel size and origin, so a
+    /// new stream can start with add_points
+    pub fn clear(&mut self) {
+        self.voxel_map.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_point_voxel_attributes_match_backend() {
+        // Two points in one voxel: colors, intensity averaged; classification by majority
+        let points = [0.1f32, 0.1, 0.1, 0.4, 0.3, 0.2];
+        let colors = [1.0f32, 0.2, 0.0, 0.0, 0.6, 1.0];
+        let intensities = [10.0f32, 30.0];
+        let classifications = [2u8, 2];
+
+        let result = voxel_downsample_with_attributes_vec_internal(
+            &points,
+            &colors,
+            Some(&intensities),
+            &classifications,
+            None,
+            &VoxelGridSpec::new(1.0, 0.0, 0.0, 0.0),
+        );
+
+        // Backend voxel_downsample_rust accumulates f32 sums and divides by the f32 count
+        let count_f = 2.0f32;
+        assert_eq!(result.count(), 1);
+        assert_eq!(
+            result.colors(),
+            vec![(1.0f32 + 0.0) / count_f, (0.2f32 + 0.6) / count_f, (0.0f32 + 1.0) / count_f]
+        );
+        assert_eq!(result.positions(), vec![(0.1f32 + 0.4) / count_f, (0.1f32 + 0.3) / count_f, (0.1f32 + 0.2) / count_f]);
+        assert_eq!(result.intensities(), vec![20.0]);
+        assert_eq!(result.classifications(), vec![2]);
+    }
+
+    #[test]
+    fn test_majority_classification_and_skipped_attributes() {
+        let points = [0.1f32, 0.1, 0.1, 0.2, 0.2, 0.2, 0.3, 0.3, 0.3];
+        let result = voxel_downsample_with_attributes_vec_internal(
+            &points,
+            &[],
+            None,
+            &[5, 7, 7],
+            None,
+            &VoxelGridSpec::new(1.0, 0.0, 0.0, 0.0),
+        );
+        assert_eq!(result.classifications(), vec![7]);
+        assert!(result.colors().is_empty());
+        assert!(result.intensities().is_empty());
+    }
+
+    #[test]
+    fn test_interleaved_matches_separate_arrays() {
+        let mut interleaved = Vec::new();
+        for i in 0..600 {
+            let t = i as f32 * 0.21;
+            interleaved.extend_from_slice(&[t.sin() * 2.0, t.cos() * 1.5, t * 0.01]);
+            interleaved.extend_from_slice(&[(i % 7) as f32 / 7.0, (i % 11) as f32 / 11.0, 0.5]);
+        }
+        let (points, colors) = deinterleave_xyzrgb(&interleaved);
+        let separate = voxel_downsample_with_attributes_vec_internal(&points, &colors, None, &[], None, &VoxelGridSpec::new(0.4, -2.0, -1.5, 0.0));
+
+        let output = voxel_downsample_interleaved_internal(&interleaved, 0.4, -2.0, -1.5, 0.0);
+        let sorted_records = |records: Vec<[f32; 6]>| {
+            let mut records = records;
+            records.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            records
+        };
+        let got: Vec<[f32; 6]> = output.chunks_exact(6).map(|r| [r[0], r[1], r[2], r[3], r[4], r[5]]).collect();
+        let expected: Vec<[f32; 6]> = separate
+            .positions()
+            .chunks_exact(3)
+            .zip(separate.colors().chunks_exact(3))
+            .map(|(p, c)| [p[0], p[1], p[2], c[0], c[1], c[2]])
+            .collect();
+        assert_eq!(got.len(), separate.count());
+        assert_eq!(sorted_records(got), sorted_records(expected));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_accumulation_matches_serial() {
+        let points: Vec<f32> = (0..100_000)
+            .flat_map(|i| {
+                let f = i as f32;
+                [(f * 0.013).sin() * 10.0, (f * 0.007).cos() * 10.0, (f * 0.0003) % 5.0]
+            })
+            .collect();
+
+        let serial = accumulate_voxels_serial(&points, 0.2, -10.0, -10.0, 0.0);
+        let parallel = accumulate_voxels_parallel(&points, 0.2, -10.0, -10.0, 0.0);
+
+        assert_eq!(serial.len(), parallel.len());
+        for (voxel_key, s) in &serial {
+            let p = &parallel[voxel_key];
+            assert_eq!(s.count, p.count);
+            let count = s.count as f32;
+            for (a, b) in [(s.sum_x, p.sum_x), (s.sum_y, p.sum_y), (s.sum_z, p.sum_z)] {
+                assert!((a / count - b / count).abs() < 1e-4, "{} vs {}", a / count, b / count);
+            }
+        }
+    }
+
+    #[test]
+    fn test_voxel_grid_query_occupied_and_empty_cells() {
+        let points = vec![
+            0.2, 0.2, 0.2, 0.6, 0.4, 0.8, // cell (0, 0, 0)
+            1.5, 0.5, 0.5, // cell (1, 0, 0)
+            -0.5, 2.5, 0.1, // cell (-1, 2, 0)
+        ];
+        let mut grid = VoxelGrid::default();
+        grid.build(&points, 1.0, 0.0, 0.0, 0.0);
+        assert_eq!(grid.occupied_count(), 3);
+
+        let (centroid, count) = grid.query_cell(0, 0, 0).unwrap();
+        assert_eq!(count, 2);
+        assert!((centroid[0] - 0.4).abs() < 1e-6 && (centroid[1] - 0.3).abs() < 1e-6 && (centroid[2] - 0.5).abs() < 1e-6);
+        assert_eq!(grid.query_cell(1, 0, 0), Some(([1.5, 0.5, 0.5], 1)));
+        assert_eq!(grid.query_cell(-1, 2, 0), Some(([-0.5, 2.5, 0.1], 1)));
+        assert_eq!(grid.query_cell(0, 1, 0), None);
+        assert_eq!(grid.query_cell(5, 5, 5), None);
+
+        // Queries stay valid until an explicit reset
+        assert_eq!(grid.query_cell(1, 0, 0).map(|(_, n)| n), Some(1));
+        grid.reset();
+        assert_eq!(grid.occupied_count(), 0);
+        assert_eq!(grid.query_cell(0, 0, 0), None);
+    }
+
+    #[test]
+    fn test_weighted_centroid_pulls_toward_heavy_point() {
+        // Three points in one voxel; the last carries almost all of the weight
+        let points = vec![0.1, 0.1, 0.1, 0.3, 0.1, 0.1, 0.9, 0.5, 0.7, 5.5, 0.5, 0.5];
+        let weights = [1.0, 1.0, 98.0, 0.0];
+        let result = voxel_downsample_with_attributes_vec_internal(&points, &[], None, &[], Some(&weights), &VoxelGridSpec::new(1.0, 0.0, 0.0, 0.0));
+        let mut positions: Vec<[f32; 3]> = result.positions().chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect();
+        positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let expected = [(0.1 + 0.3 + 98.0 * 0.9) / 100.0, (0.1 + 0.1 + 98.0 * 0.5) / 100.0, (0.1 + 0.1 + 98.0 * 0.7) / 100.0];
+        for (found, expected) in positions[0].iter().zip(expected) {
+            assert!((found - expected).abs() < 1e-5, "{:?}", positions[0]);
+        }
+        let plain_x = (0.1 + 0.3 + 0.9) / 3.0;
+        assert!(positions[0][0] - plain_x > 0.3);
+
+        // The other voxel has zero total weight and falls back to the plain mean
+        assert_eq!(positions[1], [5.5, 0.5, 0.5]);
+
+        // Negative weights count as zero instead of pushing the mean outside the voxel
+        let clamped = voxel_downsample_with_attributes_vec_internal(&points, &[], None, &[], Some(&[1.0, -50.0, 0.0, 0.0]), &VoxelGridSpec::new(1.0, 0.0, 0.0, 0.0));
+        assert!(clamped.positions().chunks_exact(3).any(|p| p == [0.1, 0.1, 0.1]), "{:?}", clamped.positions());
+
+        // Weights of the wrong length are ignored
+        let unweighted = voxel_downsample_with_attributes_vec_internal(&points, &[], None, &[], Some(&weights[..2]), &VoxelGridSpec::new(1.0, 0.0, 0.0, 0.0));
+        assert!(unweighted.positions().chunks_exact(3).any(|p| (p[0] - plain_x).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_voxels_65536_cells_apart_in_y_stay_separate() {
+        // With 16 bits for y, (0, 0, 0) and (0, 65536, 0) shared a key, as did (0, -1, 0) and
+        // (0, 65535, 0)
+        let points = vec![0.5, 0.5, 0.5, 0.5, 65536.5, 0.5, 0.5, -0.5, 0.5, 0.5, 65535.5, 0.5];
+        assert_eq!(voxel_count_internal(&points, 1.0, 0.0, 0.0, 0.0), 4);
+        let mut grid = VoxelGrid::default();
+        grid.build(&points, 1.0, 0.0, 0.0, 0.0);
+        assert_eq!(grid.query_cell(0, 0, 0), Some(([0.5, 0.5, 0.5], 1)));
+        assert_eq!(grid.query_cell(0, 65536, 0), Some(([0.5, 65536.5, 0.5], 1)));
+        assert_eq!(grid.query_cell(0, -1, 0), Some(([0.5, -0.5, 0.5], 1)));
+    }
+
+    #[test]
+    fn test_min_points_per_voxel_drops_sparse_voxels() {
+        // Four points in voxel (0,0,0) and a lone point in voxel (5,0,0)
+        let points: Vec<f32> = vec![
+            0.1, 0.1, 0.1,
+            0.2, 0.3, 0.1,
+            0.4, 0.2, 0.3,
+            0.3, 0.4, 0.2,
+            5.5, 0.5, 0.5,
+        ];
+        let mut output = vec![0.0f32; points.len()];
+        assert_eq!(voxel_downsample_internal(&points, 1.0, 0.0, 0.0, 0.0, 1, output.as_mut_ptr()), 2);
+        assert_eq!(voxel_downsample_internal(&points, 1.0, 0.0, 0.0, 0.0, 0, output.as_mut_ptr()), 2);
+
+        assert_eq!(voxel_downsample_internal(&points, 1.0, 0.0, 0.0, 0.0, 2, output.as_mut_ptr()), 1);
+        assert!((output[0] - 0.25).abs() < 1e-6);
+        assert!((output[1] - 0.25).abs() < 1e-6);
+        assert!((output[2] - 0.175).abs() < 1e-6);
+
+        assert_eq!(voxel_downsample_internal(&points, 1.0, 0.0, 0.0, 0.0, 5, output.as_mut_ptr()), 0);
+    }
+
+    #[test]
+    fn test_non_finite_points_are_skipped() {
+        let points = vec![
+            0.2, 0.2, 0.2, // cell (0, 0, 0)
+            f32::NAN, 0.4, 0.4,
+            0.4, 0.6, 0.4, // cell (0, 0, 0)
+            0.5, f32::INFINITY, 0.5,
+            1.5, 0.5, f32::NEG_INFINITY,
+        ];
+        let colors: Vec<f32> = (0..15).map(|i| i as f32).collect();
+        assert_eq!(voxel_count_internal(&points, 1.0, 0.0, 0.0, 0.0), 1);
+
+        let result = voxel_downsample_with_attributes_vec_internal(&points, &colors, None, &[], None, &VoxelGridSpec::new(1.0, 0.0, 0.0, 0.0));
+        assert_eq!(result.count(), 1);
+        assert!(result.positions().iter().zip([0.3, 0.4, 0.3]).all(|(a, b)| (a - b).abs() < 1e-6));
+        assert_eq!(result.colors(), vec![3.0, 4.0, 5.0]);
+
+        assert_eq!(voxel_assignments_internal(&points, 1.0, 0.0, 0.0, 0.0), vec![0, u32::MAX, 0, u32::MAX, u32::MAX]);
+
+        let mut grid = VoxelGrid::default();
+        grid.build(&points, 1.0, 0.0, 0.0, 0.0);
+        grid.add_points(&[f32::NAN, 0.0, 0.0, 0.6, 0.2, 0.5]);
+        assert_eq!(grid.query_cell(0, 0, 0).map(|(_, n)| n), Some(3));
+        assert_eq!(grid.occupied_count(), 1);
+    }
+
+    #[test]
+    fn test_direct_args_report_each_error() {
+        assert_eq!(check_direct_args(10, 0.5, &[1024, 4096]), Ok(()));
+        assert_eq!(check_direct_args(0, 0.5, &[1024, 4096]), Err("point count is zero"));
+        for voxel_size in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(check_direct_args(10, voxel_size, &[1024, 4096]), Err("voxel size must be positive and finite"));
+        }
+        assert_eq!(check_direct_args(10, 0.5, &[1026, 4096]), Err("buffer pointer is not 4-byte aligned"));
+        assert_eq!(check_direct_args(10, 0.5, &[1024, 4097]), Err("buffer pointer is not 4-byte aligned"));
+        // Errors are reported in order: an empty call with a bad size says the count is zero
+        assert_eq!(check_direct_args(0, -1.0, &[3]), Err("point count is zero"));
+    }
+
+    #[test]
+    fn test_index_map_points_into_each_output_voxel() {
+        let points: Vec<f32> = (0..700)
+            .flat_map(|i| {
+                let f = i as f32;
+                [(f * 0.37).sin() * 2.0, (f * 0.11).cos() * 2.0, (f * 0.05).sin()]
+            })
+            .collect();
+        let (voxel_size, min) = (0.5f32, [-2.0f32, -2.0, -1.0]);
+        let mut output = vec![0.0f32; points.len()];
+        let mut indices = vec![u32::MAX; points.len() / 3];
+        let count = voxel_downsample_with_index_map_internal(
+            &points, voxel_size, min[0], min[1], min[2], output.as_mut_ptr(), indices.as_mut_ptr(),
+        );
+
+        // Same positions as the plain fast path
+        let mut plain = vec![0.0f32; points.len()];
+        assert_eq!(voxel_downsample_internal(&points, voxel_size, min[0], min[1], min[2], 1, plain.as_mut_ptr()), count);
+        assert_eq!(output[..count * 3], plain[..count * 3]);
+
+        let voxel_of = |p: &[f32]| [0, 1, 2].map(|a| ((p[a] - min[a]) / voxel_size).floor() as i32);
+        for (slot, &index) in indices[..count].iter().enumerate() {
+            let representative = &points[index as usize * 3..index as usize * 3 + 3];
+            assert_eq!(voxel_of(representative), voxel_of(&output[slot * 3..slot * 3 + 3]), "output {}", slot);
+            // The last inserted point of the voxel
+            assert!(points[index as usize * 3 + 3..].chunks_exact(3).all(|p| voxel_of(p) != voxel_of(representative)));
+        }
+    }
+
+    #[test]
+    fn test_suggested_voxel_size_lands_near_target_count() {
+        let points: Vec<f32> = (0..40_000)
+            .flat_map(|i| {
+                let t = i as f32;
+                let (u, v) = ((t * 0.618_034) % 1.0, (t * 0.754_878) % 1.0);
+                [u * 10.0, v * 6.0, (u * 7.0).sin() * 0.5 + v]
+            })
+            .collect();
+        let bounds = crate::bounds::compute_bounds_internal(&points);
+        let (min, max) = ([bounds[0], bounds[1], bounds[2]], [bounds[3], bounds[4], bounds[5]]);
+        for target in [50, 1_000, 12_000] {
+            let size = suggest_voxel_size_internal(&points, target, min, max);
+            let count = voxel_count_internal(&points, size, min[0], min[1], min[2]);
+            let error = count.abs_diff(target) as f32 / target as f32;
+            assert!(error <= 0.2, "target {}: size {} gives {}", target, size, count);
+        }
+        assert_eq!(suggest_voxel_size_internal(&points, 0, min, max), 0.0);
+        assert_eq!(suggest_voxel_size_internal(&[1.0, 1.0, 1.0], 5, [1.0; 3], [1.0; 3]), 0.0);
+    }
+
+    #[test]
+    fn test_voxel_grid_batches_match_one_combined_downsample() {
+        let points: Vec<f32> = (0..600)
+            .flat_map(|i| {
+                let f = i as f32;
+                [(f * 0.37).sin() * 2.0, (f * 0.11).cos() * 2.0, (f * 0.05).sin()]
+            })
+            .collect();
+        let (voxel_size, min) = (0.5f32, [-2.0f32, -2.0, -1.0]);
+        let sorted_points = |flat: &[f32]| {
+            let mut points: Vec<[f32; 3]> = flat.chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect();
+            points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            points
+        };
+
+        let mut grid = VoxelGrid::default();
+        grid.build(&points[..900], voxel_size, min[0], min[1], min[2]);
+        grid.add_points(&points[900..]);
+
+        let mut combined = vec![0.0f32; points.len()];
+        let count = voxel_downsample_internal(&points, voxel_size, min[0], min[1], min[2], 1, combined.as_mut_ptr());
+        combined.truncate(count * 3);
+
+        let streamed = sorted_points(&grid.current_centroids());
+        let expected = sorted_points(&combined);
+        assert_eq!(streamed.len(), expected.len());
+        for (a, b) in streamed.iter().zip(&expected) {
+            assert!((0..3).all(|k| (a[k] - b[k]).abs() < 1e-5), "{:?} vs {:?}", a, b);
+        }
+
+        // clear keeps the voxel size and origin, so a fresh stream starts from add_points alone
+        grid.clear();
+        assert!(grid.current_centroids().is_empty());
+        grid.add_points(&points);
+        assert_eq!(grid.occupied_count(), count);
+
+        // reset frees the cells but also keeps the voxel size and origin
+        grid.reset();
+        assert_eq!(grid.occupied_count(), 0);
+        grid.add_points(&points);
+        assert_eq!(grid.occupied_count(), count);
+
+        let mut unbuilt = VoxelGrid::default();
+        unbuilt.add_points(&points);
+        assert_eq!(unbuilt.occupied_count(), 0);
+    }
+
+    #[test]
+    fn test_voxel_assignments_follow_downsample_output_order() {
+        let points: Vec<f32> = (0..500)
+            .flat_map(|i| {
+                let f = i as f32;
+                [(f * 0.37).sin() * 2.0, (f * 0.11).cos() * 2.0, (f * 0.05).sin()]
+            })
+            .collect();
+        let (voxel_size, min) = (0.5f32, [-2.0f32, -2.0, -1.0]);
+        let assignments = voxel_assignments_internal(&points, voxel_size, min[0], min[1], min[2]);
+        assert_eq!(assignments.len(), 500);
+
+        let mut output = vec![0.0f32; points.len()];
+        let output_count = voxel_downsample_internal(&points, voxel_size, min[0], min[1], min[2], 1, output.as_mut_ptr());
+        assert_eq!(*assignments.iter().max().unwrap() as usize, output_count - 1);
+
+        let cell = |i: usize| [0, 1, 2].map(|a| ((points[i * 3 + a] - min[a]) * (1.0 / voxel_size)).floor() as i32);
+        for i in 0..500 {
+            for j in i + 1..500 {
+                assert_eq!(assignments[i] == assignments[j], cell(i) == cell(j), "points {} and {}", i, j);
+            }
+        }
+
+        // Output point j is the centroid of the input points assigned to j
+        for (j, centroid) in output[..output_count * 3].chunks_exact(3).enumerate() {
+            let members: Vec<usize> = (0..500).filter(|&i| assignments[i] as usize == j).collect();
+            for axis in 0..3 {
+                let mean = members.iter().map(|&i| points[i * 3 + axis]).sum::<f32>() / members.len() as f32;
+                assert!((mean - centroid[axis]).abs() < 1e-5, "voxel {} axis {}", j, axis);
+            }
+        }
+    }
+}